@@ -0,0 +1,168 @@
+import { AbiCoder, BytesLike, Interface, Signature, TransactionRequest, getAddress, hexlify, keccak256 } from 'ethers';
+
+// Plans the EVM Resolver calls that mirror a Stellar escrow, using the exact 1inch
+// cross-chain-swap types (IBaseEscrow.Immutables, IOrderMixin.Order), so calldata matches
+// what the EVM Resolver.deploySrc / deployDst / withdraw / cancel expect
+
+/** 1inch `Address` and `MakerTraits` are uint256 words */
+const IMMUTABLES =
+  'tuple(bytes32 orderHash, bytes32 hashlock, uint256 maker, uint256 taker, uint256 token, uint256 amount, uint256 safetyDeposit, uint256 timelocks)';
+const ORDER =
+  'tuple(uint256 salt, uint256 maker, uint256 receiver, uint256 makerAsset, uint256 takerAsset, uint256 makingAmount, uint256 takingAmount, uint256 makerTraits)';
+
+export const RESOLVER_ABI = [
+  `function deploySrc(${IMMUTABLES} immutables, ${ORDER} order, bytes32 r, bytes32 vs, uint256 amount, uint256 takerTraits, bytes args) payable`,
+  `function deployDst(${IMMUTABLES} dstImmutables, uint256 srcCancellationTimestamp) payable`,
+  `function withdraw(address escrow, bytes32 secret, ${IMMUTABLES} immutables)`,
+  `function cancel(address escrow, ${IMMUTABLES} immutables)`,
+];
+
+/** Timelock stages, packed in the same order on both chains (SrcWithdrawal ... DstCancellation) */
+const STAGES = 7;
+
+/** The fields of a Stellar escrow's `get_immutables` the EVM escrow shares */
+export interface StellarEscrowImmutables {
+  orderHash: BytesLike;
+  hashlock: BytesLike;
+  /** Stellar packing: stage i's offset (seconds) in bits 8*i */
+  timelocks: bigint;
+}
+
+/** The EVM side of the swap, in EVM units (token decimals differ between chains) */
+export interface EvmCounterparty {
+  /** 20-byte EVM addresses or 32-byte `Address` words */
+  maker: string;
+  taker: string;
+  token: string;
+  amount: bigint;
+  safetyDeposit: bigint;
+}
+
+/** IBaseEscrow.Immutables */
+export interface EvmImmutables {
+  orderHash: string;
+  hashlock: string;
+  maker: bigint;
+  taker: bigint;
+  token: bigint;
+  amount: bigint;
+  safetyDeposit: bigint;
+  timelocks: bigint;
+}
+
+/** IOrderMixin.Order; addresses as in EvmCounterparty */
+export interface EvmOrder {
+  salt: bigint;
+  maker: string;
+  receiver: string;
+  makerAsset: string;
+  takerAsset: string;
+  makingAmount: bigint;
+  takingAmount: bigint;
+  makerTraits: bigint;
+}
+
+/** An `Address` word: EVM addresses are checksum-validated, 32-byte words taken as is */
+export function addressWord(address: string): bigint {
+  const length = (address.length - 2) / 2;
+  if (length === 20) {
+    return BigInt(getAddress(address));
+  }
+  if (length === 32 && address.startsWith('0x')) {
+    return BigInt(address);
+  }
+  throw new Error(`not an EVM address or 32-byte word: ${address}`);
+}
+
+/** Stage offsets of Stellar-packed timelocks */
+export function unpackStellarTimelocks(packed: bigint): number[] {
+  return Array.from({ length: STAGES }, (_, stage) => Number((packed >> BigInt(8 * stage)) & 0xffn));
+}
+
+/** TimelocksLib packing: `deployedAt` in bits 224-255, stage i's uint32 offset in bits 32*i */
+export function packEvmTimelocks(deployedAt: number, offsets: number[]): bigint {
+  if (offsets.length !== STAGES) {
+    throw new Error(`expected ${STAGES} stage offsets, got ${offsets.length}`);
+  }
+  return offsets.reduce(
+    (packed, offset, stage) => packed | (BigInt(offset >>> 0) << BigInt(32 * stage)),
+    BigInt(deployedAt >>> 0) << 224n,
+  );
+}
+
+/**
+ * EVM immutables mirroring a Stellar escrow: same order hash, hashlock and stage offsets,
+ * with the EVM parties and amounts
+ * `deployedAt` is 0 for deploy calls (the EVM side stamps it) and the EVM escrow's deployment
+ * timestamp for withdraw and cancel
+ */
+export function toEvmImmutables(stellar: StellarEscrowImmutables, evm: EvmCounterparty, deployedAt = 0): EvmImmutables {
+  return {
+    orderHash: hexlify(stellar.orderHash),
+    hashlock: hexlify(stellar.hashlock),
+    maker: addressWord(evm.maker),
+    taker: addressWord(evm.taker),
+    token: addressWord(evm.token),
+    amount: evm.amount,
+    safetyDeposit: evm.safetyDeposit,
+    timelocks: packEvmTimelocks(deployedAt, unpackStellarTimelocks(stellar.timelocks)),
+  };
+}
+
+/** `abi.encode(immutables)` */
+export function encodeImmutables(immutables: EvmImmutables): string {
+  return AbiCoder.defaultAbiCoder().encode([IMMUTABLES], [immutables]);
+}
+
+/** `ImmutablesLib.hash`, the EVM factory's escrow salt */
+export function immutablesHash(immutables: EvmImmutables): string {
+  return keccak256(encodeImmutables(immutables));
+}
+
+export class EvmMirrorPlanner {
+  private readonly iface = new Interface(RESOLVER_ABI);
+
+  constructor(public readonly resolver: string) {}
+
+  /** Fill `order` and deploy its source escrow; the safety deposit is sent as value */
+  public deploySrc(
+    immutables: EvmImmutables,
+    order: EvmOrder,
+    signature: string,
+    amount: bigint,
+    takerTraits: bigint,
+    args: BytesLike = '0x',
+  ): TransactionRequest {
+    const { r, yParityAndS: vs } = Signature.from(signature);
+    const encodedOrder = {
+      ...order,
+      maker: addressWord(order.maker),
+      receiver: addressWord(order.receiver),
+      makerAsset: addressWord(order.makerAsset),
+      takerAsset: addressWord(order.takerAsset),
+    };
+    return this.call('deploySrc', [immutables, encodedOrder, r, vs, amount, takerTraits, args], immutables.safetyDeposit);
+  }
+
+  /** Deploy the destination escrow; the safety deposit is sent as value */
+  public deployDst(immutables: EvmImmutables, srcCancellationTimestamp: bigint): TransactionRequest {
+    return this.call('deployDst', [immutables, srcCancellationTimestamp], immutables.safetyDeposit);
+  }
+
+  /** Withdraw `escrow` with the secret revealed on Stellar */
+  public withdraw(escrow: string, secret: BytesLike, immutables: EvmImmutables): TransactionRequest {
+    return this.call('withdraw', [getAddress(escrow), secret, immutables]);
+  }
+
+  public cancel(escrow: string, immutables: EvmImmutables): TransactionRequest {
+    return this.call('cancel', [getAddress(escrow), immutables]);
+  }
+
+  private call(method: string, args: unknown[], value?: bigint): TransactionRequest {
+    const request: TransactionRequest = { to: this.resolver, data: this.iface.encodeFunctionData(method, args) };
+    if (value !== undefined) {
+      request.value = value;
+    }
+    return request;
+  }
+}
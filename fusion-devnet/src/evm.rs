@@ -0,0 +1,103 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::nodes::EVM_RPC_URL;
+use crate::run_tool;
+
+/// A funded anvil account
+pub struct Account {
+    pub address: &'static str,
+    pub key: &'static str,
+}
+
+/// anvil's first two default accounts (mnemonic "test test test ... junk")
+pub const USER: Account = Account {
+    address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+    key: "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+};
+pub const RESOLVER: Account = Account {
+    address: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+    key: "0x59c6995e998f97a5a0044966f0945389dc9e86dae88e7a8412f4603b6b78690d",
+};
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+const IMMUTABLES: &str = "(bytes32,address,uint256,address,bytes32,uint256,address,address)";
+
+/// Compile the reference contracts and deploy HTLCEscrowFactory from the user account
+pub fn deploy_factory(root: &Path) -> Result<String, String> {
+    run_tool(Command::new("npx").args(["hardhat", "compile"]).current_dir(root))?;
+
+    let path = root.join("artifacts/contracts/HTLCEscrowFactory.sol/HTLCEscrowFactory.json");
+    let artifact = std::fs::read_to_string(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let artifact: serde_json::Value = serde_json::from_str(&artifact).map_err(|err| format!("{}: {err}", path.display()))?;
+    let bytecode = artifact["bytecode"].as_str().ok_or("artifact has no bytecode")?;
+
+    let receipt = send(&USER, &["--create", bytecode])?;
+    receipt["contractAddress"].as_str().map(str::to_string).ok_or_else(|| "no contract address in receipt".into())
+}
+
+/// Deploy an ETH escrow through the factory and return its address
+/// `maker` deposits and is refunded after `timelock`; withdrawals pay `taker`
+pub fn deploy_escrow(
+    factory: &str,
+    order_hash: &str,
+    hashlock: &str,
+    amount: u128,
+    timelock: u64,
+    maker: &Account,
+    taker: &Account,
+) -> Result<String, String> {
+    let immutables = format!(
+        "({order_hash},{ZERO_ADDRESS},{amount},{ZERO_ADDRESS},{hashlock},{timelock},{},{})",
+        maker.address, taker.address
+    );
+    send(maker, &[factory, &format!("deployEscrow({IMMUTABLES})"), &immutables])?;
+    call(factory, "predictEscrow(bytes32)(address)", &[order_hash])
+}
+
+/// Lock `amount` wei in an escrow (its maker only)
+pub fn deposit(escrow: &str, maker: &Account, amount: u128) -> Result<(), String> {
+    send(maker, &[escrow, "deposit()", "--value", &amount.to_string()]).map(|_| ())
+}
+
+/// Withdraw to the escrow's taker by revealing the secret (anyone)
+pub fn withdraw(escrow: &str, secret: &str, sender: &Account) -> Result<(), String> {
+    send(sender, &[escrow, "withdraw(bytes32)", secret]).map(|_| ())
+}
+
+/// The secret a withdrawal revealed (zero until then)
+pub fn revealed_secret(escrow: &str) -> Result<String, String> {
+    call(escrow, "revealedSecret()(bytes32)", &[])
+}
+
+pub fn is_withdrawn(escrow: &str) -> Result<bool, String> {
+    call(escrow, "withdrawn()(bool)", &[]).map(|withdrawn| withdrawn == "true")
+}
+
+/// keccak256 of a hex word, as the reference escrow hashes secrets
+pub fn keccak(word: &str) -> Result<String, String> {
+    run_tool(Command::new("cast").args(["keccak", word]))
+}
+
+/// Latest block timestamp, for timelocks relative to the chain's clock
+pub fn timestamp() -> Result<u64, String> {
+    let block = run_tool(Command::new("cast").args(["block", "latest", "--field", "timestamp", "--rpc-url", EVM_RPC_URL]))?;
+    block.parse().map_err(|err| format!("block timestamp {block}: {err}"))
+}
+
+/// Send a transaction and return its receipt, failing on a reverted one
+fn send(sender: &Account, args: &[&str]) -> Result<serde_json::Value, String> {
+    let receipt = run_tool(Command::new("cast")
+        .arg("send")
+        .args(args)
+        .args(["--private-key", sender.key, "--rpc-url", EVM_RPC_URL, "--json"]))?;
+    let receipt: serde_json::Value = serde_json::from_str(&receipt).map_err(|err| format!("cast send receipt: {err}"))?;
+    if receipt["status"] != "0x1" {
+        return Err(format!("transaction {} reverted", receipt["transactionHash"]));
+    }
+    Ok(receipt)
+}
+
+fn call(contract: &str, signature: &str, args: &[&str]) -> Result<String, String> {
+    run_tool(Command::new("cast").args(["call", contract, signature]).args(args).args(["--rpc-url", EVM_RPC_URL]))
+}
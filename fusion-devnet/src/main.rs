@@ -0,0 +1,108 @@
+//! fusion-devnet up
+//! fusion-devnet run [--keep] [--no-nodes]
+//! fusion-devnet down
+//!
+//! Reproduces a full Fusion+ deployment locally: a Stellar quickstart node (local network with
+//! Soroban RPC and friendbot) and an anvil EVM node in Docker, the escrow, factory and resolver
+//! contracts, and the reference EVM HTLCEscrowFactory. `run` then swaps in both directions and
+//! reports each leg, exiting non-zero if any swap didn't complete.
+//!
+//! `--keep` leaves the nodes running afterwards; `--no-nodes` uses nodes that are already up
+//! (from `fusion-devnet up`). Needs docker, the Stellar CLI (`stellar`, overridden with
+//! `STELLAR_CLI`), Foundry's `cast` and the repository's hardhat toolchain.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+mod evm;
+mod nodes;
+mod stellar;
+mod swap;
+
+const USAGE: &str = "usage: fusion-devnet <up|down|run [--keep] [--no-nodes]>";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("fusion-devnet: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns whether every swap completed
+fn run() -> Result<bool, String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, flags) = args.split_first().ok_or(USAGE)?;
+    let flag = |name: &str| flags.iter().any(|flag| flag == name);
+    if let Some(unknown) = flags.iter().find(|flag| !["--keep", "--no-nodes"].contains(&flag.as_str())) {
+        return Err(format!("unknown flag {unknown}\n{USAGE}"));
+    }
+
+    match command.as_str() {
+        "up" => nodes::up().map(|()| true),
+        "down" => nodes::down().map(|()| true),
+        "run" => {
+            if !flag("--no-nodes") {
+                nodes::up()?;
+            }
+            let result = scripted_swaps();
+            if !flag("--keep") && !flag("--no-nodes") {
+                nodes::down()?;
+            }
+            result
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Deploy everything, swap both ways and print the report
+fn scripted_swaps() -> Result<bool, String> {
+    let root = repo_root();
+    let stellar = stellar::deploy(&root)?;
+    let evm_factory = evm::deploy_factory(&root)?;
+    println!("stellar factory {}\nstellar resolver {}\nevm factory {evm_factory}", stellar.factory, stellar.resolver);
+
+    let reports = [
+        swap::evm_to_stellar(&stellar, &evm_factory)?,
+        swap::stellar_to_evm(&stellar, &evm_factory)?,
+    ];
+    for report in &reports {
+        println!("{report}");
+    }
+    Ok(reports.iter().all(|report| report.completed()))
+}
+
+/// Run a tool and return its trimmed stdout
+pub fn run_tool(command: &mut Command) -> Result<String, String> {
+    let description = format!("{} {}", command.get_program().to_string_lossy(), command
+        .get_args()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" "));
+    let output = command.output().map_err(|err| format!("{description}: {err}"))?;
+    if !output.status.success() {
+        return Err(format!("{description}: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 32 random bytes, for secrets and order hashes
+pub fn random_word() -> Result<[u8; 32], String> {
+    use std::io::Read;
+    let mut word = [0u8; 32];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut word))
+        .map_err(|err| format!("/dev/urandom: {err}"))?;
+    Ok(word)
+}
+
+/// The repository root (fusion-devnet lives one level below it)
+fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("fusion-devnet is inside the repository")
+        .to_path_buf()
+}
@@ -0,0 +1,92 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::run_tool;
+
+const STELLAR_CONTAINER: &str = "fusion-devnet-stellar";
+const STELLAR_IMAGE: &str = "stellar/quickstart:latest";
+const STELLAR_HOST: &str = "localhost:8000";
+pub const STELLAR_RPC_URL: &str = "http://localhost:8000/soroban/rpc";
+
+const EVM_CONTAINER: &str = "fusion-devnet-anvil";
+const EVM_IMAGE: &str = "ghcr.io/foundry-rs/foundry:latest";
+const EVM_HOST: &str = "localhost:8545";
+pub const EVM_RPC_URL: &str = "http://localhost:8545";
+
+/// Quickstart takes a while to start Horizon, RPC and friendbot
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(240);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Start both nodes, replacing containers left from an earlier run, and wait until they answer
+pub fn up() -> Result<(), String> {
+    down()?;
+    docker(&[
+        "run", "-d", "--rm", "--name", STELLAR_CONTAINER, "-p", "8000:8000", STELLAR_IMAGE,
+        "--local", "--enable-soroban-rpc",
+    ])?;
+    docker(&[
+        "run", "-d", "--rm", "--name", EVM_CONTAINER, "-p", "8545:8545", "--entrypoint", "anvil", EVM_IMAGE,
+        "--host", "0.0.0.0",
+    ])?;
+
+    wait_until("anvil", || {
+        rpc(EVM_HOST, "/", "eth_chainId").is_ok_and(|response| response.contains("\"result\""))
+    })?;
+    wait_until("Stellar RPC", || {
+        rpc(STELLAR_HOST, "/soroban/rpc", "getHealth").is_ok_and(|response| response.contains("healthy"))
+    })?;
+    wait_until("friendbot", || get(STELLAR_HOST, "/friendbot").is_ok_and(|response| !response.contains(" 502 ")))?;
+    println!("nodes up: {STELLAR_RPC_URL}, {EVM_RPC_URL}");
+    Ok(())
+}
+
+/// Remove both containers; missing ones are ignored
+pub fn down() -> Result<(), String> {
+    for container in [STELLAR_CONTAINER, EVM_CONTAINER] {
+        let running = docker(&["ps", "-aq", "--filter", &format!("name=^{container}$")])?;
+        if !running.is_empty() {
+            docker(&["rm", "-f", container])?;
+        }
+    }
+    Ok(())
+}
+
+fn docker(args: &[&str]) -> Result<String, String> {
+    run_tool(Command::new("docker").args(args))
+}
+
+fn wait_until(name: &str, ready: impl Fn() -> bool) -> Result<(), String> {
+    let started = Instant::now();
+    while !ready() {
+        if started.elapsed() > STARTUP_TIMEOUT {
+            return Err(format!("{name} did not come up within {}s", STARTUP_TIMEOUT.as_secs()));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// JSON-RPC call without parameters, returning the raw HTTP response
+fn rpc(host: &str, path: &str, method: &str) -> std::io::Result<String> {
+    let body = format!(r#"{{"jsonrpc":"2.0","id":1,"method":"{method}","params":[]}}"#);
+    http(host, &format!(
+        "POST {path} HTTP/1.0\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    ))
+}
+
+fn get(host: &str, path: &str) -> std::io::Result<String> {
+    http(host, &format!("GET {path} HTTP/1.0\r\nHost: {host}\r\n\r\n"))
+}
+
+/// Readiness probes only need the status line and body, so plain HTTP/1.0 over TCP does
+fn http(host: &str, request: &str) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(host)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(request.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::nodes::STELLAR_RPC_URL;
+use crate::run_tool;
+
+const NETWORK: &str = "fusion-devnet";
+const PASSPHRASE: &str = "Standalone Network ; February 2017";
+const WASM_TARGET: &str = "wasm32v1-none";
+
+/// Identities created in the Stellar CLI and funded by friendbot
+pub const ADMIN: &str = "devnet-admin";
+pub const USER: &str = "devnet-user";
+pub const RESOLVER: &str = "devnet-resolver";
+
+/// Contracts and accounts of the Stellar side
+pub struct StellarDeployment {
+    pub factory: String,
+    pub resolver: String,
+    /// Native XLM Stellar Asset Contract, the token both swaps escrow
+    pub native: String,
+    pub user: String,
+    pub resolver_account: String,
+}
+
+/// Fund the identities and deploy the escrow WASM, factory and resolver
+pub fn deploy(root: &Path) -> Result<StellarDeployment, String> {
+    cli(&["network", "add", NETWORK, "--rpc-url", STELLAR_RPC_URL, "--network-passphrase", PASSPHRASE])?;
+    for identity in [ADMIN, USER, RESOLVER] {
+        cli(&["keys", "generate", identity, "--network", NETWORK, "--fund", "--overwrite"])?;
+    }
+    let admin = address(ADMIN)?;
+    let resolver_account = address(RESOLVER)?;
+
+    // The reference EVM escrow checks keccak256(secret), so the Stellar escrow must hash the same way
+    let escrow_wasm = build_wasm(root, "stellar-fusion", "stellar_escrow", Some("hash-keccak256"))?;
+    let factory_wasm = build_wasm(root, "stellar-fusion-factory", "stellar_escrow_factory", None)?;
+    let resolver_wasm = build_wasm(root, "stellar-resolver", "stellar_resolver", None)?;
+
+    let escrow_hash = last_line(&cli(&["contract", "upload", "--wasm", &escrow_wasm, "--source", ADMIN, "--network", NETWORK])?);
+    let factory = last_line(&cli(&["contract", "deploy", "--wasm", &factory_wasm, "--source", ADMIN, "--network", NETWORK])?);
+    invoke(&factory, ADMIN, "initialize", &[("admin", &admin), ("htlc_wasm_hash", &escrow_hash)])?;
+
+    let resolver = last_line(&cli(&["contract", "deploy", "--wasm", &resolver_wasm, "--source", RESOLVER, "--network", NETWORK])?);
+    invoke(&resolver, RESOLVER, "initialize", &[
+        ("owner", &resolver_account),
+        ("htlc_contract", &factory),
+        ("factory", &factory),
+    ])?;
+
+    // Fails once the native SAC exists, which is fine
+    let _ = cli(&["contract", "asset", "deploy", "--asset", "native", "--source", ADMIN, "--network", NETWORK]);
+    let native = cli(&["contract", "id", "asset", "--asset", "native", "--network", NETWORK])?;
+
+    Ok(StellarDeployment { factory, resolver, native, user: address(USER)?, resolver_account })
+}
+
+/// Invoke a contract function as `source`, returning its result as printed by the CLI
+pub fn invoke(contract: &str, source: &str, function: &str, args: &[(&str, &str)]) -> Result<String, String> {
+    let mut command = vec![
+        "contract".to_string(), "invoke".into(),
+        "--id".into(), contract.into(),
+        "--source".into(), source.into(),
+        "--network".into(), NETWORK.into(),
+        "--".into(), function.into(),
+    ];
+    for (name, value) in args {
+        command.push(format!("--{name}"));
+        command.push(value.to_string());
+    }
+    let command: Vec<&str> = command.iter().map(String::as_str).collect();
+    cli(&command).map(|result| result.trim_matches('"').to_string())
+}
+
+/// Public key of a CLI identity
+pub fn address(identity: &str) -> Result<String, String> {
+    cli(&["keys", "address", identity])
+}
+
+fn cli(args: &[&str]) -> Result<String, String> {
+    let program = std::env::var("STELLAR_CLI").unwrap_or_else(|_| "stellar".into());
+    run_tool(Command::new(program).args(args))
+}
+
+/// Build a contract crate for the wasm target and return the path of its wasm
+fn build_wasm(root: &Path, dir: &str, artifact: &str, feature: Option<&str>) -> Result<String, String> {
+    let crate_dir = root.join(dir);
+    let mut command = Command::new("cargo");
+    command.args(["build", "--release", "--target", WASM_TARGET]).current_dir(&crate_dir);
+    if let Some(feature) = feature {
+        command.args(["--features", feature]);
+    }
+    run_tool(&mut command)?;
+
+    let wasm = crate_dir.join("target").join(WASM_TARGET).join("release").join(format!("{artifact}.wasm"));
+    wasm.to_str().map(str::to_string).ok_or_else(|| format!("non-UTF-8 path {}", wasm.display()))
+}
+
+/// Deploy and upload print progress before the id or hash they return
+fn last_line(output: &str) -> String {
+    output.lines().last().unwrap_or_default().trim().to_string()
+}
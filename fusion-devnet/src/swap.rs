@@ -0,0 +1,145 @@
+//! Scripted swaps over the devnet contracts
+//!
+//! Each swap locks native XLM on Stellar and ETH on anvil under one keccak256 hashlock. The
+//! receiving side withdraws first, revealing the secret, and the resolver's leg uses the secret
+//! read back from that chain, the way a relayer would.
+
+use std::fmt;
+
+use crate::evm::{self, Account};
+use crate::random_word;
+use crate::stellar::{self, StellarDeployment};
+
+/// 1 XLM
+const XLM_AMOUNT: &str = "10000000";
+
+/// 0.01 ETH
+const WEI_AMOUNT: u128 = 10_000_000_000_000_000;
+
+/// Seconds until the EVM maker may refund, longer than a devnet swap takes
+const EVM_TIMELOCK: u64 = 3600;
+
+/// Escrow State::Withdrawn as the CLI prints it
+const STELLAR_WITHDRAWN: &str = "1";
+
+/// Outcome of one scripted swap
+pub struct SwapReport {
+    pub direction: &'static str,
+    pub stellar_escrow: String,
+    pub evm_escrow: String,
+    pub stellar_withdrawn: bool,
+    pub evm_withdrawn: bool,
+}
+
+impl SwapReport {
+    pub fn completed(&self) -> bool {
+        self.stellar_withdrawn && self.evm_withdrawn
+    }
+}
+
+impl fmt::Display for SwapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let leg = |withdrawn: bool| if withdrawn { "withdrawn" } else { "NOT withdrawn" };
+        writeln!(f, "{}: {}", self.direction, if self.completed() { "completed" } else { "FAILED" })?;
+        writeln!(f, "  stellar escrow {} {}", self.stellar_escrow, leg(self.stellar_withdrawn))?;
+        write!(f, "  evm escrow     {} {}", self.evm_escrow, leg(self.evm_withdrawn))
+    }
+}
+
+/// The user sells ETH for XLM: the user locks on anvil, the resolver on Stellar
+pub fn evm_to_stellar(stellar: &StellarDeployment, evm_factory: &str) -> Result<SwapReport, String> {
+    let swap = Secrets::new()?;
+
+    let evm_escrow = lock_evm(evm_factory, &swap, &evm::USER, &evm::RESOLVER)?;
+    let stellar_escrow = lock_stellar(stellar, &swap, stellar::RESOLVER, stellar::USER, &stellar.resolver_account, &stellar.user)?;
+
+    // The user claims their XLM, revealing the secret on Stellar
+    stellar::invoke(&stellar_escrow, stellar::USER, "withdraw", &[("secret", &swap.secret_hex())])?;
+    let revealed = stellar::invoke(&stellar_escrow, stellar::RESOLVER, "get_revealed_secret", &[])?;
+    evm::withdraw(&evm_escrow, &format!("0x{revealed}"), &evm::RESOLVER)?;
+
+    report("evm -> stellar", stellar_escrow, evm_escrow)
+}
+
+/// The user sells XLM for ETH: the user locks on Stellar, the resolver on anvil
+pub fn stellar_to_evm(stellar: &StellarDeployment, evm_factory: &str) -> Result<SwapReport, String> {
+    let swap = Secrets::new()?;
+
+    let stellar_escrow = lock_stellar(stellar, &swap, stellar::USER, stellar::RESOLVER, &stellar.user, &stellar.resolver_account)?;
+    let evm_escrow = lock_evm(evm_factory, &swap, &evm::RESOLVER, &evm::USER)?;
+
+    // The user claims their ETH, revealing the secret on anvil
+    evm::withdraw(&evm_escrow, &swap.secret, &evm::USER)?;
+    let revealed = evm::revealed_secret(&evm_escrow)?;
+    stellar::invoke(&stellar_escrow, stellar::RESOLVER, "withdraw", &[("secret", revealed.trim_start_matches("0x"))])?;
+
+    report("stellar -> evm", stellar_escrow, evm_escrow)
+}
+
+/// A swap's secret, hashlock and order hash, as 0x-prefixed hex
+struct Secrets {
+    secret: String,
+    hashlock: String,
+    order_hash: String,
+}
+
+impl Secrets {
+    fn new() -> Result<Self, String> {
+        let secret = format!("0x{}", hex::encode(random_word()?));
+        Ok(Self {
+            hashlock: evm::keccak(&secret)?,
+            order_hash: format!("0x{}", hex::encode(random_word()?)),
+            secret,
+        })
+    }
+
+    fn secret_hex(&self) -> String {
+        self.secret.trim_start_matches("0x").to_string()
+    }
+}
+
+/// Deploy and fund an EVM escrow paying `taker` and refunding `maker`, who funds it
+fn lock_evm(factory: &str, swap: &Secrets, maker: &Account, taker: &Account) -> Result<String, String> {
+    let timelock = evm::timestamp()? + EVM_TIMELOCK;
+    let escrow = evm::deploy_escrow(factory, &swap.order_hash, &swap.hashlock, WEI_AMOUNT, timelock, maker, taker)?;
+    evm::deposit(&escrow, maker, WEI_AMOUNT)?;
+    Ok(escrow)
+}
+
+/// Deploy a native XLM escrow through the factory and fund it from `maker`
+/// The factory requires the taker's auth on deploys, so `deployer` is the taker's identity
+/// Timelocks of 0 open every stage at once; the devnet only exercises withdrawals
+fn lock_stellar(
+    stellar: &StellarDeployment,
+    swap: &Secrets,
+    funder: &str,
+    deployer: &str,
+    maker: &str,
+    taker: &str,
+) -> Result<String, String> {
+    let hex = |word: &str| word.trim_start_matches("0x").to_string();
+    let escrow = stellar::invoke(&stellar.factory, deployer, "deploy_escrow", &[
+        ("order_hash", &hex(&swap.order_hash)),
+        ("hashlock", &hex(&swap.hashlock)),
+        ("maker", maker),
+        ("taker", taker),
+        ("token", &stellar.native),
+        ("amount", XLM_AMOUNT),
+        ("safety_deposit", "0"),
+        ("timelocks", "0"),
+    ])?;
+    stellar::invoke(&stellar.native, funder, "transfer", &[("from", maker), ("to", &escrow), ("amount", XLM_AMOUNT)])?;
+    stellar::invoke(&escrow, funder, "confirm_funding", &[])?;
+    Ok(escrow)
+}
+
+fn report(direction: &'static str, stellar_escrow: String, evm_escrow: String) -> Result<SwapReport, String> {
+    let state = stellar::invoke(&stellar_escrow, stellar::USER, "get_state", &[])?;
+    Ok(SwapReport {
+        direction,
+        stellar_withdrawn: state == STELLAR_WITHDRAWN,
+        evm_withdrawn: evm::is_withdrawn(&evm_escrow)?,
+        stellar_escrow,
+        evm_escrow,
+    })
+}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use fusion_fuzz::{check_immutables_hash, ImmutablesInput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ImmutablesInput| check_immutables_hash(&input));
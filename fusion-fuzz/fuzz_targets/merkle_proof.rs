@@ -0,0 +1,6 @@
+#![no_main]
+
+use fusion_fuzz::{check_merkle_proof, MerkleInput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: MerkleInput| check_merkle_proof(&input));
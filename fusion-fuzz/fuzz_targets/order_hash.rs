@@ -0,0 +1,6 @@
+#![no_main]
+
+use fusion_fuzz::{check_order_hash, OrderInput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: OrderInput| check_order_hash(&input));
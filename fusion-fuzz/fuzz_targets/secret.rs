@@ -0,0 +1,6 @@
+#![no_main]
+
+use fusion_fuzz::{check_secret, SecretInput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: SecretInput| check_secret(&input));
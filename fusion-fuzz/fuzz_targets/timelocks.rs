@@ -0,0 +1,6 @@
+#![no_main]
+
+use fusion_fuzz::{check_timelocks, TimelocksInput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: TimelocksInput| check_timelocks(&input));
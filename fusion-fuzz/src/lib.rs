@@ -0,0 +1,312 @@
+//! Fuzz targets for the canonical encoders and decoders
+//!
+//! Each target in `fuzz_targets/` turns libFuzzer's bytes into one of the inputs below and runs
+//! its check. A check panics only when an invariant breaks: escrow calls must succeed or fail
+//! with one of the escrow's contract errors (a host abort means a hostile input reached a panic
+//! outside the intended error paths), dry runs must predict the real call, and the shared
+//! encoders must agree with each other.
+
+use arbitrary::Arbitrary;
+use fusion_shared::conversion::{derive_salt, evm_timelock, evm_timelocks};
+use fusion_shared::{merkle, order, DstEscrowParams, EvmAddress, Order};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::xdr::{FromXdr, ScErrorType};
+use soroban_sdk::{token, Address, Bytes, BytesN, Env, Vec};
+use stellar_escrow::{StellarEscrow, StellarEscrowClient};
+
+/// Most leaves and proof nodes a Merkle input keeps; deeper trees only slow the fuzzer down
+const MAX_NODES: usize = 16;
+
+/// Longest preimage tried; the escrow accepts at most 64 bytes
+const MAX_PREIMAGE: usize = 96;
+
+/// A Stellar address from arbitrary bytes: a contract id, or an account key
+#[derive(Arbitrary, Debug, Clone)]
+pub struct AddressInput {
+    pub key: [u8; 32],
+    pub account: bool,
+}
+
+impl AddressInput {
+    pub fn to_address(&self, env: &Env) -> Address {
+        let mut xdr = std::vec![0, 0, 0, 18];
+        xdr.extend_from_slice(if self.account { &[0, 0, 0, 0, 0, 0, 0, 0] } else { &[0, 0, 0, 1] });
+        xdr.extend_from_slice(&self.key);
+        Address::from_xdr(env, &Bytes::from_slice(env, &xdr)).expect("address XDR")
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct ImmutablesInput {
+    pub order_hash: [u8; 32],
+    pub hashlock: [u8; 32],
+    pub maker: AddressInput,
+    pub taker: AddressInput,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub timelocks: u64,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct TimelocksInput {
+    pub timelocks: u64,
+    pub now: u64,
+    pub caller_is_maker: bool,
+    /// EVM side: `TimelocksLib` word fields
+    pub deployed_at: u32,
+    pub offsets: [u32; 7],
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct SecretInput {
+    pub secret: [u8; 32],
+    pub preimage: std::vec::Vec<u8>,
+    /// Withdraw with the escrow's real secret instead of `secret`
+    pub use_real_secret: bool,
+    /// Seconds past the withdrawal stage (0-255), so some inputs land in later stages
+    pub delay: u8,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct OrderInput {
+    pub verifying_contract: AddressInput,
+    pub maker: AddressInput,
+    pub taker: AddressInput,
+    pub token: AddressInput,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub hashlock: [u8; 32],
+    pub timelocks: u64,
+    pub salt: u64,
+    pub nonce: u64,
+    pub chain_id: u64,
+    pub dst_maker: [u8; 20],
+    pub dst_token: [u8; 20],
+    pub dst_amount: i128,
+    pub dst_safety_deposit: i128,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct MerkleInput {
+    pub secret_hashes: std::vec::Vec<[u8; 32]>,
+    pub index: u8,
+    pub root: [u8; 32],
+    pub leaf: [u8; 32],
+    pub proof: std::vec::Vec<[u8; 32]>,
+}
+
+fn env() -> Env {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+    env
+}
+
+/// The value of a `try_` call that succeeded, None if it failed with a contract error
+fn settled<T, C: std::fmt::Debug, I: std::fmt::Debug>(
+    call: &str,
+    result: Result<Result<T, C>, Result<soroban_sdk::Error, I>>,
+) -> Option<T> {
+    match result {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(conversion)) => panic!("{call} returned an undecodable value: {conversion:?}"),
+        Err(Ok(error)) if error.is_type(ScErrorType::Contract) => None,
+        Err(error) => panic!("{call} aborted outside the escrow's error paths: {error:?}"),
+    }
+}
+
+/// Contract error code of a failed `try_` call
+fn error_code<T, C, I>(result: &Result<Result<T, C>, Result<soroban_sdk::Error, I>>) -> Option<u32> {
+    match result {
+        Err(Ok(error)) if error.is_type(ScErrorType::Contract) => Some(error.get_code()),
+        _ => None,
+    }
+}
+
+/// A funded escrow with `timelocks`, and its secret
+fn funded_escrow(env: &Env, timelocks: u64) -> (StellarEscrowClient<'_>, BytesN<32>) {
+    let maker = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    let (secret, hashlock) = stellar_escrow::testutils::secret_and_hashlock(env, 42);
+    let client = StellarEscrowClient::new(env, &env.register(StellarEscrow, ()));
+    client.deploy(
+        &BytesN::from_array(env, &[1; 32]),
+        &hashlock,
+        &maker,
+        &Address::generate(env),
+        &token,
+        &1000,
+        &0,
+        &timelocks,
+    );
+    token::StellarAssetClient::new(env, &token).mint(&client.address, &1000);
+    client.confirm_funding();
+    (client, secret)
+}
+
+/// Deploy with arbitrary immutables; accepted ones must hash like the EVM side
+pub fn check_immutables_hash(input: &ImmutablesInput) {
+    let env = env();
+    let order_hash = BytesN::from_array(&env, &input.order_hash);
+    let hashlock = BytesN::from_array(&env, &input.hashlock);
+    let (maker, taker) = (input.maker.to_address(&env), input.taker.to_address(&env));
+    let token = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+
+    let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+    let deployed = client.try_deploy(
+        &order_hash,
+        &hashlock,
+        &maker,
+        &taker,
+        &token,
+        &input.amount,
+        &input.safety_deposit,
+        &input.timelocks,
+    );
+    if settled("deploy", deployed).is_none() {
+        return;
+    }
+
+    let encoded = client.get_immutables_evm();
+    assert_eq!(encoded.len(), 8 * 32, "one ABI word per immutables field");
+    let salt = derive_salt(
+        &env,
+        &order_hash,
+        &hashlock,
+        &maker,
+        &taker,
+        &token,
+        input.amount,
+        input.safety_deposit,
+        input.timelocks,
+    );
+    assert_eq!(BytesN::<32>::from(env.crypto().keccak256(&encoded)), salt);
+    assert_eq!(client.get_immutables_hash(), client.get_immutables_hash());
+}
+
+/// Unpack arbitrary timelocks at an arbitrary time, on both chains' layouts
+pub fn check_timelocks(input: &TimelocksInput) {
+    let env = env();
+    let word = evm_timelocks(&env, input.deployed_at, input.offsets);
+    for (stage, offset) in input.offsets.iter().enumerate() {
+        assert_eq!(evm_timelock(&word, stage as u8), input.deployed_at as u64 + *offset as u64);
+    }
+
+    let (client, secret) = funded_escrow(&env, input.timelocks);
+    env.ledger().with_mut(|li| li.timestamp = input.now);
+    assert_eq!(client.get_timelocks(), input.timelocks);
+    settled("ping", client.try_ping());
+    settled("refresh_state", client.try_refresh_state());
+
+    // The dry run must predict the real call, error code included
+    let immutables = client.get_immutables();
+    let caller = if input.caller_is_maker { immutables.maker } else { Address::generate(&env) };
+    let predicted = client.dry_run_cancel(&caller);
+    let cancelled = client.try_cancel(&caller);
+    assert_eq!(predicted.success, cancelled.is_ok(), "dry_run_cancel at {}", input.now);
+    assert_eq!(predicted.error, error_code(&cancelled));
+    settled("cancel", cancelled);
+
+    let predicted = client.dry_run_withdraw(&secret);
+    let withdrawn = client.try_withdraw(&secret, &false);
+    assert_eq!(predicted.success, withdrawn.is_ok(), "dry_run_withdraw at {}", input.now);
+    assert_eq!(predicted.error, error_code(&withdrawn));
+    settled("withdraw", withdrawn);
+}
+
+/// Withdraw with arbitrary secrets and preimages once withdrawals are open
+pub fn check_secret(input: &SecretInput) {
+    let env = env();
+    let timelocks = stellar_escrow::testutils::default_timelocks();
+    let (client, real_secret) = funded_escrow(&env, timelocks);
+    stellar_escrow::testutils::advance_to_stage(&env, timelocks, 4);
+    env.ledger().with_mut(|li| li.timestamp += input.delay as u64);
+
+    let preimage = Bytes::from_slice(&env, &input.preimage[..input.preimage.len().min(MAX_PREIMAGE)]);
+    settled("withdraw_preimage", client.try_withdraw_preimage(&preimage));
+
+    let secret = if input.use_real_secret { real_secret } else { BytesN::from_array(&env, &input.secret) };
+    let predicted = client.dry_run_withdraw(&secret);
+    let withdrawn = client.try_withdraw(&secret, &false);
+    assert_eq!(predicted.success, withdrawn.is_ok(), "dry_run_withdraw");
+    assert_eq!(predicted.error, error_code(&withdrawn));
+    settled("withdraw", withdrawn);
+}
+
+/// Order hashes are deterministic and bind the verifying contract and every field fuzzed here
+pub fn check_order_hash(input: &OrderInput) {
+    let env = env();
+    let order = Order {
+        maker: input.maker.to_address(&env),
+        taker: input.taker.to_address(&env),
+        token: input.token.to_address(&env),
+        amount: input.amount,
+        safety_deposit: input.safety_deposit,
+        hashlock: BytesN::from_array(&env, &input.hashlock),
+        timelocks: input.timelocks,
+        salt: input.salt,
+        nonce: input.nonce,
+        dst: DstEscrowParams {
+            chain_id: input.chain_id,
+            maker: EvmAddress(BytesN::from_array(&env, &input.dst_maker)),
+            token: EvmAddress(BytesN::from_array(&env, &input.dst_token)),
+            amount: input.dst_amount,
+            safety_deposit: input.dst_safety_deposit,
+        },
+    };
+    let verifying_contract = input.verifying_contract.to_address(&env);
+    let hash = order::hash_order(&env, &verifying_contract, &order);
+    assert_eq!(hash, order::hash_order(&env, &verifying_contract, &order));
+
+    let resalted = Order { salt: order.salt.wrapping_add(1), ..order.clone() };
+    assert_ne!(hash, order::hash_order(&env, &verifying_contract, &resalted));
+    let renonced = Order { nonce: order.nonce.wrapping_add(1), ..order.clone() };
+    assert_ne!(hash, order::hash_order(&env, &verifying_contract, &renonced));
+    assert_ne!(hash, order::hash_order(&env, &Address::generate(&env), &order));
+}
+
+/// Arbitrary proofs verify only against the root they fold to; proofs of real leaves verify
+pub fn check_merkle_proof(input: &MerkleInput) {
+    let env = env();
+    let nodes = |items: &[[u8; 32]]| {
+        let mut nodes = Vec::new(&env);
+        for item in items.iter().take(MAX_NODES) {
+            nodes.push_back(BytesN::from_array(&env, item));
+        }
+        nodes
+    };
+
+    let root = BytesN::from_array(&env, &input.root);
+    let leaf = BytesN::from_array(&env, &input.leaf);
+    let proof = nodes(&input.proof);
+    let verified = merkle::verify_proof(&env, &root, &leaf, &proof);
+    assert_eq!(verified, merkle::process_proof(&env, &leaf, &proof) == root);
+    let escrow = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+    assert_eq!(escrow.verify_proof(&root, &leaf, &proof), verified);
+
+    if input.secret_hashes.is_empty() {
+        return;
+    }
+    let leaves: std::vec::Vec<BytesN<32>> = nodes(&input.secret_hashes).iter().enumerate()
+        .map(|(index, secret_hash)| merkle::secret_leaf(&env, index as u64, &secret_hash))
+        .collect();
+    let index = input.index as usize % leaves.len();
+
+    // Sorted-pair tree; an odd node out moves up a level unpaired
+    let mut proof = Vec::new(&env);
+    let (mut level, mut position) = (leaves.clone(), index);
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(position ^ 1) {
+            proof.push_back(sibling.clone());
+        }
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [a, b] => merkle::hash_pair(&env, a, b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        position /= 2;
+    }
+    assert!(merkle::verify_proof(&env, &level[0], &leaves[index], &proof));
+}
@@ -0,0 +1,127 @@
+use fusion_interop::{bytes, load, word, ImmutablesVector};
+use fusion_shared::conversion::{address_word, derive_salt, evm_timelock, evm_timelocks};
+use fusion_shared::hash::{HashFunction, Keccak256};
+use fusion_shared::merkle;
+use soroban_sdk::{xdr::FromXdr, Address, Bytes, BytesN, Env};
+use stellar_escrow::{StellarEscrow, StellarEscrowClient};
+
+fn bytes_n(env: &Env, hex: &str) -> BytesN<32> {
+    BytesN::from_array(env, &word(hex))
+}
+
+/// Stellar address whose 32-byte key is `hex`: an account for the taker, contracts otherwise,
+/// so both address kinds are covered
+fn address(env: &Env, hex: &str, account: bool) -> Address {
+    let mut xdr = vec![0, 0, 0, 18];
+    xdr.extend_from_slice(if account { &[0, 0, 0, 0, 0, 0, 0, 0] } else { &[0, 0, 0, 1] });
+    xdr.extend_from_slice(&word(hex));
+    Address::from_xdr(env, &Bytes::from_slice(env, &xdr)).unwrap()
+}
+
+struct Parties {
+    maker: Address,
+    taker: Address,
+    token: Address,
+}
+
+fn parties(env: &Env, vector: &ImmutablesVector) -> Parties {
+    Parties {
+        maker: address(env, &vector.maker, false),
+        taker: address(env, &vector.taker, true),
+        token: address(env, &vector.token, false),
+    }
+}
+
+#[test]
+fn test_address_words_match_reference() {
+    let env = Env::default();
+    for vector in load().immutables {
+        let parties = parties(&env, &vector);
+        assert_eq!(address_word(&env, &parties.maker).to_array(), word(&vector.maker), "{}", vector.name);
+        assert_eq!(address_word(&env, &parties.taker).to_array(), word(&vector.taker), "{}", vector.name);
+    }
+}
+
+#[test]
+fn test_salt_derivation_matches_immutables_hash() {
+    let env = Env::default();
+    for vector in load().immutables {
+        let parties = parties(&env, &vector);
+        let salt = derive_salt(
+            &env,
+            &bytes_n(&env, &vector.order_hash),
+            &bytes_n(&env, &vector.hashlock),
+            &parties.maker,
+            &parties.taker,
+            &parties.token,
+            vector.amount.parse().unwrap(),
+            vector.safety_deposit.parse().unwrap(),
+            vector.timelocks.parse().unwrap(),
+        );
+        assert_eq!(salt.to_array(), word(&vector.hash), "{}", vector.name);
+    }
+}
+
+#[test]
+fn test_escrow_immutables_encode_like_evm() {
+    let env = Env::default();
+    env.mock_all_auths();
+    for vector in load().immutables {
+        let parties = parties(&env, &vector);
+        let escrow = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        escrow.deploy(
+            &bytes_n(&env, &vector.order_hash),
+            &bytes_n(&env, &vector.hashlock),
+            &parties.maker,
+            &parties.taker,
+            &parties.token,
+            &vector.amount.parse().unwrap(),
+            &vector.safety_deposit.parse().unwrap(),
+            &vector.timelocks.parse().unwrap(),
+        );
+
+        let encoded = escrow.get_immutables_evm();
+        assert_eq!(encoded, Bytes::from_slice(&env, &bytes(&vector.abi_encoded)), "{}", vector.name);
+        assert_eq!(env.crypto().keccak256(&encoded).to_array(), word(&vector.hash), "{}", vector.name);
+    }
+}
+
+#[test]
+fn test_timelocks_pack_and_decode_like_timelocks_lib() {
+    let env = Env::default();
+    for vector in load().timelocks {
+        let packed = evm_timelocks(&env, vector.deployed_at, vector.offsets);
+        assert_eq!(packed.to_array(), word(&vector.packed), "{}", vector.name);
+        for (stage, time) in vector.stage_times.iter().enumerate() {
+            assert_eq!(evm_timelock(&packed, stage as u8), *time, "{} stage {stage}", vector.name);
+        }
+    }
+}
+
+#[test]
+fn test_keccak_hashlocks_match() {
+    let env = Env::default();
+    for vector in load().secrets {
+        let hashlock = Keccak256::digest(&env, &Bytes::from_slice(&env, &bytes(&vector.secret)));
+        assert_eq!(hashlock.to_array(), word(&vector.hashlock));
+    }
+}
+
+#[test]
+fn test_merkle_leaves_and_root_match_invalidator() {
+    let env = Env::default();
+    let vectors = load().merkle;
+    let leaves: Vec<BytesN<32>> = vectors.leaves.iter()
+        .map(|leaf| merkle::secret_leaf(&env, leaf.index, &bytes_n(&env, &leaf.secret_hash)))
+        .collect();
+    for (leaf, vector) in leaves.iter().zip(&vectors.leaves) {
+        assert_eq!(leaf.to_array(), word(&vector.leaf), "leaf {}", vector.index);
+    }
+
+    let root = merkle::hash_pair(
+        &env,
+        &merkle::hash_pair(&env, &leaves[0], &leaves[1]),
+        &merkle::hash_pair(&env, &leaves[2], &leaves[3]),
+    );
+    assert_eq!(root.to_array(), word(&vectors.root));
+}
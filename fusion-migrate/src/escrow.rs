@@ -0,0 +1,365 @@
+use stellar_xdr::curr::{LedgerKey, ScAddress, ScMap, ScMapEntry, ScVal};
+
+use crate::plan::MigrationPlan;
+use crate::snapshot::{data_key, field, instance_key, string, symbol, tuple, Snapshot};
+use crate::MigrateError;
+
+/// Persistent keys with a fixed name (see the escrow's storage module)
+pub const FIXED_KEYS: [&str; 24] = [
+    "immutables",
+    "state",
+    "funded_amt",
+    "funded_dep",
+    "filled",
+    "stages",
+    "details",
+    "pending_tot",
+    "terminal_at",
+    "finalized",
+    "payout_memo",
+    "side",
+    "used_idx",
+    "revealed",
+    "revealed_b",
+    "pending_pay",
+    "ttl_ext",
+    "exec_lock",
+    "sweep_sink",
+    "receipt",
+    "timelocks",
+    "executed",
+    "imm_ver",
+    "action_log",
+];
+
+/// Persistent keys of the form `(prefix, recipient)`
+const HOLDER_KEYS: [&str; 2] = ["pending_dep", "reward"];
+
+/// Fields the factory's Immutables carries; the rest are set by the deploy variant
+const FACTORY_FIELDS: [&str; 8] =
+    ["order_hash", "hashlock", "maker", "taker", "token", "amount", "safety_deposit", "timelocks"];
+
+const ACTIVE: u32 = 0;
+const FUNDED: u32 = 3;
+const EXPIRED: u32 = 4;
+const SIDE_SRC: u32 = 0;
+const SIDE_DST: u32 = 1;
+const IMMUTABLES_V2: u32 = 2;
+
+/// Immutables fields added after the first escrow release, with the value that matches how
+/// escrows without them behave; they are left out of the immutables hash at these values
+fn added_fields() -> [(&'static str, ScVal); 10] {
+    [
+        ("max_secret_len", 32u32.into()),
+        ("hashlock_b", ScVal::Void),
+        ("beneficiary", ScVal::Void),
+        ("incentives", tuple(vec![symbol("Default")])),
+        ("skew_tolerance", 0u32.into()),
+        ("finality_oracle", ScVal::Void),
+        ("pre_funding_hook", ScVal::Void),
+        ("post_payout_hook", ScVal::Void),
+        ("swap_id", ScVal::Void),
+        // DEFAULT_FLAGS: partial fills, public actions and fees allowed
+        ("flags", 19u32.into()),
+    ]
+}
+
+/// Ledger keys holding an escrow's state
+/// Pending deposits and keeper rewards are keyed by recipient and only read for `holders`
+pub fn ledger_keys(escrow: &ScAddress, holders: &[ScAddress]) -> Vec<LedgerKey> {
+    let mut keys = vec![instance_key(escrow)];
+    keys.extend(FIXED_KEYS.iter().map(|name| data_key(escrow, string(name))));
+    for holder in holders {
+        for prefix in HOLDER_KEYS {
+            keys.push(data_key(escrow, tuple(vec![string(prefix), ScVal::Address(holder.clone())])));
+        }
+    }
+    keys
+}
+
+/// Bring stored immutables up to the current schema, returning the names of the fields added
+/// Struct maps stay sorted by field name, as `#[contracttype]` decoding requires
+pub fn migrate_immutables(old: &ScVal) -> Result<(ScMap, Vec<&'static str>), MigrateError> {
+    let ScVal::Map(Some(map)) = old else {
+        return Err(MigrateError::Invalid("immutables is not a struct map".into()));
+    };
+    let mut entries: Vec<ScMapEntry> = map.0.to_vec();
+    let mut added = Vec::new();
+    for (name, default) in added_fields() {
+        if field(map, name).is_none() {
+            entries.push(ScMapEntry { key: symbol(name), val: default });
+            added.push(name);
+        }
+    }
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok((ScMap(entries.try_into()?), added))
+}
+
+/// Plan re-creating an escrow through `factory`
+/// Only open escrows are re-deployed; balances, fills and pending payouts stay with the old
+/// escrow and are reported as notes.
+pub fn plan(snapshot: &Snapshot, escrow: &ScAddress, factory: &ScAddress) -> Result<MigrationPlan, MigrateError> {
+    let mut plan = MigrationPlan::new(escrow);
+    let persistent = |name: &str| snapshot.persistent(&string(name));
+
+    if persistent("finalized") == Some(&ScVal::Bool(true)) {
+        plan.note("finalized: storage was reclaimed, nothing to migrate");
+        return Ok(plan);
+    }
+    let stored = persistent("immutables").ok_or(MigrateError::Missing("immutables"))?;
+    let (immutables, added) = migrate_immutables(stored)?;
+    if !added.is_empty() {
+        plan.note(format!("immutables upgraded to the current schema, defaulting {}", added.join(", ")));
+    }
+
+    // Held by withdrawn escrows whose recipient couldn't receive the payout
+    if let Some(held) = persistent("pending_pay").map(i128_of).transpose()?.filter(|held| *held > 0) {
+        plan.note(format!("{held} of the token is held for the taker: pay it out with retry_payout on the old escrow"));
+    }
+
+    let state = persistent("state").map(u32_of).transpose()?.unwrap_or(ACTIVE);
+    if ![ACTIVE, FUNDED, EXPIRED].contains(&state) {
+        plan.note("withdrawn or cancelled: nothing to re-create");
+        return Ok(plan);
+    }
+
+    let funded = persistent("funded_amt").map(i128_of).transpose()?.unwrap_or(0);
+    let funded_deposit = persistent("funded_dep").map(i128_of).transpose()?.unwrap_or(0);
+    let filled = persistent("filled").map(i128_of).transpose()?.unwrap_or(0);
+    let pending = persistent("pending_tot").map(i128_of).transpose()?.unwrap_or(0);
+    if funded > 0 || funded_deposit > 0 {
+        plan.note(format!(
+            "holds {} of the token and a {funded_deposit} stroop safety deposit; they stay in the old \
+             escrow until its cancellation opens, so fund the new escrow separately",
+            funded - filled
+        ));
+    }
+    if pending > 0 {
+        plan.note(format!(
+            "{pending} stroops of pending deposits and keeper rewards: claim them from the old escrow \
+             (claim_deposit / claim_rewards)"
+        ));
+    }
+
+    let skew = field(&immutables, "skew_tolerance").map(u32_of).transpose()?.unwrap_or(0);
+    if skew > 0 {
+        plan.note(format!("skew_tolerance {skew}: call set_skew_tolerance({skew}) on the new factory first"));
+    }
+
+    // Variants the factory can't deploy are left to the escrow's own entrypoints
+    let side = persistent("side").map(u32_of).transpose()?;
+    let max_secret_len = field(&immutables, "max_secret_len").map(u32_of).transpose()?;
+    let unsupported = if side == Some(SIDE_SRC) {
+        Some("source-side escrow: re-create it with the factory's deploy_src_escrow, which the maker funds")
+    } else if field(&immutables, "hashlock_b").is_some_and(|hashlock| *hashlock != ScVal::Void) {
+        Some("two-hashlock escrow: re-create it with the escrow's deploy_with_two_hashlocks")
+    } else if max_secret_len.is_some_and(|len| len != 32) {
+        Some("variable-length preimage escrow: re-create it with the escrow's deploy_with_preimage_len")
+    } else {
+        None
+    };
+    if let Some(reason) = unsupported {
+        plan.note(reason);
+        return Ok(plan);
+    }
+
+    let mut deployed = factory_immutables(&immutables)?;
+    if filled > 0 {
+        // Re-create only the unfilled remainder
+        let amount = field(&deployed, "amount").map(i128_of).transpose()?.unwrap_or(0);
+        set_field(&mut deployed, "amount", (amount - filled).into());
+        plan.note(format!("{filled} already withdrawn: the new escrow covers the remaining {}", amount - filled));
+    }
+    // Deadlines the parties pushed back with extend_timelocks replace the deployed ones
+    if let Some(extended) = persistent("timelocks") {
+        set_field(&mut deployed, "timelocks", extended.clone());
+        plan.note("timelocks were extended: the new escrow is deployed with the extended timelocks");
+    }
+    if field(&immutables, "swap_id").is_some_and(|val| *val != ScVal::Void) {
+        plan.note("the swap id is only recorded by cross-chain deploys; the new escrow has none");
+    }
+    // v2 escrows carry their flags, receiver and fees in the immutables, so one call re-creates them
+    if persistent("imm_ver").map(u32_of).transpose()? == Some(IMMUTABLES_V2) {
+        let v2 = immutables_v2(&deployed, &immutables, side.unwrap_or(SIDE_DST))?;
+        plan.invoke(factory, "deploy_escrow_v2", vec![ScVal::Map(Some(v2))]);
+        return Ok(plan);
+    }
+    let deployed = ScVal::Map(Some(deployed));
+    if let Some(flags) = field(&immutables, "flags").filter(|val| **val != ScVal::U32(19)) {
+        plan.note(format!("non-default flags {flags:?}: call set_flags on the new escrow before deploying it"));
+    }
+
+    let details = persistent("details").cloned();
+    let beneficiary = field(&immutables, "beneficiary").filter(|val| **val != ScVal::Void).cloned();
+    let incentives = match field(&immutables, "incentives") {
+        Some(ScVal::Vec(Some(items))) if items.len() == 2 && items[0] == symbol("Split") => Some(items[1].clone()),
+        _ => None,
+    };
+    let finality_oracle = field(&immutables, "finality_oracle").filter(|val| **val != ScVal::Void).cloned();
+    let hook = |name| field(&immutables, name).cloned().unwrap_or(ScVal::Void);
+    let hooks = [hook("pre_funding_hook"), hook("post_payout_hook")];
+    let hooks = hooks.iter().any(|val| *val != ScVal::Void).then(|| hooks.to_vec());
+    let variants = [
+        ("deploy_escrow_with_hooks", hooks),
+        ("deploy_escrow_with_oracle", finality_oracle.map(|arg| vec![arg])),
+        ("deploy_escrow_with_beneficiary", beneficiary.map(|arg| vec![arg])),
+        ("deploy_escrow_with_incentives", incentives.map(|arg| vec![arg])),
+        ("deploy_escrow_with_details", details.map(|arg| vec![arg])),
+    ];
+    let mut extras = variants.into_iter().filter_map(|(function, args)| args.map(|args| (function, args)));
+    match extras.next() {
+        Some((function, args)) => plan.invoke(factory, function, [vec![deployed], args].concat()),
+        None => plan.invoke(factory, "deploy_escrow", vec![deployed]),
+    }
+    for (function, _) in extras {
+        plan.note(format!("the factory deploys one variant per escrow; {function}'s argument is not carried over"));
+    }
+
+    Ok(plan)
+}
+
+/// The factory's Immutables (its first eight fields) out of the escrow's
+fn factory_immutables(immutables: &ScMap) -> Result<ScMap, MigrateError> {
+    let mut entries = Vec::new();
+    for name in FACTORY_FIELDS {
+        let val = field(immutables, name).ok_or(MigrateError::Missing(name))?;
+        entries.push(ScMapEntry { key: symbol(name), val: val.clone() });
+    }
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(ScMap(entries.try_into()?))
+}
+
+/// The escrow's ImmutablesV2 (version 2) for deploy_escrow_v2: the factory fields of `deployed`
+/// plus the flags, receiver (beneficiary) and fees (incentives) of the migrated `immutables`
+/// Also upgrades a v1 escrow to a v2 deploy, whose immutables hash then differs from the old one
+pub fn immutables_v2(deployed: &ScMap, immutables: &ScMap, side: u32) -> Result<ScMap, MigrateError> {
+    let mut entries = deployed.0.to_vec();
+    entries.push(ScMapEntry { key: symbol("version"), val: IMMUTABLES_V2.into() });
+    entries.push(ScMapEntry { key: symbol("side"), val: side.into() });
+    for (name, from) in [("flags", "flags"), ("receiver", "beneficiary"), ("fees", "incentives")] {
+        let val = field(immutables, from).ok_or(MigrateError::Missing(from))?;
+        entries.push(ScMapEntry { key: symbol(name), val: val.clone() });
+    }
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(ScMap(entries.try_into()?))
+}
+
+fn set_field(map: &mut ScMap, name: &str, val: ScVal) {
+    let mut entries = map.0.to_vec();
+    if let Some(entry) = entries.iter_mut().find(|entry| entry.key == symbol(name)) {
+        entry.val = val;
+    }
+    map.0 = entries.try_into().expect("same length");
+}
+
+fn u32_of(val: &ScVal) -> Result<u32, MigrateError> {
+    val.clone().try_into().map_err(|_| MigrateError::Invalid(format!("expected u32, got {val:?}")))
+}
+
+fn i128_of(val: &ScVal) -> Result<i128, MigrateError> {
+    val.clone().try_into().map_err(|_| MigrateError::Invalid(format!("expected i128, got {val:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stellar_xdr::curr::{ContractId, Hash};
+
+    fn address(byte: u8) -> ScAddress {
+        ScAddress::Contract(ContractId(Hash([byte; 32])))
+    }
+
+    /// Immutables as stored by escrows from before the optional fields existed
+    fn legacy_immutables() -> ScVal {
+        let entries: Vec<ScMapEntry> = [
+            ("amount", 1000i128.into()),
+            ("hashlock", ScVal::try_from([2u8; 32]).unwrap()),
+            ("maker", ScVal::Address(address(3))),
+            ("order_hash", ScVal::try_from([1u8; 32]).unwrap()),
+            ("safety_deposit", 10i128.into()),
+            ("taker", ScVal::Address(address(4))),
+            ("timelocks", 42u64.into()),
+            ("token", ScVal::Address(address(5))),
+        ]
+        .into_iter()
+        .map(|(name, val)| ScMapEntry { key: symbol(name), val })
+        .collect();
+        ScVal::Map(Some(ScMap(entries.try_into().unwrap())))
+    }
+
+    fn snapshot(entries: Vec<(&str, ScVal)>) -> Snapshot {
+        Snapshot {
+            persistent: entries.into_iter().map(|(name, val)| (string(name), val)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_migrate_legacy_immutables() {
+        let (migrated, added) = migrate_immutables(&legacy_immutables()).unwrap();
+        assert_eq!(added, [
+            "max_secret_len", "hashlock_b", "beneficiary", "incentives", "skew_tolerance", "finality_oracle",
+            "pre_funding_hook", "post_payout_hook", "swap_id", "flags",
+        ]);
+        assert_eq!(field(&migrated, "max_secret_len"), Some(&ScVal::U32(32)));
+        assert!(migrated.0.windows(2).all(|pair| pair[0].key < pair[1].key));
+
+        // Already current: nothing added
+        let (_, added) = migrate_immutables(&ScVal::Map(Some(migrated))).unwrap();
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    fn test_plan_redeploys_partially_filled_escrow() {
+        let snapshot = snapshot(vec![
+            ("immutables", legacy_immutables()),
+            ("state", FUNDED.into()),
+            ("funded_amt", 1000i128.into()),
+            ("filled", 300i128.into()),
+            ("timelocks", 99u64.into()),
+        ]);
+        let factory = address(9);
+        let plan = plan(&snapshot, &address(8), &factory).unwrap();
+
+        assert_eq!(plan.invocations.len(), 1);
+        let invocation = &plan.invocations[0];
+        assert_eq!((&invocation.contract, invocation.function.as_str()), (&factory, "deploy_escrow"));
+        let ScVal::Map(Some(deployed)) = &invocation.args[0] else { panic!("expected immutables") };
+        assert_eq!(deployed.len(), FACTORY_FIELDS.len());
+        assert_eq!(field(deployed, "amount"), Some(&700i128.into()));
+        assert_eq!(field(deployed, "timelocks"), Some(&99u64.into()));
+        assert!(plan.invocations[0].operation().is_ok());
+        assert!(plan.notes.iter().any(|note| note.contains("holds 700")));
+    }
+
+    #[test]
+    fn test_plan_redeploys_v2_escrow_in_one_call() {
+        let (mut migrated, _) = migrate_immutables(&legacy_immutables()).unwrap();
+        set_field(&mut migrated, "beneficiary", ScVal::Address(address(6)));
+        let snapshot = snapshot(vec![
+            ("immutables", ScVal::Map(Some(migrated))),
+            ("state", ACTIVE.into()),
+            ("imm_ver", IMMUTABLES_V2.into()),
+        ]);
+        let factory = address(9);
+        let plan = plan(&snapshot, &address(8), &factory).unwrap();
+
+        assert_eq!(plan.invocations.len(), 1);
+        let invocation = &plan.invocations[0];
+        assert_eq!(invocation.function, "deploy_escrow_v2");
+        let ScVal::Map(Some(v2)) = &invocation.args[0] else { panic!("expected v2 immutables") };
+        assert_eq!(v2.len(), FACTORY_FIELDS.len() + 5);
+        assert_eq!(field(v2, "version"), Some(&ScVal::U32(2)));
+        assert_eq!(field(v2, "side"), Some(&ScVal::U32(SIDE_DST)));
+        assert_eq!(field(v2, "receiver"), Some(&ScVal::Address(address(6))));
+        assert!(v2.0.windows(2).all(|pair| pair[0].key < pair[1].key));
+        assert!(invocation.operation().is_ok());
+    }
+
+    #[test]
+    fn test_plan_skips_closed_escrow() {
+        let snapshot = snapshot(vec![("immutables", legacy_immutables()), ("state", 1u32.into())]);
+        let plan = plan(&snapshot, &address(8), &address(9)).unwrap();
+        assert!(plan.invocations.is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+use stellar_xdr::curr::{LedgerKey, ScAddress, ScVal};
+
+use crate::plan::MigrationPlan;
+use crate::snapshot::{bytes, data_key, instance_key, symbol, tuple, Snapshot};
+use crate::MigrateError;
+
+/// Ledger keys holding a factory's configuration
+/// Chain configs and token policies are keyed by id and only read for `chains` and `tokens`;
+/// the deployed-escrow registry and order indexes are rebuilt as escrows are re-deployed
+pub fn ledger_keys(factory: &ScAddress, chains: &[u64], tokens: &[ScAddress]) -> Vec<LedgerKey> {
+    let mut keys = vec![instance_key(factory)];
+    keys.extend(chains.iter().map(|chain| data_key(factory, tuple(vec![symbol("CHAIN"), (*chain).into()]))));
+    keys.extend(
+        tokens
+            .iter()
+            .map(|token| data_key(factory, tuple(vec![symbol("TOKEN_POL"), ScVal::Address(token.clone())]))),
+    );
+    keys
+}
+
+/// Plan re-creating a factory's configuration in `target`
+/// `htlc_hash` replaces the stored escrow WASM hash, for migrations that ship a new escrow;
+/// calls are ordered so single-admin configuration lands before the governance delay, which would
+/// schedule it, and before multisig takes over
+pub fn plan(
+    snapshot: &Snapshot,
+    factory: &ScAddress,
+    target: &ScAddress,
+    htlc_hash: Option<[u8; 32]>,
+) -> Result<MigrationPlan, MigrateError> {
+    let mut plan = MigrationPlan::new(factory);
+    let instance = |name: &str| snapshot.instance(&symbol(name));
+
+    let admin = instance("ADMIN").ok_or(MigrateError::Missing("ADMIN"))?.clone();
+    let htlc_hash = match htlc_hash {
+        Some(hash) => bytes(&hash)?,
+        None => instance("HTLC_HASH").ok_or(MigrateError::Missing("HTLC_HASH"))?.clone(),
+    };
+    plan.invoke(target, "initialize", vec![admin, htlc_hash]);
+
+    if instance("ALLOWLIST") == Some(&ScVal::Bool(true)) {
+        plan.invoke(target, "set_allowlist_mode", vec![true.into()]);
+    }
+    if let Some(gap) = instance("MIN_GAP").filter(|gap| **gap != ScVal::U32(0)) {
+        plan.invoke(target, "set_min_public_gap", vec![gap.clone()]);
+    }
+    if let Some(min_deposit) = instance("MIN_DEP") {
+        plan.invoke(target, "set_min_safety_deposit", vec![min_deposit.clone()]);
+    }
+    if let Some(skew) = instance("SKEW").filter(|skew| **skew != ScVal::U32(0)) {
+        plan.invoke(target, "set_skew_tolerance", vec![skew.clone()]);
+    }
+    for (chain, config) in snapshot.persistent_by_prefix(&symbol("CHAIN")) {
+        plan.invoke(target, "set_chain_config", vec![chain.clone(), config.clone()]);
+    }
+    for (token, allowed) in snapshot.persistent_by_prefix(&symbol("TOKEN_POL")) {
+        plan.invoke(target, "set_token_policy", vec![token.clone(), allowed.clone()]);
+    }
+    if instance("PAUSED") == Some(&ScVal::Bool(true)) {
+        plan.invoke(target, "set_paused", vec![true.into()]);
+    }
+
+    // A governance delay set from zero applies at once; scheduled actions are not carried over
+    if let Some(delay) = instance("GOV_DELAY").filter(|delay| **delay != ScVal::U64(0)) {
+        plan.invoke(target, "set_governance_delay", vec![delay.clone()]);
+    }
+    if matches!(instance("PENDING"), Some(ScVal::Vec(Some(pending))) if !pending.is_empty()) {
+        plan.note("scheduled admin actions are pending: re-schedule any that should still apply");
+    }
+
+    // Multisig goes last: afterwards sensitive actions need a proposal
+    if let (Some(signers), Some(threshold)) = (instance("SIGNERS"), instance("THRESHOLD")) {
+        plan.invoke(target, "initialize_multisig", vec![signers.clone(), threshold.clone()]);
+        plan.note("open proposals are not carried over");
+    }
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stellar_xdr::curr::{ContractId, Hash};
+
+    fn address(byte: u8) -> ScAddress {
+        ScAddress::Contract(ContractId(Hash([byte; 32])))
+    }
+
+    #[test]
+    fn test_plan_orders_configuration_before_multisig() {
+        let signers = ScVal::try_from(vec![ScVal::Address(address(1)), ScVal::Address(address(2))]).unwrap();
+        let snapshot = Snapshot {
+            instance: vec![
+                (symbol("ADMIN"), ScVal::Address(address(1))),
+                (symbol("HTLC_HASH"), ScVal::try_from([7u8; 32]).unwrap()),
+                (symbol("MIN_GAP"), 60u32.into()),
+                (symbol("SKEW"), 0u32.into()),
+                (symbol("SIGNERS"), signers),
+                (symbol("THRESHOLD"), 2u32.into()),
+            ],
+            persistent: vec![(tuple(vec![symbol("TOKEN_POL"), ScVal::Address(address(5))]), true.into())],
+        };
+        let target = address(9);
+        let plan = plan(&snapshot, &address(8), &target, Some([6u8; 32])).unwrap();
+
+        let functions: Vec<&str> = plan.invocations.iter().map(|call| call.function.as_str()).collect();
+        assert_eq!(functions, ["initialize", "set_min_public_gap", "set_token_policy", "initialize_multisig"]);
+        assert_eq!(plan.invocations[0].args[1], ScVal::try_from([6u8; 32]).unwrap());
+        assert!(plan.invocations.iter().all(|call| call.contract == target));
+    }
+}
@@ -0,0 +1,44 @@
+//! Off-chain migration of escrow and factory state to new contracts
+//!
+//! Reads a contract's storage from `getLedgerEntries` results into a [`Snapshot`],
+//! transforms it to the current storage schema and plans the invocations that
+//! re-create it in the new contracts (a [`MigrationPlan`]), for storage redesigns
+//! that can't be applied in place.
+
+pub mod escrow;
+pub mod factory;
+pub mod plan;
+pub mod snapshot;
+
+use std::fmt;
+
+pub use plan::{Invocation, MigrationPlan};
+pub use snapshot::{RpcLedgerEntries, RpcLedgerEntry, Snapshot};
+
+/// Why a snapshot couldn't be read or migrated
+#[derive(Debug)]
+pub enum MigrateError {
+    Xdr(stellar_xdr::curr::Error),
+    /// A required storage entry is absent from the snapshot
+    Missing(&'static str),
+    /// A storage entry doesn't have the expected shape
+    Invalid(String),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::Xdr(err) => write!(f, "xdr: {err}"),
+            MigrateError::Missing(key) => write!(f, "storage entry {key} not found in the snapshot"),
+            MigrateError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+impl From<stellar_xdr::curr::Error> for MigrateError {
+    fn from(err: stellar_xdr::curr::Error) -> Self {
+        MigrateError::Xdr(err)
+    }
+}
@@ -0,0 +1,128 @@
+//! fusion-migrate keys escrow <ESCROW> [--holder ADDRESS]...
+//! fusion-migrate keys factory <FACTORY> [--chain ID]... [--token ADDRESS]...
+//! fusion-migrate plan escrow <ESCROW> --factory <NEW_FACTORY> <getLedgerEntries.json>...
+//! fusion-migrate plan factory <FACTORY> --target <NEW_FACTORY> [--htlc-hash HEX] <getLedgerEntries.json>...
+//!
+//! `keys` prints the ledger keys to fetch with `getLedgerEntries`; `plan` reads the
+//! results and prints the invocations re-creating the contract's state as JSON.
+
+use std::process::ExitCode;
+
+use fusion_migrate::{escrow, factory, snapshot, RpcLedgerEntries, RpcLedgerEntry, Snapshot};
+use serde_json::json;
+use stellar_xdr::curr::ScAddress;
+
+const USAGE: &str = "usage: fusion-migrate keys <escrow|factory> <CONTRACT> [--holder A] [--chain ID] [--token A]\n       \
+                     fusion-migrate plan <escrow|factory> <CONTRACT> [--factory A] [--target A] [--htlc-hash HEX] <getLedgerEntries.json>...";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("fusion-migrate: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parsed command line
+#[derive(Default)]
+struct Args {
+    command: String,
+    kind: String,
+    contract: Option<ScAddress>,
+    holders: Vec<ScAddress>,
+    chains: Vec<u64>,
+    tokens: Vec<ScAddress>,
+    factory: Option<ScAddress>,
+    target: Option<ScAddress>,
+    htlc_hash: Option<[u8; 32]>,
+    files: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args {
+        command: args.next().ok_or(USAGE)?,
+        kind: args.next().ok_or(USAGE)?,
+        contract: Some(parse_address(args.next())?),
+        ..Default::default()
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--holder" => parsed.holders.push(parse_address(args.next())?),
+            "--chain" => parsed.chains.push(args.next().and_then(|id| id.parse().ok()).ok_or("expected a chain id")?),
+            "--token" => parsed.tokens.push(parse_address(args.next())?),
+            "--factory" => parsed.factory = Some(parse_address(args.next())?),
+            "--target" => parsed.target = Some(parse_address(args.next())?),
+            "--htlc-hash" => {
+                let mut hash = [0u8; 32];
+                hex::decode_to_slice(args.next().ok_or("expected a wasm hash")?, &mut hash)
+                    .map_err(|err| format!("--htlc-hash: {err}"))?;
+                parsed.htlc_hash = Some(hash);
+            }
+            _ => parsed.files.push(arg),
+        }
+    }
+    Ok(parsed)
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args()?;
+    let contract = args.contract.as_ref().ok_or(USAGE)?;
+
+    let output = match (args.command.as_str(), args.kind.as_str()) {
+        ("keys", kind) => {
+            let keys = match kind {
+                "escrow" => escrow::ledger_keys(contract, &args.holders),
+                "factory" => factory::ledger_keys(contract, &args.chains, &args.tokens),
+                _ => return Err(USAGE.into()),
+            };
+            json!(snapshot::encode_keys(&keys).map_err(|err| err.to_string())?)
+        }
+        ("plan", kind) => {
+            if args.files.is_empty() {
+                return Err(USAGE.into());
+            }
+            let snapshot = Snapshot::from_entries(contract, &read_entries(&args.files)?).map_err(|err| err.to_string())?;
+            let plan = match kind {
+                "escrow" => escrow::plan(&snapshot, contract, args.factory.as_ref().ok_or("--factory is required")?),
+                "factory" => factory::plan(
+                    &snapshot,
+                    contract,
+                    args.target.as_ref().ok_or("--target is required")?,
+                    args.htlc_hash,
+                ),
+                _ => return Err(USAGE.into()),
+            }
+            .map_err(|err| err.to_string())?;
+            json!({
+                "contract": plan.contract,
+                "invocations": plan.encoded().map_err(|err| err.to_string())?,
+                "notes": plan.notes,
+            })
+        }
+        _ => return Err(USAGE.into()),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&output).map_err(|err| err.to_string())?);
+    Ok(())
+}
+
+/// Entries of every file (a `getLedgerEntries` result, or an array of them)
+fn read_entries(files: &[String]) -> Result<Vec<RpcLedgerEntry>, String> {
+    let mut entries = Vec::new();
+    for file in files {
+        let text = std::fs::read_to_string(file).map_err(|err| format!("{file}: {err}"))?;
+        let results: Vec<RpcLedgerEntries> = serde_json::from_str::<Vec<RpcLedgerEntries>>(&text)
+            .or_else(|_| serde_json::from_str::<RpcLedgerEntries>(&text).map(|result| vec![result]))
+            .map_err(|err| format!("{file}: {err}"))?;
+        entries.extend(results.into_iter().flat_map(|result| result.entries));
+    }
+    Ok(entries)
+}
+
+fn parse_address(value: Option<String>) -> Result<ScAddress, String> {
+    let value = value.ok_or(USAGE)?;
+    value.parse().map_err(|_| format!("{value}: expected a G... or C... address"))
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use stellar_xdr::curr::{
+    HostFunction, InvokeContractArgs, InvokeHostFunctionOp, Limits, Operation, OperationBody, ScAddress, ScSymbol,
+    ScVal, WriteXdr,
+};
+
+use crate::MigrateError;
+
+/// A contract call re-creating part of the migrated state
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Invocation {
+    pub contract: ScAddress,
+    pub function: String,
+    pub args: Vec<ScVal>,
+}
+
+impl Invocation {
+    pub fn new(contract: &ScAddress, function: &str, args: Vec<ScVal>) -> Self {
+        Self { contract: contract.clone(), function: function.into(), args }
+    }
+
+    /// The call as an unsigned `InvokeHostFunction` operation
+    /// Auth entries are left empty: simulate the transaction to fill them in before signing
+    pub fn operation(&self) -> Result<Operation, MigrateError> {
+        let function_name = ScSymbol(self.function.as_str().try_into()?);
+        Ok(Operation {
+            source_account: None,
+            body: OperationBody::InvokeHostFunction(InvokeHostFunctionOp {
+                host_function: HostFunction::InvokeContract(InvokeContractArgs {
+                    contract_address: self.contract.clone(),
+                    function_name,
+                    args: self.args.clone().try_into()?,
+                }),
+                auth: Default::default(),
+            }),
+        })
+    }
+}
+
+/// Invocations re-creating a contract's state in the new contracts, in submission order,
+/// with notes on state that can't be re-created by a call
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MigrationPlan {
+    pub contract: Option<ScAddress>,
+    pub invocations: Vec<Invocation>,
+    pub notes: Vec<String>,
+}
+
+/// An invocation as printed by the CLI: the decoded call and its operation XDR
+#[derive(Serialize)]
+pub struct EncodedInvocation<'a> {
+    #[serde(flatten)]
+    pub invocation: &'a Invocation,
+    /// Base64 `Operation`, to wrap in a transaction
+    pub operation: String,
+}
+
+impl MigrationPlan {
+    pub fn new(contract: &ScAddress) -> Self {
+        Self { contract: Some(contract.clone()), ..Default::default() }
+    }
+
+    pub fn invoke(&mut self, contract: &ScAddress, function: &str, args: Vec<ScVal>) {
+        self.invocations.push(Invocation::new(contract, function, args));
+    }
+
+    pub fn note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    /// The invocations with their base64 operation XDR
+    pub fn encoded(&self) -> Result<Vec<EncodedInvocation<'_>>, MigrateError> {
+        self.invocations
+            .iter()
+            .map(|invocation| {
+                Ok(EncodedInvocation { invocation, operation: invocation.operation()?.to_xdr_base64(Limits::none())? })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use stellar_xdr::curr::{
+    ContractDataDurability, LedgerEntryData, LedgerKey, LedgerKeyContractData, Limits, ReadXdr, ScAddress,
+    ScBytes, ScMap, ScString, ScSymbol, ScVal, ScVec, WriteXdr,
+};
+
+use crate::MigrateError;
+
+/// An entry of a Soroban RPC `getLedgerEntries` result
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLedgerEntry {
+    pub key: String,
+    /// Base64 `LedgerEntryData`
+    pub xdr: String,
+    #[serde(default)]
+    pub last_modified_ledger_seq: u32,
+    #[serde(default)]
+    pub live_until_ledger_seq: Option<u32>,
+}
+
+/// A `getLedgerEntries` result
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcLedgerEntries {
+    #[serde(default)]
+    pub entries: Vec<RpcLedgerEntry>,
+    #[serde(default)]
+    pub latest_ledger: u32,
+}
+
+/// Storage of one contract as read from its ledger entries
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    /// Instance storage (the contract instance entry's storage map)
+    pub instance: Vec<(ScVal, ScVal)>,
+    pub persistent: Vec<(ScVal, ScVal)>,
+}
+
+impl Snapshot {
+    /// Collect the storage of `contract` from ledger entries, ignoring entries of other contracts
+    pub fn from_entries(contract: &ScAddress, entries: &[RpcLedgerEntry]) -> Result<Self, MigrateError> {
+        let mut snapshot = Snapshot::default();
+        for entry in entries {
+            let LedgerEntryData::ContractData(data) = LedgerEntryData::from_xdr_base64(&entry.xdr, Limits::none())?
+            else {
+                continue;
+            };
+            if data.contract != *contract {
+                continue;
+            }
+            match (&data.key, data.val) {
+                (ScVal::LedgerKeyContractInstance, ScVal::ContractInstance(instance)) => {
+                    let storage = instance.storage.unwrap_or_default();
+                    snapshot.instance = Vec::from(storage.0).into_iter().map(|entry| (entry.key, entry.val)).collect();
+                }
+                (_, val) if data.durability == ContractDataDurability::Persistent => {
+                    snapshot.persistent.push((data.key, val));
+                }
+                // Temporary entries expire on their own and are never migrated
+                _ => {}
+            }
+        }
+        Ok(snapshot)
+    }
+
+    pub fn instance(&self, key: &ScVal) -> Option<&ScVal> {
+        self.instance.iter().find(|(k, _)| k == key).map(|(_, val)| val)
+    }
+
+    pub fn persistent(&self, key: &ScVal) -> Option<&ScVal> {
+        self.persistent.iter().find(|(k, _)| k == key).map(|(_, val)| val)
+    }
+
+    /// Persistent entries keyed by `(prefix, id)` tuples, e.g. the factory's `(CHAIN, chain_id)`
+    pub fn persistent_by_prefix<'a>(&'a self, prefix: &'a ScVal) -> impl Iterator<Item = (&'a ScVal, &'a ScVal)> {
+        self.persistent.iter().filter_map(move |(key, val)| match key {
+            ScVal::Vec(Some(items)) if items.len() == 2 && items[0] == *prefix => Some((&items[1], val)),
+            _ => None,
+        })
+    }
+}
+
+/// Ledger key of the contract instance entry (holds instance storage)
+pub fn instance_key(contract: &ScAddress) -> LedgerKey {
+    data_key(contract, ScVal::LedgerKeyContractInstance)
+}
+
+/// Ledger key of a persistent storage entry
+pub fn data_key(contract: &ScAddress, key: ScVal) -> LedgerKey {
+    LedgerKey::ContractData(LedgerKeyContractData {
+        contract: contract.clone(),
+        key,
+        durability: ContractDataDurability::Persistent,
+    })
+}
+
+/// Base64 ledger keys, ready for the `keys` parameter of `getLedgerEntries`
+pub fn encode_keys(keys: &[LedgerKey]) -> Result<Vec<String>, MigrateError> {
+    Ok(keys.iter().map(|key| key.to_xdr_base64(Limits::none())).collect::<Result<_, _>>()?)
+}
+
+/// Storage key written by `symbol_short!` / `Symbol`
+pub fn symbol(name: &str) -> ScVal {
+    ScVal::Symbol(ScSymbol(name.try_into().expect("symbol fits 32 bytes")))
+}
+
+/// Storage key written from a `&str` constant (stored as a string, not a symbol)
+pub fn string(name: &str) -> ScVal {
+    ScVal::String(ScString(name.try_into().expect("key fits a string")))
+}
+
+/// A `Bytes` / `BytesN` value
+pub fn bytes(value: &[u8]) -> Result<ScVal, MigrateError> {
+    Ok(ScVal::Bytes(ScBytes(value.to_vec().try_into()?)))
+}
+
+/// A tuple key or value, e.g. `(PREFIX, id)`
+pub fn tuple(items: Vec<ScVal>) -> ScVal {
+    ScVal::Vec(Some(ScVec(items.try_into().expect("short tuple"))))
+}
+
+/// Field of a `#[contracttype]` struct, which is stored as a map keyed by field name
+pub fn field<'a>(map: &'a ScMap, name: &str) -> Option<&'a ScVal> {
+    map.0.iter().find(|entry| entry.key == symbol(name)).map(|entry| &entry.val)
+}
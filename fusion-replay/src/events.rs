@@ -0,0 +1,234 @@
+use stellar_xdr::curr::{
+    ContractEvent, ContractEventBody, ContractEventType, ScVal, TransactionMeta,
+};
+
+/// An escrow or factory event decoded from contract event XDR
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FusionEvent {
+    /// escrow/created: (order_hash, maker, taker)
+    EscrowCreated { order_hash: String, maker: String, taker: String },
+    /// escrow/details: metadata blob
+    Details { details: String },
+    /// escrow/funded: (amount, token, funder, total_funded); funder is None for confirm_funding
+    Funded { amount: i128, token: String, funder: Option<String>, total_funded: i128 },
+    /// escrow/withdraw/<hashlock>: secret revealed by withdraw or public_withdraw
+    Withdrawn { secret: String },
+    /// escrow/secret_b: second secret revealed by withdraw2
+    SecondSecret { secret: String },
+    /// escrow/partial: (secret, amount, filled)
+    PartialWithdraw { secret: String, amount: i128, filled: i128 },
+    /// escrow/cancel: (refunded, filled)
+    Cancelled { refunded: i128, filled: i128 },
+    /// escrow/expired: marked by refresh_state
+    Expired,
+    /// escrow/finalized: storage reclaimed after a terminal state
+    Finalized,
+    /// escrow/stage_transition: (stage, stage_start, now)
+    StageTransition { stage: u32 },
+    /// escrow/deposit_failed: (recipient, amount)
+    DepositFailed { recipient: String, amount: i128 },
+    /// escrow/deposit_claimed: (recipient, amount)
+    DepositClaimed { recipient: String, amount: i128 },
+    /// factory escrow_deployed: (escrow, order_hash, salt, swap_id)
+    EscrowDeployed { escrow: String, order_hash: String, salt: String },
+}
+
+impl FusionEvent {
+    /// Event name: the second topic of escrow events, the first of factory events
+    pub fn name(&self) -> &'static str {
+        match self {
+            FusionEvent::EscrowCreated { .. } => "created",
+            FusionEvent::Details { .. } => "details",
+            FusionEvent::Funded { .. } => "funded",
+            FusionEvent::Withdrawn { .. } => "withdraw",
+            FusionEvent::SecondSecret { .. } => "secret_b",
+            FusionEvent::PartialWithdraw { .. } => "partial",
+            FusionEvent::Cancelled { .. } => "cancel",
+            FusionEvent::Expired => "expired",
+            FusionEvent::Finalized => "finalized",
+            FusionEvent::StageTransition { .. } => "stage_transition",
+            FusionEvent::DepositFailed { .. } => "deposit_failed",
+            FusionEvent::DepositClaimed { .. } => "deposit_claimed",
+            FusionEvent::EscrowDeployed { .. } => "escrow_deployed",
+        }
+    }
+}
+
+/// A decoded event with the contract that emitted it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmittedEvent {
+    pub contract: String,
+    pub event: FusionEvent,
+    /// Cross-chain swap id (hex) carried by v3 escrow events and the factory's escrow_deployed,
+    /// None for escrows deployed without one
+    pub swap_id: Option<String>,
+}
+
+/// Collect the contract events of a successful transaction (meta V3 and V4)
+pub fn contract_events(meta: &TransactionMeta) -> Vec<&ContractEvent> {
+    match meta {
+        TransactionMeta::V3(v3) => v3
+            .soroban_meta
+            .as_ref()
+            .map(|soroban| soroban.events.iter().collect())
+            .unwrap_or_default(),
+        TransactionMeta::V4(v4) => v4
+            .operations
+            .iter()
+            .flat_map(|op| op.events.iter())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Latest escrow event schema version this decoder understands
+pub const EVENT_SCHEMA_VERSION: u32 = 3;
+
+/// Schema version of an event's topics: escrow code since v2 prefixes them with a `v<N>` symbol,
+/// older code emits unversioned (v1) topics
+fn schema_version(topics: &[&ScVal]) -> Option<u32> {
+    topics.first()
+        .and_then(|topic| symbol(topic)?.strip_prefix('v')?.parse().ok())
+}
+
+/// Decode an escrow or factory event, ignoring anything else
+/// Events of a schema newer than `EVENT_SCHEMA_VERSION` are ignored rather than misparsed
+pub fn decode_event(event: &ContractEvent) -> Option<EmittedEvent> {
+    if event.type_ != ContractEventType::Contract {
+        return None;
+    }
+    let contract = event.contract_id.as_ref()?.to_string();
+    let ContractEventBody::V0(body) = &event.body;
+    let mut topics: Vec<&ScVal> = body.topics.iter().collect();
+    // v1 and v2 payloads are identical; v2 only adds the version topic
+    // v3 wraps each payload as (swap_id, payload)
+    let mut raw = &body.data;
+    let mut swap_id = None;
+    if let Some(version) = schema_version(&topics) {
+        if version > EVENT_SCHEMA_VERSION {
+            return None;
+        }
+        topics.remove(0);
+        if version >= 3 {
+            let [id, payload] = tuple(raw)[..] else {
+                return None;
+            };
+            swap_id = optional_bytes(id)?;
+            raw = payload;
+        }
+    }
+    let data = tuple(raw);
+
+    let event = match (topics.first().and_then(|t| symbol(t)), topics.get(1).and_then(|t| symbol(t))) {
+        (Some("escrow"), Some(name)) => decode_escrow_event(name, raw, &data)?,
+        (Some("escrow_deployed"), None) => {
+            // Factories before swap ids emit three elements
+            swap_id = match data.get(3) {
+                Some(id) => optional_bytes(id)?,
+                None => None,
+            };
+            FusionEvent::EscrowDeployed {
+                escrow: address(data.first()?)?,
+                order_hash: bytes(data.get(1)?)?,
+                salt: bytes(data.get(2)?)?,
+            }
+        }
+        _ => return None,
+    };
+
+    Some(EmittedEvent { contract, event, swap_id })
+}
+
+fn decode_escrow_event(name: &str, raw: &ScVal, data: &[&ScVal]) -> Option<FusionEvent> {
+    let event = match name {
+        "created" => FusionEvent::EscrowCreated {
+            order_hash: bytes(data.first()?)?,
+            maker: address(data.get(1)?)?,
+            taker: address(data.get(2)?)?,
+        },
+        "details" => FusionEvent::Details { details: bytes(raw)? },
+        "funded" => FusionEvent::Funded {
+            amount: int(data.first()?)?,
+            token: address(data.get(1)?)?,
+            funder: match data.get(2)? {
+                ScVal::Void => None,
+                funder => Some(address(funder)?),
+            },
+            total_funded: int(data.get(3)?)?,
+        },
+        "withdraw" => FusionEvent::Withdrawn { secret: bytes(raw)? },
+        "secret_b" => FusionEvent::SecondSecret { secret: bytes(raw)? },
+        "partial" => FusionEvent::PartialWithdraw {
+            secret: bytes(data.first()?)?,
+            amount: int(data.get(1)?)?,
+            filled: int(data.get(2)?)?,
+        },
+        "cancel" => FusionEvent::Cancelled {
+            refunded: int(data.first()?)?,
+            filled: int(data.get(1)?)?,
+        },
+        "expired" => FusionEvent::Expired,
+        "finalized" => FusionEvent::Finalized,
+        "stage_transition" => FusionEvent::StageTransition {
+            stage: match data.first()? {
+                ScVal::U32(stage) => *stage,
+                _ => return None,
+            },
+        },
+        "deposit_failed" => FusionEvent::DepositFailed {
+            recipient: address(data.first()?)?,
+            amount: int(data.get(1)?)?,
+        },
+        "deposit_claimed" => FusionEvent::DepositClaimed {
+            recipient: address(data.first()?)?,
+            amount: int(data.get(1)?)?,
+        },
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// Event data published as a tuple arrives as an ScVec
+fn tuple(value: &ScVal) -> Vec<&ScVal> {
+    match value {
+        ScVal::Vec(Some(items)) => items.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn symbol(value: &ScVal) -> Option<&str> {
+    match value {
+        ScVal::Symbol(symbol) => std::str::from_utf8(symbol.0.as_slice()).ok(),
+        _ => None,
+    }
+}
+
+fn address(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Address(address) => Some(address.to_string()),
+        _ => None,
+    }
+}
+
+/// Bytes and BytesN values as lowercase hex
+fn bytes(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Bytes(bytes) => Some(hex::encode(bytes.as_slice())),
+        _ => None,
+    }
+}
+
+/// An `Option<BytesN>`: Some(None) for Void, None if it's neither
+fn optional_bytes(value: &ScVal) -> Option<Option<String>> {
+    match value {
+        ScVal::Void => Some(None),
+        value => bytes(value).map(Some),
+    }
+}
+
+fn int(value: &ScVal) -> Option<i128> {
+    match value {
+        ScVal::I128(parts) => Some(((parts.hi as i128) << 64) | parts.lo as i128),
+        _ => None,
+    }
+}
@@ -0,0 +1,54 @@
+//! Off-chain reconstruction of escrow state from Stellar transaction metadata
+//!
+//! Feeds escrow and factory events from `getTransactions` results through a
+//! [`Replay`] to rebuild the state of every escrow seen (state, fill amounts,
+//! revealed secrets), for audits and relayer cold starts.
+
+pub mod events;
+pub mod state;
+
+use serde::Deserialize;
+use stellar_xdr::curr::{Limits, ReadXdr, TransactionMeta};
+
+pub use events::{decode_event, EmittedEvent, FusionEvent};
+pub use state::{AppliedEvent, EscrowRecord, EscrowState, Replay};
+
+/// A transaction as returned by Soroban RPC `getTransactions`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTransaction {
+    pub status: String,
+    pub ledger: u32,
+    #[serde(default)]
+    pub tx_hash: String,
+    pub result_meta_xdr: String,
+}
+
+/// A `getTransactions` result page
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTransactionPage {
+    pub transactions: Vec<RpcTransaction>,
+    #[serde(default)]
+    pub latest_ledger: u32,
+    /// Pagination cursor for the next page
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+impl Replay {
+    /// Apply the escrow and factory events of a transaction, returning them in order
+    /// Failed transactions emit no events and are skipped
+    pub fn apply_transaction(&mut self, tx: &RpcTransaction) -> Result<Vec<AppliedEvent>, stellar_xdr::curr::Error> {
+        if tx.status != "SUCCESS" {
+            return Ok(Vec::new());
+        }
+
+        let meta = TransactionMeta::from_xdr_base64(&tx.result_meta_xdr, Limits::none())?;
+        Ok(events::contract_events(&meta)
+            .into_iter()
+            .filter_map(decode_event)
+            .map(|emitted| self.apply(tx.ledger, &emitted))
+            .collect())
+    }
+}
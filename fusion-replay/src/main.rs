@@ -0,0 +1,66 @@
+//! fusion-replay [--from-ledger N] [--to-ledger N] <getTransactions.json>...
+//!
+//! Replays the transactions in each file (a `getTransactions` result, or an array
+//! of them) and prints the reconstructed escrows as JSON.
+
+use std::process::ExitCode;
+
+use fusion_replay::{Replay, RpcTransactionPage};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("fusion-replay: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut from_ledger = 0u32;
+    let mut to_ledger = u32::MAX;
+    let mut files = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from-ledger" => from_ledger = parse_ledger(args.next())?,
+            "--to-ledger" => to_ledger = parse_ledger(args.next())?,
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        return Err("usage: fusion-replay [--from-ledger N] [--to-ledger N] <getTransactions.json>...".into());
+    }
+
+    let mut transactions = Vec::new();
+    for file in &files {
+        let text = std::fs::read_to_string(file).map_err(|err| format!("{file}: {err}"))?;
+        let pages: Vec<RpcTransactionPage> = serde_json::from_str::<Vec<RpcTransactionPage>>(&text)
+            .or_else(|_| serde_json::from_str::<RpcTransactionPage>(&text).map(|page| vec![page]))
+            .map_err(|err| format!("{file}: {err}"))?;
+        transactions.extend(pages.into_iter().flat_map(|page| page.transactions));
+    }
+
+    // Pages may overlap or arrive out of order; events must be applied in ledger order
+    transactions.retain(|tx| (from_ledger..=to_ledger).contains(&tx.ledger));
+    transactions.sort_by_key(|tx| tx.ledger);
+
+    let mut replay = Replay::new();
+    for tx in &transactions {
+        replay
+            .apply_transaction(tx)
+            .map_err(|err| format!("transaction {}: {err}", tx.tx_hash))?;
+    }
+
+    let output = serde_json::to_string_pretty(replay.escrows()).map_err(|err| err.to_string())?;
+    println!("{output}");
+    Ok(())
+}
+
+fn parse_ledger(value: Option<String>) -> Result<u32, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| "expected a ledger sequence number".to_string())
+}
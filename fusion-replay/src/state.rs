@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EmittedEvent, FusionEvent};
+
+/// Escrow state as reconstructed from its events
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscrowState {
+    #[default]
+    Active,
+    Funded,
+    Withdrawn,
+    Cancelled,
+    Expired,
+}
+
+/// Everything known about one escrow after replaying its events
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct EscrowRecord {
+    pub order_hash: Option<String>,
+    /// Cross-chain swap id, shared with the other leg's escrow
+    pub swap_id: Option<String>,
+    pub maker: Option<String>,
+    pub taker: Option<String>,
+    /// Factory that deployed the escrow, with the salt it used
+    pub factory: Option<String>,
+    pub salt: Option<String>,
+    pub details: Option<String>,
+    /// Escrowed token, from the funding event
+    pub token: Option<String>,
+    pub state: EscrowState,
+    /// Amount recorded by pull_funding/confirm_funding, and who pulled it
+    pub funded_amount: i128,
+    pub funder: Option<String>,
+    /// Total paid out to the taker (fill ledger)
+    pub filled_amount: i128,
+    /// Unfilled remainder returned to the maker on cancel
+    pub refunded_amount: i128,
+    pub revealed_secret: Option<String>,
+    /// Second secret of a two-hashlock escrow
+    pub revealed_second_secret: Option<String>,
+    /// Highest timelock stage reported by ping
+    pub last_stage: Option<u32>,
+    /// Safety deposits left claimable after a failed inline transfer, by recipient
+    pub pending_deposits: BTreeMap<String, i128>,
+    /// Storage reclaimed by finalize; the escrow no longer answers getters
+    pub finalized: bool,
+    pub first_ledger: u32,
+    pub last_ledger: u32,
+}
+
+/// An event applied to an escrow, with the escrow's state before it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppliedEvent {
+    pub escrow: String,
+    pub event: FusionEvent,
+    /// None when the event is the first seen for the escrow
+    pub previous_state: Option<EscrowState>,
+}
+
+/// Reconstructs escrow state by applying events in ledger order
+#[derive(Debug, Default)]
+pub struct Replay {
+    escrows: BTreeMap<String, EscrowRecord>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply one decoded event emitted in `ledger`
+    pub fn apply(&mut self, ledger: u32, emitted: &EmittedEvent) -> AppliedEvent {
+        let escrow_address = match &emitted.event {
+            FusionEvent::EscrowDeployed { escrow, .. } => escrow,
+            _ => &emitted.contract,
+        };
+        let previous_state = self.escrows.get(escrow_address).map(|record| record.state);
+        let record = self.escrows.entry(escrow_address.clone()).or_insert_with(|| EscrowRecord {
+            first_ledger: ledger,
+            ..Default::default()
+        });
+        record.last_ledger = ledger;
+        if let Some(swap_id) = &emitted.swap_id {
+            record.swap_id.get_or_insert_with(|| swap_id.clone());
+        }
+
+        match &emitted.event {
+            FusionEvent::EscrowDeployed { order_hash, salt, .. } => {
+                record.factory = Some(emitted.contract.clone());
+                record.salt = Some(salt.clone());
+                record.order_hash.get_or_insert_with(|| order_hash.clone());
+            }
+            FusionEvent::EscrowCreated { order_hash, maker, taker } => {
+                record.order_hash = Some(order_hash.clone());
+                record.maker = Some(maker.clone());
+                record.taker = Some(taker.clone());
+            }
+            FusionEvent::Details { details } => record.details = Some(details.clone()),
+            FusionEvent::Funded { token, funder, total_funded, .. } => {
+                record.token = Some(token.clone());
+                record.funded_amount = *total_funded;
+                record.funder = funder.clone();
+                if record.state == EscrowState::Active {
+                    record.state = EscrowState::Funded;
+                }
+            }
+            FusionEvent::Withdrawn { secret } => {
+                record.revealed_secret = Some(secret.clone());
+                record.state = EscrowState::Withdrawn;
+            }
+            FusionEvent::SecondSecret { secret } => record.revealed_second_secret = Some(secret.clone()),
+            FusionEvent::PartialWithdraw { secret, filled, .. } => {
+                record.revealed_secret = Some(secret.clone());
+                record.filled_amount = *filled;
+            }
+            FusionEvent::Cancelled { refunded, filled } => {
+                record.refunded_amount = *refunded;
+                record.filled_amount = *filled;
+                record.state = EscrowState::Cancelled;
+            }
+            FusionEvent::Expired => record.state = EscrowState::Expired,
+            FusionEvent::Finalized => record.finalized = true,
+            FusionEvent::StageTransition { stage } => {
+                record.last_stage = Some(record.last_stage.map_or(*stage, |last| last.max(*stage)));
+            }
+            FusionEvent::DepositFailed { recipient, amount } => {
+                *record.pending_deposits.entry(recipient.clone()).or_default() += amount;
+            }
+            FusionEvent::DepositClaimed { recipient, .. } => {
+                record.pending_deposits.remove(recipient);
+            }
+        }
+
+        AppliedEvent { escrow: escrow_address.clone(), event: emitted.event.clone(), previous_state }
+    }
+
+    /// Reconstructed escrows keyed by escrow address
+    pub fn escrows(&self) -> &BTreeMap<String, EscrowRecord> {
+        &self.escrows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::decode_event;
+    use stellar_xdr::curr::{
+        ContractEvent, ContractEventBody, ContractEventType, ContractEventV0, ContractId, ExtensionPoint,
+        Hash, Int128Parts, ScAddress, ScSymbol, ScVal, ScVec,
+    };
+
+    fn sym(name: &str) -> ScVal {
+        ScVal::Symbol(ScSymbol(name.try_into().unwrap()))
+    }
+
+    fn i128_val(value: i128) -> ScVal {
+        ScVal::I128(Int128Parts { hi: (value >> 64) as i64, lo: value as u64 })
+    }
+
+    fn escrow_event(contract: u8, name: &str, data: ScVal) -> ContractEvent {
+        versioned_event(contract, vec![sym("v3"), sym("escrow"), sym(name)], tuple(vec![ScVal::Void, data]))
+    }
+
+    fn versioned_event(contract: u8, topics: Vec<ScVal>, data: ScVal) -> ContractEvent {
+        ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: Some(ContractId(Hash([contract; 32]))),
+            type_: ContractEventType::Contract,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: topics.try_into().unwrap(),
+                data,
+            }),
+        }
+    }
+
+    fn tuple(items: Vec<ScVal>) -> ScVal {
+        ScVal::Vec(Some(ScVec(items.try_into().unwrap())))
+    }
+
+    #[test]
+    fn test_partial_fill_then_cancel() {
+        let mut replay = Replay::new();
+        let secret = ScVal::Bytes(vec![7u8; 32].try_into().unwrap());
+        let events = [
+            escrow_event(1, "partial", tuple(vec![secret, i128_val(300), i128_val(300)])),
+            escrow_event(1, "cancel", tuple(vec![i128_val(700), i128_val(300)])),
+        ];
+        for (ledger, event) in events.iter().enumerate() {
+            replay.apply(ledger as u32 + 10, &decode_event(event).unwrap());
+        }
+
+        let record = replay.escrows().values().next().unwrap();
+        assert_eq!(record.state, EscrowState::Cancelled);
+        assert_eq!(record.filled_amount, 300);
+        assert_eq!(record.refunded_amount, 700);
+        assert_eq!(record.revealed_secret.as_deref(), Some(hex::encode([7u8; 32]).as_str()));
+        assert_eq!((record.first_ledger, record.last_ledger), (10, 11));
+    }
+
+    #[test]
+    fn test_ignores_unrelated_events() {
+        let event = escrow_event(1, "unknown", ScVal::Void);
+        assert_eq!(decode_event(&event), None);
+    }
+
+    #[test]
+    fn test_decodes_unversioned_and_rejects_newer_schemas() {
+        let data = tuple(vec![i128_val(700), i128_val(300)]);
+        let current = decode_event(&escrow_event(1, "cancel", data.clone())).unwrap();
+        let legacy = versioned_event(1, vec![sym("escrow"), sym("cancel")], data.clone());
+        assert_eq!(decode_event(&legacy), Some(current.clone()));
+
+        let v2 = versioned_event(1, vec![sym("v2"), sym("escrow"), sym("cancel")], data.clone());
+        assert_eq!(decode_event(&v2), Some(current));
+
+        let newer = versioned_event(1, vec![sym("v4"), sym("escrow"), sym("cancel")], data);
+        assert_eq!(decode_event(&newer), None);
+    }
+
+    #[test]
+    fn test_swap_id_correlates_factory_and_escrow_events() {
+        let swap_id = ScVal::Bytes(vec![9u8; 32].try_into().unwrap());
+        let escrow = ScVal::Address(ScAddress::Contract(ContractId(Hash([1; 32]))));
+        let bytes32 = |byte: u8| ScVal::Bytes(vec![byte; 32].try_into().unwrap());
+        let deployed = versioned_event(
+            2,
+            vec![sym("escrow_deployed")],
+            tuple(vec![escrow, bytes32(3), bytes32(4), swap_id.clone()]),
+        );
+        let cancelled = versioned_event(
+            1,
+            vec![sym("v3"), sym("escrow"), sym("cancel")],
+            tuple(vec![swap_id, tuple(vec![i128_val(700), i128_val(0)])]),
+        );
+
+        let mut replay = Replay::new();
+        for event in [deployed, cancelled] {
+            let emitted = decode_event(&event).unwrap();
+            assert_eq!(emitted.swap_id, Some(hex::encode([9u8; 32])));
+            replay.apply(10, &emitted);
+        }
+        let record = replay.escrows().values().next().unwrap();
+        assert_eq!(replay.escrows().len(), 1);
+        assert_eq!(record.swap_id, Some(hex::encode([9u8; 32])));
+        assert_eq!(record.state, EscrowState::Cancelled);
+    }
+}
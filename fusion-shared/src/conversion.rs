@@ -0,0 +1,141 @@
+//! EVM ABI encoding of Stellar values
+//!
+//! The EVM escrow factory derives escrow addresses from
+//! `keccak256(abi.encode(immutables))`. Encoding the Stellar immutables the same
+//! way lets both chains compute each other's salts: every field becomes one
+//! 32-byte word, with Stellar addresses mapped to their 32-byte account key or
+//! contract id.
+
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env};
+
+/// Builds an EVM ABI encoding of static (one word per value) fields
+pub struct AbiEncoder {
+    bytes: Bytes,
+}
+
+impl AbiEncoder {
+    pub fn new(env: &Env) -> Self {
+        Self { bytes: Bytes::new(env) }
+    }
+
+    /// `bytes32`
+    pub fn bytes32(&mut self, value: &BytesN<32>) -> &mut Self {
+        self.bytes.append(&value.clone().into());
+        self
+    }
+
+    /// Stellar address as the 32-byte word expected by the EVM side
+    pub fn address(&mut self, value: &Address) -> &mut Self {
+        let word = address_word(self.bytes.env(), value);
+        self.bytes32(&word)
+    }
+
+    /// `int256`, sign-extended
+    pub fn int(&mut self, value: i128) -> &mut Self {
+        let fill = if value < 0 { 0xff } else { 0 };
+        self.bytes.extend_from_array(&[fill; 16]);
+        self.bytes.extend_from_array(&value.to_be_bytes());
+        self
+    }
+
+    /// `address`, left-padded to a word
+    pub fn evm_address(&mut self, value: &BytesN<20>) -> &mut Self {
+        self.bytes.extend_from_array(&[0u8; 12]);
+        self.bytes.append(&value.clone().into());
+        self
+    }
+
+    /// `uint256`
+    pub fn uint(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_array(&[0u8; 24]);
+        self.bytes.extend_from_array(&value.to_be_bytes());
+        self
+    }
+
+    /// The encoded words
+    pub fn finish(&self) -> Bytes {
+        self.bytes.clone()
+    }
+
+    /// keccak256 of the encoded words
+    pub fn keccak256(&self) -> BytesN<32> {
+        self.bytes.env().crypto().keccak256(&self.bytes).into()
+    }
+}
+
+/// The 32-byte payload of an address: the ed25519 key of an account or the contract id
+pub fn address_word(env: &Env, address: &Address) -> BytesN<32> {
+    // ScVal::Address XDR ends with the 32-byte key for both account and contract addresses
+    let xdr = address.clone().to_xdr(env);
+    let mut word = [0u8; 32];
+    xdr.slice(xdr.len() - 32..).copy_into_slice(&mut word);
+    BytesN::from_array(env, &word)
+}
+
+/// Escrow salt: keccak256 of the ABI-encoded immutables, in EVM `Immutables` field order
+#[allow(clippy::too_many_arguments)]
+pub fn derive_salt(
+    env: &Env,
+    order_hash: &BytesN<32>,
+    hashlock: &BytesN<32>,
+    maker: &Address,
+    taker: &Address,
+    token: &Address,
+    amount: i128,
+    safety_deposit: i128,
+    timelocks: u64,
+) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(order_hash)
+        .bytes32(hashlock)
+        .address(maker)
+        .address(taker)
+        .address(token)
+        .int(amount)
+        .int(safety_deposit)
+        .uint(timelocks)
+        .keccak256()
+}
+
+/// 1inch `Timelocks` word: `deployed_at` in the top 32 bits and one uint32 offset (seconds after
+/// deployment) per stage from bit 0, in `Stage` order (src withdrawal ... dst cancellation)
+pub fn evm_timelocks(env: &Env, deployed_at: u32, offsets: [u32; 7]) -> BytesN<32> {
+    let mut word = [0u8; 32];
+    word[..4].copy_from_slice(&deployed_at.to_be_bytes());
+    for (stage, offset) in offsets.iter().enumerate() {
+        let end = 32 - 4 * stage;
+        word[end - 4..end].copy_from_slice(&offset.to_be_bytes());
+    }
+    BytesN::from_array(env, &word)
+}
+
+/// Absolute time at which `stage` of a 1inch `Timelocks` word opens (`TimelocksLib.get`)
+pub fn evm_timelock(word: &BytesN<32>, stage: u8) -> u64 {
+    let word = word.to_array();
+    let uint32_at = |end: usize| u32::from_be_bytes([word[end - 4], word[end - 3], word[end - 2], word[end - 1]]);
+    uint32_at(4) as u64 + uint32_at(32 - 4 * stage as usize) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::testutils::Address as _;
+
+    #[test]
+    fn test_abi_words() {
+        let env = Env::default();
+        let contract = Address::generate(&env);
+        let mut encoder = AbiEncoder::new(&env);
+        encoder.int(-2).uint(0x0102).address(&contract);
+        let encoded = encoder.finish();
+
+        assert_eq!(encoded.len(), 96);
+        let mut words = [0u8; 96];
+        encoded.copy_into_slice(&mut words);
+        assert!(words[..31].iter().all(|b| *b == 0xff));
+        assert_eq!(words[31], 0xfe);
+        assert!(words[32..62].iter().all(|b| *b == 0));
+        assert_eq!(&words[62..64], &[0x01, 0x02]);
+        assert_eq!(&words[64..], &address_word(&env, &contract).to_array());
+    }
+}
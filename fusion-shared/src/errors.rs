@@ -0,0 +1,62 @@
+//! Contract error code namespaces
+//!
+//! Each contract numbers its `contracterror` variants inside its own range so a
+//! relayer can tell from an `Error(Contract, #code)` HostError which contract
+//! failed, even across cross-contract calls:
+//!
+//! | Contract | Codes |
+//! |----------|-------|
+//! | Escrow   | 101-199 |
+//! | Factory  | 201-299 |
+//! | Resolver | 301-399 |
+//! | Finality oracle | 401-499 |
+//! | Conversion hook | 501-599 |
+
+pub const ESCROW_ERROR_BASE: u32 = 100;
+pub const FACTORY_ERROR_BASE: u32 = 200;
+pub const RESOLVER_ERROR_BASE: u32 = 300;
+pub const ORACLE_ERROR_BASE: u32 = 400;
+pub const HOOK_ERROR_BASE: u32 = 500;
+
+/// Width of each contract's error range
+pub const ERROR_RANGE: u32 = 100;
+
+/// Contract that owns an error code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorSource {
+    Escrow,
+    Factory,
+    Resolver,
+    Oracle,
+    Hook,
+}
+
+/// Map a contract error code to the contract that defines it
+/// Returns the source and the code's offset within its range
+pub fn error_source(code: u32) -> Option<(ErrorSource, u32)> {
+    let source = match code / ERROR_RANGE * ERROR_RANGE {
+        ESCROW_ERROR_BASE => ErrorSource::Escrow,
+        FACTORY_ERROR_BASE => ErrorSource::Factory,
+        RESOLVER_ERROR_BASE => ErrorSource::Resolver,
+        ORACLE_ERROR_BASE => ErrorSource::Oracle,
+        HOOK_ERROR_BASE => ErrorSource::Hook,
+        _ => return None,
+    };
+    Some((source, code % ERROR_RANGE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_source() {
+        assert_eq!(error_source(101), Some((ErrorSource::Escrow, 1)));
+        assert_eq!(error_source(203), Some((ErrorSource::Factory, 3)));
+        assert_eq!(error_source(301), Some((ErrorSource::Resolver, 1)));
+        assert_eq!(error_source(402), Some((ErrorSource::Oracle, 2)));
+        assert_eq!(error_source(503), Some((ErrorSource::Hook, 3)));
+        assert_eq!(error_source(1), None);
+        assert_eq!(error_source(600), None);
+    }
+}
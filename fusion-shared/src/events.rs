@@ -0,0 +1,11 @@
+//! Event publishing shared by the contracts
+
+use soroban_sdk::{events::Topics, Env, IntoVal, Val};
+
+/// Publish a `(topics, data)` event, the tuple layout the relayer parses
+/// `Events::publish` is deprecated in SDK 23 in favour of `#[contractevent]`; this is the one
+/// place that allows it, so other deprecations still warn in the contract crates
+#[allow(deprecated)]
+pub fn publish<T: Topics, D: IntoVal<Env, Val>>(env: &Env, topics: T, data: D) {
+    env.events().publish(topics, data);
+}
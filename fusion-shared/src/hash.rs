@@ -0,0 +1,93 @@
+//! Hash functions behind one interface
+//!
+//! Hashlocks and immutables hashes must use the hash native to the counterparty chain's
+//! escrow. Contracts pick a `HashFunction` at build time instead of calling a specific
+//! host function at every entrypoint. sha3-256 has no Soroban host function and is
+//! computed in wasm, behind the `sha3` feature.
+
+use soroban_sdk::{Bytes, BytesN, Env};
+
+/// A 32-byte hash function
+pub trait HashFunction {
+    /// Digest of `data`
+    fn digest(env: &Env, data: &Bytes) -> BytesN<32>;
+}
+
+/// SHA-256 (host function); the Fusion+ default for hashlocks
+pub struct Sha256;
+
+impl HashFunction for Sha256 {
+    fn digest(env: &Env, data: &Bytes) -> BytesN<32> {
+        env.crypto().sha256(data).to_bytes()
+    }
+}
+
+/// Keccak-256 (host function), as used natively by EVM chains
+pub struct Keccak256;
+
+impl HashFunction for Keccak256 {
+    fn digest(env: &Env, data: &Bytes) -> BytesN<32> {
+        env.crypto().keccak256(data).to_bytes()
+    }
+}
+
+/// SHA3-256 (FIPS 202), computed in wasm
+#[cfg(feature = "sha3")]
+pub struct Sha3_256;
+
+#[cfg(feature = "sha3")]
+impl HashFunction for Sha3_256 {
+    fn digest(env: &Env, data: &Bytes) -> BytesN<32> {
+        use sha3::Digest;
+
+        let mut hasher = sha3::Sha3_256::new();
+        let mut chunk = [0u8; 64];
+        let mut start = 0;
+        while start < data.len() {
+            let end = (start + chunk.len() as u32).min(data.len());
+            let len = (end - start) as usize;
+            data.slice(start..end).copy_into_slice(&mut chunk[..len]);
+            hasher.update(&chunk[..len]);
+            start = end;
+        }
+        BytesN::from_array(env, &hasher.finalize().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_of<H: HashFunction>(env: &Env, data: &[u8]) -> [u8; 32] {
+        H::digest(env, &Bytes::from_slice(env, data)).to_array()
+    }
+
+    #[test]
+    fn test_known_digests() {
+        let env = Env::default();
+        assert_eq!(
+            digest_of::<Sha256>(&env, b"abc")[..4],
+            [0xba, 0x78, 0x16, 0xbf]
+        );
+        assert_eq!(
+            digest_of::<Keccak256>(&env, b"")[..4],
+            [0xc5, 0xd2, 0x46, 0x01]
+        );
+    }
+
+    #[cfg(feature = "sha3")]
+    #[test]
+    fn test_sha3_spans_chunks() {
+        let env = Env::default();
+        assert_eq!(
+            digest_of::<Sha3_256>(&env, b"abc")[..4],
+            [0x3a, 0x98, 0x5d, 0xa7]
+        );
+        // Inputs longer than one copy chunk hash the same as when hashed in one go
+        let long = [7u8; 150];
+        let mut hasher = <sha3::Sha3_256 as sha3::Digest>::new();
+        sha3::Digest::update(&mut hasher, long);
+        let expected: [u8; 32] = sha3::Digest::finalize(hasher).into();
+        assert_eq!(digest_of::<Sha3_256>(&env, &long), expected);
+    }
+}
@@ -0,0 +1,109 @@
+// Contract interfaces take each field as a separate argument, like the contracts themselves
+#![allow(clippy::too_many_arguments)]
+
+use soroban_sdk::{contractclient, Address, BytesN, Env};
+
+use crate::types::{EvmAddress, Immutables, State};
+
+/// Entrypoints every escrow implementation must provide
+/// Alternative escrows (multi-escrow, fee-enabled, partial-fill) stay reachable
+/// through the generated `EscrowClient` as long as they expose these
+#[contractclient(name = "EscrowClient")]
+pub trait EscrowInterface {
+    /// Initialize the escrow; returns its own address
+    fn deploy(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Address;
+
+    /// Fund the escrow by pulling tokens from `from` using a prior approval
+    fn pull_funding(env: Env, from: Address);
+
+    /// Confirm funding pushed directly to the escrow address
+    fn confirm_funding(env: Env);
+
+    /// Withdraw to the taker by revealing the secret
+    fn withdraw(env: Env, secret: BytesN<32>, unwrap_native: bool);
+
+    /// Cancel and refund the maker once the timelock allows it
+    fn cancel(env: Env, caller: Address);
+
+    /// Withdraw to the caller after the public withdrawal timelock
+    fn public_withdraw(env: Env, secret: BytesN<32>, caller: Address);
+
+    /// Collect the safety deposit shares credited to `caller` as a keeper
+    fn claim_rewards(env: Env, caller: Address) -> i128;
+
+    fn get_state(env: Env) -> State;
+
+    fn get_immutables(env: Env) -> Immutables;
+
+    fn get_funded_amount(env: Env) -> i128;
+
+    fn get_filled_amount(env: Env) -> i128;
+}
+
+/// Entrypoints every escrow factory implementation must provide
+#[contractclient(name = "FactoryClient")]
+pub trait FactoryInterface {
+    /// Deploy and initialize an escrow at the address derived from its immutables
+    /// (see `conversion::derive_salt`); the taker must authorize it
+    fn deploy_escrow(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Address;
+
+    /// Address the escrow for these immutables would have
+    fn calculate_escrow_address(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Address;
+
+    fn is_deployed(env: Env, salt: BytesN<32>) -> bool;
+
+    fn get_htlc_hash(env: Env) -> BytesN<32>;
+
+    /// Stellar equivalent of an EVM token on a registered chain, if mapped
+    fn get_wrapped_token(env: Env, chain_id: u64, evm_token: EvmAddress) -> Option<Address>;
+}
+
+/// Finality confirmation consumed by escrows deployed with a finality oracle
+/// Light clients and attestation contracts plug in by exposing this one entrypoint
+#[contractclient(name = "FinalityOracleClient")]
+pub trait FinalityOracleInterface {
+    /// Whether the source-chain lock of `order_hash` can no longer be reorganized away
+    fn is_final(env: Env, order_hash: BytesN<32>) -> bool;
+}
+
+/// Integration hooks of escrows deployed with `deploy_with_hooks`, like LOP pre/post interactions
+/// The escrow isolates hook failures: a hook that errors is skipped with a `hook_failed` event,
+/// so it can't keep funds locked
+#[contractclient(name = "EscrowHookClient")]
+pub trait EscrowHookInterface {
+    /// Called before `escrow` accepts its `amount` of `token` as funding
+    fn before_funding(env: Env, escrow: Address, order_hash: BytesN<32>, token: Address, amount: i128);
+
+    /// Called after `escrow` paid `amount` of `token` to `recipient`
+    fn after_payout(env: Env, escrow: Address, order_hash: BytesN<32>, token: Address, recipient: Address, amount: i128);
+}
@@ -0,0 +1,23 @@
+#![no_std]
+//! Types and interfaces shared by the escrow, factory and resolver contracts
+//!
+//! Contracts keep their own `contracttype` definitions; the types here mirror
+//! their XDR layout so trait-generated clients can talk to any compliant
+//! implementation.
+//!
+//! Events are published as `(topics, data)` tuples through `events::publish`, the layout the
+//! relayer parses.
+
+pub mod conversion;
+pub mod errors;
+pub mod events;
+pub mod hash;
+pub mod interfaces;
+pub mod merkle;
+pub mod order;
+pub mod swap_id;
+mod trace;
+pub mod types;
+
+pub use interfaces::*;
+pub use types::*;
@@ -0,0 +1,58 @@
+//! Merkle proofs for multi-secret (partial fill) orders
+//!
+//! Matches 1inch's `MerkleStorageInvalidator`: an order that can be filled in parts
+//! commits to one secret per part, and its hashlock is the root of a tree whose leaves
+//! are `keccak256(uint64 index ++ secret_hash)`. Pairs are hashed in sorted order
+//! (OpenZeppelin `MerkleProof`), so proofs carry no left/right flags.
+
+use soroban_sdk::{Bytes, BytesN, Env, Vec};
+
+/// Leaf committing to the secret of the fill at `index`
+pub fn secret_leaf(env: &Env, index: u64, secret_hash: &BytesN<32>) -> BytesN<32> {
+    let mut bytes = Bytes::from_array(env, &index.to_be_bytes());
+    bytes.append(&secret_hash.clone().into());
+    env.crypto().keccak256(&bytes).into()
+}
+
+/// keccak256 of a pair of nodes, smaller one first
+pub fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (first, second) = if a.to_array() <= b.to_array() { (a, b) } else { (b, a) };
+    let mut bytes: Bytes = first.clone().into();
+    bytes.append(&second.clone().into());
+    env.crypto().keccak256(&bytes).into()
+}
+
+/// Root reached by folding `proof` into `leaf`
+pub fn process_proof(env: &Env, leaf: &BytesN<32>, proof: &Vec<BytesN<32>>) -> BytesN<32> {
+    proof.iter().fold(leaf.clone(), |node, sibling| hash_pair(env, &node, &sibling))
+}
+
+/// Whether `proof` shows `leaf` is part of the tree with root `root`
+pub fn verify_proof(env: &Env, root: &BytesN<32>, leaf: &BytesN<32>, proof: &Vec<BytesN<32>>) -> bool {
+    process_proof(env, leaf, proof) == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::vec;
+
+    #[test]
+    fn test_verify_four_leaf_tree() {
+        let env = Env::default();
+        let leaves: [BytesN<32>; 4] = core::array::from_fn(|i| {
+            secret_leaf(&env, i as u64, &BytesN::from_array(&env, &[i as u8 + 1; 32]))
+        });
+        let left = hash_pair(&env, &leaves[0], &leaves[1]);
+        let right = hash_pair(&env, &leaves[2], &leaves[3]);
+        let root = hash_pair(&env, &left, &right);
+
+        // Sorted pairs: argument order doesn't matter
+        assert_eq!(hash_pair(&env, &leaves[1], &leaves[0]), left);
+
+        let proof = vec![&env, leaves[3].clone(), left.clone()];
+        assert!(verify_proof(&env, &root, &leaves[2], &proof));
+        assert!(!verify_proof(&env, &root, &leaves[1], &proof));
+        assert!(!verify_proof(&env, &root, &leaves[2], &vec![&env, leaves[3].clone()]));
+    }
+}
@@ -0,0 +1,174 @@
+//! EIP-712-compatible hashing of Stellar-origin orders
+//!
+//! `hash_order = keccak256(0x19 0x01 ++ domainSeparator ++ hashStruct(order))`, with the
+//! domain binding the network (its network id as `chainId`), the verifying factory and the
+//! order format version, so an order signed for one network or factory can't be replayed on
+//! another. Stellar addresses are encoded as their 32-byte key (`bytes32`), EVM addresses as
+//! `address`, so EVM tooling can reproduce the hash from the type strings below.
+//!
+//! Same-chain limit orders (`match_orders`) are hashed under the same domain with their own type.
+
+use soroban_sdk::{Address, Bytes, BytesN, Env};
+
+use crate::conversion::AbiEncoder;
+use crate::types::{DstEscrowParams, LimitOrder, Order};
+
+pub const DOMAIN_NAME: &str = "Fusion+ Stellar";
+
+/// Bumped whenever the Order layout or its encoding changes
+pub const DOMAIN_VERSION: &str = "2";
+
+pub const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,bytes32 verifyingContract)";
+
+pub const DST_ESCROW_PARAMS_TYPE: &str =
+    "DstEscrowParams(uint256 chainId,address maker,address token,int256 amount,int256 safetyDeposit)";
+
+/// Referenced struct types are appended after the primary type, as EIP-712 requires
+pub const ORDER_TYPE: &str = "Order(bytes32 maker,bytes32 taker,bytes32 token,int256 amount,int256 safetyDeposit,\
+bytes32 hashlock,uint256 timelocks,uint256 salt,uint256 nonce,DstEscrowParams dst)\
+DstEscrowParams(uint256 chainId,address maker,address token,int256 amount,int256 safetyDeposit)";
+
+pub const LIMIT_ORDER_TYPE: &str = "LimitOrder(bytes32 maker,bytes32 makerToken,int256 makerAmount,\
+bytes32 takerToken,int256 takerAmount,uint256 salt,uint256 nonce)";
+
+fn keccak_str(env: &Env, value: &str) -> BytesN<32> {
+    env.crypto().keccak256(&Bytes::from_slice(env, value.as_bytes())).into()
+}
+
+/// Domain separator for orders verified by `verifying_contract` on the current network
+pub fn domain_separator(env: &Env, verifying_contract: &Address) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(&keccak_str(env, DOMAIN_TYPE))
+        .bytes32(&keccak_str(env, DOMAIN_NAME))
+        .bytes32(&keccak_str(env, DOMAIN_VERSION))
+        .bytes32(&env.ledger().network_id())
+        .address(verifying_contract)
+        .keccak256()
+}
+
+fn hash_dst(env: &Env, dst: &DstEscrowParams) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(&keccak_str(env, DST_ESCROW_PARAMS_TYPE))
+        .uint(dst.chain_id)
+        .evm_address(&dst.maker.0)
+        .evm_address(&dst.token.0)
+        .int(dst.amount)
+        .int(dst.safety_deposit)
+        .keccak256()
+}
+
+/// `hashStruct(order)`
+pub fn hash_struct(env: &Env, order: &Order) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(&keccak_str(env, ORDER_TYPE))
+        .address(&order.maker)
+        .address(&order.taker)
+        .address(&order.token)
+        .int(order.amount)
+        .int(order.safety_deposit)
+        .bytes32(&order.hashlock)
+        .uint(order.timelocks)
+        .uint(order.salt)
+        .uint(order.nonce)
+        .bytes32(&hash_dst(env, &order.dst))
+        .keccak256()
+}
+
+/// `hashStruct(order)` of a limit order
+pub fn hash_limit_struct(env: &Env, order: &LimitOrder) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(&keccak_str(env, LIMIT_ORDER_TYPE))
+        .address(&order.maker)
+        .address(&order.maker_token)
+        .int(order.maker_amount)
+        .address(&order.taker_token)
+        .int(order.taker_amount)
+        .uint(order.salt)
+        .uint(order.nonce)
+        .keccak256()
+}
+
+fn typed_data_hash(env: &Env, verifying_contract: &Address, struct_hash: BytesN<32>) -> BytesN<32> {
+    let mut bytes = Bytes::from_array(env, &[0x19, 0x01]);
+    bytes.append(&domain_separator(env, verifying_contract).into());
+    bytes.append(&struct_hash.into());
+    env.crypto().keccak256(&bytes).into()
+}
+
+/// Order hash the maker authorizes, bound to `verifying_contract` (the factory) and the network
+pub fn hash_order(env: &Env, verifying_contract: &Address, order: &Order) -> BytesN<32> {
+    typed_data_hash(env, verifying_contract, hash_struct(env, order))
+}
+
+/// Limit order hash the maker authorizes, under the same domain as `hash_order`
+pub fn hash_limit_order(env: &Env, verifying_contract: &Address, order: &LimitOrder) -> BytesN<32> {
+    typed_data_hash(env, verifying_contract, hash_limit_struct(env, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::EvmAddress;
+    use soroban_sdk::testutils::{Address as _, Ledger};
+
+    fn order(env: &Env) -> Order {
+        Order {
+            maker: Address::generate(env),
+            taker: Address::generate(env),
+            token: Address::generate(env),
+            amount: 1000,
+            safety_deposit: 10,
+            hashlock: BytesN::from_array(env, &[1; 32]),
+            timelocks: 42,
+            salt: 7,
+            nonce: 0,
+            dst: DstEscrowParams {
+                chain_id: 8453,
+                maker: EvmAddress(BytesN::from_array(env, &[2; 20])),
+                token: EvmAddress(BytesN::from_array(env, &[3; 20])),
+                amount: 990,
+                safety_deposit: 5,
+            },
+        }
+    }
+
+    #[test]
+    fn test_hash_is_domain_separated() {
+        let env = Env::default();
+        let order = order(&env);
+        let factory = Address::generate(&env);
+        let hash = hash_order(&env, &factory, &order);
+        assert_eq!(hash_order(&env, &factory, &order), hash);
+
+        // Another factory, another network or another order field: another hash
+        assert_ne!(hash_order(&env, &Address::generate(&env), &order), hash);
+        assert_ne!(hash_order(&env, &factory, &Order { salt: 8, ..order.clone() }), hash);
+        assert_ne!(hash_order(&env, &factory, &Order { nonce: 1, ..order.clone() }), hash);
+        env.ledger().set_network_id([9; 32]);
+        assert_ne!(hash_order(&env, &factory, &order), hash);
+    }
+
+    #[test]
+    fn test_limit_order_hash_is_domain_separated() {
+        let env = Env::default();
+        let order = LimitOrder {
+            maker: Address::generate(&env),
+            maker_token: Address::generate(&env),
+            maker_amount: 1000,
+            taker_token: Address::generate(&env),
+            taker_amount: 500,
+            salt: 7,
+            nonce: 0,
+        };
+        let factory = Address::generate(&env);
+        let hash = hash_limit_order(&env, &factory, &order);
+        assert_eq!(hash_limit_order(&env, &factory, &order), hash);
+
+        assert_ne!(hash_limit_order(&env, &Address::generate(&env), &order), hash);
+        assert_ne!(hash_limit_order(&env, &factory, &LimitOrder { taker_amount: 501, ..order.clone() }), hash);
+        assert_ne!(hash_limit_order(&env, &factory, &LimitOrder { nonce: 1, ..order.clone() }), hash);
+        env.ledger().set_network_id([9; 32]);
+        assert_ne!(hash_limit_order(&env, &factory, &order), hash);
+    }
+}
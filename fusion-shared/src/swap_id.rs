@@ -0,0 +1,57 @@
+//! Cross-chain swap identifiers
+//!
+//! `swap_id = keccak256(abi.encode(bytes32 orderHash, uint256 srcChainId, uint256 dstChainId))`
+//! names a swap rather than one of its escrows: both legs derive the same id, Stellar being the
+//! source chain of a source-side escrow and the destination chain of a destination-side one.
+//! Escrows, factory and resolver records, events and relayer logs all carry it, so one value
+//! correlates the swap across contracts and services. EVM tooling reproduces it from the
+//! encoding above.
+
+use soroban_sdk::{BytesN, Env};
+
+use crate::conversion::AbiEncoder;
+
+/// Chain id standing for Stellar in swap ids and orders (the relayer's `STELLAR_CHAIN_ID`)
+pub const STELLAR_CHAIN_ID: u64 = 1001;
+
+/// Id of the swap filling `order_hash` from `src_chain_id` to `dst_chain_id`
+pub fn swap_id(env: &Env, order_hash: &BytesN<32>, src_chain_id: u64, dst_chain_id: u64) -> BytesN<32> {
+    AbiEncoder::new(env)
+        .bytes32(order_hash)
+        .uint(src_chain_id)
+        .uint(dst_chain_id)
+        .keccak256()
+}
+
+/// Swap id seen from the Stellar leg: `stellar_is_src` for source-side escrows, with the
+/// other leg on `counterparty_chain_id`
+pub fn stellar_swap_id(env: &Env, order_hash: &BytesN<32>, stellar_is_src: bool, counterparty_chain_id: u64) -> BytesN<32> {
+    if stellar_is_src {
+        swap_id(env, order_hash, STELLAR_CHAIN_ID, counterparty_chain_id)
+    } else {
+        swap_id(env, order_hash, counterparty_chain_id, STELLAR_CHAIN_ID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::Bytes;
+
+    #[test]
+    fn test_swap_id_encodes_like_abi_and_names_the_route() {
+        let env = Env::default();
+        let order_hash = BytesN::from_array(&env, &[7; 32]);
+
+        let mut encoded = [0u8; 96];
+        encoded[..32].copy_from_slice(&[7; 32]);
+        encoded[56..64].copy_from_slice(&8453u64.to_be_bytes());
+        encoded[88..96].copy_from_slice(&STELLAR_CHAIN_ID.to_be_bytes());
+        let expected: BytesN<32> = env.crypto().keccak256(&Bytes::from_array(&env, &encoded)).into();
+        assert_eq!(swap_id(&env, &order_hash, 8453, STELLAR_CHAIN_ID), expected);
+
+        // Both legs agree; the direction is part of the id
+        assert_eq!(stellar_swap_id(&env, &order_hash, false, 8453), expected);
+        assert_ne!(stellar_swap_id(&env, &order_hash, true, 8453), expected);
+    }
+}
@@ -0,0 +1,26 @@
+//! Diagnostic events for debugging swaps on testnet
+//!
+//! `trace!` publishes a `("trace", name)` event when the *calling* contract is built with
+//! its `trace` feature, and compiles to nothing otherwise, so production wasm carries no
+//! instrumentation. Each contract declares the feature itself:
+//!
+//! ```toml
+//! [features]
+//! trace = []
+//! ```
+
+/// Publish a `("trace", $name)` diagnostic event with `$data` when the calling crate's
+/// `trace` feature is enabled; `$name` must be a short symbol (at most 9 characters)
+#[macro_export]
+macro_rules! trace {
+    ($env:expr, $name:literal, $data:expr) => {
+        #[cfg(feature = "trace")]
+        #[allow(deprecated)]
+        {
+            $env.events().publish(
+                (soroban_sdk::symbol_short!("trace"), soroban_sdk::symbol_short!($name)),
+                $data,
+            );
+        }
+    };
+}
@@ -0,0 +1,190 @@
+use soroban_sdk::{contracttype, Address, BytesN};
+
+/// Escrow immutables as returned by `get_immutables`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Immutables {
+    pub order_hash: BytesN<32>,
+    pub hashlock: BytesN<32>,
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub timelocks: u64,
+    pub max_secret_len: u32,
+    /// Second hashlock of two-secret escrows (withdrawn via `withdraw2`)
+    pub hashlock_b: Option<BytesN<32>>,
+    /// Recipient of withdrawals instead of the taker (`deploy_with_beneficiary`)
+    pub beneficiary: Option<Address>,
+    /// Per-action safety deposit splits (`deploy_with_incentives`)
+    pub incentives: DepositPolicy,
+    /// Seconds cancellation stages open after their timelock (factory-configured)
+    pub skew_tolerance: u32,
+    /// Contract whose `is_final(order_hash)` gates withdrawals (`deploy_with_finality_oracle`)
+    pub finality_oracle: Option<Address>,
+    /// Contract whose `before_funding` runs before funding is accepted (`deploy_with_hooks`)
+    pub pre_funding_hook: Option<Address>,
+    /// Contract whose `after_payout` runs after each withdrawal payout (`deploy_with_hooks`)
+    pub post_payout_hook: Option<Address>,
+    /// Cross-chain swap id (`swap_id::swap_id`), recorded by the factory's cross-chain deploys
+    pub swap_id: Option<BytesN<32>>,
+    /// Behavior toggles (the escrow's `FLAG_*` bits, `set_flags`)
+    pub flags: u32,
+    /// Equal parts of a multi-secret escrow, which has `parts + 1` secrets (`set_parts`)
+    pub parts: u32,
+}
+
+/// Immutables version of escrows deployed with `deploy` and its variants
+pub const IMMUTABLES_V1_VERSION: u32 = 1;
+/// Immutables version of escrows deployed with `deploy_v2`, leading their immutables hash
+pub const IMMUTABLES_V2_VERSION: u32 = 2;
+
+/// Escrow immutables in the v2 layout, as returned by `get_immutables_v2`
+/// The v1 core plus the swap leg, flags, receiver and fee policy in one struct; v1 escrows
+/// present theirs through `from_v1`, keeping `version` 1 since their hash is the v1 one
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct ImmutablesV2 {
+    pub version: u32,
+    pub order_hash: BytesN<32>,
+    pub hashlock: BytesN<32>,
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub timelocks: u64,
+    pub side: EscrowSide,
+    pub flags: u32,
+    /// Recipient of withdrawals instead of the taker (v1 `beneficiary`)
+    pub receiver: Option<Address>,
+    /// Safety deposit policy (v1 `incentives`)
+    pub fees: DepositPolicy,
+}
+
+impl ImmutablesV2 {
+    /// The v2 view of a v1 escrow's immutables (version 1); `side` is its `get_side`
+    pub fn from_v1(immutables: &Immutables, side: EscrowSide) -> Self {
+        ImmutablesV2 {
+            version: IMMUTABLES_V1_VERSION,
+            order_hash: immutables.order_hash.clone(),
+            hashlock: immutables.hashlock.clone(),
+            maker: immutables.maker.clone(),
+            taker: immutables.taker.clone(),
+            token: immutables.token.clone(),
+            amount: immutables.amount,
+            safety_deposit: immutables.safety_deposit,
+            timelocks: immutables.timelocks,
+            side,
+            flags: immutables.flags,
+            receiver: immutables.beneficiary.clone(),
+            fees: immutables.incentives.clone(),
+        }
+    }
+}
+
+/// Which leg of the swap an escrow holds, as returned by `get_side`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum EscrowSide {
+    Src = 0,
+    Dst = 1,
+}
+
+/// How an escrow distributes its safety deposit
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositPolicy {
+    Default,
+    Split(DepositIncentives),
+}
+
+/// Basis-point shares of the safety deposit for one action
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositSplit {
+    pub executor_bps: u32,
+    pub maker_bps: u32,
+    pub protocol_bps: u32,
+}
+
+/// Safety deposit splits per escrow action
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositIncentives {
+    pub withdraw: DepositSplit,
+    pub public_withdraw: DepositSplit,
+    pub cancel: DepositSplit,
+    pub public_cancel: DepositSplit,
+    pub protocol: Address,
+    pub remainder: DepositParty,
+    pub dust_threshold: i128,
+}
+
+/// Recipient of a deposit share
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositParty {
+    Executor = 0,
+    Maker = 1,
+    Protocol = 2,
+}
+
+/// A 20-byte EVM address
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct EvmAddress(pub BytesN<20>);
+
+/// Destination leg of a Stellar → EVM swap (mirrors the factory's DstEscrowParams)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DstEscrowParams {
+    pub chain_id: u64,
+    pub maker: EvmAddress,
+    pub token: EvmAddress,
+    pub amount: i128,
+    pub safety_deposit: i128,
+}
+
+/// A maker's Stellar-origin order (mirrors the factory's Order), hashed by `order::hash_order`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Order {
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub hashlock: BytesN<32>,
+    pub timelocks: u64,
+    pub salt: u64,
+    pub nonce: u64,
+    pub dst: DstEscrowParams,
+}
+
+/// A maker's same-chain limit order (mirrors the factory's LimitOrder), hashed by
+/// `order::hash_limit_order`: `maker_amount` of `maker_token` offered for at least
+/// `taker_amount` of `taker_token`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct LimitOrder {
+    pub maker: Address,
+    pub maker_token: Address,
+    pub maker_amount: i128,
+    pub taker_token: Address,
+    pub taker_amount: i128,
+    pub salt: u64,
+    pub nonce: u64,
+}
+
+/// Escrow state as returned by `get_state`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum State {
+    Active = 0,
+    Withdrawn = 1,
+    Cancelled = 2,
+    Funded = 3,
+    Expired = 4,
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use fusion_replay::{Replay, RpcTransactionPage};
+use serde_json::json;
+use tokio::sync::{mpsc, RwLock};
+
+use crate::notify::SwapEvent;
+
+/// Maximum page size accepted by getTransactions
+const PAGE_LIMIT: u32 = 200;
+
+/// Escrow state shared between the indexer and the HTTP handlers
+#[derive(Default)]
+pub struct Index {
+    pub replay: Replay,
+    /// Latest ledger reported by the RPC node
+    pub latest_ledger: u32,
+}
+
+pub type SharedIndex = Arc<RwLock<Index>>;
+
+/// Replays escrow and factory events by polling Soroban RPC getTransactions
+pub struct Indexer {
+    client: reqwest::Client,
+    rpc_url: String,
+    start_ledger: u32,
+    cursor: Option<String>,
+    /// Receives every applied event when notifications are configured
+    events: Option<mpsc::Sender<SwapEvent>>,
+}
+
+impl Indexer {
+    pub fn new(rpc_url: String, start_ledger: u32) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url,
+            start_ledger,
+            cursor: None,
+            events: None,
+        }
+    }
+
+    /// Forward applied events to a Notifier
+    pub fn with_notifications(mut self, events: mpsc::Sender<SwapEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Poll forever, applying each new page to the index
+    pub async fn run(mut self, index: SharedIndex, poll_interval: Duration) {
+        loop {
+            match self.poll(&index).await {
+                // Keep paging while full pages come back (catching up)
+                Ok(count) if count == PAGE_LIMIT as usize => continue,
+                Ok(_) => {}
+                Err(err) => eprintln!("indexer: {err}"),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Fetch and apply one page, returning the number of transactions seen
+    async fn poll(&mut self, index: &SharedIndex) -> Result<usize, String> {
+        let pagination = match &self.cursor {
+            Some(cursor) => json!({ "cursor": cursor, "limit": PAGE_LIMIT }),
+            None => json!({ "limit": PAGE_LIMIT }),
+        };
+        let mut params = json!({ "pagination": pagination });
+        if self.cursor.is_none() {
+            params["startLedger"] = json!(self.start_ledger);
+        }
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getTransactions",
+            "params": params,
+        });
+        let response: serde_json::Value = self
+            .client
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?
+            .json()
+            .await
+            .map_err(|err| err.to_string())?;
+
+        if let Some(error) = response.get("error") {
+            return Err(error.to_string());
+        }
+        let page: RpcTransactionPage = serde_json::from_value(response["result"].clone())
+            .map_err(|err| err.to_string())?;
+
+        let mut swap_events = Vec::new();
+        {
+            let mut index = index.write().await;
+            for tx in &page.transactions {
+                let applied = match index.replay.apply_transaction(tx) {
+                    Ok(applied) => applied,
+                    Err(err) => {
+                        eprintln!("indexer: transaction {}: {err}", tx.tx_hash);
+                        continue;
+                    }
+                };
+                if self.events.is_some() {
+                    swap_events.extend(applied.iter().map(|applied| {
+                        SwapEvent::new(applied, &index.replay.escrows()[&applied.escrow], tx.ledger, &tx.tx_hash)
+                    }));
+                }
+            }
+            index.latest_ledger = page.latest_ledger;
+        }
+
+        // Sent after releasing the index so slow deliveries never block the HTTP handlers
+        if let Some(events) = &self.events {
+            for event in swap_events {
+                if events.send(event).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        if page.cursor.is_some() {
+            self.cursor = page.cursor;
+        }
+        Ok(page.transactions.len())
+    }
+}
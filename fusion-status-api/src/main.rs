@@ -0,0 +1,54 @@
+//! fusion-status-api: REST endpoints exposing escrow and order status
+//!
+//! Configuration (environment):
+//! - `SOROBAN_RPC_URL` - Soroban RPC endpoint (default: testnet)
+//! - `START_LEDGER` - first ledger to index (required by getTransactions on a cold start)
+//! - `LISTEN_ADDR` - HTTP listen address (default: 0.0.0.0:3010)
+//! - `POLL_INTERVAL_SECS` - indexer poll interval (default: 5)
+//! - `NOTIFY_CONFIG` - JSON file of event subscriptions (webhooks, NATS); none when unset
+
+mod indexer;
+mod notify;
+mod routes;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use indexer::{Index, Indexer};
+use notify::{Notifier, NotifyConfig};
+
+const DEFAULT_RPC_URL: &str = "https://soroban-testnet.stellar.org";
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:3010";
+
+/// Events buffered for the notifier before the indexer waits on deliveries
+const NOTIFY_QUEUE: usize = 1024;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let rpc_url = std::env::var("SOROBAN_RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string());
+    let start_ledger: u32 = std::env::var("START_LEDGER")?.parse()?;
+    let listen_addr = std::env::var("LISTEN_ADDR").unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_string());
+    let poll_interval = std::env::var("POLL_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(5));
+
+    let mut indexer = Indexer::new(rpc_url, start_ledger);
+    if let Ok(path) = std::env::var("NOTIFY_CONFIG") {
+        let config: NotifyConfig = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(NOTIFY_QUEUE);
+        tokio::spawn(Notifier::new(config).run(receiver));
+        indexer = indexer.with_notifications(sender);
+    }
+
+    let index = Arc::new(RwLock::new(Index::default()));
+    tokio::spawn(indexer.run(index.clone(), poll_interval));
+
+    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
+    println!("fusion-status-api listening on {listen_addr}");
+    axum::serve(listener, routes::router(index)).await?;
+    Ok(())
+}
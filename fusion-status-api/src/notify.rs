@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use fusion_replay::{AppliedEvent, EscrowRecord, EscrowState};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Webhook deliveries are attempted this many times, with growing delays in between
+const DELIVERY_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// A swap lifecycle event as delivered to subscribers
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SwapEvent {
+    pub escrow: String,
+    /// Event name (`created`, `funded`, `withdraw`, `cancel`, ...)
+    pub event: String,
+    /// Escrow state before and after the event
+    pub previous_state: Option<EscrowState>,
+    pub state: EscrowState,
+    pub order_hash: Option<String>,
+    pub maker: Option<String>,
+    pub taker: Option<String>,
+    pub token: Option<String>,
+    pub ledger: u32,
+    pub tx_hash: String,
+}
+
+impl SwapEvent {
+    pub fn new(applied: &AppliedEvent, record: &EscrowRecord, ledger: u32, tx_hash: &str) -> Self {
+        Self {
+            escrow: applied.escrow.clone(),
+            event: applied.event.name().to_string(),
+            previous_state: applied.previous_state,
+            state: record.state,
+            order_hash: record.order_hash.clone(),
+            maker: record.maker.clone(),
+            taker: record.taker.clone(),
+            token: record.token.clone(),
+            ledger,
+            tx_hash: tx_hash.to_string(),
+        }
+    }
+
+    /// Whether the event moved the escrow to another state
+    pub fn is_transition(&self) -> bool {
+        self.previous_state.is_some_and(|previous| previous != self.state)
+    }
+}
+
+/// Which events a subscription receives; empty lists match anything
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    pub makers: Vec<String>,
+    pub tokens: Vec<String>,
+    /// Only state transitions into one of these states
+    pub transitions: Vec<EscrowState>,
+    /// Only these event names
+    pub events: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, event: &SwapEvent) -> bool {
+        let listed = |list: &[String], value: &Option<String>| {
+            list.is_empty() || value.as_ref().is_some_and(|value| list.contains(value))
+        };
+        listed(&self.makers, &event.maker)
+            && listed(&self.tokens, &event.token)
+            && (self.events.is_empty() || self.events.contains(&event.event))
+            && (self.transitions.is_empty() || (event.is_transition() && self.transitions.contains(&event.state)))
+    }
+}
+
+/// Where matching events are delivered
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Sink {
+    /// HTTP POST of the event as JSON
+    Webhook { url: String },
+    /// NATS core publish on `subject`; `url` is `host:port`
+    Nats { url: String, subject: String },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Subscription {
+    #[serde(default)]
+    pub filter: Filter,
+    pub sink: Sink,
+}
+
+/// Contents of the NOTIFY_CONFIG file
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotifyConfig {
+    pub subscriptions: Vec<Subscription>,
+}
+
+/// Delivers swap events from the indexer to every matching subscription
+pub struct Notifier {
+    client: reqwest::Client,
+    config: NotifyConfig,
+}
+
+impl Notifier {
+    pub fn new(config: NotifyConfig) -> Self {
+        Self { client: reqwest::Client::new(), config }
+    }
+
+    /// Deliver events until the indexer drops its sender
+    /// Events are delivered in indexing order; a failing sink delays the ones behind it
+    pub async fn run(self, mut events: mpsc::Receiver<SwapEvent>) {
+        while let Some(event) = events.recv().await {
+            for subscription in &self.config.subscriptions {
+                if !subscription.filter.matches(&event) {
+                    continue;
+                }
+                if let Err(err) = self.deliver(&subscription.sink, &event).await {
+                    eprintln!("notify: {} for escrow {}: {err}", event.event, event.escrow);
+                }
+            }
+        }
+    }
+
+    async fn deliver(&self, sink: &Sink, event: &SwapEvent) -> Result<(), String> {
+        let payload = serde_json::to_vec(event).map_err(|err| err.to_string())?;
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let result = match sink {
+                Sink::Webhook { url } => self.post(url, payload.clone()).await,
+                Sink::Nats { url, subject } => publish_nats(url, subject, &payload).await,
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= DELIVERY_ATTEMPTS => return Err(err),
+                Err(_) => {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, payload: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .client
+            .post(url)
+            .header("content-type", "application/json")
+            .body(payload)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("{url} answered {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// Publish one message with the NATS text protocol, on a connection of its own
+/// PING/PONG after the publish confirms the server processed it
+async fn publish_nats(url: &str, subject: &str, payload: &[u8]) -> Result<(), String> {
+    let stream = TcpStream::connect(url).await.map_err(|err| err.to_string())?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let info = lines.next_line().await.map_err(|err| err.to_string())?.unwrap_or_default();
+    if !info.starts_with("INFO") {
+        return Err(format!("unexpected NATS greeting: {info}"));
+    }
+
+    let mut message = format!("CONNECT {{\"verbose\":false,\"pedantic\":false}}\r\nPUB {subject} {}\r\n", payload.len())
+        .into_bytes();
+    message.extend_from_slice(payload);
+    message.extend_from_slice(b"\r\nPING\r\n");
+    writer.write_all(&message).await.map_err(|err| err.to_string())?;
+
+    match lines.next_line().await.map_err(|err| err.to_string())? {
+        Some(line) if line == "PONG" => Ok(()),
+        Some(line) => Err(format!("NATS: {line}")),
+        None => Err("NATS closed the connection".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(previous_state: Option<EscrowState>, state: EscrowState) -> SwapEvent {
+        SwapEvent {
+            escrow: "CESCROW".into(),
+            event: "withdraw".into(),
+            previous_state,
+            state,
+            order_hash: None,
+            maker: Some("GMAKER".into()),
+            taker: Some("GRESOLVER".into()),
+            token: Some("CUSDC".into()),
+            ledger: 7,
+            tx_hash: "00".into(),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_maker_token_and_transition() {
+        let config: NotifyConfig = serde_json::from_str(
+            r#"{ "subscriptions": [{
+                "filter": { "makers": ["GMAKER"], "tokens": ["CUSDC"], "transitions": ["Withdrawn"] },
+                "sink": { "type": "webhook", "url": "http://localhost:8080/hook" }
+            }] }"#,
+        )
+        .unwrap();
+        let filter = &config.subscriptions[0].filter;
+
+        assert!(filter.matches(&event(Some(EscrowState::Funded), EscrowState::Withdrawn)));
+        // Not a transition: the escrow was already withdrawn
+        assert!(!filter.matches(&event(Some(EscrowState::Withdrawn), EscrowState::Withdrawn)));
+        assert!(!filter.matches(&SwapEvent { token: Some("CXLM".into()), ..event(Some(EscrowState::Funded), EscrowState::Withdrawn) }));
+        assert!(Filter::default().matches(&event(None, EscrowState::Active)));
+    }
+}
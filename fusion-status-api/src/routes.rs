@@ -0,0 +1,144 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use fusion_replay::{EscrowRecord, EscrowState};
+use serde::Serialize;
+
+use crate::indexer::{Index, SharedIndex};
+
+/// An escrow record together with its address
+#[derive(Clone, Debug, Serialize)]
+pub struct EscrowStatus {
+    pub address: String,
+    #[serde(flatten)]
+    pub record: EscrowRecord,
+}
+
+/// Escrows a resolver is party to, split by whether they still hold funds
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Inventory {
+    pub resolver: String,
+    pub latest_ledger: u32,
+    pub open: Vec<EscrowStatus>,
+    pub closed: Vec<EscrowStatus>,
+}
+
+pub fn router(index: SharedIndex) -> Router {
+    Router::new()
+        .route("/escrows/{address}", get(get_escrow))
+        .route("/orders/{order_hash}", get(get_order))
+        .route("/resolver/{address}/inventory", get(get_inventory))
+        .with_state(index)
+}
+
+async fn get_escrow(
+    State(index): State<SharedIndex>,
+    Path(address): Path<String>,
+) -> Result<Json<EscrowStatus>, StatusCode> {
+    let index = index.read().await;
+    find_escrow(&index, &address).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_order(
+    State(index): State<SharedIndex>,
+    Path(order_hash): Path<String>,
+) -> Result<Json<Vec<EscrowStatus>>, StatusCode> {
+    let index = index.read().await;
+    let escrows = escrows_for_order(&index, &order_hash);
+    if escrows.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(escrows))
+}
+
+async fn get_inventory(
+    State(index): State<SharedIndex>,
+    Path(address): Path<String>,
+) -> Json<Inventory> {
+    let index = index.read().await;
+    Json(resolver_inventory(&index, &address))
+}
+
+fn find_escrow(index: &Index, address: &str) -> Option<EscrowStatus> {
+    index.replay.escrows().get(address).map(|record| EscrowStatus {
+        address: address.to_string(),
+        record: record.clone(),
+    })
+}
+
+/// Escrows created for an order; order hashes match case-insensitively, with or without 0x
+fn escrows_for_order(index: &Index, order_hash: &str) -> Vec<EscrowStatus> {
+    let order_hash = order_hash.trim_start_matches("0x").to_ascii_lowercase();
+    index
+        .replay
+        .escrows()
+        .iter()
+        .filter(|(_, record)| record.order_hash.as_deref() == Some(order_hash.as_str()))
+        .map(|(address, record)| EscrowStatus { address: address.clone(), record: record.clone() })
+        .collect()
+}
+
+/// Escrows where the resolver is maker or taker
+fn resolver_inventory(index: &Index, resolver: &str) -> Inventory {
+    let mut inventory = Inventory {
+        resolver: resolver.to_string(),
+        latest_ledger: index.latest_ledger,
+        ..Default::default()
+    };
+
+    for (address, record) in index.replay.escrows() {
+        let is_party = record.maker.as_deref() == Some(resolver) || record.taker.as_deref() == Some(resolver);
+        if !is_party {
+            continue;
+        }
+        let status = EscrowStatus { address: address.clone(), record: record.clone() };
+        if matches!(record.state, EscrowState::Active | EscrowState::Funded | EscrowState::Expired) {
+            inventory.open.push(status);
+        } else {
+            inventory.closed.push(status);
+        }
+    }
+
+    inventory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusion_replay::{EmittedEvent, FusionEvent};
+
+    fn index_with_escrow() -> Index {
+        let mut index = Index::default();
+        let events = [
+            FusionEvent::EscrowCreated {
+                order_hash: "ab".repeat(32),
+                maker: "GRESOLVER".to_string(),
+                taker: "GUSER".to_string(),
+            },
+            FusionEvent::Withdrawn { secret: "01".repeat(32) },
+        ];
+        for event in events {
+            index.replay.apply(5, &EmittedEvent { contract: "CESCROW".to_string(), event, swap_id: None });
+        }
+        index
+    }
+
+    #[test]
+    fn test_order_lookup_accepts_prefixed_hash() {
+        let index = index_with_escrow();
+        let order_hash = format!("0x{}", "AB".repeat(32));
+        let escrows = escrows_for_order(&index, &order_hash);
+        assert_eq!(escrows.len(), 1);
+        assert_eq!(escrows[0].address, "CESCROW");
+    }
+
+    #[test]
+    fn test_inventory_splits_open_and_closed() {
+        let index = index_with_escrow();
+        let inventory = resolver_inventory(&index, "GRESOLVER");
+        assert!(inventory.open.is_empty());
+        assert_eq!(inventory.closed.len(), 1);
+        assert!(resolver_inventory(&index, "GOTHER").closed.is_empty());
+    }
+}
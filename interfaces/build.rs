@@ -0,0 +1,12 @@
+//! Enable each contract module once `cargo xtask bindings` has generated it
+
+fn main() {
+    for module in ["escrow", "factory", "resolver"] {
+        let path = format!("src/generated/{module}.rs");
+        println!("cargo::rustc-check-cfg=cfg(bindings_{module})");
+        println!("cargo::rerun-if-changed={path}");
+        if std::path::Path::new(&path).exists() {
+            println!("cargo::rustc-cfg=bindings_{module}");
+        }
+    }
+}
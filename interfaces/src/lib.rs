@@ -0,0 +1,21 @@
+#![no_std]
+//! Client bindings generated from the contracts' wasm specs
+//!
+//! Regenerate with `cargo xtask bindings` after changing a contract's interface;
+//! `cargo xtask bindings --check` fails when the committed bindings are stale.
+//! A module is only compiled once its generated source exists.
+
+#[cfg(bindings_escrow)]
+pub mod escrow {
+    include!("generated/escrow.rs");
+}
+
+#[cfg(bindings_factory)]
+pub mod factory {
+    include!("generated/factory.rs");
+}
+
+#[cfg(bindings_resolver)]
+pub mod resolver {
+    include!("generated/resolver.rs");
+}
@@ -343,8 +343,8 @@ export class MainnetHTLCHandler {
           const errorMessage = error instanceof Error ? error.message : String(error);
           logger.error('Factory deployment failed', { error: errorMessage });
           
-          // Check if it's an "already deployed" error
-          if (errorMessage.includes('AlreadyDeployed') || errorMessage.includes('UnreachableCodeReached')) {
+          // Check if it's an "already deployed" error (factory AlreadyDeployed = 203)
+          if (errorMessage.includes('AlreadyDeployed') || errorMessage.includes('Error(Contract, #203)') || errorMessage.includes('UnreachableCodeReached')) {
             logger.info('Escrow may already exist, attempting to generate new unique parameters');
             
             // Try with a new unique order hash by adding timestamp
@@ -4,6 +4,7 @@ import { SwapRepository } from '../../database/repositories/SwapRepository';
 import { BaseMonitor } from './chains/BaseMonitor';
 import { EscrowEvent, SwapOrder, SwapStatus } from '../../types/swap';
 import { logger } from './utils/logger';
+import { swapId } from './utils/swapId';
 
 export class SwapOrchestrator extends EventEmitter {
   private processingQueue: Map<string, Promise<void>> = new Map();
@@ -97,6 +98,7 @@ export class SwapOrchestrator extends EventEmitter {
     };
 
     await this.swapRepository.create(swap);
+    logger.info(`Created swap ${swap.id}`, this.logContext(swap));
     
     // Deploy counterpart escrow on destination chain
     await this.deployCounterpartEscrow(swap, destinationChain);
@@ -110,7 +112,7 @@ export class SwapOrchestrator extends EventEmitter {
    * Handle counterpart escrow creation
    */
   async handleCounterpartCreation(swap: SwapOrder, chain: string, event: EscrowEvent): Promise<void> {
-    logger.info(`Counterpart escrow created on ${chain}`, { orderHash: swap.orderHash });
+    logger.info(`Counterpart escrow created on ${chain}`, this.logContext(swap));
     
     // Update swap status
     if (chain === swap.destinationChain) {
@@ -143,7 +145,7 @@ export class SwapOrchestrator extends EventEmitter {
       const escrowAddress = await monitor.deployEscrow(params);
       
       logger.info(`Deployed counterpart escrow on ${chain}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         escrowAddress,
       });
       
@@ -153,7 +155,7 @@ export class SwapOrchestrator extends EventEmitter {
       
     } catch (error) {
       logger.error(`Failed to deploy counterpart escrow: ${error}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         chain,
       });
       
@@ -224,13 +226,13 @@ export class SwapOrchestrator extends EventEmitter {
       const txHash = await monitor.withdrawFromEscrow(escrowAddress, swap.secret);
       
       logger.info(`Revealed secret on ${chain}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         txHash,
       });
       
     } catch (error) {
       logger.error(`Failed to reveal secret: ${error}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         chain,
       });
     }
@@ -241,7 +243,7 @@ export class SwapOrchestrator extends EventEmitter {
    */
   async executeWithdrawal(swap: SwapOrder, chain: string): Promise<void> {
     if (!swap.secret) {
-      logger.error('Cannot withdraw without secret', { orderHash: swap.orderHash });
+      logger.error('Cannot withdraw without secret', this.logContext(swap));
       return;
     }
 
@@ -255,13 +257,13 @@ export class SwapOrchestrator extends EventEmitter {
       const txHash = await monitor.withdrawFromEscrow(escrowAddress, swap.secret);
       
       logger.info(`Executed withdrawal on ${chain}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         txHash,
       });
       
     } catch (error) {
       logger.error(`Failed to execute withdrawal: ${error}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         chain,
       });
     }
@@ -272,7 +274,7 @@ export class SwapOrchestrator extends EventEmitter {
    */
   async executePublicWithdrawal(swap: SwapOrder, chain: string): Promise<void> {
     if (!swap.secret) {
-      logger.error('Cannot withdraw without secret', { orderHash: swap.orderHash });
+      logger.error('Cannot withdraw without secret', this.logContext(swap));
       return;
     }
 
@@ -295,13 +297,13 @@ export class SwapOrchestrator extends EventEmitter {
       const txHash = await monitor.cancelEscrow(escrowAddress);
       
       logger.info(`Executed cancellation on ${chain}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         txHash,
       });
       
     } catch (error) {
       logger.error(`Failed to execute cancellation: ${error}`, {
-        orderHash: swap.orderHash,
+        ...this.logContext(swap),
         chain,
       });
     }
@@ -349,6 +351,18 @@ export class SwapOrchestrator extends EventEmitter {
     throw new Error(`Escrow address not found for ${chain}`);
   }
 
+  /**
+   * Log metadata correlating a swap across chains: its order hash and swap id
+   */
+  private logContext(swap: SwapOrder): { orderHash: string; swapId?: string } {
+    try {
+      return { orderHash: swap.orderHash, swapId: swapId(swap.orderHash, swap.sourceChain, swap.destinationChain) };
+    } catch {
+      // Unconfigured chain or malformed order hash: log what is known
+      return { orderHash: swap.orderHash };
+    }
+  }
+
   /**
    * Get counterpart chain
    */
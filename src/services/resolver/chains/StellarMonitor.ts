@@ -14,6 +14,7 @@ import { BaseMonitor, ResolverAccount } from './BaseMonitor';
 import { ChainConfig } from '../../../config/chains';
 import { EscrowEvent } from '../../../types/swap';
 import { logger } from '../utils/logger';
+import { decodeImmutables } from '../utils/immutables';
 
 export class StellarMonitor extends BaseMonitor {
   private server: rpc.Server;
@@ -340,11 +341,20 @@ export class StellarMonitor extends BaseMonitor {
   }
 
   async getEscrowState(escrowAddress: string): Promise<any> {
+    try {
+      return await this.readEscrowState(escrowAddress, 'get_immutables_v2');
+    } catch (error) {
+      // Escrows deployed before ImmutablesV2 only expose the v1 get_immutables
+      return this.readEscrowState(escrowAddress, 'get_immutables');
+    }
+  }
+
+  private async readEscrowState(escrowAddress: string, immutablesView: string): Promise<any> {
     const contract = new Contract(escrowAddress);
     
     // Get state
     const stateOp = contract.call('get_state');
-    const immutablesOp = contract.call('get_immutables');
+    const immutablesOp = contract.call(immutablesView);
 
     const account = await this.server.getAccount(this.keypair.publicKey());
     const transaction = new TransactionBuilder(account, {
@@ -361,7 +371,7 @@ export class StellarMonitor extends BaseMonitor {
     if ('results' in simulated && (simulated as any).results && (simulated as any).results.length === 2) {
       return {
         state: scValToNative((simulated as any).results[0].xdr),
-        immutables: scValToNative((simulated as any).results[1].xdr),
+        immutables: decodeImmutables(scValToNative((simulated as any).results[1].xdr)),
         address: escrowAddress,
       };
     }
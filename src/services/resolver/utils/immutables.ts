@@ -0,0 +1,60 @@
+/** Which leg of the swap a Stellar escrow holds (the escrow's EscrowSide) */
+export enum EscrowSide {
+  Src = 0,
+  Dst = 1,
+}
+
+/** Immutables version of escrows deployed with `deploy` and its variants */
+export const IMMUTABLES_V1_VERSION = 1;
+/** Immutables version of escrows deployed with `deploy_v2` */
+export const IMMUTABLES_V2_VERSION = 2;
+/** Flags of escrows deployed without `set_flags`: partial fills, public actions and fees */
+export const DEFAULT_FLAGS = 19;
+
+/**
+ * Stellar escrow immutables in the v2 layout (the escrow's ImmutablesV2); v1 escrows decode to
+ * version 1, whose `get_immutables_hash` is the v1 hash
+ */
+export interface StellarImmutables {
+  version: number;
+  orderHash: string;
+  hashlock: string;
+  maker: string;
+  taker: string;
+  token: string;
+  amount: bigint;
+  safetyDeposit: bigint;
+  timelocks: bigint;
+  side: EscrowSide;
+  flags: number;
+  /** Recipient of withdrawals instead of the taker (v1 `beneficiary`) */
+  receiver?: string;
+  /** Safety deposit policy (v1 `incentives`), as scValToNative decodes it */
+  fees: unknown;
+}
+
+const hex = (bytes: Uint8Array): string => `0x${Buffer.from(bytes).toString('hex')}`;
+
+/**
+ * Decode immutables as scValToNative returns them: `get_immutables_v2` results carry a version,
+ * v1 `get_immutables` results don't and take `side` from the escrow's `get_side`
+ */
+export function decodeImmutables(native: Record<string, any>, side: EscrowSide = EscrowSide.Dst): StellarImmutables {
+  const v2 = native.version !== undefined;
+  return {
+    version: v2 ? Number(native.version) : IMMUTABLES_V1_VERSION,
+    orderHash: hex(native.order_hash),
+    hashlock: hex(native.hashlock),
+    maker: String(native.maker),
+    taker: String(native.taker),
+    token: String(native.token),
+    amount: BigInt(native.amount),
+    safetyDeposit: BigInt(native.safety_deposit),
+    timelocks: BigInt(native.timelocks),
+    side: v2 ? Number(native.side) : side,
+    // Escrows from before flags existed behave as DEFAULT_FLAGS
+    flags: native.flags === undefined ? DEFAULT_FLAGS : Number(native.flags),
+    receiver: (v2 ? native.receiver : native.beneficiary) ?? undefined,
+    fees: (v2 ? native.fees : native.incentives) ?? ['Default'],
+  };
+}
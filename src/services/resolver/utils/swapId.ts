@@ -0,0 +1,29 @@
+import { AbiCoder, keccak256 } from 'ethers';
+import { CHAIN_CONFIGS } from '../../../config/chains';
+
+/** Chain id standing for Stellar in swap ids (fusion_shared::swap_id::STELLAR_CHAIN_ID) */
+export const STELLAR_CHAIN_ID = 1001n;
+
+/**
+ * Numeric chain id of a configured chain; Stellar networks, configured with string ids, use
+ * STELLAR_CHAIN_ID
+ */
+export function numericChainId(chain: string): bigint {
+  const config = CHAIN_CONFIGS[chain];
+  if (!config) {
+    throw new Error(`Unknown chain: ${chain}`);
+  }
+  return typeof config.id === 'number' ? BigInt(config.id) : STELLAR_CHAIN_ID;
+}
+
+/**
+ * `keccak256(abi.encode(bytes32 orderHash, uint256 srcChainId, uint256 dstChainId))`, the id
+ * the Stellar escrows, factory and resolver record for a swap, so both legs and these logs share it
+ */
+export function swapId(orderHash: string, sourceChain: string, destinationChain: string): string {
+  const encoded = AbiCoder.defaultAbiCoder().encode(
+    ['bytes32', 'uint256', 'uint256'],
+    [orderHash, numericChainId(sourceChain), numericChainId(destinationChain)],
+  );
+  return keccak256(encoded);
+}
@@ -16,6 +16,9 @@ export interface EscrowParams {
   timelocks: string;
 }
 
+/** Hex without a 0x prefix */
+const stripHex = (value: string) => (value.startsWith('0x') ? value.slice(2) : value);
+
 export class StellarFactoryService {
   private server: rpc.Server;
   private factoryId: string;
@@ -218,6 +221,62 @@ export class StellarFactoryService {
     }
   }
 
+  /**
+   * Simulate deploy_and_fund and return the maker's unsigned authorization entry (base64 XDR)
+   * The maker signs it (e.g. with StellarSdk.authorizeEntry) and hands it back for deployAndFund,
+   * so they never submit an approval transaction themselves
+   */
+  async buildPermitRequest(params: EscrowParams, expiresAt: number, sourcePublicKey: string): Promise<string> {
+    const auth = await this.simulateDeployAndFund(params, expiresAt, sourcePublicKey);
+    const makerEntry = auth.find(entry => this.isAuthFor(entry, params.maker));
+    if (!makerEntry) {
+      throw new Error('Simulation returned no maker authorization entry');
+    }
+    return makerEntry.toXDR('base64');
+  }
+
+  /**
+   * Deploy and fund an escrow in one transaction, using the maker's signed permit entry
+   */
+  async deployAndFund(
+    params: EscrowParams & { sourceKeypair: StellarSdk.Keypair },
+    expiresAt: number,
+    signedMakerAuth: string
+  ): Promise<string> {
+    const sourcePublicKey = params.sourceKeypair.publicKey();
+    const signedEntry = StellarSdk.xdr.SorobanAuthorizationEntry.fromXDR(signedMakerAuth, 'base64');
+
+    // Keep the simulated entries (e.g. the taker's deposit transfer) and swap in the maker's signed one
+    const auth = (await this.simulateDeployAndFund(params, expiresAt, sourcePublicKey))
+      .map(entry => (this.isAuthFor(entry, params.maker) ? signedEntry : entry));
+
+    const sourceAccount = await this.server.getAccount(sourcePublicKey);
+    const transaction = new StellarSdk.TransactionBuilder(sourceAccount, {
+      fee: '1000000', // Higher fee for contract deployment
+      networkPassphrase: this.networkPassphrase(),
+    })
+      .addOperation(this.deployAndFundOperation(params, expiresAt, auth))
+      .setTimeout(180)
+      .build();
+
+    const preparedTx = await this.server.prepareTransaction(transaction);
+    preparedTx.sign(params.sourceKeypair);
+
+    console.log('📤 Submitting deploy_and_fund transaction...');
+    const response = await this.server.sendTransaction(preparedTx);
+    if (response.status !== 'PENDING') {
+      throw new Error(`Transaction submission failed: ${response.status}`);
+    }
+
+    const result = await this.waitForTransaction(response.hash);
+    if (result.status !== 'SUCCESS') {
+      throw new Error(`Transaction failed with status: ${result.status}`);
+    }
+    const escrowAddress = await this.parseEscrowAddressFromResult(result);
+    console.log('✅ Escrow deployed and funded at:', escrowAddress);
+    return escrowAddress;
+  }
+
   /**
    * Fund an HTLC escrow with tokens using transfer operation
    */
@@ -281,6 +340,79 @@ export class StellarFactoryService {
     }
   }
 
+  /**
+   * Authorization entries a deploy_and_fund call needs, as returned by simulation
+   */
+  private async simulateDeployAndFund(
+    params: EscrowParams,
+    expiresAt: number,
+    sourcePublicKey: string
+  ): Promise<StellarSdk.xdr.SorobanAuthorizationEntry[]> {
+    const sourceAccount = await this.server.getAccount(sourcePublicKey);
+    const transaction = new StellarSdk.TransactionBuilder(sourceAccount, {
+      fee: '100',
+      networkPassphrase: this.networkPassphrase(),
+    })
+      .addOperation(this.deployAndFundOperation(params, expiresAt))
+      .setTimeout(30)
+      .build();
+
+    const response = await this.server.simulateTransaction(transaction);
+    if (!rpc.Api.isSimulationSuccess(response) || !response.result) {
+      throw new Error('deploy_and_fund simulation failed: ' + JSON.stringify(response));
+    }
+    return response.result.auth;
+  }
+
+  /**
+   * deploy_and_fund(immutables, permit) invocation; contracttype structs encode as symbol-keyed maps
+   */
+  private deployAndFundOperation(
+    params: EscrowParams,
+    expiresAt: number,
+    auth: StellarSdk.xdr.SorobanAuthorizationEntry[] = []
+  ): StellarSdk.xdr.Operation {
+    const field = (key: string, val: StellarSdk.xdr.ScVal) =>
+      new StellarSdk.xdr.ScMapEntry({ key: StellarSdk.xdr.ScVal.scvSymbol(key), val });
+
+    // Map keys must be sorted
+    const immutables = StellarSdk.xdr.ScVal.scvMap([
+      field('amount', StellarSdk.nativeToScVal(params.amount, { type: 'i128' })),
+      field('hashlock', StellarSdk.xdr.ScVal.scvBytes(Buffer.from(stripHex(params.hashlock), 'hex'))),
+      field('maker', StellarSdk.Address.fromString(params.maker).toScVal()),
+      field('order_hash', StellarSdk.xdr.ScVal.scvBytes(Buffer.from(stripHex(params.orderHash), 'hex'))),
+      field('safety_deposit', StellarSdk.nativeToScVal(params.safetyDeposit, { type: 'i128' })),
+      field('taker', StellarSdk.Address.fromString(params.taker).toScVal()),
+      field('timelocks', StellarSdk.nativeToScVal(params.timelocks, { type: 'u64' })),
+      field('token', StellarSdk.Address.fromString(params.token).toScVal()),
+    ]);
+    const permit = StellarSdk.xdr.ScVal.scvMap([
+      field('expires_at', StellarSdk.nativeToScVal(expiresAt, { type: 'u64' })),
+    ]);
+
+    return StellarSdk.Operation.invokeContractFunction({
+      contract: this.factoryId,
+      function: 'deploy_and_fund',
+      args: [immutables, permit],
+      auth,
+    });
+  }
+
+  /**
+   * Whether an authorization entry is signed by `address` (rather than the source account)
+   */
+  private isAuthFor(entry: StellarSdk.xdr.SorobanAuthorizationEntry, address: string): boolean {
+    const credentials = entry.credentials();
+    return credentials.switch() === StellarSdk.xdr.SorobanCredentialsType.sorobanCredentialsAddress()
+      && StellarSdk.Address.fromScAddress(credentials.address().address()).toString() === address;
+  }
+
+  private networkPassphrase(): string {
+    return this.network === 'mainnet'
+      ? StellarSdk.Networks.PUBLIC
+      : StellarSdk.Networks.TESTNET;
+  }
+
   /**
    * Wait for transaction confirmation
    */
@@ -0,0 +1,226 @@
+#![no_std]
+//! Post-payout hook converting escrow withdrawals into the taker's preferred asset
+//!
+//! Escrows deployed with this contract as `post_payout_hook` (fusion_shared::EscrowHookInterface)
+//! call `after_payout` once they paid it: the taker routes a withdrawal here with `withdraw_to`,
+//! and the hook swaps the tokens through a Soroswap-compatible router along the route the taker
+//! set, then forwards the output to the route's recipient in the same transaction.
+//!
+//! The hook never keeps funds: a swap that fails (slippage, missing pair) forwards the withdrawn
+//! token unconverted, and payouts for escrows without a route stay claimable by their taker.
+
+use fusion_shared::{events, EscrowClient};
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error,
+    symbol_short, token::TokenClient, vec, Address, BytesN, Env, IntoVal, Symbol, Vec,
+};
+
+/// Hook errors use the 500+ range (see fusion_shared::errors)
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 501,
+    AlreadyInitialized = 502,
+    InvalidRoute = 503,
+    NothingToClaim = 504,
+}
+
+/// `min_rate` is the least output per RATE_SCALE input units (7 decimals, like Stellar assets)
+pub const RATE_SCALE: i128 = 10_000_000;
+
+/// Longest swap path, bounding the router call
+pub const MAX_PATH_LEN: u32 = 4;
+
+const ADMIN: Symbol = symbol_short!("ADMIN");
+const ROUTER: Symbol = symbol_short!("ROUTER");
+const ROUTE: Symbol = symbol_short!("ROUTE");
+const UNCLAIMED: Symbol = symbol_short!("UNCLAIMED");
+
+/// Ledgers per day at ~5s ledger close time
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Routes are extended once their TTL drops below this many ledgers...
+const ROUTE_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+
+/// ...to this many, outliving the escrow timelocks
+const ROUTE_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+/// How a taker wants an escrow's payouts converted
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct ConversionRoute {
+    /// The escrow's taker, who set the route
+    pub taker: Address,
+    /// Receives the converted tokens
+    pub recipient: Address,
+    /// Router swap path, from the escrowed token to the preferred asset
+    pub path: Vec<Address>,
+    /// Least output per RATE_SCALE units paid out; lower quotes forward the payout unconverted
+    pub min_rate: i128,
+}
+
+/// The subset of the Soroswap router the hook swaps through
+#[contractclient(name = "RouterClient")]
+pub trait SoroswapRouterInterface {
+    /// Pair contract holding the reserves of `token_a` and `token_b`
+    fn router_pair_for(env: Env, token_a: Address, token_b: Address) -> Address;
+
+    /// Swap `amount_in` of `path[0]` from `to` along `path`, paying the output to `to`
+    /// Returns the amounts at each step of the path
+    fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        deadline: u64,
+    ) -> Vec<i128>;
+}
+
+#[contract]
+pub struct ConversionHook;
+
+#[contractimpl]
+impl ConversionHook {
+    /// Initialize with the admin and the router swaps go through
+    pub fn initialize(env: Env, admin: Address, router: Address) {
+        admin.require_auth();
+
+        if env.storage().instance().has(&ADMIN) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        env.storage().instance().set(&ADMIN, &admin);
+        env.storage().instance().set(&ROUTER, &router);
+    }
+
+    /// Point the hook at another router (admin only)
+    pub fn set_router(env: Env, router: Address) {
+        let admin: Address = env.storage().instance().get(&ADMIN)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::NotInitialized));
+        admin.require_auth();
+
+        env.storage().instance().set(&ROUTER, &router);
+    }
+
+    pub fn get_router(env: Env) -> Option<Address> {
+        env.storage().instance().get(&ROUTER)
+    }
+
+    /// Set how `escrow`'s payouts to this hook are converted (the escrow's taker only)
+    /// `path` must start with the escrowed token and have 2..=MAX_PATH_LEN distinct tokens
+    pub fn set_route(env: Env, escrow: Address, recipient: Address, path: Vec<Address>, min_rate: i128) {
+        let immutables = EscrowClient::new(&env, &escrow).get_immutables();
+        immutables.taker.require_auth();
+
+        let distinct = path.iter().enumerate()
+            .all(|(index, token)| path.first_index_of(&token) == Some(index as u32));
+        if path.len() < 2 || path.len() > MAX_PATH_LEN || !distinct
+            || path.first() != Some(immutables.token) || min_rate < 0 {
+            panic_with_error!(&env, Error::InvalidRoute);
+        }
+
+        let key = (ROUTE, escrow.clone());
+        let route = ConversionRoute { taker: immutables.taker, recipient, path, min_rate };
+        env.storage().persistent().set(&key, &route);
+        env.storage().persistent().extend_ttl(&key, ROUTE_TTL_THRESHOLD, ROUTE_TTL_EXTEND_TO);
+        events::publish(&env, (Symbol::new(&env, "route_set"), escrow), route.path);
+    }
+
+    pub fn get_route(env: Env, escrow: Address) -> Option<ConversionRoute> {
+        env.storage().persistent().get(&(ROUTE, escrow))
+    }
+
+    /// Convert a payout `escrow` just made to this hook and forward it (escrow only)
+    /// Payouts to other recipients are ignored; payouts without a route are kept for `claim`
+    pub fn after_payout(env: Env, escrow: Address, order_hash: BytesN<32>, token: Address, recipient: Address, amount: i128) {
+        escrow.require_auth();
+
+        let hook = env.current_contract_address();
+        if recipient != hook || amount <= 0 {
+            return;
+        }
+        let route: ConversionRoute = match Self::get_route(env.clone(), escrow.clone()) {
+            Some(route) if route.path.first() == Some(token.clone()) => route,
+            _ => {
+                let key = (UNCLAIMED, escrow.clone(), token.clone());
+                let unclaimed: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+                env.storage().persistent().set(&key, &(unclaimed + amount));
+                events::publish(&env, (Symbol::new(&env, "unrouted"), escrow), (order_hash, token, amount));
+                return;
+            }
+        };
+
+        match swap(&env, &route, amount) {
+            Some(amount_out) => {
+                let token_out = route.path.last().unwrap_or(token.clone());
+                TokenClient::new(&env, &token_out).transfer(&hook, &route.recipient, &amount_out);
+                events::publish(
+                    &env,
+                    (Symbol::new(&env, "converted"), escrow),
+                    (order_hash, token, amount, token_out, amount_out),
+                );
+            }
+            None => {
+                TokenClient::new(&env, &token).transfer(&hook, &route.recipient, &amount);
+                events::publish(&env, (Symbol::new(&env, "forwarded"), escrow), (order_hash, token, amount));
+            }
+        }
+    }
+
+    /// Pay payouts `escrow` made without a route to its taker; returns the claimed amount
+    pub fn claim(env: Env, escrow: Address, token: Address) -> i128 {
+        let immutables = EscrowClient::new(&env, &escrow).get_immutables();
+        immutables.taker.require_auth();
+
+        let key = (UNCLAIMED, escrow, token.clone());
+        let amount: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::NothingToClaim);
+        }
+        env.storage().persistent().remove(&key);
+        TokenClient::new(&env, &token).transfer(&env.current_contract_address(), &immutables.taker, &amount);
+        amount
+    }
+
+    /// Payouts of `escrow` in `token` awaiting `claim`
+    pub fn get_unclaimed(env: Env, escrow: Address, token: Address) -> i128 {
+        env.storage().persistent().get(&(UNCLAIMED, escrow, token)).unwrap_or(0)
+    }
+}
+
+/// Swap `amount` along the route, returning the output received or None if the swap failed
+/// The output is measured on the hook's balance rather than taken from the router's answer
+fn swap(env: &Env, route: &ConversionRoute, amount: i128) -> Option<i128> {
+    let router: Address = env.storage().instance().get(&ROUTER)?;
+    let router = RouterClient::new(env, &router);
+    let (token_in, next) = (route.path.get(0)?, route.path.get(1)?);
+    let token_out = TokenClient::new(env, &route.path.last()?);
+    let pair = router.try_router_pair_for(&token_in, &next).ok()?.ok()?;
+    // Split so large payouts don't overflow; a rate that still does disables the swap
+    let min_out = (amount / RATE_SCALE).checked_mul(route.min_rate)?
+        .checked_add((amount % RATE_SCALE).checked_mul(route.min_rate)? / RATE_SCALE)?;
+
+    // The router moves the input from the hook to the first pair, below the hook's own call
+    let hook = env.current_contract_address();
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in,
+                fn_name: symbol_short!("transfer"),
+                args: (hook.clone(), pair, amount).into_val(env),
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+    let before = token_out.balance(&hook);
+    let deadline = env.ledger().timestamp();
+    router.try_swap_exact_tokens_for_tokens(&amount, &min_out, &route.path, &hook, &deadline).ok()?.ok()?;
+    let received = token_out.balance(&hook) - before;
+    (received >= min_out).then_some(received)
+}
+
+#[cfg(test)]
+mod test;
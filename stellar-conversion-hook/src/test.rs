@@ -0,0 +1,131 @@
+#![cfg(test)]
+
+use crate::{ConversionHook, ConversionHookClient, Error, RATE_SCALE};
+use soroban_sdk::{
+    contract, contractimpl, symbol_short, testutils::Address as _, token, vec, Address, Env, Vec,
+};
+use stellar_escrow::testutils::{advance_to_stage, create_funded_escrow, EscrowFixture};
+use stellar_escrow::{StellarEscrow, StellarEscrowClient};
+
+/// Router swapping at a fixed rate: the input goes to a pair address, the output is minted
+#[contract]
+pub struct MockRouter;
+
+#[contractimpl]
+impl MockRouter {
+    pub fn set_rate(env: Env, rate: i128) {
+        env.storage().instance().set(&symbol_short!("rate"), &rate);
+    }
+
+    pub fn router_pair_for(env: Env, token_a: Address, token_b: Address) -> Address {
+        let _ = (token_a, token_b);
+        env.storage().instance().get(&symbol_short!("pair")).unwrap()
+    }
+
+    pub fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        deadline: u64,
+    ) -> Vec<i128> {
+        let _ = deadline;
+        to.require_auth();
+        let rate: i128 = env.storage().instance().get(&symbol_short!("rate")).unwrap();
+        let amount_out = amount_in * rate / RATE_SCALE;
+        if amount_out < amount_out_min {
+            panic!("insufficient output amount");
+        }
+        let pair = Self::router_pair_for(env.clone(), path.get(0).unwrap(), path.get(1).unwrap());
+        token::TokenClient::new(&env, &path.get(0).unwrap()).transfer(&to, &pair, &amount_in);
+        token::StellarAssetClient::new(&env, &path.last().unwrap()).mint(&to, &amount_out);
+        vec![&env, amount_in, amount_out]
+    }
+}
+
+struct Setup {
+    fixture: EscrowFixture,
+    hook: ConversionHookClient<'static>,
+    router: MockRouterClient<'static>,
+    escrow: StellarEscrowClient<'static>,
+    target: Address,
+}
+
+/// A funded escrow paying `amount` whose post-payout hook is a ConversionHook, and a router
+/// that can mint the target asset
+fn setup(env: &Env, amount: i128) -> Setup {
+    let fixture = create_funded_escrow(env, amount);
+    // The router's transfer out of the hook is authorized below the root invocation
+    env.mock_all_auths_allowing_non_root_auth();
+    let router = MockRouterClient::new(env, &env.register(MockRouter, ()));
+    env.as_contract(&router.address, || {
+        env.storage().instance().set(&symbol_short!("pair"), &Address::generate(env));
+    });
+    let target = env.register_stellar_asset_contract_v2(router.address.clone()).address();
+    let hook = ConversionHookClient::new(env, &env.register(ConversionHook, ()));
+    hook.initialize(&Address::generate(env), &router.address);
+
+    let escrow = StellarEscrowClient::new(env, &env.register(StellarEscrow, ()));
+    escrow.deploy_with_hooks(
+        &fixture.order_hash,
+        &fixture.hashlock,
+        &fixture.maker,
+        &fixture.taker,
+        &fixture.token,
+        &amount,
+        &0i128,
+        &fixture.timelocks,
+        &None,
+        &Some(hook.address.clone()),
+    );
+    token::StellarAssetClient::new(env, &fixture.token).mint(&escrow.address, &amount);
+    escrow.confirm_funding();
+    // Dst withdrawal stage
+    advance_to_stage(env, fixture.timelocks, 4);
+
+    Setup { fixture, hook, router, escrow, target }
+}
+
+#[test]
+fn test_payout_converted_into_target_asset() {
+    let env = Env::default();
+    let Setup { fixture, hook, router, escrow, target } = setup(&env, 1000);
+    let recipient = Address::generate(&env);
+    router.set_rate(&(2 * RATE_SCALE));
+    let path = vec![&env, fixture.token.clone(), target.clone()];
+    hook.set_route(&escrow.address, &recipient, &path, &RATE_SCALE);
+
+    escrow.withdraw_to(&fixture.secret, &hook.address, &None);
+    assert_eq!(token::TokenClient::new(&env, &target).balance(&recipient), 2000);
+    assert_eq!(fixture.token_client(&env).balance(&hook.address), 0);
+
+    // Routes must start with the escrowed token
+    let wrong = vec![&env, target.clone(), fixture.token.clone()];
+    let result = hook.try_set_route(&escrow.address, &recipient, &wrong, &RATE_SCALE);
+    assert_eq!(result.err().unwrap().unwrap().get_code(), Error::InvalidRoute as u32);
+}
+
+#[test]
+fn test_failed_swap_and_unrouted_payouts_release_funds() {
+    let env = Env::default();
+    let Setup { fixture, hook, router, escrow, target } = setup(&env, 1000);
+    let recipient = Address::generate(&env);
+    // Quote below the route's minimum: the payout is forwarded unconverted
+    router.set_rate(&(RATE_SCALE / 2));
+    let path = vec![&env, fixture.token.clone(), target.clone()];
+    hook.set_route(&escrow.address, &recipient, &path, &RATE_SCALE);
+
+    escrow.withdraw_to(&fixture.secret, &hook.address, &None);
+    assert_eq!(fixture.token_client(&env).balance(&recipient), 1000);
+    assert_eq!(token::TokenClient::new(&env, &target).balance(&recipient), 0);
+
+    // Without a route the payout waits for the taker's claim
+    let Setup { fixture, hook, escrow, .. } = setup(&env, 500);
+    escrow.withdraw_to(&fixture.secret, &hook.address, &None);
+    assert_eq!(hook.get_unclaimed(&escrow.address, &fixture.token), 500);
+    assert_eq!(hook.claim(&escrow.address, &fixture.token), 500);
+    assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 500);
+    let again = hook.try_claim(&escrow.address, &fixture.token);
+    assert_eq!(again.err().unwrap().unwrap().get_code(), Error::NothingToClaim as u32);
+}
@@ -0,0 +1,132 @@
+#![no_std]
+//! Attestation-based finality oracle for escrows deployed with a finality oracle
+//!
+//! An admin-managed set of attestors (relayers, watchtowers) attests to orders whose source-chain
+//! lock has reached finality; an order is final once `threshold` of the current attestors have
+//! attested it. Escrows only call `is_final` (fusion_shared::FinalityOracleInterface), so a
+//! light-client oracle can later replace this contract without escrow changes.
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, panic_with_error, symbol_short, Address, BytesN, Env,
+    Symbol, Vec,
+};
+
+/// Oracle errors use the 400+ range (see fusion_shared::errors)
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 401,
+    AlreadyInitialized = 402,
+    InvalidAttestorSet = 403,
+    NotAttestor = 404,
+    AlreadyAttested = 405,
+}
+
+/// Largest attestor set, bounding the cost of is_final
+pub const MAX_ATTESTORS: u32 = 20;
+
+const ADMIN: Symbol = symbol_short!("ADMIN");
+const ATTESTORS: Symbol = symbol_short!("ATTESTORS");
+const THRESHOLD: Symbol = symbol_short!("THRESHOLD");
+const ATTESTED: Symbol = symbol_short!("ATTESTED");
+
+/// Ledgers per day at ~5s ledger close time
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Attestations are extended once their TTL drops below this many ledgers...
+const ATTESTATION_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+
+/// ...to this many, outliving the escrow timelocks they gate
+const ATTESTATION_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+#[contract]
+pub struct AttestedFinalityOracle;
+
+#[contractimpl]
+impl AttestedFinalityOracle {
+    /// Initialize with the admin managing the attestor set and the attestations `threshold`
+    /// an order needs to be final
+    pub fn initialize(env: Env, admin: Address, attestors: Vec<Address>, threshold: u32) {
+        admin.require_auth();
+
+        if env.storage().instance().has(&ADMIN) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        set_attestor_set(&env, &attestors, threshold);
+        env.storage().instance().set(&ADMIN, &admin);
+    }
+
+    /// Replace the attestor set and threshold (admin only)
+    /// Attestations by removed attestors stop counting, so orders they made final may no longer be
+    // Events::publish is deprecated in SDK 23 but keeps the tuple layout the relayer parses
+    #[allow(deprecated)]
+    pub fn set_attestors(env: Env, attestors: Vec<Address>, threshold: u32) {
+        let admin: Address = env.storage().instance().get(&ADMIN)
+            .unwrap_or_else(|| panic_with_error!(&env, Error::NotInitialized));
+        admin.require_auth();
+
+        set_attestor_set(&env, &attestors, threshold);
+        env.events().publish((Symbol::new(&env, "attestors_set"),), (attestors.len(), threshold));
+    }
+
+    /// Attest that the source-chain lock of `order_hash` is final (attestors only, once each)
+    /// Returns whether the order is now final
+    #[allow(deprecated)]
+    pub fn attest(env: Env, attestor: Address, order_hash: BytesN<32>) -> bool {
+        attestor.require_auth();
+
+        if !Self::get_attestors(env.clone()).contains(&attestor) {
+            panic_with_error!(&env, Error::NotAttestor);
+        }
+        let key = (ATTESTED, order_hash.clone());
+        let mut attested = Self::get_attestations(env.clone(), order_hash.clone());
+        if attested.contains(&attestor) {
+            panic_with_error!(&env, Error::AlreadyAttested);
+        }
+        attested.push_back(attestor.clone());
+        env.storage().persistent().set(&key, &attested);
+        env.storage().persistent().extend_ttl(&key, ATTESTATION_TTL_THRESHOLD, ATTESTATION_TTL_EXTEND_TO);
+
+        env.events().publish((Symbol::new(&env, "attested"), order_hash.clone()), attestor);
+        Self::is_final(env, order_hash)
+    }
+
+    /// Whether at least `threshold` current attestors attested `order_hash`
+    pub fn is_final(env: Env, order_hash: BytesN<32>) -> bool {
+        let attestors = Self::get_attestors(env.clone());
+        let threshold = Self::get_threshold(env.clone());
+        let count = Self::get_attestations(env, order_hash).iter()
+            .filter(|attestor| attestors.contains(attestor))
+            .count();
+        threshold > 0 && count >= threshold as usize
+    }
+
+    /// Attestors that attested `order_hash`, in attestation order (including since-removed ones)
+    pub fn get_attestations(env: Env, order_hash: BytesN<32>) -> Vec<Address> {
+        env.storage().persistent().get(&(ATTESTED, order_hash)).unwrap_or_else(|| Vec::new(&env))
+    }
+
+    pub fn get_attestors(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&ATTESTORS).unwrap_or_else(|| Vec::new(&env))
+    }
+
+    /// Attestations an order needs to be final (0 until initialized)
+    pub fn get_threshold(env: Env) -> u32 {
+        env.storage().instance().get(&THRESHOLD).unwrap_or(0)
+    }
+}
+
+/// Store an attestor set of 1..=MAX_ATTESTORS distinct addresses with 1 <= threshold <= its size
+fn set_attestor_set(env: &Env, attestors: &Vec<Address>, threshold: u32) {
+    let distinct = attestors.iter().enumerate()
+        .all(|(index, attestor)| attestors.first_index_of(&attestor) == Some(index as u32));
+    if attestors.len() > MAX_ATTESTORS || threshold == 0 || threshold > attestors.len() || !distinct {
+        panic_with_error!(env, Error::InvalidAttestorSet);
+    }
+    env.storage().instance().set(&ATTESTORS, attestors);
+    env.storage().instance().set(&THRESHOLD, &threshold);
+}
+
+#[cfg(test)]
+mod test;
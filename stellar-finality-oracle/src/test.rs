@@ -0,0 +1,59 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::{AttestedFinalityOracle, AttestedFinalityOracleClient, Error};
+    use fusion_shared::FinalityOracleClient;
+    use soroban_sdk::{testutils::Address as _, vec, Address, BytesN, Env};
+
+    fn setup(env: &Env, attestors: usize, threshold: u32) -> (AttestedFinalityOracleClient<'_>, soroban_sdk::Vec<Address>) {
+        env.mock_all_auths();
+        let oracle = env.register(AttestedFinalityOracle, ());
+        let client = AttestedFinalityOracleClient::new(env, &oracle);
+        let mut set = vec![env];
+        for _ in 0..attestors {
+            set.push_back(Address::generate(env));
+        }
+        client.initialize(&Address::generate(env), &set, &threshold);
+        (client, set)
+    }
+
+    #[test]
+    fn test_order_final_at_threshold() {
+        let env = Env::default();
+        let (client, attestors) = setup(&env, 3, 2);
+        let order_hash = BytesN::from_array(&env, &[7u8; 32]);
+        // Escrows read it through the shared interface
+        let escrow_view = FinalityOracleClient::new(&env, &client.address);
+
+        assert!(!client.attest(&attestors.get(0).unwrap(), &order_hash));
+        assert!(!escrow_view.is_final(&order_hash));
+        let again = client.try_attest(&attestors.get(0).unwrap(), &order_hash);
+        assert_eq!(again.err().unwrap().unwrap().get_code(), Error::AlreadyAttested as u32);
+
+        assert!(client.attest(&attestors.get(1).unwrap(), &order_hash));
+        assert!(escrow_view.is_final(&order_hash));
+        assert!(!escrow_view.is_final(&BytesN::from_array(&env, &[8u8; 32])));
+
+        let outsider = client.try_attest(&Address::generate(&env), &order_hash);
+        assert_eq!(outsider.err().unwrap().unwrap().get_code(), Error::NotAttestor as u32);
+    }
+
+    #[test]
+    fn test_removed_attestors_stop_counting() {
+        let env = Env::default();
+        let (client, attestors) = setup(&env, 2, 2);
+        let order_hash = BytesN::from_array(&env, &[7u8; 32]);
+        client.attest(&attestors.get(0).unwrap(), &order_hash);
+        client.attest(&attestors.get(1).unwrap(), &order_hash);
+        assert!(client.is_final(&order_hash));
+
+        let replacement = Address::generate(&env);
+        client.set_attestors(&vec![&env, attestors.get(0).unwrap(), replacement.clone()], &2);
+        assert!(!client.is_final(&order_hash));
+        assert!(client.attest(&replacement, &order_hash));
+
+        let duplicate = vec![&env, replacement.clone(), replacement];
+        assert_eq!(client.try_set_attestors(&duplicate, &1).err().unwrap().unwrap().get_code(), Error::InvalidAttestorSet as u32);
+        assert_eq!(client.try_set_attestors(&attestors, &3).err().unwrap().unwrap().get_code(), Error::InvalidAttestorSet as u32);
+    }
+}
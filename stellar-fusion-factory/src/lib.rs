@@ -1,22 +1,57 @@
 #![no_std]
+#![allow(clippy::too_many_arguments)]
 
+use fusion_shared::{conversion, events, swap_id::stellar_swap_id, trace};
 use soroban_sdk::{
     contract, contractimpl, contracttype, contracterror,
-    Address, BytesN, Env, Symbol, log, symbol_short, vec, IntoVal,
+    xdr::ToXdr, token, Address, Bytes, BytesN, Env, FromVal, String, Symbol, Val, Vec, log, symbol_short, vec, IntoVal,
 };
 
 // Error types for better handling
+// Factory errors use the 200+ range (see fusion_shared::errors)
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
 pub enum Error {
-    AlreadyInitialized = 1,
-    NotInitialized = 2,
-    AlreadyDeployed = 3,
-    InvalidParams = 4,
-    DeploymentFailed = 5,
+    AlreadyInitialized = 201,
+    NotInitialized = 202,
+    AlreadyDeployed = 203,
+    InvalidParams = 204,
+    DeploymentFailed = 205,
+    UnsupportedChain = 206,
+    UnsupportedToken = 207,
+    TimelockTooShort = 208,
+    MultisigRequired = 209,
+    NotSigner = 210,
+    ProposalNotFound = 211,
+    AlreadyApproved = 212,
+    InsufficientApprovals = 213,
+    ProposalExecuted = 214,
+    Paused = 215,
+    ActionNotFound = 216,
+    ActionNotReady = 217,
+    TokenNotAllowed = 218,
+    PublicWindowTooSoon = 219,
+    OrderHashConflict = 220,
+    PermitExpired = 221,
+    SafetyDepositTooLow = 222,
+    NonceCancelled = 223,
+    RateLimited = 224,
+    TokenMismatch = 225,
+    MakerIsTaker = 226,
+    InvalidAmount = 227,
+    ZeroHashlock = 228,
+    ZeroOrderHash = 229,
+    UnknownEscrow = 230,
+    HtlcCodeMismatch = 231,
+    SelfSponsored = 232,
+    CounterpartyImported = 233,
+    OrdersDontCross = 234,
+    OrderFilled = 235,
+    ResolverNotTaker = 236,
 }
 
+#[derive(Clone)]
 #[contracttype]
 pub struct Immutables {
     pub order_hash: BytesN<32>,
@@ -29,10 +64,378 @@ pub struct Immutables {
     pub timelocks: u64,
 }
 
+/// A 20-byte EVM address (mirrors the escrow crate's EvmAddress)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct EvmAddress(pub BytesN<20>);
+
+/// Safety deposit shares for one escrow action (mirrors the escrow crate's DepositSplit)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositSplit {
+    pub executor_bps: u32,
+    pub maker_bps: u32,
+    pub protocol_bps: u32,
+}
+
+/// Per-action safety deposit splits (mirrors the escrow crate's DepositIncentives)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositIncentives {
+    pub withdraw: DepositSplit,
+    pub public_withdraw: DepositSplit,
+    pub cancel: DepositSplit,
+    pub public_cancel: DepositSplit,
+    pub protocol: Address,
+    pub remainder: DepositParty,
+    pub dust_threshold: i128,
+}
+
+/// Recipient of a deposit share (mirrors the escrow crate's DepositParty)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositParty {
+    Executor = 0,
+    Maker = 1,
+    Protocol = 2,
+}
+
+/// How an escrow distributes its safety deposit (mirrors the escrow crate's DepositPolicy)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositPolicy {
+    Default,
+    Split(DepositIncentives),
+}
+
+/// Which leg of the swap an escrow holds (mirrors the escrow crate's EscrowSide)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum EscrowSide {
+    Src = 0,
+    Dst = 1,
+}
+
+/// Version of the escrow immutables layout deploy_escrow_v2 deploys
+pub const IMMUTABLES_V2_VERSION: u32 = 2;
+
+/// Escrow immutables in the v2 layout (mirrors the escrow crate's ImmutablesV2)
+/// The first eight fields are the v1 Immutables, from which the escrow salt is still derived
+#[derive(Clone)]
+#[contracttype]
+pub struct ImmutablesV2 {
+    pub version: u32,
+    pub order_hash: BytesN<32>,
+    pub hashlock: BytesN<32>,
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub timelocks: u64,
+    pub side: EscrowSide,
+    pub flags: u32,
+    pub receiver: Option<Address>,
+    pub fees: DepositPolicy,
+}
+
+/// Extra argument passed to a variant of the escrow's deploy function
+enum EscrowInit {
+    Details(Bytes),
+    Beneficiary(Address),
+    Incentives(DepositIncentives),
+    FinalityOracle(Address),
+    /// Pre-funding and post-payout hooks (`deploy_with_hooks`)
+    Hooks(Option<Address>, Option<Address>),
+    /// Source-side escrow holding the maker's funds (`deploy_src`)
+    Src,
+    /// Behavior flags recorded with `set_flags` before a plain `deploy`
+    Flags(u32),
+    /// Parts count of a multi-secret escrow, recorded with `set_parts` before a plain `deploy`
+    Parts(u32),
+    /// The whole v2 immutables (an ImmutablesV2), replacing the v1 arguments (`deploy_v2`)
+    V2(Val),
+}
+
+/// Configuration for swaps sourced from a foreign chain
+#[derive(Clone)]
+#[contracttype]
+pub struct ChainConfig {
+    /// Per-stage minimum timelock values, packed like the escrow timelocks
+    pub min_timelocks: u64,
+    /// Tokens escrowable for this chain; empty allows any token
+    pub supported_tokens: Vec<Address>,
+    /// EscrowFactory deployed on the foreign chain
+    pub evm_factory: EvmAddress,
+}
+
+/// The destination leg of a Stellar → EVM swap, as the EVM resolver deploys it (EscrowDst)
+/// Order hash, hashlock and timelocks are shared with the Stellar source escrow
+#[derive(Clone)]
+#[contracttype]
+pub struct DstEscrowParams {
+    /// Registered chain the destination escrow is deployed on
+    pub chain_id: u64,
+    /// Maker's address on the destination chain, receiving the funds
+    pub maker: EvmAddress,
+    pub token: EvmAddress,
+    pub amount: i128,
+    pub safety_deposit: i128,
+}
+
+/// A maker's Stellar-origin order (Stellar → EVM), bound to its src escrow by create_src_escrow
+/// The escrow's order_hash is derived from the order on-chain, never supplied by the resolver
+#[derive(Clone)]
+#[contracttype]
+pub struct Order {
+    pub maker: Address,
+    /// Resolver allowed to fill the order
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub hashlock: BytesN<32>,
+    pub timelocks: u64,
+    /// Maker-chosen nonce keeping otherwise identical orders distinct
+    pub salt: u64,
+    /// Maker epoch the order was signed in; cancel_nonce invalidates every lower one
+    pub nonce: u64,
+    pub dst: DstEscrowParams,
+}
+
+/// A maker's same-chain limit order, settled against a crossing one by match_orders
+/// `maker_amount` of `maker_token` is offered for at least `taker_amount` of `taker_token`
+#[derive(Clone)]
+#[contracttype]
+pub struct LimitOrder {
+    pub maker: Address,
+    pub maker_token: Address,
+    pub maker_amount: i128,
+    pub taker_token: Address,
+    pub taker_amount: i128,
+    /// Maker-chosen nonce keeping otherwise identical orders distinct
+    pub salt: u64,
+    /// Maker epoch the order was signed in; cancel_nonce invalidates every lower one
+    pub nonce: u64,
+}
+
+/// Maker-signed permission for deploy_and_fund to pull the escrowed tokens
+/// The maker authorizes `(order_hash, token, amount, expires_at)` together with the token
+/// transfer it covers, so the permit can't be replayed for another order or amount
+#[derive(Clone)]
+#[contracttype]
+pub struct Permit {
+    /// Ledger timestamp after which the permit is rejected
+    pub expires_at: u64,
+}
+
+/// Minimum safety deposit required at deploy, so public withdrawal/cancellation keepers
+/// always have an incentive; the larger of the two bounds applies
+#[derive(Clone)]
+#[contracttype]
+pub struct MinSafetyDeposit {
+    /// Absolute minimum in stroops of XLM
+    pub min_amount: i128,
+    /// Minimum as basis points of the escrow amount (compares raw units, so meant for XLM escrows)
+    pub min_bps: u32,
+}
+
+/// Outcome of deploy_escrow_idempotent
+#[derive(Clone)]
+#[contracttype]
+pub struct DeployResult {
+    pub escrow: Address,
+    /// Whether the escrow already existed and this call deployed nothing
+    pub reused: bool,
+}
+
+/// Storage rent an escrow's deployment bought, recorded for every deploy (`get_deployment_info`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DeploymentInfo {
+    pub escrow: Address,
+    /// Ledger the escrow was deployed at
+    pub deployed_at: u32,
+    /// Ledgers of storage TTL the escrow's initialization purchased (0 if the escrow can't report it)
+    pub ttl_purchased: u32,
+    /// Ledger at which the escrow's storage is archived unless extended; moved forward by
+    /// extend_escrow_storage
+    pub expires_at: u32,
+}
+
+/// Who covered an escrow's deployment (`deploy_escrow_sponsored`) and who requested it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Sponsorship {
+    /// Fee payer that authorized covering the deployment
+    pub sponsor: Address,
+    /// The resolver (taker) the deployment was made for
+    pub resolver: Address,
+}
+
+/// The EVM-side escrow of a swap, recorded against its Stellar escrow by import_counterparty
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct CounterpartyEscrow {
+    /// Stellar escrow deployed for the order (its first, for partial fills)
+    pub stellar_escrow: Address,
+    pub evm_escrow: EvmAddress,
+    /// The EVM escrow's `keccak256(abi.encode(immutables))`, which its deterministic address derives from
+    pub evm_immutables_hash: BytesN<32>,
+}
+
+/// Factory configuration and counters in one read, for dashboards and health checks
+#[derive(Clone)]
+#[contracttype]
+pub struct ProtocolStats {
+    pub initialized: bool,
+    pub admin: Option<Address>,
+    pub htlc_hash: Option<BytesN<32>>,
+    /// Escrow WASM hashes installed so far (1 after initialize, +1 per update)
+    pub htlc_version: u32,
+    pub paused: bool,
+    pub allowlist_mode: bool,
+    pub governance_delay: u64,
+    /// Multisig approval threshold (0 while a single admin)
+    pub multisig_threshold: u32,
+    pub pending_actions: u32,
+    pub ttl_bounty: i128,
+    pub fee_pool: i128,
+    pub rate_limit: u32,
+    pub registry_retention: u64,
+    /// MinSafetyDeposit bounds (0 when unset)
+    pub min_deposit_amount: i128,
+    pub min_deposit_bps: u32,
+    /// Escrows deployed through this factory
+    pub escrows_deployed: u64,
+    /// Tokens with an allow or deny policy
+    pub token_policies: u32,
+}
+
+/// Escrows deployed for one order_hash
+#[derive(Clone)]
+#[contracttype]
+pub struct OrderRecord {
+    pub maker: Address,
+    pub token: Address,
+    /// Salts of the escrows deployed for the order, in deployment order
+    pub salts: Vec<BytesN<32>>,
+}
+
+/// Admin actions, which require threshold approval once multisig is enabled
+/// All but pausing and cancellation are time-delayed when a governance delay is set
+#[derive(Clone)]
+#[contracttype]
+pub enum AdminAction {
+    UpdateHtlcHash(BytesN<32>),
+    SetPaused(bool),
+    SetGovernanceDelay(u64),
+    CancelScheduled(u64),
+    SetWrappedToken(u64, EvmAddress, Address),
+    RemoveWrappedToken(u64, EvmAddress),
+    SetChainConfig(u64, ChainConfig),
+    RemoveChainConfig(u64),
+    SetTokenPolicy(Address, bool),
+    RemoveTokenPolicy(Address),
+    SetAllowlistMode(bool),
+    SetMinPublicGap(u32),
+    SetMinSafetyDeposit(MinSafetyDeposit),
+    SetSkewTolerance(u32),
+    SetRateLimit(u32),
+    SetTtlBounty(i128),
+    SetRegistryRetention(u64),
+}
+
+/// A time-delayed admin action waiting for its delay to pass
+#[derive(Clone)]
+#[contracttype]
+pub struct ScheduledAction {
+    pub action: AdminAction,
+    /// Ledger timestamp from which the action can be executed
+    pub executable_at: u64,
+}
+
+/// A pending or executed multisig proposal
+#[derive(Clone)]
+#[contracttype]
+pub struct Proposal {
+    pub action: AdminAction,
+    pub approvals: Vec<Address>,
+    pub executed: bool,
+}
+
 const ADMIN: Symbol = symbol_short!("ADMIN");
 const HTLC_HASH: Symbol = symbol_short!("HTLC_HASH");
-const NONCE: Symbol = symbol_short!("NONCE");
 const DEPLOYED: Symbol = symbol_short!("DEPLOYED");
+const MAKER_IDX: Symbol = symbol_short!("MAKER_IDX");
+const TAKER_IDX: Symbol = symbol_short!("TAKER_IDX");
+const CHAIN: Symbol = symbol_short!("CHAIN");
+const SRC_CHAIN: Symbol = symbol_short!("SRC_CHAIN");
+const SIGNERS: Symbol = symbol_short!("SIGNERS");
+const THRESHOLD: Symbol = symbol_short!("THRESHOLD");
+const PROP_ID: Symbol = symbol_short!("PROP_ID");
+const PROPOSAL: Symbol = symbol_short!("PROPOSAL");
+const PAUSED: Symbol = symbol_short!("PAUSED");
+const GOV_DELAY: Symbol = symbol_short!("GOV_DELAY");
+const ACTION_ID: Symbol = symbol_short!("ACTION_ID");
+const SCHEDULED: Symbol = symbol_short!("SCHEDULED");
+const PENDING: Symbol = symbol_short!("PENDING");
+const INTEGR: Symbol = symbol_short!("INTEGR");
+const INT_VOL: Symbol = symbol_short!("INT_VOL");
+const INT_CNT: Symbol = symbol_short!("INT_CNT");
+const INT_PEND: Symbol = symbol_short!("INT_PEND");
+const TOKEN_POL: Symbol = symbol_short!("TOKEN_POL");
+const ALLOWLIST: Symbol = symbol_short!("ALLOWLIST");
+const MIN_GAP: Symbol = symbol_short!("MIN_GAP");
+const ORDER: Symbol = symbol_short!("ORDER");
+const MULTI_FIL: Symbol = symbol_short!("MULTI_FIL");
+const MIN_DEP: Symbol = symbol_short!("MIN_DEP");
+const SKEW: Symbol = symbol_short!("SKEW");
+const DST_ESC: Symbol = symbol_short!("DST_ESC");
+const EPOCH: Symbol = symbol_short!("EPOCH");
+const RETENTION: Symbol = symbol_short!("RETENTION");
+const RATE_LIM: Symbol = symbol_short!("RATE_LIM");
+const DEP_CNT: Symbol = symbol_short!("DEP_CNT");
+const WRAPPED: Symbol = symbol_short!("WRAPPED");
+const FEE_POOL: Symbol = symbol_short!("FEE_POOL");
+const TTL_BNTY: Symbol = symbol_short!("TTL_BNTY");
+const HTLC_VER: Symbol = symbol_short!("HTLC_VER");
+const DEPLOYS: Symbol = symbol_short!("DEPLOYS");
+const POL_CNT: Symbol = symbol_short!("POL_CNT");
+const SPONSOR: Symbol = symbol_short!("SPONSOR");
+const SPN_CNT: Symbol = symbol_short!("SPN_CNT");
+const SWAP_ID: Symbol = symbol_short!("SWAP_ID");
+const CPARTY: Symbol = symbol_short!("CPARTY");
+const LIM_FILL: Symbol = symbol_short!("LIM_FILL");
+const RECEIPT: Symbol = symbol_short!("RECEIPT");
+
+/// Ledgers per day at the 5-second target close time
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Registry entries are extended once their TTL drops below this many ledgers...
+const REGISTRY_TTL_THRESHOLD: u32 = 30 * DAY_IN_LEDGERS;
+
+/// ...to this many ledgers
+const REGISTRY_TTL_EXTEND_TO: u32 = 120 * DAY_IN_LEDGERS;
+
+/// Escrow state values for Withdrawn and Cancelled (the escrow's State enum)
+const TERMINAL_STATES: [u32; 2] = [1, 2];
+
+/// Largest clock-skew tolerance the admin can configure, in seconds
+const MAX_SKEW_TOLERANCE: u32 = 600;
+
+/// Number of timelock stages packed into the timelocks value
+const TIMELOCK_STAGES: u8 = 7;
+
+/// (private, public) timelock stage pairs: src withdrawal, src cancellation, dst withdrawal
+const PUBLIC_STAGE_PAIRS: [(u8, u8); 3] = [(0, 1), (2, 3), (4, 5)];
+
+/// Source-side stages in the order they open: withdrawal, public withdrawal, cancellation,
+/// public cancellation
+const SRC_STAGES: [u8; 4] = [0, 1, 2, 3];
+
+/// Maximum number of entries returned by paginated getters
+const MAX_PAGE_SIZE: u32 = 100;
 
 #[contract]
 pub struct EscrowFactory;
@@ -52,10 +455,11 @@ impl EscrowFactory {
         // Store admin and HTLC WASM hash
         env.storage().instance().set(&ADMIN, &admin);
         env.storage().instance().set(&HTLC_HASH, &htlc_wasm_hash);
-        env.storage().instance().set(&NONCE, &0u64);
+        env.storage().instance().set(&HTLC_VER, &1u32);
         
         // Emit initialization event
-        env.events().publish(
+        events::publish(
+            &env,
             (Symbol::new(&env, "initialized"),),
             (admin, htlc_wasm_hash),
         );
@@ -64,26 +468,194 @@ impl EscrowFactory {
     }
     
     /// Update the HTLC WASM hash (admin only)
+    /// With a governance delay set, the change is scheduled instead of applied
+    /// Once multisig is enabled this must go through propose/approve/execute instead
     pub fn update_htlc_hash(env: Env, new_hash: BytesN<32>) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::UpdateHtlcHash(new_hash))
+    }
+    
+    /// Pause or resume escrow deployments (admin only, never delayed)
+    /// Once multisig is enabled this must go through propose/approve/execute instead
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetPaused(paused))
+    }
+    
+    /// Set the delay (seconds) applied to every admin change but pausing and cancellation (admin only)
+    /// Changing an existing non-zero delay is itself delayed
+    pub fn set_governance_delay(env: Env, delay: u64) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetGovernanceDelay(delay))
+    }
+    
+    /// Cancel a scheduled action before it is executed (admin only)
+    pub fn cancel_scheduled(env: Env, action_id: u64) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::CancelScheduled(action_id))
+    }
+    
+    /// Execute a scheduled action once its delay has passed (anyone)
+    pub fn execute_scheduled(env: Env, action_id: u64) -> Result<(), Error> {
+        let scheduled: ScheduledAction = env.storage().persistent()
+            .get(&(SCHEDULED, action_id))
+            .ok_or(Error::ActionNotFound)?;
+        if env.ledger().timestamp() < scheduled.executable_at {
+            return Err(Error::ActionNotReady);
+        }
+        
+        remove_scheduled(&env, action_id);
+        apply_action(&env, &scheduled.action)?;
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "action_executed"),),
+            action_id,
+        );
+        
+        Ok(())
+    }
+    
+    /// Get the governance delay in seconds (0 when changes apply immediately)
+    pub fn get_governance_delay(env: Env) -> u64 {
+        env.storage().instance().get(&GOV_DELAY).unwrap_or(0)
+    }
+    
+    /// Get a scheduled action by id
+    pub fn get_scheduled_action(env: Env, action_id: u64) -> Option<ScheduledAction> {
+        env.storage().persistent().get(&(SCHEDULED, action_id))
+    }
+    
+    /// Get the ids of all scheduled actions not yet executed or cancelled
+    pub fn get_pending_actions(env: Env) -> Vec<u64> {
+        env.storage().instance().get(&PENDING).unwrap_or_else(|| Vec::new(&env))
+    }
+    
+    /// Check whether escrow deployments are paused
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&PAUSED).unwrap_or(false)
+    }
+    
+    /// Replace the single admin with an M-of-N signer set (admin only, one-time)
+    /// Every AdminAction setter then fails with MultisigRequired and goes through proposals
+    pub fn initialize_multisig(env: Env, admins: Vec<Address>, threshold: u32) -> Result<(), Error> {
         let admin: Address = env.storage().instance()
             .get(&ADMIN)
             .ok_or(Error::NotInitialized)?;
         admin.require_auth();
         
-        env.storage().instance().set(&HTLC_HASH, &new_hash);
+        if env.storage().instance().has(&SIGNERS) {
+            return Err(Error::AlreadyInitialized);
+        }
+        if threshold == 0 || threshold > admins.len() {
+            return Err(Error::InvalidParams);
+        }
+        for (i, signer) in admins.iter().enumerate() {
+            if admins.first_index_of(&signer) != Some(i as u32) {
+                return Err(Error::InvalidParams);
+            }
+        }
+        
+        env.storage().instance().set(&SIGNERS, &admins);
+        env.storage().instance().set(&THRESHOLD, &threshold);
+        env.storage().instance().set(&PROP_ID, &0u64);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "multisig_initialized"),),
+            (admins, threshold),
+        );
+        
+        Ok(())
+    }
+    
+    /// Propose a sensitive admin action (signers only); the proposer's approval is counted
+    /// Returns the proposal id
+    pub fn propose(env: Env, proposer: Address, action: AdminAction) -> Result<u64, Error> {
+        require_signer(&env, &proposer)?;
+        check_action(&env, &action)?;
+        
+        let id: u64 = env.storage().instance().get(&PROP_ID).unwrap_or(0);
+        env.storage().instance().set(&PROP_ID, &(id + 1));
+        
+        let proposal = Proposal {
+            action,
+            approvals: vec![&env, proposer.clone()],
+            executed: false,
+        };
+        env.storage().persistent().set(&(PROPOSAL, id), &proposal);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "proposal_created"),),
+            (id, proposer),
+        );
+        
+        Ok(id)
+    }
+    
+    /// Approve a pending proposal (signers only)
+    pub fn approve(env: Env, signer: Address, proposal_id: u64) -> Result<(), Error> {
+        require_signer(&env, &signer)?;
+        
+        let mut proposal = load_proposal(&env, proposal_id)?;
+        if proposal.approvals.contains(&signer) {
+            return Err(Error::AlreadyApproved);
+        }
+        proposal.approvals.push_back(signer.clone());
+        env.storage().persistent().set(&(PROPOSAL, proposal_id), &proposal);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "proposal_approved"),),
+            (proposal_id, signer, proposal.approvals.len()),
+        );
+        
+        Ok(())
+    }
+    
+    /// Execute a proposal that has reached the approval threshold (anyone)
+    pub fn execute(env: Env, proposal_id: u64) -> Result<(), Error> {
+        let mut proposal = load_proposal(&env, proposal_id)?;
+        let threshold: u32 = env.storage().instance()
+            .get(&THRESHOLD)
+            .ok_or(Error::NotInitialized)?;
+        if proposal.approvals.len() < threshold {
+            return Err(Error::InsufficientApprovals);
+        }
+        
+        proposal.executed = true;
+        env.storage().persistent().set(&(PROPOSAL, proposal_id), &proposal);
+        enact_action(&env, proposal.action)?;
         
-        env.events().publish(
-            (Symbol::new(&env, "htlc_hash_updated"),),
-            new_hash,
+        events::publish(
+            &env,
+            (Symbol::new(&env, "proposal_executed"),),
+            proposal_id,
         );
         
         Ok(())
     }
     
-    /// Deploy an escrow contract with client-provided salt
+    /// Get a proposal by id
+    pub fn get_proposal(env: Env, proposal_id: u64) -> Option<Proposal> {
+        env.storage().persistent().get(&(PROPOSAL, proposal_id))
+    }
+    
+    /// Get the multisig signer set (empty when multisig is not enabled)
+    pub fn get_signers(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&SIGNERS).unwrap_or_else(|| Vec::new(&env))
+    }
+    
+    /// Get the multisig approval threshold (0 when multisig is not enabled)
+    pub fn get_threshold(env: Env) -> u32 {
+        env.storage().instance().get(&THRESHOLD).unwrap_or(0)
+    }
+    
+    /// Deploy an escrow contract at the address derived from its immutables
+    /// The taker authorizes the deploy, so nobody else can claim its order hash with other terms
     pub fn deploy_escrow(
         env: Env,
-        salt: BytesN<32>,  // Client computes this off-chain using keccak256
         order_hash: BytesN<32>,
         hashlock: BytesN<32>,
         maker: Address,
@@ -93,92 +665,1722 @@ impl EscrowFactory {
         safety_deposit: i128,
         timelocks: u64,
     ) -> Result<Address, Error> {
-        // Get the HTLC WASM hash
-        let htlc_wasm_hash: BytesN<32> = env.storage().instance()
-            .get(&HTLC_HASH)
-            .ok_or(Error::NotInitialized)?;
+        taker.require_auth();
+        let immutables = Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+        };
         
-        // Check if already deployed with this salt
-        let deployed_key = (DEPLOYED, salt.clone());
-        if env.storage().persistent().has(&deployed_key) {
-            let existing_address: Address = env.storage().persistent().get(&deployed_key).unwrap();
-            log!(&env, "Escrow already deployed at: {}", existing_address);
-            return Err(Error::AlreadyDeployed);
+        deploy_escrow_internal(&env, &immutables, None, None, None)
+    }
+    
+    /// Deploy an escrow on behalf of `resolver`, its taker, which authorizes exactly these immutables
+    /// A contract resolver reaching the factory through another contract covers the call with an
+    /// invoker-contract auth entry, so an intermediary that alters the terms fails the check
+    pub fn deploy_escrow_for(env: Env, resolver: Address, immutables: Immutables) -> Result<Address, Error> {
+        if resolver != immutables.taker {
+            return Err(Error::ResolverNotTaker);
         }
+        resolver.require_auth_for_args((immutables.clone(),).into_val(&env));
         
-        // Deploy the escrow contract with deterministic address
-        let escrow = env.deployer()
-            .with_current_contract(salt.clone())
-            .deploy_v2(htlc_wasm_hash, ());
+        deploy_escrow_internal(&env, &immutables, None, None, None)
+    }
+    
+    /// Deploy an escrow, or return the existing one if these exact immutables were already deployed
+    /// The salt is derived from the immutables, so an existing salt means an identical escrow;
+    /// relayers can retry after a timeout without telling AlreadyDeployed apart from a real failure.
+    /// The taker authorizes it like deploy_escrow
+    pub fn deploy_escrow_idempotent(env: Env, immutables: Immutables) -> Result<DeployResult, Error> {
+        immutables.taker.require_auth();
+        if !env.storage().instance().has(&HTLC_HASH) {
+            return Err(Error::NotInitialized);
+        }
         
-        // Store deployed address
-        env.storage().persistent().set(&deployed_key, &escrow);
+        let salt = derive_salt_internal(&env, &immutables);
+        if let Some(escrow) = get_deployed(&env, &salt) {
+            return Ok(DeployResult { escrow, reused: true });
+        }
         
-        // Initialize the escrow by calling its deploy function
-        let _: () = env.invoke_contract(
-            &escrow,
-            &Symbol::new(&env, "deploy"),
-            vec![
-                &env,
-                order_hash.to_val(),
-                hashlock.to_val(),
-                maker.to_val(),
-                taker.to_val(),
-                token.to_val(),
-                amount.into_val(&env),
-                safety_deposit.into_val(&env),
-                timelocks.into_val(&env),
-            ],
-        );
-        
-        // Emit event
-        env.events().publish(
-            (Symbol::new(&env, "escrow_deployed"),),
-            (escrow.clone(), order_hash, salt),
+        let escrow = deploy_escrow_internal(&env, &immutables, None, None, None)?;
+        Ok(DeployResult { escrow, reused: false })
+    }
+    
+    /// Deploy an escrow attributed to the integrator (front-end) that routed the order
+    /// The attribution is stored per escrow; only the taker can attach it, as the salt doesn't cover
+    /// it. The escrow counts towards the integrator's volume once record_integrator_volume sees it funded
+    pub fn deploy_escrow_with_integrator(
+        env: Env,
+        immutables: Immutables,
+        integrator: Address,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, None, Some(integrator), None)
+    }
+    
+    /// Count an attributed escrow in its integrator's volume and order count once it is funded (anyone)
+    /// Counted once per escrow; fails with `UnknownEscrow` for salts without a pending attribution
+    /// and `InvalidParams` while the escrow isn't funded. Returns the amount counted
+    pub fn record_integrator_volume(env: Env, salt: BytesN<32>) -> Result<i128, Error> {
+        let pending_key = (INT_PEND, salt.clone());
+        let (token, amount): (Address, i128) = env.storage().persistent()
+            .get(&pending_key)
+            .ok_or(Error::UnknownEscrow)?;
+        let integrator: Address = env.storage().persistent()
+            .get(&(INTEGR, salt.clone()))
+            .ok_or(Error::UnknownEscrow)?;
+        let escrow = get_deployed(&env, &salt).ok_or(Error::UnknownEscrow)?;
+        let funded = env.try_invoke_contract::<i128, soroban_sdk::Error>(&escrow, &Symbol::new(&env, "get_funded_amount"), vec![&env]);
+        if !matches!(funded, Ok(Ok(funded)) if funded >= amount) {
+            return Err(Error::InvalidParams);
+        }
+        env.storage().persistent().remove(&pending_key);
+        
+        let volume_key = (INT_VOL, integrator.clone(), token.clone());
+        let volume: i128 = env.storage().persistent().get(&volume_key).unwrap_or(0);
+        env.storage().persistent().set(&volume_key, &volume.saturating_add(amount));
+        let count_key = (INT_CNT, integrator.clone());
+        let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+        env.storage().persistent().set(&count_key, &(count + 1));
+        
+        events::publish(&env, (Symbol::new(&env, "integrator_volume"), integrator), (escrow, token, amount));
+        
+        Ok(amount)
+    }
+    
+    /// Deploy an escrow on behalf of its resolver (the taker) with a third-party fee payer
+    /// The resolver authorizes the deployment as the logical caller; the sponsor separately
+    /// authorizes covering it for this order and resolver, and typically submits the (fee-bump)
+    /// transaction. The sponsorship is recorded apart from the resolver's own deployments
+    pub fn deploy_escrow_sponsored(env: Env, immutables: Immutables, sponsor: Address) -> Result<Address, Error> {
+        if sponsor == immutables.taker {
+            return Err(Error::SelfSponsored);
+        }
+        immutables.taker.require_auth();
+        sponsor.require_auth_for_args((immutables.order_hash.clone(), immutables.taker.clone()).into_val(&env));
+        
+        let escrow = deploy_escrow_internal(&env, &immutables, None, None, None)?;
+        let salt = derive_salt_internal(&env, &immutables);
+        let sponsorship = Sponsorship { sponsor: sponsor.clone(), resolver: immutables.taker.clone() };
+        env.storage().persistent().set(&(SPONSOR, salt), &sponsorship);
+        let count_key = (SPN_CNT, sponsor.clone());
+        let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+        env.storage().persistent().set(&count_key, &(count + 1));
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "escrow_sponsored"), sponsor),
+            (escrow.clone(), immutables.order_hash.clone(), immutables.taker.clone()),
+        );
+        Ok(escrow)
+    }
+    
+    /// Get who sponsored an escrow's deployment, if it was deployed with deploy_escrow_sponsored
+    pub fn get_escrow_sponsorship(env: Env, salt: BytesN<32>) -> Option<Sponsorship> {
+        env.storage().persistent().get(&(SPONSOR, salt))
+    }
+    
+    /// Get the number of escrow deployments `sponsor` has covered
+    pub fn get_sponsored_count(env: Env, sponsor: Address) -> u32 {
+        env.storage().persistent().get(&(SPN_CNT, sponsor)).unwrap_or(0)
+    }
+    
+    /// Deploy an escrow and fund it from the maker in one call, using a maker-signed permit
+    /// instead of a prior token approval; the caller (resolver) submits the maker's
+    /// authorization entry with the transaction and authorizes the deploy as the taker, since the
+    /// permit doesn't cover the other terms. A safety deposit is paid by the taker
+    pub fn deploy_and_fund(env: Env, immutables: Immutables, permit: Permit) -> Result<Address, Error> {
+        if env.ledger().timestamp() > permit.expires_at {
+            return Err(Error::PermitExpired);
+        }
+        immutables.taker.require_auth();
+        immutables.maker.require_auth_for_args(
+            (
+                immutables.order_hash.clone(),
+                immutables.token.clone(),
+                immutables.amount,
+                permit.expires_at,
+            ).into_val(&env),
+        );
+        
+        let escrow = deploy_escrow_internal(&env, &immutables, None, None, None)?;
+        fund_escrow(&env, &immutables, &escrow);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "permit_used"), immutables.maker.clone()),
+            (escrow.clone(), immutables.order_hash.clone(), permit.expires_at),
         );
         
         Ok(escrow)
     }
     
-    /// Calculate the address that would be generated for given salt
-    pub fn calculate_escrow_address(
+    /// Deploy an escrow with an attached metadata blob (e.g. IPFS hash or order UID)
+    /// The escrow stores it and rejects blobs longer than 64 bytes. The blob isn't part of the salt,
+    /// so only the taker can attach it
+    pub fn deploy_escrow_with_details(
         env: Env,
-        salt: BytesN<32>,  // Client provides pre-computed salt
+        immutables: Immutables,
+        details: Bytes,
     ) -> Result<Address, Error> {
-        // Verify factory is initialized
-        let _htlc_wasm_hash: BytesN<32> = env.storage().instance()
-            .get(&HTLC_HASH)
-            .ok_or(Error::NotInitialized)?;
-        
-        // Check if would collide
-        let deployed_key = (DEPLOYED, salt.clone());
-        if env.storage().persistent().has(&deployed_key) {
-            return Err(Error::AlreadyDeployed);
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Details(details)), None, None)
+    }
+    
+    /// Deploy a destination escrow whose withdrawals pay `beneficiary` (the resolver's treasury)
+    /// instead of the taker; only the taker can designate it
+    pub fn deploy_escrow_with_beneficiary(
+        env: Env,
+        immutables: Immutables,
+        beneficiary: Address,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Beneficiary(beneficiary)), None, None)
+    }
+    
+    /// Deploy an escrow whose safety deposit is split per action between the executor, the maker
+    /// and a protocol address; the escrow rejects splits that don't sum to 10_000 bps
+    /// Only the taker can set the splits, which the salt doesn't cover
+    pub fn deploy_escrow_with_incentives(
+        env: Env,
+        immutables: Immutables,
+        incentives: DepositIncentives,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Incentives(incentives)), None, None)
+    }
+    
+    /// Deploy an escrow whose withdrawals also wait for `finality_oracle`'s `is_final(order_hash)`,
+    /// for swaps sourced from probabilistic-finality chains; the taker picks the oracle, which the
+    /// salt doesn't cover
+    pub fn deploy_escrow_with_oracle(
+        env: Env,
+        immutables: Immutables,
+        finality_oracle: Address,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::FinalityOracle(finality_oracle)), None, None)
+    }
+    
+    /// Deploy an escrow calling `pre_funding_hook` before funding is accepted and `post_payout_hook`
+    /// after each withdrawal payout; failing hooks are skipped by the escrow
+    /// The hooks aren't part of the salt, so only the taker can install them
+    pub fn deploy_escrow_with_hooks(
+        env: Env,
+        immutables: Immutables,
+        pre_funding_hook: Option<Address>,
+        post_payout_hook: Option<Address>,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Hooks(pre_funding_hook, post_payout_hook)), None, None)
+    }
+    
+    /// Deploy an escrow with non-default behavior flags (the escrow's FLAG_* bits), e.g. one
+    /// that disables partial fills or requires the taker's authorization for withdrawals
+    /// Only the taker can choose them, as the salt doesn't cover the flags
+    pub fn deploy_escrow_with_flags(env: Env, immutables: Immutables, flags: u32) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Flags(flags)), None, None)
+    }
+    
+    /// Deploy a multi-secret escrow filled in `parts` tranches, whose hashlock is the Merkle root
+    /// of `parts + 1` secrets (see the escrow's `set_parts` and `withdraw_partial_with_proof`)
+    /// Only the taker can set the count, as the salt doesn't cover it
+    pub fn deploy_escrow_with_parts(env: Env, immutables: Immutables, parts: u32) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        deploy_escrow_internal(&env, &immutables, Some(EscrowInit::Parts(parts)), None, None)
+    }
+    
+    /// Deploy an escrow from v2 immutables (side, flags, receiver and fees in one struct)
+    /// The address is derived from the eight v1 fields like deploy_escrow's, so the EVM-compatible
+    /// salt is unchanged and one order can't have both a v1 and a v2 escrow. Source-side escrows
+    /// still go through deploy_src_escrow, which checks the maker's authorization and chain config.
+    /// The salt leaves out the v2 fields, so only the taker can deploy
+    pub fn deploy_escrow_v2(env: Env, immutables: ImmutablesV2) -> Result<Address, Error> {
+        if immutables.version != IMMUTABLES_V2_VERSION || immutables.side != EscrowSide::Dst {
+            return Err(Error::InvalidParams);
         }
-        
-        // Calculate the deterministic address
-        Ok(env.deployer()
-            .with_current_contract(salt)
-            .deployed_address())
+        immutables.taker.require_auth();
+        let core = Immutables {
+            order_hash: immutables.order_hash.clone(),
+            hashlock: immutables.hashlock.clone(),
+            maker: immutables.maker.clone(),
+            taker: immutables.taker.clone(),
+            token: immutables.token.clone(),
+            amount: immutables.amount,
+            safety_deposit: immutables.safety_deposit,
+            timelocks: immutables.timelocks,
+        };
+        let immutables = immutables.into_val(&env);
+        deploy_escrow_internal(&env, &core, Some(EscrowInit::V2(immutables)), None, None)
     }
     
-    /// Get the current HTLC WASM hash
-    pub fn get_htlc_hash(env: Env) -> Result<BytesN<32>, Error> {
-        env.storage().instance()
-            .get(&HTLC_HASH)
-            .ok_or(Error::NotInitialized)
+    /// Deploy a source escrow locking the maker's tokens for a Stellar → EVM swap
+    /// The maker funds it in the same call (the taker pays the safety deposit, if any) and it
+    /// runs on the SRC_* stages, which must be in order. The `src_escrow_deployed` event carries
+    /// what the EVM resolver needs to deploy the mirrored EscrowDst
+    pub fn deploy_src_escrow(
+        env: Env,
+        immutables: Immutables,
+        dst: DstEscrowParams,
+    ) -> Result<Address, Error> {
+        immutables.maker.require_auth();
+        deploy_src_escrow_internal(&env, &immutables, dst)
     }
     
-    /// Get the admin address
-    pub fn get_admin(env: Env) -> Result<Address, Error> {
-        env.storage().instance()
-            .get(&ADMIN)
-            .ok_or(Error::NotInitialized)
+    /// Deploy and fund the src escrow of a maker-signed order (Stellar → EVM)
+    /// The maker authorizes `(order_hash, maker_auth.expires_at)`, covering the transfer of its
+    /// tokens, and the resolver submits that authorization entry, as with a signed LOP order on
+    /// the EVM leg. The order hash is derived here, so the escrow can only be bound to the order
+    /// the maker signed; a replayed order maps to the same escrow and fails with AlreadyDeployed
+    pub fn create_src_escrow(env: Env, order: Order, maker_auth: Permit) -> Result<Address, Error> {
+        if env.ledger().timestamp() > maker_auth.expires_at {
+            return Err(Error::PermitExpired);
+        }
+        if order.nonce < maker_nonce(&env, &order.maker) {
+            return Err(Error::NonceCancelled);
+        }
+        let order_hash = hash_order_internal(&env, &order);
+        order.maker.require_auth_for_args((order_hash.clone(), maker_auth.expires_at).into_val(&env));
+        
+        let immutables = Immutables {
+            order_hash,
+            hashlock: order.hashlock,
+            maker: order.maker,
+            taker: order.taker,
+            token: order.token,
+            amount: order.amount,
+            safety_deposit: order.safety_deposit,
+            timelocks: order.timelocks,
+        };
+        deploy_src_escrow_internal(&env, &immutables, order.dst)
     }
     
-    /// Check if an escrow is already deployed with given salt
-    pub fn is_deployed(env: Env, salt: BytesN<32>) -> bool {
-        let deployed_key = (DEPLOYED, salt);
-        env.storage().persistent().has(&deployed_key)
+    /// Hash a maker authorizes for create_src_escrow (see fusion_shared::order)
+    /// Domain-separated by network, factory address and order version, so it can't be replayed
+    /// against another deployment
+    pub fn hash_order(env: Env, order: Order) -> BytesN<32> {
+        hash_order_internal(&env, &order)
     }
-}
\ No newline at end of file
+    
+    /// Settle two crossing same-chain limit orders token-for-token, without escrows or a hashlock (anyone)
+    /// Each maker authorizes `(order_hash, permit.expires_at)` as for create_src_escrow, and
+    /// cancel_nonce invalidates limit orders too. Each maker receives the other's whole
+    /// `maker_amount`, which must cover its `taker_amount` (`OrdersDontCross`), so any price
+    /// improvement goes to the makers, not the caller. Each order fills once (`OrderFilled`);
+    /// returns both order hashes
+    pub fn match_orders(
+        env: Env,
+        order_a: LimitOrder,
+        order_b: LimitOrder,
+        sig_a: Permit,
+        sig_b: Permit,
+    ) -> Result<(BytesN<32>, BytesN<32>), Error> {
+        if env.storage().instance().get(&PAUSED).unwrap_or(false) {
+            return Err(Error::Paused);
+        }
+        if order_a.maker == order_b.maker {
+            return Err(Error::MakerIsTaker);
+        }
+        if order_a.maker_token != order_b.taker_token
+            || order_a.taker_token != order_b.maker_token
+            || order_a.maker_token == order_a.taker_token
+        {
+            return Err(Error::TokenMismatch);
+        }
+        if !is_token_allowed(&env, &order_a.maker_token) || !is_token_allowed(&env, &order_b.maker_token) {
+            return Err(Error::TokenNotAllowed);
+        }
+        if order_b.maker_amount < order_a.taker_amount || order_a.maker_amount < order_b.taker_amount {
+            return Err(Error::OrdersDontCross);
+        }
+        
+        let hash_a = claim_limit_order(&env, &order_a, &sig_a)?;
+        let hash_b = claim_limit_order(&env, &order_b, &sig_b)?;
+        
+        token::TokenClient::new(&env, &order_a.maker_token)
+            .transfer(&order_a.maker, &order_b.maker, &order_a.maker_amount);
+        token::TokenClient::new(&env, &order_b.maker_token)
+            .transfer(&order_b.maker, &order_a.maker, &order_b.maker_amount);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "orders_matched"), hash_a.clone(), hash_b.clone()),
+            (order_a.maker, order_b.maker, order_a.maker_amount, order_b.maker_amount),
+        );
+        
+        Ok((hash_a, hash_b))
+    }
+    
+    /// Hash a maker authorizes for match_orders (see fusion_shared::order::hash_limit_order)
+    pub fn hash_limit_order(env: Env, order: LimitOrder) -> BytesN<32> {
+        hash_limit_order_internal(&env, &order)
+    }
+    
+    /// Check whether match_orders has filled a limit order
+    pub fn is_order_filled(env: Env, order_hash: BytesN<32>) -> bool {
+        env.storage().persistent().has(&(LIM_FILL, order_hash))
+    }
+    
+    /// Cancel every outstanding order of `maker` signed with a nonce below `nonce` (maker only)
+    /// The maker's epoch only moves forward, so one call invalidates all older orders at once,
+    /// like the epoch of 1inch's limit order protocol; orders at `nonce` or above stay valid
+    pub fn cancel_nonce(env: Env, maker: Address, nonce: u64) -> Result<(), Error> {
+        maker.require_auth();
+        
+        if nonce <= maker_nonce(&env, &maker) {
+            return Err(Error::InvalidParams);
+        }
+        env.storage().persistent().set(&(EPOCH, maker.clone()), &nonce);
+        
+        events::publish(&env, (Symbol::new(&env, "nonce_cancelled"), maker), nonce);
+        
+        Ok(())
+    }
+    
+    /// Lowest order nonce create_src_escrow still accepts for `maker` (0 if never cancelled)
+    pub fn get_maker_nonce(env: Env, maker: Address) -> u64 {
+        maker_nonce(&env, &maker)
+    }
+    
+    /// Get the destination leg recorded for a source escrow by deploy_src_escrow
+    pub fn get_dst_escrow_params(env: Env, salt: BytesN<32>) -> Option<DstEscrowParams> {
+        env.storage().persistent().get(&(DST_ESC, salt))
+    }
+    
+    /// Deploy an escrow for a swap sourced from a registered foreign chain
+    /// The immutables are validated against the chain's configuration; the taker authorizes the deploy
+    pub fn deploy_escrow_from_chain(
+        env: Env,
+        src_chain_id: u64,
+        immutables: Immutables,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        validate_chain(&env, src_chain_id, &immutables)?;
+        deploy_from_chain_internal(&env, src_chain_id, &immutables)
+    }
+    
+    /// Deploy the Stellar escrow of an EVM order whose taker asset is `taker_asset`
+    /// Like deploy_escrow_from_chain, but the escrowed token must be the registered Stellar
+    /// equivalent of the order's EVM taker asset (UnsupportedToken without a mapping,
+    /// TokenMismatch for another token), so the resolver's token choice isn't trusted.
+    /// The resolver (the taker) authorizes the deploy
+    pub fn deploy_escrow_for_evm_order(
+        env: Env,
+        src_chain_id: u64,
+        taker_asset: EvmAddress,
+        immutables: Immutables,
+    ) -> Result<Address, Error> {
+        immutables.taker.require_auth();
+        validate_chain(&env, src_chain_id, &immutables)?;
+        
+        let wrapped: Address = env.storage().persistent()
+            .get(&(WRAPPED, src_chain_id, taker_asset))
+            .ok_or(Error::UnsupportedToken)?;
+        if wrapped != immutables.token {
+            return Err(Error::TokenMismatch);
+        }
+        
+        deploy_from_chain_internal(&env, src_chain_id, &immutables)
+    }
+    
+    /// Record the EVM escrow holding the other leg of an order's swap (taker of its Stellar escrow only)
+    /// Lets verification tooling tie both legs together on-chain; set once per order, fails with
+    /// `UnknownEscrow` while the order's Stellar escrow isn't deployed (or is pruned or finalized) and
+    /// `CounterpartyImported` after
+    pub fn import_counterparty(
+        env: Env,
+        order_hash: BytesN<32>,
+        evm_escrow_address: EvmAddress,
+        evm_immutables_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        let record: OrderRecord = env.storage().persistent()
+            .get(&(ORDER, order_hash.clone()))
+            .ok_or(Error::UnknownEscrow)?;
+        let salt = record.salts.first().ok_or(Error::UnknownEscrow)?;
+        let stellar_escrow: Address = env.storage().persistent()
+            .get(&(DEPLOYED, salt))
+            .ok_or(Error::UnknownEscrow)?;
+        // A finalized escrow no longer has parties to report
+        let parties = env.try_invoke_contract::<(Address, Address), soroban_sdk::Error>(
+            &stellar_escrow,
+            &Symbol::new(&env, "get_parties"),
+            vec![&env],
+        );
+        let Ok(Ok((_, taker))) = parties else {
+            return Err(Error::UnknownEscrow);
+        };
+        taker.require_auth();
+        
+        if evm_immutables_hash == BytesN::from_array(&env, &[0; 32]) {
+            return Err(Error::InvalidParams);
+        }
+        let key = (CPARTY, order_hash.clone());
+        if env.storage().persistent().has(&key) {
+            return Err(Error::CounterpartyImported);
+        }
+        
+        let counterparty = CounterpartyEscrow {
+            stellar_escrow: stellar_escrow.clone(),
+            evm_escrow: evm_escrow_address.clone(),
+            evm_immutables_hash: evm_immutables_hash.clone(),
+        };
+        env.storage().persistent().set(&key, &counterparty);
+        env.storage().persistent().extend_ttl(&key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "counterparty_imported"), order_hash),
+            (stellar_escrow, evm_escrow_address, evm_immutables_hash),
+        );
+        
+        Ok(())
+    }
+    
+    /// Map an EVM token on a registered chain to its Stellar equivalent (admin only)
+    /// Used to check the token of escrows for EVM orders, in both directions
+    pub fn set_wrapped_token(env: Env, chain_id: u64, evm_token: EvmAddress, token: Address) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetWrappedToken(chain_id, evm_token, token))
+    }
+    
+    /// Remove an EVM token mapping (admin only)
+    pub fn remove_wrapped_token(env: Env, chain_id: u64, evm_token: EvmAddress) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::RemoveWrappedToken(chain_id, evm_token))
+    }
+    
+    /// Get the Stellar equivalent of an EVM token, if one is registered
+    pub fn get_wrapped_token(env: Env, chain_id: u64, evm_token: EvmAddress) -> Option<Address> {
+        env.storage().persistent().get(&(WRAPPED, chain_id, evm_token))
+    }
+    
+    /// Register or update a foreign chain's configuration (admin only)
+    pub fn set_chain_config(env: Env, chain_id: u64, config: ChainConfig) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetChainConfig(chain_id, config))
+    }
+    
+    /// Remove a foreign chain from the registry (admin only)
+    pub fn remove_chain_config(env: Env, chain_id: u64) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::RemoveChainConfig(chain_id))
+    }
+    
+    /// Allow or deny a token for escrows (admin only)
+    /// Denied tokens are always rejected; in allow-list mode only allowed tokens are accepted
+    pub fn set_token_policy(env: Env, token: Address, allowed: bool) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetTokenPolicy(token, allowed))
+    }
+    
+    /// Forget a token's policy, returning it to the default for the current mode (admin only)
+    pub fn remove_token_policy(env: Env, token: Address) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::RemoveTokenPolicy(token))
+    }
+    
+    /// Switch between deny-list mode (default: any token not denied) and allow-list mode
+    /// (only tokens explicitly allowed) (admin only)
+    pub fn set_allowlist_mode(env: Env, enabled: bool) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetAllowlistMode(enabled))
+    }
+    
+    /// Require every public stage to start at least `gap` seconds after its private stage (admin only)
+    /// Keeps misconfigured orders from giving the exclusive resolver no private window; 0 disables
+    pub fn set_min_public_gap(env: Env, gap: u32) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetMinPublicGap(gap))
+    }
+    
+    /// Require deploys to carry at least the configured safety deposit (admin only)
+    /// Once either bound is non-zero, zero-deposit escrows are rejected too; both 0 disables
+    pub fn set_min_safety_deposit(env: Env, config: MinSafetyDeposit) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetMinSafetyDeposit(config))
+    }
+    
+    /// Get the minimum safety deposit configuration, if set
+    pub fn get_min_safety_deposit(env: Env) -> Option<MinSafetyDeposit> {
+        env.storage().instance().get(&MIN_DEP)
+    }
+    
+    /// Set the clock-skew tolerance (seconds) given to escrows deployed from now on (admin only)
+    /// Their cancellation stages open this long after the timelock, so a withdrawal racing the
+    /// boundary on a chain with a drifting clock isn't cut off; at most MAX_SKEW_TOLERANCE
+    pub fn set_skew_tolerance(env: Env, skew_tolerance: u32) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetSkewTolerance(skew_tolerance))
+    }
+    
+    /// Get the clock-skew tolerance given to new escrows (0 if unset)
+    pub fn get_skew_tolerance(env: Env) -> u32 {
+        env.storage().instance().get(&SKEW).unwrap_or(0)
+    }
+    
+    /// Cap deployments per taker in one ledger (admin only); 0 removes the cap
+    /// While a cap is set, every deploy counts against the escrow's taker (the resolver
+    /// submitting it), who must authorize the deploy so the count can't be charged to others
+    pub fn set_rate_limit(env: Env, max_per_ledger: u32) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetRateLimit(max_per_ledger))
+    }
+    
+    /// Get the per-ledger deployment cap per taker (0 if unlimited)
+    pub fn get_rate_limit(env: Env) -> u32 {
+        env.storage().instance().get(&RATE_LIM).unwrap_or(0)
+    }
+    
+    /// Allow further escrows for `order_hash` with different immutables (maker only)
+    /// Needed for partial fills, where each fill deploys its own escrow; every escrow for the
+    /// order must still share the maker and token of the first one
+    pub fn enable_partial_fills(env: Env, maker: Address, order_hash: BytesN<32>) -> Result<(), Error> {
+        maker.require_auth();
+        
+        if let Some(record) = env.storage().persistent().get::<_, OrderRecord>(&(ORDER, order_hash.clone())) {
+            if record.maker != maker {
+                return Err(Error::OrderHashConflict);
+            }
+        }
+        env.storage().persistent().set(&(MULTI_FIL, order_hash.clone()), &maker);
+        
+        events::publish(
+            &env,
+            (Symbol::new(&env, "partial_fills_enabled"),),
+            (order_hash, maker),
+        );
+        
+        Ok(())
+    }
+    
+    /// Check whether the maker enabled partial fills for an order
+    pub fn is_partial_fill_enabled(env: Env, order_hash: BytesN<32>) -> bool {
+        env.storage().persistent().has(&(MULTI_FIL, order_hash))
+    }
+    
+    /// Get the escrows deployed for an order_hash, if any
+    pub fn get_order_record(env: Env, order_hash: BytesN<32>) -> Option<OrderRecord> {
+        env.storage().persistent().get(&(ORDER, order_hash))
+    }
+    
+    /// Get the minimum gap between private and public stages
+    pub fn get_min_public_gap(env: Env) -> u32 {
+        env.storage().instance().get(&MIN_GAP).unwrap_or(0)
+    }
+    
+    /// Get the explicit policy for a token, if one is set
+    pub fn get_token_policy(env: Env, token: Address) -> Option<bool> {
+        env.storage().persistent().get(&(TOKEN_POL, token))
+    }
+    
+    /// Check whether escrows for `token` can currently be deployed
+    pub fn is_token_allowed(env: Env, token: Address) -> bool {
+        is_token_allowed(&env, &token)
+    }
+    
+    /// Get a foreign chain's configuration
+    pub fn get_chain_config(env: Env, chain_id: u64) -> Option<ChainConfig> {
+        env.storage().persistent().get(&(CHAIN, chain_id))
+    }
+    
+    /// Get the source chain an escrow was deployed for, if it was tagged with one
+    pub fn get_escrow_chain(env: Env, salt: BytesN<32>) -> Option<u64> {
+        env.storage().persistent().get(&(SRC_CHAIN, salt))
+    }
+    
+    /// Get the escrow a cross-chain deploy recorded for `swap_id` (see fusion_shared::swap_id)
+    pub fn get_escrow_by_swap_id(env: Env, swap_id: BytesN<32>) -> Option<Address> {
+        env.storage().persistent().get(&(SWAP_ID, swap_id))
+    }
+    
+    /// Get the EVM escrow imported for an order, if any
+    pub fn get_counterparty(env: Env, order_hash: BytesN<32>) -> Option<CounterpartyEscrow> {
+        env.storage().persistent().get(&(CPARTY, order_hash))
+    }
+    
+    /// Get the integrator an escrow was attributed to, if any
+    pub fn get_escrow_integrator(env: Env, salt: BytesN<32>) -> Option<Address> {
+        env.storage().persistent().get(&(INTEGR, salt))
+    }
+    
+    /// Get the total amount of `token` escrowed through funded orders attributed to `integrator`
+    pub fn get_integrator_volume(env: Env, integrator: Address, token: Address) -> i128 {
+        env.storage().persistent().get(&(INT_VOL, integrator, token)).unwrap_or(0)
+    }
+    
+    /// Get the number of funded escrows attributed to `integrator`
+    pub fn get_integrator_order_count(env: Env, integrator: Address) -> u32 {
+        env.storage().persistent().get(&(INT_CNT, integrator)).unwrap_or(0)
+    }
+    
+    /// Deploy an escrow for a classic Stellar asset (code + issuer)
+    /// The asset's Stellar Asset Contract is deployed first if it doesn't exist yet
+    pub fn deploy_escrow_for_asset(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        asset_code: String,
+        issuer: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Result<Address, Error> {
+        let token = Self::resolve_asset_contract(env.clone(), asset_code, issuer)?;
+
+        Self::deploy_escrow(
+            env,
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+        )
+    }
+
+    /// Get the Stellar Asset Contract address for a classic asset, deploying it if needed
+    pub fn resolve_asset_contract(
+        env: Env,
+        asset_code: String,
+        issuer: Address,
+    ) -> Result<Address, Error> {
+        let serialized_asset = serialize_classic_asset(&env, &asset_code, &issuer)?;
+        let deployer = env.deployer().with_stellar_asset(serialized_asset);
+
+        let sac = deployer.deployed_address();
+        if sac.exists() {
+            return Ok(sac);
+        }
+
+        let sac = deployer.deploy();
+        events::publish(
+            &env,
+            (Symbol::new(&env, "asset_wrapped"),),
+            (sac.clone(), asset_code, issuer),
+        );
+
+        Ok(sac)
+    }
+
+    /// Calculate the address the escrow for these immutables would be deployed at
+    pub fn calculate_escrow_address(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Result<Address, Error> {
+        // Verify factory is initialized
+        let _htlc_wasm_hash: BytesN<32> = env.storage().instance()
+            .get(&HTLC_HASH)
+            .ok_or(Error::NotInitialized)?;
+        
+        let salt = derive_salt_internal(&env, &Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+        });
+        
+        // Check if would collide
+        if get_deployed(&env, &salt).is_some() {
+            return Err(Error::AlreadyDeployed);
+        }
+        
+        // Calculate the deterministic address
+        Ok(env.deployer()
+            .with_current_contract(salt)
+            .deployed_address())
+    }
+    
+    /// Derive the deployment salt for a set of immutables
+    /// keccak256 over the EVM-ABI-encoded immutables, matching the EVM factory's CREATE2 salt
+    pub fn derive_salt(env: Env, immutables: Immutables) -> BytesN<32> {
+        derive_salt_internal(&env, &immutables)
+    }
+    
+    /// Get the current HTLC WASM hash
+    pub fn get_htlc_hash(env: Env) -> Result<BytesN<32>, Error> {
+        env.storage().instance()
+            .get(&HTLC_HASH)
+            .ok_or(Error::NotInitialized)
+    }
+    
+    /// Fail with HtlcCodeMismatch unless new escrows are deployed from `expected_hash`
+    /// Lets monitoring (or a guard operation ahead of a deployment) pin the audited escrow code
+    pub fn verify_htlc_code(env: Env, expected_hash: BytesN<32>) -> Result<(), Error> {
+        if Self::get_htlc_hash(env)? != expected_hash {
+            return Err(Error::HtlcCodeMismatch);
+        }
+        Ok(())
+    }
+    
+    /// Configuration, fee settings and counters in one read (dashboards, health checks)
+    /// Counters start with the release that introduced them
+    pub fn get_protocol_stats(env: Env) -> ProtocolStats {
+        let instance = env.storage().instance();
+        let initialized = instance.has(&ADMIN);
+        let min_deposit: Option<MinSafetyDeposit> = instance.get(&MIN_DEP);
+        ProtocolStats {
+            initialized,
+            admin: instance.get(&ADMIN),
+            htlc_hash: instance.get(&HTLC_HASH),
+            htlc_version: instance.get(&HTLC_VER).unwrap_or(if initialized { 1 } else { 0 }),
+            paused: instance.get(&PAUSED).unwrap_or(false),
+            allowlist_mode: instance.get(&ALLOWLIST).unwrap_or(false),
+            governance_delay: instance.get(&GOV_DELAY).unwrap_or(0),
+            multisig_threshold: instance.get(&THRESHOLD).unwrap_or(0),
+            pending_actions: instance.get::<_, Vec<u64>>(&PENDING).map_or(0, |pending| pending.len()),
+            ttl_bounty: instance.get(&TTL_BNTY).unwrap_or(0),
+            fee_pool: instance.get(&FEE_POOL).unwrap_or(0),
+            rate_limit: instance.get(&RATE_LIM).unwrap_or(0),
+            registry_retention: instance.get(&RETENTION).unwrap_or(0),
+            min_deposit_amount: min_deposit.as_ref().map_or(0, |min| min.min_amount),
+            min_deposit_bps: min_deposit.map_or(0, |min| min.min_bps),
+            escrows_deployed: instance.get(&DEPLOYS).unwrap_or(0),
+            token_policies: instance.get(&POL_CNT).unwrap_or(0),
+        }
+    }
+    
+    /// Get the admin address
+    pub fn get_admin(env: Env) -> Result<Address, Error> {
+        env.storage().instance()
+            .get(&ADMIN)
+            .ok_or(Error::NotInitialized)
+    }
+    
+    /// Get the storage rent an escrow's deployment bought and when its storage expires, so
+    /// extensions can be scheduled ahead of archival (None for unknown salts or escrows deployed
+    /// before receipts were recorded)
+    pub fn get_deployment_info(env: Env, salt: BytesN<32>) -> Option<DeploymentInfo> {
+        env.storage().persistent().get(&(RECEIPT, salt))
+    }
+    
+    /// Check if an escrow is already deployed with given salt
+    pub fn is_deployed(env: Env, salt: BytesN<32>) -> bool {
+        get_deployed(&env, &salt).is_some()
+    }
+    
+    /// Extend the TTL of the registry entries of `salts` (anyone, e.g. a maintenance job)
+    /// Entries are also extended whenever the factory reads them; this covers escrows nobody
+    /// touches. Unknown salts are skipped; returns how many entries were extended
+    pub fn extend_registry_ttl(env: Env, salts: Vec<BytesN<32>>) -> Result<u32, Error> {
+        if salts.len() > MAX_PAGE_SIZE {
+            return Err(Error::InvalidParams);
+        }
+        env.storage().instance().extend_ttl(REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+        
+        let mut extended = 0;
+        for salt in salts.iter() {
+            if get_deployed(&env, &salt).is_some() {
+                extended += 1;
+            }
+        }
+        Ok(extended)
+    }
+    
+    /// Add native XLM to the fee pool that pays keeper bounties (anyone); returns the new pool
+    pub fn fund_fee_pool(env: Env, from: Address, amount: i128) -> Result<i128, Error> {
+        from.require_auth();
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        
+        token::TokenClient::new(&env, &native_asset_contract(&env))
+            .transfer(&from, env.current_contract_address(), &amount);
+        let pool = Self::get_fee_pool(env.clone()) + amount;
+        env.storage().instance().set(&FEE_POOL, &pool);
+        
+        events::publish(&env, (Symbol::new(&env, "fee_pool_funded"), from), (amount, pool));
+        
+        Ok(pool)
+    }
+    
+    /// Set the native bounty (stroops) extend_escrow_storage pays per due extension (admin only);
+    /// 0 disables bounties
+    pub fn set_ttl_bounty(env: Env, bounty: i128) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetTtlBounty(bounty))
+    }
+    
+    /// Get the TTL keeper bounty (0 if disabled)
+    pub fn get_ttl_bounty(env: Env) -> i128 {
+        env.storage().instance().get(&TTL_BNTY).unwrap_or(0)
+    }
+    
+    /// Get the native XLM left in the fee pool
+    pub fn get_fee_pool(env: Env) -> i128 {
+        env.storage().instance().get(&FEE_POOL).unwrap_or(0)
+    }
+    
+    /// Extend the storage TTL of a deployed escrow (anyone) and pay `keeper` the TTL bounty if
+    /// the escrow reports it was due (funded and open, with its TTL below its threshold)
+    /// Once the fee pool can't cover the bounty, extensions go unpaid. Returns the bounty paid
+    pub fn extend_escrow_storage(env: Env, keeper: Address, salt: BytesN<32>) -> Result<i128, Error> {
+        let escrow = get_deployed(&env, &salt).ok_or(Error::UnknownEscrow)?;
+        let due: bool = env.invoke_contract(&escrow, &Symbol::new(&env, "extend_storage"), vec![&env]);
+        refresh_deployment_info(&env, &salt, &escrow);
+        
+        let bounty = Self::get_ttl_bounty(env.clone());
+        let pool = Self::get_fee_pool(env.clone());
+        if !due || bounty == 0 || pool < bounty {
+            return Ok(0);
+        }
+        env.storage().instance().set(&FEE_POOL, &(pool - bounty));
+        token::TokenClient::new(&env, &native_asset_contract(&env))
+            .transfer(&env.current_contract_address(), &keeper, &bounty);
+        
+        events::publish(&env, (Symbol::new(&env, "ttl_bounty_paid"), keeper), (escrow, bounty));
+        
+        Ok(bounty)
+    }
+    
+    /// Set how long (seconds) after an escrow is withdrawn or cancelled its registry entries may
+    /// be pruned (admin only); 0 disables pruning
+    pub fn set_registry_retention(env: Env, retention: u64) -> Result<(), Error> {
+        require_single_admin(&env)?;
+        enact_action(&env, AdminAction::SetRegistryRetention(retention))
+    }
+    
+    /// Get the registry retention period (0 if pruning is disabled)
+    pub fn get_registry_retention(env: Env) -> u64 {
+        env.storage().instance().get(&RETENTION).unwrap_or(0)
+    }
+    
+    /// Remove the registry entries of terminal escrows past the retention period (anyone)
+    /// Removes the deployed address, its swap id entry and the salt-keyed chain, integrator and
+    /// dst records; the escrow itself is untouched. Finalized escrows, which no longer record when they closed,
+    /// are always prunable. Salts not eligible are skipped; returns how many were pruned
+    pub fn prune_registry(env: Env, salts: Vec<BytesN<32>>) -> Result<u32, Error> {
+        let retention = Self::get_registry_retention(env.clone());
+        if retention == 0 || salts.len() > MAX_PAGE_SIZE {
+            return Err(Error::InvalidParams);
+        }
+        
+        let mut pruned = 0;
+        for salt in salts.iter() {
+            let Some(escrow) = env.storage().persistent().get::<_, Address>(&(DEPLOYED, salt.clone())) else {
+                continue;
+            };
+            if !is_prunable(&env, &escrow, retention) {
+                continue;
+            }
+            
+            env.storage().persistent().remove(&(DEPLOYED, salt.clone()));
+            env.storage().persistent().remove(&(SRC_CHAIN, salt.clone()));
+            env.storage().persistent().remove(&(INTEGR, salt.clone()));
+            env.storage().persistent().remove(&(INT_PEND, salt.clone()));
+            env.storage().persistent().remove(&(DST_ESC, salt.clone()));
+            env.storage().persistent().remove(&(RECEIPT, salt.clone()));
+            let swap_id = env.try_invoke_contract::<Option<BytesN<32>>, soroban_sdk::Error>(&escrow, &Symbol::new(&env, "get_swap_id"), vec![&env]);
+            if let Ok(Ok(Some(swap_id))) = swap_id {
+                env.storage().persistent().remove(&(SWAP_ID, swap_id));
+            }
+            events::publish(&env, (Symbol::new(&env, "registry_pruned"), salt), escrow);
+            pruned += 1;
+        }
+        Ok(pruned)
+    }
+    
+    /// Get order hashes of escrows where `maker` is the maker (oldest first)
+    pub fn get_escrows_by_maker(env: Env, maker: Address, offset: u32, limit: u32) -> Vec<BytesN<32>> {
+        page_order_index(&env, MAKER_IDX, &maker, offset, limit)
+    }
+    
+    /// Get order hashes of escrows where `taker` is the taker (oldest first)
+    pub fn get_escrows_by_taker(env: Env, taker: Address, offset: u32, limit: u32) -> Vec<BytesN<32>> {
+        page_order_index(&env, TAKER_IDX, &taker, offset, limit)
+    }
+    
+    /// Get the number of escrows deployed for a maker
+    pub fn get_maker_escrow_count(env: Env, maker: Address) -> u32 {
+        order_index_len(&env, MAKER_IDX, &maker)
+    }
+    
+    /// Get the number of escrows deployed for a taker
+    pub fn get_taker_escrow_count(env: Env, taker: Address) -> u32 {
+        order_index_len(&env, TAKER_IDX, &taker)
+    }
+}
+
+/// Authorize a sensitive action under the single-admin model
+fn require_single_admin(env: &Env) -> Result<(), Error> {
+    let admin: Address = env.storage().instance()
+        .get(&ADMIN)
+        .ok_or(Error::NotInitialized)?;
+    if env.storage().instance().has(&SIGNERS) {
+        return Err(Error::MultisigRequired);
+    }
+    admin.require_auth();
+    Ok(())
+}
+
+/// Authorize a multisig signer
+fn require_signer(env: &Env, signer: &Address) -> Result<(), Error> {
+    let signers: Vec<Address> = env.storage().instance()
+        .get(&SIGNERS)
+        .ok_or(Error::NotInitialized)?;
+    if !signers.contains(signer) {
+        return Err(Error::NotSigner);
+    }
+    signer.require_auth();
+    Ok(())
+}
+
+/// Load a proposal that has not been executed yet
+fn load_proposal(env: &Env, proposal_id: u64) -> Result<Proposal, Error> {
+    let proposal: Proposal = env.storage().persistent()
+        .get(&(PROPOSAL, proposal_id))
+        .ok_or(Error::ProposalNotFound)?;
+    if proposal.executed {
+        return Err(Error::ProposalExecuted);
+    }
+    Ok(proposal)
+}
+
+/// Apply an authorized admin action, or schedule it when it is subject to the governance delay
+fn enact_action(env: &Env, action: AdminAction) -> Result<(), Error> {
+    let delay: u64 = env.storage().instance().get(&GOV_DELAY).unwrap_or(0);
+    let delayed = !matches!(action, AdminAction::SetPaused(_) | AdminAction::CancelScheduled(_));
+    if delay == 0 || !delayed {
+        return apply_action(env, &action);
+    }
+    check_action(env, &action)?;
+    
+    let action_id: u64 = env.storage().instance().get(&ACTION_ID).unwrap_or(0);
+    env.storage().instance().set(&ACTION_ID, &(action_id + 1));
+    
+    let executable_at = env.ledger().timestamp() + delay;
+    env.storage().persistent().set(&(SCHEDULED, action_id), &ScheduledAction { action, executable_at });
+    
+    let mut pending: Vec<u64> = env.storage().instance().get(&PENDING).unwrap_or_else(|| Vec::new(env));
+    pending.push_back(action_id);
+    env.storage().instance().set(&PENDING, &pending);
+    
+    events::publish(
+        env,
+        (Symbol::new(env, "action_scheduled"),),
+        (action_id, executable_at),
+    );
+    
+    Ok(())
+}
+
+/// Drop a scheduled action and its pending entry
+fn remove_scheduled(env: &Env, action_id: u64) {
+    env.storage().persistent().remove(&(SCHEDULED, action_id));
+    
+    let mut pending: Vec<u64> = env.storage().instance().get(&PENDING).unwrap_or_else(|| Vec::new(env));
+    if let Some(index) = pending.first_index_of(action_id) {
+        pending.remove(index);
+    }
+    env.storage().instance().set(&PENDING, &pending);
+}
+
+/// Reject an admin action with out-of-range parameters, before it is proposed, scheduled or applied
+fn check_action(env: &Env, action: &AdminAction) -> Result<(), Error> {
+    match action {
+        AdminAction::SetWrappedToken(chain_id, _, _) if !env.storage().persistent().has(&(CHAIN, *chain_id)) => {
+            Err(Error::UnsupportedChain)
+        }
+        AdminAction::SetMinSafetyDeposit(config) if config.min_amount < 0 || config.min_bps > 10_000 => {
+            Err(Error::InvalidParams)
+        }
+        AdminAction::SetSkewTolerance(skew_tolerance) if *skew_tolerance > MAX_SKEW_TOLERANCE => {
+            Err(Error::InvalidParams)
+        }
+        AdminAction::SetTtlBounty(bounty) if *bounty < 0 => Err(Error::InvalidAmount),
+        _ => Ok(()),
+    }
+}
+
+/// Apply an authorized admin action immediately
+fn apply_action(env: &Env, action: &AdminAction) -> Result<(), Error> {
+    check_action(env, action)?;
+    match action {
+        AdminAction::UpdateHtlcHash(new_hash) => {
+            env.storage().instance().set(&HTLC_HASH, new_hash);
+            let version: u32 = env.storage().instance().get(&HTLC_VER).unwrap_or(1);
+            env.storage().instance().set(&HTLC_VER, &(version + 1));
+            events::publish(
+                env,
+                (Symbol::new(env, "htlc_hash_updated"),),
+                new_hash.clone(),
+            );
+        }
+        AdminAction::SetPaused(paused) => {
+            env.storage().instance().set(&PAUSED, paused);
+            events::publish(
+                env,
+                (Symbol::new(env, "paused_set"),),
+                *paused,
+            );
+        }
+        AdminAction::SetGovernanceDelay(delay) => {
+            env.storage().instance().set(&GOV_DELAY, delay);
+            events::publish(
+                env,
+                (Symbol::new(env, "governance_delay_set"),),
+                *delay,
+            );
+        }
+        AdminAction::CancelScheduled(action_id) => {
+            if !env.storage().persistent().has(&(SCHEDULED, *action_id)) {
+                return Err(Error::ActionNotFound);
+            }
+            remove_scheduled(env, *action_id);
+            events::publish(
+                env,
+                (Symbol::new(env, "action_cancelled"),),
+                *action_id,
+            );
+        }
+        AdminAction::SetWrappedToken(chain_id, evm_token, token) => {
+            env.storage().persistent().set(&(WRAPPED, *chain_id, evm_token.clone()), token);
+            events::publish(
+                env,
+                (Symbol::new(env, "wrapped_token_set"), *chain_id),
+                (evm_token.clone(), token.clone()),
+            );
+        }
+        AdminAction::RemoveWrappedToken(chain_id, evm_token) => {
+            env.storage().persistent().remove(&(WRAPPED, *chain_id, evm_token.clone()));
+            events::publish(env, (Symbol::new(env, "wrapped_token_removed"), *chain_id), evm_token.clone());
+        }
+        AdminAction::SetChainConfig(chain_id, config) => {
+            env.storage().persistent().set(&(CHAIN, *chain_id), config);
+            events::publish(
+                env,
+                (Symbol::new(env, "chain_configured"),),
+                (*chain_id, config.evm_factory.clone()),
+            );
+        }
+        AdminAction::RemoveChainConfig(chain_id) => {
+            env.storage().persistent().remove(&(CHAIN, *chain_id));
+            events::publish(
+                env,
+                (Symbol::new(env, "chain_removed"),),
+                *chain_id,
+            );
+        }
+        AdminAction::SetTokenPolicy(token, allowed) => {
+            let key = (TOKEN_POL, token.clone());
+            if !env.storage().persistent().has(&key) {
+                adjust_policy_count(env, 1);
+            }
+            env.storage().persistent().set(&key, allowed);
+            events::publish(
+                env,
+                (Symbol::new(env, "token_policy_set"),),
+                (token.clone(), *allowed),
+            );
+        }
+        AdminAction::RemoveTokenPolicy(token) => {
+            let key = (TOKEN_POL, token.clone());
+            if env.storage().persistent().has(&key) {
+                adjust_policy_count(env, -1);
+            }
+            env.storage().persistent().remove(&key);
+            events::publish(
+                env,
+                (Symbol::new(env, "token_policy_removed"),),
+                token.clone(),
+            );
+        }
+        AdminAction::SetAllowlistMode(enabled) => {
+            env.storage().instance().set(&ALLOWLIST, enabled);
+            events::publish(
+                env,
+                (Symbol::new(env, "allowlist_mode"),),
+                *enabled,
+            );
+        }
+        AdminAction::SetMinPublicGap(gap) => {
+            env.storage().instance().set(&MIN_GAP, gap);
+            events::publish(
+                env,
+                (Symbol::new(env, "min_public_gap_set"),),
+                *gap,
+            );
+        }
+        AdminAction::SetMinSafetyDeposit(config) => {
+            env.storage().instance().set(&MIN_DEP, config);
+            events::publish(
+                env,
+                (Symbol::new(env, "min_safety_deposit_set"),),
+                (config.min_amount, config.min_bps),
+            );
+        }
+        AdminAction::SetSkewTolerance(skew_tolerance) => {
+            env.storage().instance().set(&SKEW, skew_tolerance);
+            events::publish(env, (Symbol::new(env, "skew_tolerance_set"),), *skew_tolerance);
+        }
+        AdminAction::SetRateLimit(max_per_ledger) => {
+            env.storage().instance().set(&RATE_LIM, max_per_ledger);
+            events::publish(env, (Symbol::new(env, "rate_limit_set"),), *max_per_ledger);
+        }
+        AdminAction::SetTtlBounty(bounty) => {
+            env.storage().instance().set(&TTL_BNTY, bounty);
+            events::publish(env, (Symbol::new(env, "ttl_bounty_set"),), *bounty);
+        }
+        AdminAction::SetRegistryRetention(retention) => {
+            env.storage().instance().set(&RETENTION, retention);
+            events::publish(env, (Symbol::new(env, "registry_retention_set"),), *retention);
+        }
+    }
+    Ok(())
+}
+
+/// Number of order hashes in a party's index, kept under `(index, party)`
+fn order_index_len(env: &Env, index: Symbol, party: &Address) -> u32 {
+    env.storage().persistent().get(&(index, party.clone())).unwrap_or(0)
+}
+
+/// Append an order hash to a party's index
+/// Each order is its own `(index, party, n)` entry, so deploys naming a party cost the same however
+/// many orders it already has
+fn push_order_index(env: &Env, index: Symbol, party: &Address, order_hash: &BytesN<32>) {
+    let len = order_index_len(env, index.clone(), party);
+    env.storage().persistent().set(&(index.clone(), party.clone(), len), order_hash);
+    env.storage().persistent().set(&(index, party.clone()), &(len + 1));
+}
+
+/// Read a page of a party's index, capped at MAX_PAGE_SIZE entries
+fn page_order_index(env: &Env, index: Symbol, party: &Address, offset: u32, limit: u32) -> Vec<BytesN<32>> {
+    let len = order_index_len(env, index.clone(), party);
+    let start = offset.min(len);
+    let end = start.saturating_add(limit.min(MAX_PAGE_SIZE)).min(len);
+    let mut orders = Vec::new(env);
+    for n in start..end {
+        if let Some(order_hash) = env.storage().persistent().get(&(index.clone(), party.clone(), n)) {
+            orders.push_back(order_hash);
+        }
+    }
+    orders
+}
+
+/// Serialize a classic asset into `Asset` XDR (AlphaNum4 / AlphaNum12)
+fn serialize_classic_asset(env: &Env, asset_code: &String, issuer: &Address) -> Result<Bytes, Error> {
+    let code_len = asset_code.len() as usize;
+    let (asset_type, padded_len): (u8, usize) = match code_len {
+        1..=4 => (1, 4),
+        5..=12 => (2, 12),
+        _ => return Err(Error::InvalidParams),
+    };
+
+    let mut code = [0u8; 12];
+    asset_code.copy_into_slice(&mut code[..code_len]);
+
+    // Address XDR is ScVal::Address(ScAddress::Account(PublicKey::Ed25519(key))):
+    // a 12 byte header of discriminants followed by the 32 byte key
+    let issuer_xdr = issuer.clone().to_xdr(env);
+    if issuer_xdr.len() != 44 || issuer_xdr.get(7) != Some(0) {
+        // Issuers must be classic accounts, not contracts
+        return Err(Error::InvalidParams);
+    }
+
+    let mut asset = Bytes::from_array(env, &[0, 0, 0, asset_type]);
+    asset.extend_from_slice(&code[..padded_len]);
+    asset.extend_from_array(&[0, 0, 0, 0]);
+    asset.append(&issuer_xdr.slice(12..44));
+
+    Ok(asset)
+}
+
+/// Track how many tokens have a policy, for get_protocol_stats
+fn adjust_policy_count(env: &Env, delta: i32) {
+    let count: u32 = env.storage().instance().get(&POL_CNT).unwrap_or(0);
+    env.storage().instance().set(&POL_CNT, &count.saturating_add_signed(delta));
+}
+
+/// Stellar Asset Contract of native XLM on the current network
+fn native_asset_contract(env: &Env) -> Address {
+    // `Asset::Native` XDR is the bare discriminant
+    env.deployer()
+        .with_stellar_asset(Bytes::from_array(env, &[0, 0, 0, 0]))
+        .deployed_address()
+}
+
+/// Deploy and initialize an escrow at the address derived from its immutables
+fn deploy_escrow_internal(
+    env: &Env,
+    immutables: &Immutables,
+    init: Option<EscrowInit>,
+    integrator: Option<Address>,
+    swap_id: Option<BytesN<32>>,
+) -> Result<Address, Error> {
+    // Get the HTLC WASM hash
+    let htlc_wasm_hash: BytesN<32> = env.storage().instance()
+        .get(&HTLC_HASH)
+        .ok_or(Error::NotInitialized)?;
+    
+    if env.storage().instance().get(&PAUSED).unwrap_or(false) {
+        return Err(Error::Paused);
+    }
+    
+    validate_immutables(env, immutables)?;
+    
+    let token_allowed = is_token_allowed(env, &immutables.token);
+    trace!(env, "token", (immutables.token.clone(), token_allowed));
+    if !token_allowed {
+        return Err(Error::TokenNotAllowed);
+    }
+    
+    count_deployment(env, &immutables.taker)?;
+    
+    let min_gap: u32 = env.storage().instance().get(&MIN_GAP).unwrap_or(0);
+    if min_gap > 0 {
+        for (private, public) in PUBLIC_STAGE_PAIRS {
+            let private_start = get_timelock(immutables.timelocks, private);
+            let public_start = get_timelock(immutables.timelocks, public);
+            trace!(env, "gap", (private as u32, private_start, public_start, min_gap));
+            // A gap overflowing the stage offset can't be met
+            if private_start.checked_add(min_gap).is_none_or(|earliest| public_start < earliest) {
+                return Err(Error::PublicWindowTooSoon);
+            }
+        }
+    }
+    
+    if let Some(min_deposit) = env.storage().instance().get::<_, MinSafetyDeposit>(&MIN_DEP) {
+        let bps_minimum = immutables.amount
+            .checked_mul(min_deposit.min_bps as i128)
+            .map(|scaled| scaled / 10_000)
+            .ok_or(Error::InvalidParams)?;
+        let required = min_deposit.min_amount.max(bps_minimum);
+        let enabled = min_deposit.min_amount > 0 || min_deposit.min_bps > 0;
+        trace!(env, "deposit", (immutables.safety_deposit, required));
+        if enabled && (immutables.safety_deposit <= 0 || immutables.safety_deposit < required) {
+            return Err(Error::SafetyDepositTooLow);
+        }
+    }
+    
+    // Check if already deployed with this salt
+    let salt = derive_salt_internal(env, immutables);
+    trace!(env, "salt", (immutables.order_hash.clone(), salt.clone()));
+    let deployed_key = (DEPLOYED, salt.clone());
+    if let Some(existing_address) = get_deployed(env, &salt) {
+        log!(env, "Escrow already deployed at: {}", existing_address);
+        return Err(Error::AlreadyDeployed);
+    }
+    
+    claim_order_hash(env, immutables, &salt)?;
+    
+    // Deploy the escrow contract with deterministic address
+    let escrow = env.deployer()
+        .with_current_contract(salt.clone())
+        .deploy_v2(htlc_wasm_hash.clone(), ());
+    
+    // Store deployed address
+    env.storage().persistent().set(&deployed_key, &escrow);
+    let deployed: u64 = env.storage().instance().get(&DEPLOYS).unwrap_or(0);
+    env.storage().instance().set(&DEPLOYS, &(deployed + 1));
+    env.storage().persistent().extend_ttl(&deployed_key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+    
+    // Index the order for both parties
+    push_order_index(env, MAKER_IDX, &immutables.maker, &immutables.order_hash);
+    push_order_index(env, TAKER_IDX, &immutables.taker, &immutables.order_hash);
+    
+    // Initialize the escrow by calling its deploy function (returns the escrow's own address)
+    let mut init_args = vec![
+        env,
+        immutables.order_hash.to_val(),
+        immutables.hashlock.to_val(),
+        immutables.maker.to_val(),
+        immutables.taker.to_val(),
+        immutables.token.to_val(),
+        immutables.amount.into_val(env),
+        immutables.safety_deposit.into_val(env),
+        immutables.timelocks.into_val(env),
+    ];
+    let init_fn = match init {
+        Some(EscrowInit::Details(details)) => {
+            init_args.push_back(details.to_val());
+            Symbol::new(env, "deploy_with_details")
+        }
+        Some(EscrowInit::Beneficiary(beneficiary)) => {
+            init_args.push_back(beneficiary.to_val());
+            Symbol::new(env, "deploy_with_beneficiary")
+        }
+        Some(EscrowInit::Incentives(incentives)) => {
+            init_args.push_back(incentives.into_val(env));
+            Symbol::new(env, "deploy_with_incentives")
+        }
+        Some(EscrowInit::FinalityOracle(finality_oracle)) => {
+            init_args.push_back(finality_oracle.to_val());
+            Symbol::new(env, "deploy_with_finality_oracle")
+        }
+        Some(EscrowInit::Hooks(pre_funding_hook, post_payout_hook)) => {
+            init_args.push_back(pre_funding_hook.into_val(env));
+            init_args.push_back(post_payout_hook.into_val(env));
+            Symbol::new(env, "deploy_with_hooks")
+        }
+        Some(EscrowInit::Src) => Symbol::new(env, "deploy_src"),
+        Some(EscrowInit::Flags(flags)) => {
+            let _: () = env.invoke_contract(&escrow, &Symbol::new(env, "set_flags"), vec![env, flags.into_val(env)]);
+            Symbol::new(env, "deploy")
+        }
+        Some(EscrowInit::Parts(parts)) => {
+            let _: () = env.invoke_contract(&escrow, &Symbol::new(env, "set_parts"), vec![env, parts.into_val(env)]);
+            Symbol::new(env, "deploy")
+        }
+        Some(EscrowInit::V2(immutables)) => {
+            init_args = vec![env, immutables];
+            Symbol::new(env, "deploy_v2")
+        }
+        None => Symbol::new(env, "deploy"),
+    };
+    // The escrow records the skew tolerance in its immutables on initialization
+    let skew_tolerance: u32 = env.storage().instance().get(&SKEW).unwrap_or(0);
+    if skew_tolerance > 0 {
+        let _: () = env.invoke_contract(
+            &escrow,
+            &Symbol::new(env, "set_skew_tolerance"),
+            vec![env, skew_tolerance.into_val(env)],
+        );
+    }
+    // and the swap id of cross-chain deploys, which every escrow event then carries
+    if let Some(swap_id) = &swap_id {
+        let _: () = env.invoke_contract(
+            &escrow,
+            &Symbol::new(env, "set_swap_id"),
+            vec![env, swap_id.to_val()],
+        );
+        let swap_key = (SWAP_ID, swap_id.clone());
+        env.storage().persistent().set(&swap_key, &escrow);
+        env.storage().persistent().extend_ttl(&swap_key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+    }
+    let _: Address = env.invoke_contract(&escrow, &init_fn, init_args);
+    record_deployment_info(env, &salt, &escrow);
+    
+    // Emit event
+    events::publish(
+        env,
+        (Symbol::new(env, "escrow_deployed"),),
+        (escrow.clone(), immutables.order_hash.clone(), salt.clone(), swap_id),
+    );
+    // The code hash actually used, so monitors can alert on unexpected escrow code
+    events::publish(env, (Symbol::new(env, "escrow_code"), htlc_wasm_hash), escrow.clone());
+    
+    if let Some(integrator) = integrator {
+        record_integrator(env, &salt, &escrow, immutables, integrator);
+    }
+    
+    Ok(escrow)
+}
+
+/// Shared tail of deploy_src_escrow and create_src_escrow, once the maker has authorized
+fn deploy_src_escrow_internal(env: &Env, immutables: &Immutables, dst: DstEscrowParams) -> Result<Address, Error> {
+    // The maker picked the taker, whose rate limit the deploy counts against
+    if env.storage().instance().get::<_, u32>(&RATE_LIM).unwrap_or(0) > 0 {
+        immutables.taker.require_auth();
+    }
+    let config = validate_chain(env, dst.chain_id, immutables)?;
+    
+    let stages = SRC_STAGES.map(|stage| get_timelock(immutables.timelocks, stage));
+    if stages.windows(2).any(|pair| pair[0] > pair[1]) || stages[0] >= stages[2] {
+        return Err(Error::InvalidParams);
+    }
+    
+    // A mapped EVM token must be the equivalent of the token the maker locks
+    let wrapped: Option<Address> = env.storage().persistent().get(&(WRAPPED, dst.chain_id, dst.token.clone()));
+    if wrapped.is_some_and(|wrapped| wrapped != immutables.token) {
+        return Err(Error::TokenMismatch);
+    }
+    
+    let swap_id = stellar_swap_id(env, &immutables.order_hash, true, dst.chain_id);
+    let escrow = deploy_escrow_internal(env, immutables, Some(EscrowInit::Src), None, Some(swap_id))?;
+    fund_escrow(env, immutables, &escrow);
+    env.storage().persistent().set(&(DST_ESC, derive_salt_internal(env, immutables)), &dst);
+    
+    events::publish(
+        env,
+        (Symbol::new(env, "src_escrow_deployed"), immutables.order_hash.clone()),
+        (
+            escrow.clone(),
+            immutables.hashlock.clone(),
+            immutables.timelocks,
+            config.evm_factory,
+            dst,
+        ),
+    );
+    
+    Ok(escrow)
+}
+
+/// The escrow's deploy-time invariants, checked before anything is deployed or counted
+fn validate_immutables(env: &Env, immutables: &Immutables) -> Result<(), Error> {
+    if immutables.maker == immutables.taker {
+        return Err(Error::MakerIsTaker);
+    }
+    if immutables.amount <= 0
+        || immutables.safety_deposit < 0
+        || immutables.amount.checked_add(immutables.safety_deposit).is_none()
+    {
+        return Err(Error::InvalidAmount);
+    }
+    let zero = BytesN::from_array(env, &[0u8; 32]);
+    if immutables.hashlock == zero {
+        return Err(Error::ZeroHashlock);
+    }
+    if immutables.order_hash == zero {
+        return Err(Error::ZeroOrderHash);
+    }
+    Ok(())
+}
+
+/// Count a deployment against `taker` for the current ledger, failing once the cap is reached
+/// Counts live in temporary storage keyed by ledger sequence and expire on their own. Every deploy
+/// path has the taker authorize (source escrows while a cap is set), so it can't be charged with
+/// another's spam
+fn count_deployment(env: &Env, taker: &Address) -> Result<(), Error> {
+    let limit: u32 = env.storage().instance().get(&RATE_LIM).unwrap_or(0);
+    if limit == 0 {
+        return Ok(());
+    }
+    
+    let key = (DEP_CNT, taker.clone(), env.ledger().sequence());
+    let count: u32 = env.storage().temporary().get(&key).unwrap_or(0);
+    trace!(env, "rate", (count, limit));
+    if count >= limit {
+        return Err(Error::RateLimited);
+    }
+    env.storage().temporary().set(&key, &(count + 1));
+    
+    Ok(())
+}
+
+/// Read a registry entry, extending its TTL so entries the factory still reads aren't archived
+fn get_deployed(env: &Env, salt: &BytesN<32>) -> Option<Address> {
+    let key = (DEPLOYED, salt.clone());
+    let escrow = env.storage().persistent().get(&key);
+    if escrow.is_some() {
+        env.storage().persistent().extend_ttl(&key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+    }
+    escrow
+}
+
+/// Whether the escrow was withdrawn or cancelled at least `retention` seconds ago
+/// Escrows that can't answer (older WASM without get_terminal_at) are kept
+fn is_prunable(env: &Env, escrow: &Address, retention: u64) -> bool {
+    let finalized = env.try_invoke_contract::<bool, soroban_sdk::Error>(escrow, &Symbol::new(env, "is_finalized"), vec![env]);
+    if matches!(finalized, Ok(Ok(true))) {
+        return true;
+    }
+    
+    let state = env.try_invoke_contract::<u32, soroban_sdk::Error>(escrow, &Symbol::new(env, "get_state"), vec![env]);
+    if !matches!(state, Ok(Ok(state)) if TERMINAL_STATES.contains(&state)) {
+        return false;
+    }
+    let terminal_at = env.try_invoke_contract::<Option<u64>, soroban_sdk::Error>(escrow, &Symbol::new(env, "get_terminal_at"), vec![env]);
+    match terminal_at {
+        Ok(Ok(Some(terminal_at))) => env.ledger().timestamp() >= terminal_at.saturating_add(retention),
+        _ => false,
+    }
+}
+
+fn maker_nonce(env: &Env, maker: &Address) -> u64 {
+    env.storage().persistent().get(&(EPOCH, maker.clone())).unwrap_or(0)
+}
+
+/// EIP-712-style order hash, domain-separated by network, this factory and the order version
+fn hash_order_internal(env: &Env, order: &Order) -> BytesN<32> {
+    let order: Val = order.into_val(env);
+    let order = fusion_shared::Order::from_val(env, &order);
+    fusion_shared::order::hash_order(env, &env.current_contract_address(), &order)
+}
+
+fn hash_limit_order_internal(env: &Env, order: &LimitOrder) -> BytesN<32> {
+    let order: Val = order.into_val(env);
+    let order = fusion_shared::LimitOrder::from_val(env, &order);
+    fusion_shared::order::hash_limit_order(env, &env.current_contract_address(), &order)
+}
+
+/// Check one side of match_orders and mark it filled, requiring its maker's authorization
+fn claim_limit_order(env: &Env, order: &LimitOrder, permit: &Permit) -> Result<BytesN<32>, Error> {
+    if env.ledger().timestamp() > permit.expires_at {
+        return Err(Error::PermitExpired);
+    }
+    if order.nonce < maker_nonce(env, &order.maker) {
+        return Err(Error::NonceCancelled);
+    }
+    if order.maker_amount <= 0 || order.taker_amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    
+    let order_hash = hash_limit_order_internal(env, order);
+    order.maker.require_auth_for_args((order_hash.clone(), permit.expires_at).into_val(env));
+    
+    let key = (LIM_FILL, order_hash.clone());
+    if env.storage().persistent().has(&key) {
+        return Err(Error::OrderFilled);
+    }
+    env.storage().persistent().set(&key, &env.ledger().timestamp());
+    env.storage().persistent().extend_ttl(&key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+    Ok(order_hash)
+}
+
+/// Deploy an escrow whose chain was validated, recording the source chain by salt
+fn deploy_from_chain_internal(env: &Env, src_chain_id: u64, immutables: &Immutables) -> Result<Address, Error> {
+    let swap_id = stellar_swap_id(env, &immutables.order_hash, false, src_chain_id);
+    let escrow = deploy_escrow_internal(env, immutables, None, None, Some(swap_id))?;
+    env.storage().persistent().set(&(SRC_CHAIN, derive_salt_internal(env, immutables)), &src_chain_id);
+    Ok(escrow)
+}
+
+/// Check the immutables against a registered foreign chain's configuration
+fn validate_chain(env: &Env, chain_id: u64, immutables: &Immutables) -> Result<ChainConfig, Error> {
+    let config: ChainConfig = env.storage().persistent()
+        .get(&(CHAIN, chain_id))
+        .ok_or(Error::UnsupportedChain)?;
+    
+    if !config.supported_tokens.is_empty() && !config.supported_tokens.contains(&immutables.token) {
+        return Err(Error::UnsupportedToken);
+    }
+    
+    for stage in 0..TIMELOCK_STAGES {
+        if get_timelock(immutables.timelocks, stage) < get_timelock(config.min_timelocks, stage) {
+            return Err(Error::TimelockTooShort);
+        }
+    }
+    
+    Ok(config)
+}
+
+/// Move the maker's tokens and the taker's safety deposit into a fresh escrow and confirm funding
+fn fund_escrow(env: &Env, immutables: &Immutables, escrow: &Address) {
+    token::TokenClient::new(env, &immutables.token)
+        .transfer(&immutables.maker, escrow, &immutables.amount);
+    if immutables.safety_deposit > 0 {
+        token::TokenClient::new(env, &native_asset_contract(env))
+            .transfer(&immutables.taker, escrow, &immutables.safety_deposit);
+    }
+    let _: () = env.invoke_contract(escrow, &Symbol::new(env, "confirm_funding"), vec![env]);
+}
+
+/// Register the escrow under its order_hash
+/// A second escrow for the same order (necessarily with different immutables, or the salt would
+/// collide) is rejected unless the maker enabled partial fills, and must keep the maker and token.
+/// The first escrow wins, so every caller has the taker authorize, or the maker authorize the whole
+/// order for source escrows
+fn claim_order_hash(env: &Env, immutables: &Immutables, salt: &BytesN<32>) -> Result<(), Error> {
+    let order_key = (ORDER, immutables.order_hash.clone());
+    let record = match env.storage().persistent().get::<_, OrderRecord>(&order_key) {
+        None => OrderRecord {
+            maker: immutables.maker.clone(),
+            token: immutables.token.clone(),
+            salts: vec![env, salt.clone()],
+        },
+        Some(mut record) => {
+            let partial_fills: Option<Address> =
+                env.storage().persistent().get(&(MULTI_FIL, immutables.order_hash.clone()));
+            if partial_fills.as_ref() != Some(&record.maker)
+                || record.maker != immutables.maker
+                || record.token != immutables.token
+            {
+                return Err(Error::OrderHashConflict);
+            }
+            record.salts.push_back(salt.clone());
+            record
+        }
+    };
+    env.storage().persistent().set(&order_key, &record);
+    Ok(())
+}
+
+/// Apply the token policy: explicit entries win, unlisted tokens pass unless in allow-list mode
+fn is_token_allowed(env: &Env, token: &Address) -> bool {
+    let policy: Option<bool> = env.storage().persistent().get(&(TOKEN_POL, token.clone()));
+    match policy {
+        Some(allowed) => allowed,
+        None => !env.storage().instance().get(&ALLOWLIST).unwrap_or(false),
+    }
+}
+
+/// Deployment salt shared with the EVM side (see fusion_shared::conversion)
+fn derive_salt_internal(env: &Env, immutables: &Immutables) -> BytesN<32> {
+    conversion::derive_salt(
+        env,
+        &immutables.order_hash,
+        &immutables.hashlock,
+        &immutables.maker,
+        &immutables.taker,
+        &immutables.token,
+        immutables.amount,
+        immutables.safety_deposit,
+        immutables.timelocks,
+    )
+}
+
+/// Ledgers left of the escrow's storage TTL, as it estimates them
+/// Escrows that can't answer (older WASM without get_storage_ttl) report 0
+fn escrow_storage_ttl(env: &Env, escrow: &Address) -> u32 {
+    let ttl = env.try_invoke_contract::<u32, soroban_sdk::Error>(escrow, &Symbol::new(env, "get_storage_ttl"), vec![env]);
+    match ttl {
+        Ok(Ok(ttl)) => ttl,
+        _ => 0,
+    }
+}
+
+/// Record the TTL a freshly initialized escrow purchased and the ledger it runs out at
+fn record_deployment_info(env: &Env, salt: &BytesN<32>, escrow: &Address) {
+    let deployed_at = env.ledger().sequence();
+    let ttl_purchased = escrow_storage_ttl(env, escrow);
+    let info = DeploymentInfo {
+        escrow: escrow.clone(),
+        deployed_at,
+        ttl_purchased,
+        expires_at: deployed_at + ttl_purchased,
+    };
+    let key = (RECEIPT, salt.clone());
+    env.storage().persistent().set(&key, &info);
+    env.storage().persistent().extend_ttl(&key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+}
+
+/// Move a receipt's expiry to the escrow's TTL after an extension
+fn refresh_deployment_info(env: &Env, salt: &BytesN<32>, escrow: &Address) {
+    let key = (RECEIPT, salt.clone());
+    let Some(mut info) = env.storage().persistent().get::<_, DeploymentInfo>(&key) else {
+        return;
+    };
+    info.expires_at = env.ledger().sequence() + escrow_storage_ttl(env, escrow);
+    env.storage().persistent().set(&key, &info);
+    env.storage().persistent().extend_ttl(&key, REGISTRY_TTL_THRESHOLD, REGISTRY_TTL_EXTEND_TO);
+}
+
+/// Attribute a deployed escrow to an integrator, leaving its volume pending until it is funded
+fn record_integrator(env: &Env, salt: &BytesN<32>, escrow: &Address, immutables: &Immutables, integrator: Address) {
+    env.storage().persistent().set(&(INTEGR, salt.clone()), &integrator);
+    env.storage().persistent().set(&(INT_PEND, salt.clone()), &(immutables.token.clone(), immutables.amount));
+    
+    events::publish(
+        env,
+        (Symbol::new(env, "integrator_attributed"), integrator),
+        (escrow.clone(), immutables.order_hash.clone(), immutables.token.clone(), immutables.amount),
+    );
+}
+
+/// Extract a stage's value from packed timelocks (same layout as the escrow)
+fn get_timelock(timelocks: u64, stage: u8) -> u32 {
+    ((timelocks >> (stage * 8)) & 0xFF) as u32
+}
+
+#[cfg(test)]
+mod test;
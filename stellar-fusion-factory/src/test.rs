@@ -0,0 +1,1320 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use crate::{
+        AdminAction, ChainConfig, CounterpartyEscrow, DepositIncentives, DepositParty, DepositSplit,
+        DepositPolicy, DstEscrowParams, EscrowFactory, EscrowFactoryClient, EscrowSide, Error, EvmAddress,
+        Immutables, ImmutablesV2, LimitOrder, MinSafetyDeposit, Order, Permit, Sponsorship,
+    };
+    use soroban_sdk::{
+        contract, contractimpl, symbol_short,
+        testutils::{Address as _, AuthorizedFunction, Ledger, MockAuth, MockAuthInvoke},
+        token, vec, Address, Bytes, BytesN, Env, IntoVal, MuxedAddress, Symbol, Val, Vec,
+    };
+
+    // The escrow the factory deploys; build it first with
+    // `cargo build --release --target wasm32v1-none` in stellar-fusion
+    const ESCROW_WASM: &[u8] = include_bytes!("../../stellar-fusion/target/wasm32v1-none/release/stellar_escrow.wasm");
+
+    // Stand-in for the native XLM contract, which pays fee pool bounties
+    #[contract]
+    struct MockNative;
+
+    #[contractimpl]
+    impl MockNative {
+        pub fn mint(env: Env, to: Address, amount: i128) {
+            let balance = Self::balance(env.clone(), to.clone());
+            env.storage().persistent().set(&(symbol_short!("balance"), to), &(balance + amount));
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().persistent().get(&(symbol_short!("balance"), id)).unwrap_or(0)
+        }
+
+        pub fn transfer(env: Env, from: Address, to: MuxedAddress, amount: i128) {
+            from.require_auth();
+            let balance = Self::balance(env.clone(), from.clone());
+            assert!(amount >= 0 && balance >= amount, "transfer failed");
+            env.storage().persistent().set(&(symbol_short!("balance"), from), &(balance - amount));
+            Self::mint(env, to.address(), amount);
+        }
+    }
+
+    // Helper function to register MockNative at the native asset contract's address
+    fn register_native(env: &Env) -> MockNativeClient<'_> {
+        let native = env.deployer()
+            .with_stellar_asset(Bytes::from_array(env, &[0, 0, 0, 0]))
+            .deployed_address();
+        MockNativeClient::new(env, &env.register_at(&native, MockNative, ()))
+    }
+
+    // Helper function to register and initialize a factory with the escrow WASM
+    fn setup(env: &Env) -> (EscrowFactoryClient<'_>, Address) {
+        env.mock_all_auths();
+        let factory = EscrowFactoryClient::new(env, &env.register(EscrowFactory, ()));
+        let admin = Address::generate(env);
+        let htlc_hash = env.deployer().upload_contract_wasm(ESCROW_WASM);
+        factory.initialize(&admin, &htlc_hash);
+        (factory, admin)
+    }
+
+    // Helper function to create a token with a minted balance
+    fn setup_token(env: &Env, holder: &Address, balance: i128) -> Address {
+        let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+        token::StellarAssetClient::new(env, &token).mint(holder, &balance);
+        token
+    }
+
+    // Helper function to build immutables; no safety deposit, so the taker needs no native XLM
+    fn immutables(env: &Env, token: &Address, seed: u8) -> Immutables {
+        Immutables {
+            order_hash: BytesN::from_array(env, &[seed; 32]),
+            hashlock: BytesN::from_array(env, &[seed.wrapping_add(1); 32]),
+            maker: Address::generate(env),
+            taker: Address::generate(env),
+            token: token.clone(),
+            amount: 1_000,
+            safety_deposit: 0,
+            timelocks: 0,
+        }
+    }
+
+    // Helper function to deploy through deploy_escrow, returning the contract error if any
+    fn deploy(factory: &EscrowFactoryClient, immutables: &Immutables) -> Result<Address, Error> {
+        factory
+            .try_deploy_escrow(
+                &immutables.order_hash,
+                &immutables.hashlock,
+                &immutables.maker,
+                &immutables.taker,
+                &immutables.token,
+                &immutables.amount,
+                &immutables.safety_deposit,
+                &immutables.timelocks,
+            )
+            .map(|escrow| escrow.unwrap())
+            .map_err(|error| error.unwrap())
+    }
+
+    // Helper function to register a destination chain and build a funded maker's order for it
+    fn src_order(env: &Env, factory: &EscrowFactoryClient) -> Order {
+        let chain_id = 1;
+        factory.set_chain_config(&chain_id, &ChainConfig {
+            min_timelocks: 0,
+            supported_tokens: Vec::new(env),
+            evm_factory: EvmAddress(BytesN::from_array(env, &[7; 20])),
+        });
+        let maker = Address::generate(env);
+        Order {
+            maker: maker.clone(),
+            taker: Address::generate(env),
+            token: setup_token(env, &maker, 1_000),
+            amount: 1_000,
+            safety_deposit: 0,
+            hashlock: BytesN::from_array(env, &[2; 32]),
+            // Src withdrawal, public withdrawal, cancellation and public cancellation stages
+            timelocks: u64::from_le_bytes([10, 20, 30, 40, 0, 0, 0, 0]),
+            salt: 0,
+            nonce: 0,
+            dst: DstEscrowParams {
+                chain_id,
+                maker: EvmAddress(BytesN::from_array(env, &[8; 20])),
+                token: EvmAddress(BytesN::from_array(env, &[9; 20])),
+                amount: 1_000,
+                safety_deposit: 0,
+            },
+        }
+    }
+
+    // Helper function to authorize create_src_escrow as the maker, for `order_hash` only
+    fn sign_order(env: &Env, factory: &EscrowFactoryClient, order: &Order, order_hash: &BytesN<32>, expires_at: u64) {
+        let escrow = factory.calculate_escrow_address(
+            &factory.hash_order(order),
+            &order.hashlock,
+            &order.maker,
+            &order.taker,
+            &order.token,
+            &order.amount,
+            &order.safety_deposit,
+            &order.timelocks,
+        );
+        env.mock_auths(&[MockAuth {
+            address: &order.maker,
+            invoke: &MockAuthInvoke {
+                contract: &factory.address,
+                fn_name: "create_src_escrow",
+                args: (order_hash.clone(), expires_at).into_val(env),
+                sub_invokes: &[MockAuthInvoke {
+                    contract: &order.token,
+                    fn_name: "transfer",
+                    args: (order.maker.clone(), escrow, order.amount).into_val(env),
+                    sub_invokes: &[],
+                }],
+            },
+        }]);
+    }
+
+    // Helper function to build two crossing limit orders: 100 A for 50 B, and 60 B for 100 A
+    fn crossing_orders(env: &Env) -> (LimitOrder, LimitOrder) {
+        let (maker_a, maker_b) = (Address::generate(env), Address::generate(env));
+        let (token_a, token_b) = (setup_token(env, &maker_a, 100), setup_token(env, &maker_b, 60));
+        let order_a = LimitOrder {
+            maker: maker_a,
+            maker_token: token_a.clone(),
+            maker_amount: 100,
+            taker_token: token_b.clone(),
+            taker_amount: 50,
+            salt: 0,
+            nonce: 0,
+        };
+        let order_b = LimitOrder {
+            maker: maker_b,
+            maker_token: token_b,
+            maker_amount: 60,
+            taker_token: token_a,
+            taker_amount: 100,
+            salt: 0,
+            nonce: 0,
+        };
+        (order_a, order_b)
+    }
+
+    // Helper function to authorize `signer` for exactly one call to the factory
+    fn authorize(env: &Env, factory: &EscrowFactoryClient, signer: &Address, fn_name: &str, args: Vec<Val>) {
+        env.mock_auths(&[MockAuth {
+            address: signer,
+            invoke: &MockAuthInvoke { contract: &factory.address, fn_name, args, sub_invokes: &[] },
+        }]);
+    }
+
+    // Helper function to enable a 2-of-3 multisig
+    fn enable_multisig(env: &Env, factory: &EscrowFactoryClient) -> Vec<Address> {
+        let signers = vec![env, Address::generate(env), Address::generate(env), Address::generate(env)];
+        factory.initialize_multisig(&signers, &2);
+        signers
+    }
+
+    #[test]
+    fn test_deploy_escrow() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+
+        let escrow = deploy(&factory, &immutables).unwrap();
+
+        let salt = factory.derive_salt(&immutables);
+        assert!(factory.is_deployed(&salt));
+        let hashlock: BytesN<32> = env.invoke_contract(&escrow, &Symbol::new(&env, "get_hashlock"), vec![&env]);
+        assert_eq!(hashlock, immutables.hashlock);
+    }
+
+    #[test]
+    fn test_second_escrow_for_an_order_hash_is_rejected() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        deploy(&factory, &immutables).unwrap();
+
+        let smaller = Immutables { amount: 500, ..immutables.clone() };
+        assert_eq!(deploy(&factory, &smaller), Err(Error::OrderHashConflict));
+        assert_eq!(deploy(&factory, &immutables), Err(Error::AlreadyDeployed));
+        assert_eq!(factory.get_order_record(&immutables.order_hash).unwrap().salts.len(), 1);
+    }
+
+    #[test]
+    fn test_partial_fills_allow_more_escrows_for_the_same_maker_and_token() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        deploy(&factory, &immutables).unwrap();
+        factory.enable_partial_fills(&immutables.maker, &immutables.order_hash);
+
+        let second = Immutables { amount: 500, ..immutables.clone() };
+        deploy(&factory, &second).unwrap();
+        let other_token = Immutables { token: Address::generate(&env), amount: 250, ..immutables.clone() };
+        assert_eq!(deploy(&factory, &other_token), Err(Error::OrderHashConflict));
+        let other_maker = Immutables { maker: Address::generate(&env), amount: 250, ..immutables.clone() };
+        assert_eq!(deploy(&factory, &other_maker), Err(Error::OrderHashConflict));
+
+        let record = factory.get_order_record(&immutables.order_hash).unwrap();
+        assert_eq!(
+            record.salts,
+            vec![&env, factory.derive_salt(&immutables), factory.derive_salt(&second)]
+        );
+    }
+
+    #[test]
+    fn test_only_the_recorded_maker_can_enable_partial_fills() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        deploy(&factory, &immutables).unwrap();
+
+        let result = factory.try_enable_partial_fills(&Address::generate(&env), &immutables.order_hash);
+        assert_eq!(result, Err(Ok(Error::OrderHashConflict)));
+        assert!(!factory.is_partial_fill_enabled(&immutables.order_hash));
+    }
+
+    #[test]
+    fn test_party_indexes_page_order_hashes_oldest_first() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let token = Address::generate(&env);
+        let first = immutables(&env, &token, 1);
+        let maker = first.maker.clone();
+        let orders = [first, immutables(&env, &token, 3), immutables(&env, &token, 5)]
+            .map(|immutables| Immutables { maker: maker.clone(), ..immutables });
+        for immutables in &orders {
+            deploy(&factory, immutables).unwrap();
+        }
+        let [first, second, third] = orders.map(|immutables| immutables.order_hash);
+
+        assert_eq!(factory.get_maker_escrow_count(&maker), 3);
+        assert_eq!(factory.get_escrows_by_maker(&maker, &0, &2), vec![&env, first.clone(), second]);
+        assert_eq!(factory.get_escrows_by_maker(&maker, &2, &10), vec![&env, third]);
+        assert!(factory.get_escrows_by_maker(&maker, &3, &10).is_empty());
+        assert!(factory.get_escrows_by_maker(&maker, &u32::MAX, &u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_each_party_gets_its_own_index() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        deploy(&factory, &immutables).unwrap();
+
+        assert_eq!(factory.get_taker_escrow_count(&immutables.taker), 1);
+        assert_eq!(
+            factory.get_escrows_by_taker(&immutables.taker, &0, &10),
+            vec![&env, immutables.order_hash.clone()]
+        );
+        assert_eq!(factory.get_taker_escrow_count(&immutables.maker), 0);
+        assert!(factory.get_escrows_by_maker(&immutables.taker, &0, &10).is_empty());
+    }
+
+    #[test]
+    fn test_ttl_bounty_is_paid_only_for_funded_escrows() {
+        let env = Env::default();
+        // Keep the factory and tokens live across the month of ledgers the test skips
+        env.ledger().with_mut(|li| {
+            li.min_persistent_entry_ttl = 2_000_000;
+            li.max_entry_ttl = 3_000_000;
+        });
+        let (factory, _) = setup(&env);
+        let native = register_native(&env);
+        let keeper = Address::generate(&env);
+        native.mint(&keeper, &100);
+        factory.fund_fee_pool(&keeper, &100);
+        factory.set_ttl_bounty(&10);
+
+        let unfunded = immutables(&env, &Address::generate(&env), 1);
+        deploy(&factory, &unfunded).unwrap();
+        let mut funded = immutables(&env, &Address::generate(&env), 3);
+        funded.token = setup_token(&env, &funded.maker, 0);
+        let escrow = deploy(&factory, &funded).unwrap();
+        token::StellarAssetClient::new(&env, &funded.token).mint(&escrow, &funded.amount);
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "confirm_funding"), vec![&env]);
+
+        // Past the escrows' extension threshold (23 days of ledgers)
+        env.ledger().with_mut(|li| li.sequence_number += 23 * 17_280 + 1);
+        assert_eq!(factory.extend_escrow_storage(&keeper, &factory.derive_salt(&unfunded)), 0);
+        assert_eq!(factory.extend_escrow_storage(&keeper, &factory.derive_salt(&funded)), 10);
+        assert_eq!(native.balance(&keeper), 10);
+        assert_eq!(factory.get_fee_pool(), 90);
+    }
+
+    #[test]
+    fn test_integrator_volume_counts_only_once_the_escrow_is_funded() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.token = setup_token(&env, &immutables.maker, 0);
+        let integrator = Address::generate(&env);
+        let escrow = factory.deploy_escrow_with_integrator(&immutables, &integrator);
+        let salt = factory.derive_salt(&immutables);
+
+        assert_eq!(factory.get_escrow_integrator(&salt), Some(integrator.clone()));
+        assert_eq!(factory.try_record_integrator_volume(&salt), Err(Ok(Error::InvalidParams)));
+        assert_eq!(factory.get_integrator_volume(&integrator, &immutables.token), 0);
+        assert_eq!(factory.get_integrator_order_count(&integrator), 0);
+
+        token::StellarAssetClient::new(&env, &immutables.token).mint(&escrow, &immutables.amount);
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "confirm_funding"), vec![&env]);
+        assert_eq!(factory.record_integrator_volume(&salt), 1_000);
+        assert_eq!(factory.get_integrator_volume(&integrator, &immutables.token), 1_000);
+        assert_eq!(factory.get_integrator_order_count(&integrator), 1);
+
+        assert_eq!(factory.try_record_integrator_volume(&salt), Err(Ok(Error::UnknownEscrow)));
+        assert_eq!(factory.get_integrator_volume(&integrator, &immutables.token), 1_000);
+    }
+
+    #[test]
+    fn test_initialize_multisig_rejects_bad_thresholds_and_duplicates() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let signer = Address::generate(&env);
+        let other = Address::generate(&env);
+
+        let zero = factory.try_initialize_multisig(&vec![&env, signer.clone(), other.clone()], &0);
+        assert_eq!(zero, Err(Ok(Error::InvalidParams)));
+        let too_high = factory.try_initialize_multisig(&vec![&env, signer.clone(), other.clone()], &3);
+        assert_eq!(too_high, Err(Ok(Error::InvalidParams)));
+        let duplicate = factory.try_initialize_multisig(&vec![&env, signer.clone(), signer.clone()], &2);
+        assert_eq!(duplicate, Err(Ok(Error::InvalidParams)));
+
+        factory.initialize_multisig(&vec![&env, signer, other.clone()], &2);
+        let again = factory.try_initialize_multisig(&vec![&env, other], &1);
+        assert_eq!(again, Err(Ok(Error::AlreadyInitialized)));
+    }
+
+    #[test]
+    fn test_multisig_replaces_the_single_admin_for_sensitive_actions() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        enable_multisig(&env, &factory);
+
+        assert_eq!(factory.try_set_paused(&true), Err(Ok(Error::MultisigRequired)));
+        assert_eq!(factory.try_set_governance_delay(&60), Err(Ok(Error::MultisigRequired)));
+        assert_eq!(
+            factory.try_update_htlc_hash(&BytesN::from_array(&env, &[9; 32])),
+            Err(Ok(Error::MultisigRequired))
+        );
+        assert!(!factory.is_paused());
+    }
+
+    #[test]
+    fn test_proposal_executes_once_the_threshold_is_reached() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let signers = enable_multisig(&env, &factory);
+
+        let id = factory.propose(&signers.get(0).unwrap(), &AdminAction::SetPaused(true));
+        assert_eq!(factory.try_execute(&id), Err(Ok(Error::InsufficientApprovals)));
+        assert_eq!(
+            factory.try_approve(&signers.get(0).unwrap(), &id),
+            Err(Ok(Error::AlreadyApproved))
+        );
+
+        factory.approve(&signers.get(1).unwrap(), &id);
+        factory.execute(&id);
+
+        assert!(factory.is_paused());
+        assert!(factory.get_proposal(&id).unwrap().executed);
+        assert_eq!(factory.try_execute(&id), Err(Ok(Error::ProposalExecuted)));
+        assert_eq!(
+            factory.try_approve(&signers.get(2).unwrap(), &id),
+            Err(Ok(Error::ProposalExecuted))
+        );
+    }
+
+    #[test]
+    fn test_only_signers_can_propose_and_approve() {
+        let env = Env::default();
+        let (factory, admin) = setup(&env);
+        let signers = enable_multisig(&env, &factory);
+
+        assert_eq!(
+            factory.try_propose(&admin, &AdminAction::SetPaused(true)),
+            Err(Ok(Error::NotSigner))
+        );
+        let id = factory.propose(&signers.get(0).unwrap(), &AdminAction::SetPaused(true));
+        assert_eq!(factory.try_approve(&admin, &id), Err(Ok(Error::NotSigner)));
+        assert_eq!(
+            factory.try_approve(&signers.get(1).unwrap(), &(id + 1)),
+            Err(Ok(Error::ProposalNotFound))
+        );
+    }
+
+    #[test]
+    fn test_configuration_setters_go_through_proposals_under_multisig() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let signers = enable_multisig(&env, &factory);
+        let token = Address::generate(&env);
+
+        assert_eq!(factory.try_set_token_policy(&token, &false), Err(Ok(Error::MultisigRequired)));
+        assert_eq!(factory.try_set_rate_limit(&1), Err(Ok(Error::MultisigRequired)));
+        assert_eq!(factory.try_set_ttl_bounty(&10), Err(Ok(Error::MultisigRequired)));
+
+        let id = factory.propose(&signers.get(0).unwrap(), &AdminAction::SetTokenPolicy(token.clone(), false));
+        factory.approve(&signers.get(1).unwrap(), &id);
+        factory.execute(&id);
+        assert_eq!(factory.get_token_policy(&token), Some(false));
+    }
+
+    #[test]
+    fn test_proposals_are_checked_like_the_setters() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let signers = enable_multisig(&env, &factory);
+        let proposer = signers.get(0).unwrap();
+
+        assert_eq!(
+            factory.try_propose(&proposer, &AdminAction::SetSkewTolerance(601)),
+            Err(Ok(Error::InvalidParams))
+        );
+        assert_eq!(
+            factory.try_propose(&proposer, &AdminAction::SetTtlBounty(-1)),
+            Err(Ok(Error::InvalidAmount))
+        );
+        let unknown_chain = AdminAction::SetWrappedToken(
+            1,
+            EvmAddress(BytesN::from_array(&env, &[7; 20])),
+            Address::generate(&env),
+        );
+        assert_eq!(factory.try_propose(&proposer, &unknown_chain), Err(Ok(Error::UnsupportedChain)));
+    }
+
+    #[test]
+    fn test_governance_delay_schedules_htlc_hash_updates() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let original = factory.get_htlc_hash();
+        let new_hash = BytesN::from_array(&env, &[9; 32]);
+        factory.set_governance_delay(&3_600);
+
+        factory.update_htlc_hash(&new_hash);
+        assert_eq!(factory.get_htlc_hash(), original);
+        assert_eq!(factory.get_pending_actions(), vec![&env, 0]);
+        assert_eq!(factory.try_execute_scheduled(&0), Err(Ok(Error::ActionNotReady)));
+
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        factory.execute_scheduled(&0);
+        assert_eq!(factory.get_htlc_hash(), new_hash);
+        assert!(factory.get_pending_actions().is_empty());
+        assert_eq!(factory.try_execute_scheduled(&0), Err(Ok(Error::ActionNotFound)));
+    }
+
+    #[test]
+    fn test_governance_delay_schedules_configuration_changes() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        factory.set_governance_delay(&3_600);
+
+        factory.set_rate_limit(&1);
+        factory.set_allowlist_mode(&true);
+        assert_eq!(factory.get_rate_limit(), 0);
+        assert_eq!(factory.get_pending_actions(), vec![&env, 0, 1]);
+        assert_eq!(factory.try_set_skew_tolerance(&601), Err(Ok(Error::InvalidParams)));
+
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        factory.execute_scheduled(&0);
+        assert_eq!(factory.get_rate_limit(), 1);
+        assert_eq!(factory.get_pending_actions(), vec![&env, 1]);
+    }
+
+    #[test]
+    fn test_cancelled_action_cannot_be_executed() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let original = factory.get_htlc_hash();
+        factory.set_governance_delay(&3_600);
+        factory.update_htlc_hash(&BytesN::from_array(&env, &[9; 32]));
+
+        factory.cancel_scheduled(&0);
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+
+        assert_eq!(factory.try_execute_scheduled(&0), Err(Ok(Error::ActionNotFound)));
+        assert_eq!(factory.try_cancel_scheduled(&0), Err(Ok(Error::ActionNotFound)));
+        assert_eq!(factory.get_htlc_hash(), original);
+    }
+
+    #[test]
+    fn test_changing_a_set_delay_is_delayed_but_pausing_is_not() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        factory.set_governance_delay(&3_600);
+
+        factory.set_governance_delay(&0);
+        assert_eq!(factory.get_governance_delay(), 3_600);
+        factory.set_paused(&true);
+        assert!(factory.is_paused());
+
+        env.ledger().with_mut(|li| li.timestamp += 3_600);
+        factory.execute_scheduled(&0);
+        assert_eq!(factory.get_governance_delay(), 0);
+    }
+
+    #[test]
+    fn test_deploy_and_fund_pulls_the_makers_tokens_under_its_permit() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.token = setup_token(&env, &immutables.maker, 1_000);
+        let permit = Permit { expires_at: env.ledger().timestamp() + 60 };
+
+        let escrow = factory.deploy_and_fund(&immutables, &permit);
+
+        let (_, invocation) = env.auths().into_iter()
+            .find(|(signer, _)| *signer == immutables.maker)
+            .unwrap();
+        assert_eq!(
+            invocation.function,
+            AuthorizedFunction::Contract((
+                factory.address.clone(),
+                Symbol::new(&env, "deploy_and_fund"),
+                (
+                    immutables.order_hash.clone(),
+                    immutables.token.clone(),
+                    immutables.amount,
+                    permit.expires_at,
+                ).into_val(&env),
+            ))
+        );
+        let token = token::TokenClient::new(&env, &immutables.token);
+        assert_eq!(token.balance(&immutables.maker), 0);
+        assert_eq!(token.balance(&escrow), 1_000);
+    }
+
+    #[test]
+    fn test_deploy_and_fund_rejects_an_expired_permit() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.token = setup_token(&env, &immutables.maker, 1_000);
+        env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+        let result = factory.try_deploy_and_fund(&immutables, &Permit { expires_at: 999 });
+
+        assert_eq!(result, Err(Ok(Error::PermitExpired)));
+        assert!(!factory.is_deployed(&factory.derive_salt(&immutables)));
+    }
+
+    #[test]
+    fn test_create_src_escrow_binds_the_escrow_to_the_signed_order() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        let order_hash = factory.hash_order(&order);
+        sign_order(&env, &factory, &order, &order_hash, 60);
+
+        let escrow = factory.create_src_escrow(&order, &Permit { expires_at: 60 });
+
+        let hashlock: BytesN<32> = env.invoke_contract(&escrow, &Symbol::new(&env, "get_hashlock"), vec![&env]);
+        assert_eq!(hashlock, order.hashlock);
+        assert_eq!(token::TokenClient::new(&env, &order.token).balance(&escrow), 1_000);
+        assert_eq!(factory.get_order_record(&order_hash).unwrap().maker, order.maker);
+    }
+
+    #[test]
+    fn test_create_src_escrow_rejects_an_order_altered_after_signing() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        let order_hash = factory.hash_order(&order);
+        let altered = Order { amount: 500, ..order.clone() };
+        sign_order(&env, &factory, &altered, &order_hash, 60);
+
+        assert!(factory.try_create_src_escrow(&altered, &Permit { expires_at: 60 }).is_err());
+        assert!(factory.get_order_record(&factory.hash_order(&altered)).is_none());
+    }
+
+    #[test]
+    fn test_create_src_escrow_rejects_expired_and_cancelled_orders() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        env.ledger().with_mut(|li| li.timestamp = 100);
+
+        let expired = factory.try_create_src_escrow(&order, &Permit { expires_at: 99 });
+        assert_eq!(expired, Err(Ok(Error::PermitExpired)));
+
+        factory.cancel_nonce(&order.maker, &1);
+        let cancelled = factory.try_create_src_escrow(&order, &Permit { expires_at: 200 });
+        assert_eq!(cancelled, Err(Ok(Error::NonceCancelled)));
+    }
+
+    #[test]
+    fn test_rate_limit_caps_deploys_per_taker_and_ledger() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        factory.set_rate_limit(&2);
+        let token = Address::generate(&env);
+        let first = immutables(&env, &token, 1);
+        let taker = first.taker.clone();
+        let with_taker = |seed| Immutables { taker: taker.clone(), ..immutables(&env, &token, seed) };
+
+        deploy(&factory, &first).unwrap();
+        deploy(&factory, &with_taker(3)).unwrap();
+        assert_eq!(deploy(&factory, &with_taker(5)), Err(Error::RateLimited));
+        deploy(&factory, &immutables(&env, &token, 7)).unwrap();
+
+        env.ledger().with_mut(|li| li.sequence_number += 1);
+        deploy(&factory, &with_taker(5)).unwrap();
+    }
+
+    #[test]
+    fn test_order_hash_cannot_be_claimed_without_the_takers_auth() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        let tiny = Immutables { amount: 1, ..immutables.clone() };
+        env.set_auths(&[]);
+
+        assert!(factory.try_deploy_escrow_idempotent(&tiny).is_err());
+        assert!(factory.try_deploy_escrow_from_chain(&1, &tiny).is_err());
+        assert!(factory.try_deploy_and_fund(&tiny, &Permit { expires_at: 60 }).is_err());
+        assert!(factory.get_order_record(&immutables.order_hash).is_none());
+
+        // Rate-limited deploys count against the same authorization
+        env.mock_all_auths();
+        factory.set_rate_limit(&1);
+        let args: Vec<Val> = (
+            immutables.order_hash.clone(),
+            immutables.hashlock.clone(),
+            immutables.maker.clone(),
+            immutables.taker.clone(),
+            immutables.token.clone(),
+            immutables.amount,
+            immutables.safety_deposit,
+            immutables.timelocks,
+        ).into_val(&env);
+        authorize(&env, &factory, &immutables.taker, "deploy_escrow", args);
+        deploy(&factory, &immutables).unwrap();
+        assert_eq!(factory.get_order_record(&immutables.order_hash).unwrap().salts.len(), 1);
+    }
+
+    #[test]
+    fn test_sponsored_deploy_records_the_sponsor_apart_from_the_resolver() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        let sponsor = Address::generate(&env);
+
+        factory.deploy_escrow_sponsored(&immutables, &sponsor);
+
+        let (_, invocation) = env.auths().into_iter()
+            .find(|(signer, _)| *signer == sponsor)
+            .unwrap();
+        assert_eq!(
+            invocation.function,
+            AuthorizedFunction::Contract((
+                factory.address.clone(),
+                Symbol::new(&env, "deploy_escrow_sponsored"),
+                (immutables.order_hash.clone(), immutables.taker.clone()).into_val(&env),
+            ))
+        );
+        assert!(env.auths().iter().any(|(signer, _)| *signer == immutables.taker));
+        assert_eq!(
+            factory.get_escrow_sponsorship(&factory.derive_salt(&immutables)),
+            Some(Sponsorship { sponsor: sponsor.clone(), resolver: immutables.taker.clone() })
+        );
+        assert_eq!(factory.get_sponsored_count(&sponsor), 1);
+    }
+
+    #[test]
+    fn test_taker_cannot_sponsor_its_own_deploy() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+
+        let result = factory.try_deploy_escrow_sponsored(&immutables, &immutables.taker);
+
+        assert_eq!(result, Err(Ok(Error::SelfSponsored)));
+        assert_eq!(factory.get_sponsored_count(&immutables.taker), 0);
+    }
+
+    #[test]
+    fn test_taker_imports_the_evm_counterparty_once() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        let escrow = deploy(&factory, &immutables).unwrap();
+        let evm_escrow = EvmAddress(BytesN::from_array(&env, &[7; 20]));
+        let evm_hash = BytesN::from_array(&env, &[8; 32]);
+
+        factory.import_counterparty(&immutables.order_hash, &evm_escrow, &evm_hash);
+
+        let (signer, invocation) = env.auths().pop().unwrap();
+        assert_eq!(signer, immutables.taker);
+        assert!(matches!(
+            invocation.function,
+            AuthorizedFunction::Contract((contract, function, _))
+                if contract == factory.address && function == Symbol::new(&env, "import_counterparty")
+        ));
+        assert_eq!(
+            factory.get_counterparty(&immutables.order_hash),
+            Some(CounterpartyEscrow {
+                stellar_escrow: escrow,
+                evm_escrow: evm_escrow.clone(),
+                evm_immutables_hash: evm_hash.clone(),
+            })
+        );
+        assert_eq!(
+            factory.try_import_counterparty(&immutables.order_hash, &evm_escrow, &evm_hash),
+            Err(Ok(Error::CounterpartyImported))
+        );
+    }
+
+    #[test]
+    fn test_import_counterparty_rejects_unknown_orders_and_a_zero_hash() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        let evm_escrow = EvmAddress(BytesN::from_array(&env, &[7; 20]));
+
+        let unknown = factory.try_import_counterparty(&immutables.order_hash, &evm_escrow, &BytesN::from_array(&env, &[8; 32]));
+        assert_eq!(unknown, Err(Ok(Error::UnknownEscrow)));
+
+        deploy(&factory, &immutables).unwrap();
+        let zero = factory.try_import_counterparty(&immutables.order_hash, &evm_escrow, &BytesN::from_array(&env, &[0; 32]));
+        assert_eq!(zero, Err(Ok(Error::InvalidParams)));
+        assert!(factory.get_counterparty(&immutables.order_hash).is_none());
+    }
+
+    #[test]
+    fn test_import_counterparty_rejects_a_finalized_escrow() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &setup_token(&env, &Address::generate(&env), 0), 1);
+        let escrow = deploy(&factory, &immutables).unwrap();
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "cancel"), vec![&env, immutables.taker.to_val()]);
+        env.ledger().with_mut(|li| li.timestamp += 7 * 24 * 60 * 60);
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "finalize"), vec![&env]);
+
+        let evm_escrow = EvmAddress(BytesN::from_array(&env, &[7; 20]));
+        let result = factory.try_import_counterparty(&immutables.order_hash, &evm_escrow, &BytesN::from_array(&env, &[8; 32]));
+        assert_eq!(result, Err(Ok(Error::UnknownEscrow)));
+        assert!(factory.get_counterparty(&immutables.order_hash).is_none());
+    }
+
+    #[test]
+    fn test_match_orders_gives_each_maker_the_others_whole_amount_once() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let (order_a, order_b) = crossing_orders(&env);
+        let permit = Permit { expires_at: 60 };
+
+        let (hash_a, hash_b) = factory.match_orders(&order_a, &order_b, &permit, &permit);
+
+        assert!(env.auths().iter().any(|(signer, invocation)| *signer == order_a.maker
+            && invocation.function == AuthorizedFunction::Contract((
+                factory.address.clone(),
+                Symbol::new(&env, "match_orders"),
+                (hash_a.clone(), permit.expires_at).into_val(&env),
+            ))));
+        assert_eq!(hash_a, factory.hash_limit_order(&order_a));
+        assert_eq!(hash_b, factory.hash_limit_order(&order_b));
+        assert!(factory.is_order_filled(&hash_a) && factory.is_order_filled(&hash_b));
+        let token_a = token::TokenClient::new(&env, &order_a.maker_token);
+        let token_b = token::TokenClient::new(&env, &order_b.maker_token);
+        assert_eq!(token_a.balance(&order_b.maker), 100);
+        assert_eq!(token_b.balance(&order_a.maker), 60);
+
+        token::StellarAssetClient::new(&env, &order_a.maker_token).mint(&order_a.maker, &100);
+        let replay = factory.try_match_orders(&order_a, &order_b, &permit, &permit);
+        assert_eq!(replay, Err(Ok(Error::OrderFilled)));
+    }
+
+    #[test]
+    fn test_match_orders_rejects_orders_that_dont_cross() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let (order_a, order_b) = crossing_orders(&env);
+        let permit = Permit { expires_at: 60 };
+
+        let short = LimitOrder { maker_amount: 40, ..order_b.clone() };
+        let result = factory.try_match_orders(&order_a, &short, &permit, &permit);
+        assert_eq!(result, Err(Ok(Error::OrdersDontCross)));
+
+        let other_token = LimitOrder { maker_token: Address::generate(&env), ..order_b.clone() };
+        let result = factory.try_match_orders(&order_a, &other_token, &permit, &permit);
+        assert_eq!(result, Err(Ok(Error::TokenMismatch)));
+
+        let same_maker = LimitOrder { maker: order_a.maker.clone(), ..order_b };
+        let result = factory.try_match_orders(&order_a, &same_maker, &permit, &permit);
+        assert_eq!(result, Err(Ok(Error::MakerIsTaker)));
+        assert!(!factory.is_order_filled(&factory.hash_limit_order(&order_a)));
+    }
+
+    #[test]
+    fn test_min_public_gap_overflowing_the_private_start_is_rejected() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        // Every private stage at 1, every public stage at 255
+        immutables.timelocks = u64::from_le_bytes([1, 255, 1, 255, 1, 255, 0, 0]);
+
+        factory.set_min_public_gap(&254);
+        let within = Immutables { order_hash: BytesN::from_array(&env, &[3; 32]), ..immutables.clone() };
+        deploy(&factory, &within).unwrap();
+
+        factory.set_min_public_gap(&u32::MAX);
+        assert_eq!(deploy(&factory, &immutables), Err(Error::PublicWindowTooSoon));
+    }
+
+    #[test]
+    fn test_only_the_taker_can_deploy() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let token = Address::generate(&env);
+        let taker_asset = EvmAddress(BytesN::from_array(&env, &[9; 20]));
+        factory.set_chain_config(&1, &ChainConfig {
+            min_timelocks: 0,
+            supported_tokens: Vec::new(&env),
+            evm_factory: EvmAddress(BytesN::from_array(&env, &[7; 20])),
+        });
+        factory.set_wrapped_token(&1, &taker_asset, &token);
+        let details = Bytes::from_slice(&env, b"order-uid");
+        let split = DepositSplit { executor_bps: 5_000, maker_bps: 5_000, protocol_bps: 0 };
+        let incentives = DepositIncentives {
+            withdraw: split.clone(),
+            public_withdraw: split.clone(),
+            cancel: split.clone(),
+            public_cancel: split,
+            protocol: Address::generate(&env),
+            remainder: DepositParty::Maker,
+            dust_threshold: 0,
+        };
+        // Public actions only: no partial fills, no fees
+        let flags = 1u32 << 1;
+        let v2 = |core: &Immutables| ImmutablesV2 {
+            version: 2,
+            order_hash: core.order_hash.clone(),
+            hashlock: core.hashlock.clone(),
+            maker: core.maker.clone(),
+            taker: core.taker.clone(),
+            token: core.token.clone(),
+            amount: core.amount,
+            safety_deposit: core.safety_deposit,
+            timelocks: core.timelocks,
+            side: EscrowSide::Dst,
+            flags,
+            receiver: Some(Address::generate(&env)),
+            fees: DepositPolicy::Default,
+        };
+
+        // Each entrypoint with its own order (so the salts differ) and its arguments
+        let with = |seed: u8| immutables(&env, &token, seed);
+        let deploys = [
+            ("deploy_escrow", with(1)),
+            ("deploy_escrow_from_chain", with(3)),
+            ("deploy_escrow_for_evm_order", with(5)),
+            ("deploy_escrow_with_integrator", with(7)),
+            ("deploy_escrow_with_details", with(9)),
+            ("deploy_escrow_with_beneficiary", with(11)),
+            ("deploy_escrow_with_incentives", with(13)),
+            ("deploy_escrow_with_oracle", with(15)),
+            ("deploy_escrow_with_hooks", with(17)),
+            ("deploy_escrow_with_flags", with(19)),
+            ("deploy_escrow_with_parts", with(21)),
+            ("deploy_escrow_v2", with(23)),
+        ];
+        for (fn_name, immutables) in deploys {
+            let imm = immutables.clone();
+            let args: Vec<Val> = match fn_name {
+                "deploy_escrow" => (
+                    imm.order_hash, imm.hashlock, imm.maker, imm.taker, imm.token, imm.amount, imm.safety_deposit, imm.timelocks,
+                ).into_val(&env),
+                "deploy_escrow_from_chain" => (1u64, imm).into_val(&env),
+                "deploy_escrow_for_evm_order" => (1u64, taker_asset.clone(), imm).into_val(&env),
+                "deploy_escrow_with_details" => (imm, details.clone()).into_val(&env),
+                "deploy_escrow_with_incentives" => (imm, incentives.clone()).into_val(&env),
+                "deploy_escrow_with_hooks" => (imm, Some(Address::generate(&env)), None::<Address>).into_val(&env),
+                "deploy_escrow_with_flags" => (imm, flags).into_val(&env),
+                "deploy_escrow_with_parts" => (imm, 1u32).into_val(&env),
+                "deploy_escrow_v2" => (v2(&imm),).into_val(&env),
+                // Integrator, beneficiary and oracle each take one more address
+                _ => (imm, Address::generate(&env)).into_val(&env),
+            };
+            let function = Symbol::new(&env, fn_name);
+
+            authorize(&env, &factory, &Address::generate(&env), fn_name, args.clone());
+            let stranger = env.try_invoke_contract::<Address, Error>(&factory.address, &function, args.clone());
+            assert!(stranger.is_err(), "{fn_name} deployed without the taker's auth");
+
+            authorize(&env, &factory, &immutables.taker, fn_name, args.clone());
+            let escrow: Address = env.invoke_contract(&factory.address, &function, args);
+            let salt = factory.derive_salt(&immutables);
+            assert_eq!(factory.get_deployment_info(&salt).unwrap().escrow, escrow);
+
+            match fn_name {
+                "deploy_escrow_from_chain" | "deploy_escrow_for_evm_order" => {
+                    assert_eq!(factory.get_escrow_chain(&salt), Some(1));
+                }
+                "deploy_escrow_with_integrator" => assert!(factory.get_escrow_integrator(&salt).is_some()),
+                "deploy_escrow_with_details" => {
+                    let stored: Option<Bytes> = env.invoke_contract(&escrow, &Symbol::new(&env, "get_details"), vec![&env]);
+                    assert_eq!(stored, Some(details.clone()));
+                }
+                "deploy_escrow_with_flags" | "deploy_escrow_v2" => {
+                    let stored: u32 = env.invoke_contract(&escrow, &Symbol::new(&env, "get_flags"), vec![&env]);
+                    assert_eq!(stored, flags);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_parts_escrow_is_filled_tranche_by_tranche() {
+        use fusion_shared::merkle;
+
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let secrets: [BytesN<32>; 2] = core::array::from_fn(|i| BytesN::from_array(&env, &[i as u8 + 1; 32]));
+        let leaves: [BytesN<32>; 2] = core::array::from_fn(|i| {
+            let hash = env.crypto().sha256(&Bytes::from(secrets[i].clone())).to_bytes();
+            merkle::secret_leaf(&env, i as u64, &hash)
+        });
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.token = setup_token(&env, &immutables.maker, 0);
+        immutables.hashlock = merkle::hash_pair(&env, &leaves[0], &leaves[1]);
+        let args: Vec<Val> = (immutables.clone(), 1u32).into_val(&env);
+
+        authorize(&env, &factory, &Address::generate(&env), "deploy_escrow_with_parts", args.clone());
+        assert!(factory.try_deploy_escrow_with_parts(&immutables, &1).is_err());
+        authorize(&env, &factory, &immutables.taker, "deploy_escrow_with_parts", args);
+        let escrow = factory.deploy_escrow_with_parts(&immutables, &1);
+
+        env.mock_all_auths();
+        token::StellarAssetClient::new(&env, &immutables.token).mint(&escrow, &immutables.amount);
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "confirm_funding"), vec![&env]);
+        let withdraw = Symbol::new(&env, "withdraw_partial_with_proof");
+        for (index, proof, amount) in [(0u64, &leaves[1], 400i128), (1, &leaves[0], 600)] {
+            let secret = &secrets[index as usize];
+            let _: () = env.invoke_contract(&escrow, &withdraw, (secret, index, vec![&env, proof.clone()], amount).into_val(&env));
+        }
+        let taker_balance = token::Client::new(&env, &immutables.token).balance(&immutables.taker);
+        assert_eq!(taker_balance, immutables.amount);
+    }
+
+    #[test]
+    fn test_deploy_escrow_for_requires_the_resolver_to_be_the_taker() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+
+        let result = factory.try_deploy_escrow_for(&immutables.maker, &immutables);
+        assert_eq!(result, Err(Ok(Error::ResolverNotTaker)));
+
+        factory.deploy_escrow_for(&immutables.taker, &immutables);
+        assert!(factory.is_deployed(&factory.derive_salt(&immutables)));
+    }
+
+    #[test]
+    fn test_deploy_from_chain_checks_the_chain_registry() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let allowed = Address::generate(&env);
+        let next = immutables(&env, &allowed, 3);
+        let mut immutables = immutables(&env, &allowed, 1);
+        // Dst cancellation opens at 30 seconds, the chain needs at least 20
+        immutables.timelocks = u64::from_le_bytes([0, 0, 0, 0, 0, 0, 30, 0]);
+        let config = ChainConfig {
+            min_timelocks: u64::from_le_bytes([0, 0, 0, 0, 0, 0, 20, 0]),
+            supported_tokens: vec![&env, allowed.clone()],
+            evm_factory: EvmAddress(BytesN::from_array(&env, &[7; 20])),
+        };
+
+        let from_chain = |immutables: &Immutables| factory.try_deploy_escrow_from_chain(&1, immutables).err();
+        assert_eq!(from_chain(&immutables), Some(Ok(Error::UnsupportedChain)));
+        factory.set_chain_config(&1, &config);
+        assert_eq!(factory.get_chain_config(&1).unwrap().min_timelocks, config.min_timelocks);
+        let other_token = Immutables { token: Address::generate(&env), ..immutables.clone() };
+        assert_eq!(from_chain(&other_token), Some(Ok(Error::UnsupportedToken)));
+        let too_short = Immutables { timelocks: u64::from_le_bytes([0, 0, 0, 0, 0, 0, 10, 0]), ..immutables.clone() };
+        assert_eq!(from_chain(&too_short), Some(Ok(Error::TimelockTooShort)));
+
+        factory.deploy_escrow_from_chain(&1, &immutables);
+        assert_eq!(factory.get_escrow_chain(&factory.derive_salt(&immutables)), Some(1));
+
+        factory.remove_chain_config(&1);
+        assert!(factory.get_chain_config(&1).is_none());
+        assert_eq!(from_chain(&next), Some(Ok(Error::UnsupportedChain)));
+    }
+
+    #[test]
+    fn test_salt_is_the_keccak_of_the_abi_encoded_immutables() {
+        use fusion_shared::conversion::address_word;
+
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.timelocks = 42;
+
+        // abi.encode: bytes32 order hash and hashlock, three address words, int256 amount and
+        // deposit, uint256 timelocks
+        let mut abi = Bytes::from(immutables.order_hash.clone());
+        abi.append(&immutables.hashlock.clone().into());
+        for address in [&immutables.maker, &immutables.taker, &immutables.token] {
+            abi.append(&address_word(&env, address).into());
+        }
+        for value in [immutables.amount, immutables.safety_deposit] {
+            abi.extend_from_array(&[0; 16]);
+            abi.extend_from_array(&value.to_be_bytes());
+        }
+        abi.extend_from_array(&[0; 24]);
+        abi.extend_from_array(&immutables.timelocks.to_be_bytes());
+        let salt: BytesN<32> = env.crypto().keccak256(&abi).into();
+        assert_eq!(factory.derive_salt(&immutables), salt);
+
+        let predicted = factory.calculate_escrow_address(
+            &immutables.order_hash,
+            &immutables.hashlock,
+            &immutables.maker,
+            &immutables.taker,
+            &immutables.token,
+            &immutables.amount,
+            &immutables.safety_deposit,
+            &immutables.timelocks,
+        );
+        let escrow = deploy(&factory, &immutables).unwrap();
+        assert_eq!(escrow, predicted);
+        let escrow_abi: Bytes = env.invoke_contract(&escrow, &Symbol::new(&env, "get_immutables_evm"), vec![&env]);
+        assert_eq!(escrow_abi, abi);
+    }
+
+    #[test]
+    fn test_token_policy_denies_tokens_and_allowlist_mode_admits_only_allowed_ones() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let token = Address::generate(&env);
+
+        factory.set_token_policy(&token, &false);
+        assert_eq!(factory.get_token_policy(&token), Some(false));
+        assert_eq!(deploy(&factory, &immutables(&env, &token, 1)), Err(Error::TokenNotAllowed));
+        factory.remove_token_policy(&token);
+        assert!(factory.is_token_allowed(&token));
+        deploy(&factory, &immutables(&env, &token, 1)).unwrap();
+
+        // Allow-list mode: unlisted tokens are rejected until allowed
+        factory.set_allowlist_mode(&true);
+        let other = Address::generate(&env);
+        assert!(!factory.is_token_allowed(&other));
+        assert_eq!(deploy(&factory, &immutables(&env, &other, 3)), Err(Error::TokenNotAllowed));
+        factory.set_token_policy(&other, &true);
+        deploy(&factory, &immutables(&env, &other, 3)).unwrap();
+        assert_eq!(deploy(&factory, &immutables(&env, &token, 5)), Err(Error::TokenNotAllowed));
+    }
+
+    #[test]
+    fn test_min_safety_deposit_takes_the_larger_bound_and_rejects_zero() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let token = Address::generate(&env);
+        let with_deposit = |seed: u8, safety_deposit: i128| Immutables { safety_deposit, ..immutables(&env, &token, seed) };
+
+        // 5% of 1_000 is 50, below the 100 floor
+        factory.set_min_safety_deposit(&MinSafetyDeposit { min_amount: 100, min_bps: 500 });
+        assert_eq!(deploy(&factory, &with_deposit(1, 0)), Err(Error::SafetyDepositTooLow));
+        assert_eq!(deploy(&factory, &with_deposit(1, 99)), Err(Error::SafetyDepositTooLow));
+        deploy(&factory, &with_deposit(1, 100)).unwrap();
+
+        // 20% of 1_000 is now the larger bound
+        factory.set_min_safety_deposit(&MinSafetyDeposit { min_amount: 100, min_bps: 2_000 });
+        assert_eq!(deploy(&factory, &with_deposit(3, 199)), Err(Error::SafetyDepositTooLow));
+        deploy(&factory, &with_deposit(3, 200)).unwrap();
+
+        factory.set_min_safety_deposit(&MinSafetyDeposit { min_amount: 0, min_bps: 0 });
+        deploy(&factory, &with_deposit(5, 0)).unwrap();
+    }
+
+    #[test]
+    fn test_deploy_src_escrow_locks_the_makers_tokens_and_records_the_dst_leg() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        let immutables = Immutables {
+            order_hash: BytesN::from_array(&env, &[1; 32]),
+            hashlock: order.hashlock.clone(),
+            maker: order.maker.clone(),
+            taker: order.taker.clone(),
+            token: order.token.clone(),
+            amount: order.amount,
+            safety_deposit: 0,
+            timelocks: order.timelocks,
+        };
+
+        // Src stages out of order, and a dst token mapped to another Stellar token
+        let unordered = Immutables { timelocks: u64::from_le_bytes([20, 10, 30, 40, 0, 0, 0, 0]), ..immutables.clone() };
+        assert_eq!(factory.try_deploy_src_escrow(&unordered, &order.dst).err(), Some(Ok(Error::InvalidParams)));
+        factory.set_wrapped_token(&order.dst.chain_id, &order.dst.token, &Address::generate(&env));
+        assert_eq!(factory.try_deploy_src_escrow(&immutables, &order.dst).err(), Some(Ok(Error::TokenMismatch)));
+        factory.set_wrapped_token(&order.dst.chain_id, &order.dst.token, &immutables.token);
+
+        let escrow = factory.deploy_src_escrow(&immutables, &order.dst);
+        assert!(env.auths().iter().any(|(signer, _)| *signer == immutables.maker));
+        let token = token::Client::new(&env, &immutables.token);
+        assert_eq!((token.balance(&immutables.maker), token.balance(&escrow)), (0, immutables.amount));
+        let salt = factory.derive_salt(&immutables);
+        assert_eq!(factory.get_dst_escrow_params(&salt).unwrap().maker, order.dst.maker);
+        let side: EscrowSide = env.invoke_contract(&escrow, &Symbol::new(&env, "get_side"), vec![&env]);
+        assert_eq!(side, EscrowSide::Src);
+    }
+
+    #[test]
+    fn test_order_hash_is_bound_to_the_factory_and_network() {
+        use fusion_shared::order::{domain_separator, hash_struct};
+        use soroban_sdk::FromVal;
+
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        let order_hash = factory.hash_order(&order);
+
+        // keccak256(0x1901 ++ domain separator ++ hashStruct(order))
+        let order_val: Val = order.clone().into_val(&env);
+        let shared = fusion_shared::Order::from_val(&env, &order_val);
+        let mut typed = Bytes::from_array(&env, &[0x19, 0x01]);
+        typed.append(&domain_separator(&env, &factory.address).into());
+        typed.append(&hash_struct(&env, &shared).into());
+        assert_eq!(order_hash, env.crypto().keccak256(&typed).to_bytes());
+
+        let other_factory = EscrowFactoryClient::new(&env, &env.register(EscrowFactory, ()));
+        assert_ne!(other_factory.hash_order(&order), order_hash);
+        env.ledger().with_mut(|li| li.network_id = [9; 32]);
+        assert_ne!(factory.hash_order(&order), order_hash);
+    }
+
+    #[test]
+    fn test_cancel_nonce_only_moves_the_makers_epoch_forward() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let order = src_order(&env, &factory);
+        let args: Vec<Val> = (order.maker.clone(), 2u64).into_val(&env);
+
+        authorize(&env, &factory, &Address::generate(&env), "cancel_nonce", args.clone());
+        assert!(factory.try_cancel_nonce(&order.maker, &2).is_err());
+        authorize(&env, &factory, &order.maker, "cancel_nonce", args);
+        factory.cancel_nonce(&order.maker, &2);
+        assert_eq!(factory.get_maker_nonce(&order.maker), 2);
+
+        env.mock_all_auths();
+        for nonce in [1, 2] {
+            assert_eq!(factory.try_cancel_nonce(&order.maker, &nonce).err(), Some(Ok(Error::InvalidParams)));
+        }
+        let cancelled = Order { nonce: 1, ..order.clone() };
+        assert_eq!(factory.try_create_src_escrow(&cancelled, &Permit { expires_at: 60 }).err(), Some(Ok(Error::NonceCancelled)));
+
+        // Orders at the new epoch still fill
+        let current = Order { nonce: 2, ..order };
+        sign_order(&env, &factory, &current, &factory.hash_order(&current), 60);
+        factory.create_src_escrow(&current, &Permit { expires_at: 60 });
+    }
+
+    #[test]
+    fn test_idempotent_deploy_reports_whether_the_escrow_was_reused() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+
+        let first = factory.deploy_escrow_idempotent(&immutables);
+        assert!(!first.reused);
+        let retry = factory.deploy_escrow_idempotent(&immutables);
+        assert!(retry.reused);
+        assert_eq!(retry.escrow, first.escrow);
+        assert_eq!(factory.get_taker_escrow_count(&immutables.taker), 1);
+        // A plain deploy of the same immutables still fails
+        assert_eq!(deploy(&factory, &immutables), Err(Error::AlreadyDeployed));
+    }
+
+    #[test]
+    fn test_evm_order_escrows_must_hold_the_mapped_token() {
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let immutables = immutables(&env, &Address::generate(&env), 1);
+        let taker_asset = EvmAddress(BytesN::from_array(&env, &[9; 20]));
+        factory.set_chain_config(&1, &ChainConfig {
+            min_timelocks: 0,
+            supported_tokens: Vec::new(&env),
+            evm_factory: EvmAddress(BytesN::from_array(&env, &[7; 20])),
+        });
+        let for_order = |immutables: &Immutables| factory.try_deploy_escrow_for_evm_order(&1, &taker_asset, immutables).err();
+
+        assert_eq!(for_order(&immutables), Some(Ok(Error::UnsupportedToken)));
+        factory.set_wrapped_token(&1, &taker_asset, &immutables.token);
+        assert_eq!(factory.get_wrapped_token(&1, &taker_asset), Some(immutables.token.clone()));
+        let other_token = Immutables { token: Address::generate(&env), ..immutables.clone() };
+        assert_eq!(for_order(&other_token), Some(Ok(Error::TokenMismatch)));
+        assert_eq!(for_order(&immutables), None);
+
+        factory.remove_wrapped_token(&1, &taker_asset);
+        assert!(factory.get_wrapped_token(&1, &taker_asset).is_none());
+        assert_eq!(for_order(&Immutables { order_hash: BytesN::from_array(&env, &[3; 32]), ..immutables }), Some(Ok(Error::UnsupportedToken)));
+    }
+
+    #[test]
+    fn test_protocol_stats_track_configuration_and_counters() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let uninitialized = EscrowFactoryClient::new(&env, &env.register(EscrowFactory, ()));
+        let stats = uninitialized.get_protocol_stats();
+        assert!(!stats.initialized && stats.admin.is_none() && stats.htlc_hash.is_none());
+        assert_eq!(stats.htlc_version, 0);
+
+        let (factory, admin) = setup(&env);
+        let (first, second) = (Address::generate(&env), Address::generate(&env));
+        factory.set_rate_limit(&3);
+        factory.set_min_safety_deposit(&MinSafetyDeposit { min_amount: 5, min_bps: 10 });
+        // Re-setting a policy doesn't count the token twice
+        factory.set_token_policy(&first, &true);
+        factory.set_token_policy(&first, &false);
+        factory.set_token_policy(&second, &true);
+        factory.remove_token_policy(&second);
+        deploy(&factory, &Immutables { safety_deposit: 5, ..immutables(&env, &second, 1) }).unwrap();
+        factory.set_paused(&true);
+
+        let stats = factory.get_protocol_stats();
+        assert!(stats.initialized && stats.paused);
+        assert_eq!(stats.admin, Some(admin));
+        assert_eq!(stats.htlc_hash, Some(factory.get_htlc_hash()));
+        assert_eq!(stats.htlc_version, 1);
+        assert_eq!((stats.rate_limit, stats.min_deposit_amount, stats.min_deposit_bps), (3, 5, 10));
+        assert_eq!((stats.escrows_deployed, stats.token_policies), (1, 1));
+    }
+
+    #[test]
+    fn test_verify_htlc_code_pins_the_escrow_code_and_deploys_report_it() {
+        use soroban_sdk::{testutils::Events, FromVal};
+
+        let env = Env::default();
+        let (factory, _) = setup(&env);
+        let htlc_hash = factory.get_htlc_hash();
+        factory.verify_htlc_code(&htlc_hash);
+
+        let escrow = deploy(&factory, &immutables(&env, &Address::generate(&env), 1)).unwrap();
+        let topics: Vec<Val> = (Symbol::new(&env, "escrow_code"), htlc_hash.clone()).into_val(&env);
+        let event = env.events().all().iter().find(|(contract, event_topics, _)| {
+            *contract == factory.address && *event_topics == topics
+        });
+        let (_, _, data) = event.expect("escrow_code event for the deployed code");
+        assert_eq!(Address::from_val(&env, &data), escrow);
+
+        let other_hash = BytesN::from_array(&env, &[5; 32]);
+        assert_eq!(factory.try_verify_htlc_code(&other_hash).err(), Some(Ok(Error::HtlcCodeMismatch)));
+        factory.update_htlc_hash(&other_hash);
+        factory.verify_htlc_code(&other_hash);
+        assert_eq!(factory.try_verify_htlc_code(&htlc_hash).err(), Some(Ok(Error::HtlcCodeMismatch)));
+    }
+
+    #[test]
+    fn test_deployment_info_records_the_purchased_ttl_and_follows_extensions() {
+        let env = Env::default();
+        env.ledger().with_mut(|li| {
+            li.sequence_number = 1_000;
+            li.min_persistent_entry_ttl = 2_000_000;
+            li.max_entry_ttl = 3_000_000;
+        });
+        let (factory, _) = setup(&env);
+        let mut immutables = immutables(&env, &Address::generate(&env), 1);
+        immutables.token = setup_token(&env, &immutables.maker, 0);
+        let salt = factory.derive_salt(&immutables);
+        assert!(factory.get_deployment_info(&salt).is_none());
+
+        let escrow = deploy(&factory, &immutables).unwrap();
+        let info = factory.get_deployment_info(&salt).unwrap();
+        assert_eq!((info.escrow, info.deployed_at), (escrow.clone(), 1_000));
+        assert!(info.ttl_purchased > 0);
+        assert_eq!(info.expires_at, info.deployed_at + info.ttl_purchased);
+
+        // Past the escrow's extension threshold (23 days of ledgers), extending moves the expiry
+        token::StellarAssetClient::new(&env, &immutables.token).mint(&escrow, &immutables.amount);
+        let _: () = env.invoke_contract(&escrow, &Symbol::new(&env, "confirm_funding"), vec![&env]);
+        env.ledger().with_mut(|li| li.sequence_number += 23 * 17_280 + 1);
+        factory.extend_escrow_storage(&Address::generate(&env), &salt);
+        let extended = factory.get_deployment_info(&salt).unwrap();
+        assert!(extended.expires_at > info.expires_at);
+        assert_eq!((extended.deployed_at, extended.ttl_purchased), (info.deployed_at, info.ttl_purchased));
+    }
+}
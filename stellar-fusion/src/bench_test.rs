@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod bench_tests {
+    extern crate std;
+
+    use crate::testutils::{self, EscrowFixture};
+    use crate::types::{DST_CANCELLATION_TIMELOCK, DST_WITHDRAWAL_TIMELOCK};
+    use crate::{StellarEscrow, StellarEscrowClient};
+    use soroban_sdk::{testutils::Address as _, Address, BytesN, Env};
+
+    // Budgets per entrypoint invocation, far below the Soroban per-transaction
+    // limits (100M instructions, 40MB memory)
+    // Native test runs underestimate wasm costs, so the budgets track regressions
+    // relative to current native costs (~300k instructions, ~40KB for payouts)
+    const MAX_CPU_INSTRUCTIONS: u64 = 2_000_000;
+    const MAX_MEMORY_BYTES: u64 = 400_000;
+
+    /// Measure a single invocation and fail if it exceeds the budget
+    fn measure(env: &Env, name: &str, call: impl FnOnce()) {
+        env.cost_estimate().budget().reset_default();
+        call();
+
+        let budget = env.cost_estimate().budget();
+        let cpu = budget.cpu_instruction_cost();
+        let mem = budget.memory_bytes_cost();
+        std::println!("{name}: {cpu} cpu instructions, {mem} memory bytes");
+
+        assert!(cpu <= MAX_CPU_INSTRUCTIONS, "{name} used {cpu} cpu instructions");
+        assert!(mem <= MAX_MEMORY_BYTES, "{name} used {mem} memory bytes");
+    }
+
+    fn funded_escrow(env: &Env) -> EscrowFixture {
+        testutils::create_funded_escrow(env, 1000i128)
+    }
+
+    #[test]
+    fn bench_deploy() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token = Address::generate(&env);
+        let order_hash = BytesN::from_array(&env, &[1u8; 32]);
+        let (_, hashlock) = testutils::secret_and_hashlock(&env, 42);
+
+        measure(&env, "deploy", || {
+            client.deploy(
+                &order_hash,
+                &hashlock,
+                &maker,
+                &taker,
+                &token,
+                &1000i128,
+                &0i128,
+                &testutils::default_timelocks(),
+            );
+        });
+    }
+
+    #[test]
+    fn bench_withdraw() {
+        let env = Env::default();
+        let fixture = funded_escrow(&env);
+        let client = fixture.client(&env);
+
+        testutils::advance_to_stage(&env, fixture.timelocks, DST_WITHDRAWAL_TIMELOCK);
+        measure(&env, "withdraw", || client.withdraw(&fixture.secret, &false));
+    }
+
+    #[test]
+    fn bench_withdraw_partial() {
+        let env = Env::default();
+        let fixture = funded_escrow(&env);
+        let client = fixture.client(&env);
+
+        testutils::advance_to_stage(&env, fixture.timelocks, DST_WITHDRAWAL_TIMELOCK);
+        measure(&env, "withdraw_partial", || client.withdraw_partial(&fixture.secret, &400i128));
+    }
+
+    #[test]
+    fn bench_cancel() {
+        let env = Env::default();
+        let fixture = funded_escrow(&env);
+        let client = fixture.client(&env);
+
+        testutils::advance_to_stage(&env, fixture.timelocks, DST_CANCELLATION_TIMELOCK);
+        measure(&env, "cancel", || client.cancel(&fixture.maker));
+    }
+}
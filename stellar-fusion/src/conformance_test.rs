@@ -0,0 +1,237 @@
+// Vectors are generated with the default sha256 escrow hash
+#[cfg(all(test, not(any(feature = "hash-keccak256", feature = "hash-sha3"))))]
+mod conformance {
+    extern crate std;
+
+    use std::{format, string::{String as StdString, ToString}, vec::Vec as StdVec};
+
+    use crate::timelocks::{get_timelock, pack_timelocks, STAGE_COUNT};
+    use crate::types::Immutables;
+    use fusion_shared::conversion;
+    use serde_json::{json, Value};
+    use soroban_sdk::{testutils::Ledger, Address, Bytes, BytesN, Env};
+
+    /// Reference vectors published for the TypeScript and EVM implementations
+    const VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/conformance.json");
+    const REFERENCE_VECTORS: &str = include_str!("../test-vectors/conformance.json");
+
+    /// Escrow addresses depend on the network; vectors use testnet
+    const NETWORK_PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    const STAGE_NAMES: [&str; 7] = [
+        "src_withdrawal",
+        "src_public_withdrawal",
+        "src_cancellation",
+        "src_public_cancellation",
+        "dst_withdrawal",
+        "dst_public_withdrawal",
+        "dst_cancellation",
+    ];
+
+    /// Inputs of one vector
+    struct Case {
+        name: &'static str,
+        secret: [u8; 32],
+        order_hash: [u8; 32],
+        maker: &'static str,
+        taker: &'static str,
+        token: &'static str,
+        factory: &'static str,
+        amount: i128,
+        safety_deposit: i128,
+        stages: [u32; 7],
+    }
+
+    const MAKER: &str = "GAIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCF6M";
+    const TAKER: &str = "GARCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCFRVX";
+    const FACTORY: &str = "CAZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGMZTGGJH";
+    const TOKEN: &str = "CBCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEIRCEJ5HZ";
+    const NATIVE: &str = "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC";
+
+    const CASES: [Case; 3] = [
+        Case {
+            name: "fixture_defaults",
+            secret: [42; 32],
+            order_hash: [1; 32],
+            maker: MAKER,
+            taker: TAKER,
+            token: TOKEN,
+            factory: FACTORY,
+            amount: 1000,
+            safety_deposit: 0,
+            stages: [10, 20, 30, 40, 10, 20, 30],
+        },
+        Case {
+            name: "native_with_deposit",
+            secret: [7; 32],
+            order_hash: [0xab; 32],
+            maker: MAKER,
+            taker: TAKER,
+            token: NATIVE,
+            factory: FACTORY,
+            amount: 10_000_000,
+            safety_deposit: 1_000_000,
+            stages: [12, 24, 60, 120, 6, 18, 48],
+        },
+        Case {
+            name: "max_values",
+            secret: [0xff; 32],
+            order_hash: [0xfe; 32],
+            maker: TAKER,
+            taker: MAKER,
+            token: TOKEN,
+            factory: FACTORY,
+            amount: i128::MAX,
+            safety_deposit: i128::MAX,
+            stages: [255; 7],
+        },
+    ];
+
+    fn test_env() -> Env {
+        let env = Env::default();
+        let network_id = env.crypto().sha256(&Bytes::from_slice(&env, NETWORK_PASSPHRASE.as_bytes()));
+        env.ledger().set_network_id(network_id.to_array());
+        env
+    }
+
+    fn hex32(value: &BytesN<32>) -> StdString {
+        hex::encode(value.to_array())
+    }
+
+    fn strkey(address: &Address) -> StdString {
+        let text = address.to_string();
+        let mut buf = std::vec![0u8; text.len() as usize];
+        text.copy_into_slice(&mut buf);
+        StdString::from_utf8(buf).unwrap()
+    }
+
+    fn parse_hex32(env: &Env, value: &Value) -> BytesN<32> {
+        let mut raw = [0u8; 32];
+        hex::decode_to_slice(value.as_str().unwrap(), &mut raw).unwrap();
+        BytesN::from_array(env, &raw)
+    }
+
+    fn parse_int(value: &Value) -> i128 {
+        value.as_str().unwrap().parse().unwrap()
+    }
+
+    /// Compute the outputs of a vector from its inputs
+    fn outputs(env: &Env, inputs: &Value) -> Value {
+        let secret = parse_hex32(env, &inputs["secret"]);
+        let address = |key: &str| Address::from_str(env, inputs[key].as_str().unwrap());
+        let stages: StdVec<u32> = STAGE_NAMES
+            .iter()
+            .map(|name| inputs["timelocks"][*name].as_u64().unwrap() as u32)
+            .collect();
+        let timelocks = pack_timelocks(stages.try_into().unwrap());
+
+        let immutables = Immutables {
+            order_hash: parse_hex32(env, &inputs["order_hash"]),
+            hashlock: crate::hash_secret(env, &Bytes::from(secret)),
+            maker: address("maker"),
+            taker: address("taker"),
+            token: address("token"),
+            amount: parse_int(&inputs["amount"]),
+            safety_deposit: parse_int(&inputs["safety_deposit"]),
+            timelocks,
+            max_secret_len: 32,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: crate::types::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: crate::types::DEFAULT_FLAGS,
+            parts: 0,
+        };
+        let salt = conversion::derive_salt(
+            env,
+            &immutables.order_hash,
+            &immutables.hashlock,
+            &immutables.maker,
+            &immutables.taker,
+            &immutables.token,
+            immutables.amount,
+            immutables.safety_deposit,
+            immutables.timelocks,
+        );
+        let escrow = env.deployer().with_address(address("factory"), salt.clone()).deployed_address();
+
+        let schedule: serde_json::Map<StdString, Value> = (0..STAGE_COUNT)
+            .map(|stage| (STAGE_NAMES[stage as usize].into(), json!(get_timelock(timelocks, stage))))
+            .collect();
+
+        json!({
+            "hashlock": hex32(&immutables.hashlock),
+            "immutables_hash": hex32(&immutables.hash(env)),
+            "timelocks": timelocks.to_string(),
+            "schedule": schedule,
+            "salt": hex32(&salt),
+            "escrow_address": strkey(&escrow),
+        })
+    }
+
+    fn case_inputs(case: &Case) -> Value {
+        let timelocks: serde_json::Map<StdString, Value> = STAGE_NAMES
+            .iter()
+            .zip(case.stages)
+            .map(|(name, value)| ((*name).into(), json!(value)))
+            .collect();
+        json!({
+            "secret": hex::encode(case.secret),
+            "order_hash": hex::encode(case.order_hash),
+            "maker": case.maker,
+            "taker": case.taker,
+            "token": case.token,
+            "factory": case.factory,
+            "amount": case.amount.to_string(),
+            "safety_deposit": case.safety_deposit.to_string(),
+            "timelocks": timelocks,
+        })
+    }
+
+    /// The full vector file generated from CASES
+    fn generate() -> StdString {
+        let env = test_env();
+        let vectors: StdVec<Value> = CASES
+            .iter()
+            .map(|case| {
+                let inputs = case_inputs(case);
+                json!({ "name": case.name, "outputs": outputs(&env, &inputs), "inputs": inputs })
+            })
+            .collect();
+        let file = json!({
+            "hash": "sha256",
+            "network_passphrase": NETWORK_PASSPHRASE,
+            "vectors": vectors,
+        });
+        format!("{}\n", serde_json::to_string_pretty(&file).unwrap())
+    }
+
+    /// Regenerate with `UPDATE_VECTORS=1 cargo test conformance`
+    #[test]
+    fn test_reference_vectors_up_to_date() {
+        let generated = generate();
+        if std::env::var_os("UPDATE_VECTORS").is_some() {
+            std::fs::write(VECTORS_PATH, &generated).unwrap();
+            return;
+        }
+        assert_eq!(generated, REFERENCE_VECTORS, "test-vectors/conformance.json is stale");
+    }
+
+    #[test]
+    fn test_conformance_with_reference_vectors() {
+        let env = test_env();
+        let file: Value = serde_json::from_str(REFERENCE_VECTORS).unwrap();
+        assert_eq!(file["hash"], "sha256");
+        assert_eq!(file["network_passphrase"], NETWORK_PASSPHRASE);
+
+        let vectors = file["vectors"].as_array().unwrap();
+        assert!(!vectors.is_empty());
+        for vector in vectors {
+            assert_eq!(outputs(&env, &vector["inputs"]), vector["outputs"], "vector {}", vector["name"]);
+        }
+    }
+}
@@ -1,24 +1,53 @@
 use soroban_sdk::contracterror;
 
+/// Escrow errors use the 100+ range (see fusion_shared::errors)
 #[contracterror]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
 pub enum Error {
-    InvalidState = 1,
-    InvalidSecret = 2,
-    TimelockNotExpired = 3,
-    CannotCancel = 4,
-    InvalidAmount = 5,
-    InvalidAddress = 6,
-    AlreadyInitialized = 7,
-    NotInitialized = 8,
-    InsufficientBalance = 9,
-    UnauthorizedCaller = 10,
+    InvalidState = 101,
+    InvalidSecret = 102,
+    TimelockNotExpired = 103,
+    CannotCancel = 104,
+    InvalidAmount = 105,
+    InvalidAddress = 106,
+    AlreadyInitialized = 107,
+    NotInitialized = 108,
+    InsufficientBalance = 109,
+    UnauthorizedCaller = 110,
+    OverFunded = 111,
+    AlreadyFunded = 112,
+    InvalidEvmAddress = 113,
+    DetailsTooLong = 114,
+    InvalidSecretLength = 115,
+    FinalizeTooEarly = 116,
+    MemoTooLong = 117,
+    SecondSecretRequired = 118,
+    DuplicateHashlock = 119,
+    SecretAlreadyUsed = 120,
+    BeneficiaryMismatch = 121,
+    InvalidIncentives = 122,
+    RecipientCannotReceive = 123,
+    MakerIsTaker = 124,
+    ZeroHashlock = 125,
+    ZeroOrderHash = 126,
+    ExecutionClaimed = 127,
+    ExecutorLocked = 128,
+    NotFinal = 129,
+    BatchTooLarge = 130,
+    EscrowNotEmpty = 131,
+    TimelocksNotLater = 132,
+    AlreadyExecuted = 133,
+    FlagDisabled = 134,
+    InvalidFlags = 135,
+    InvalidImmutablesVersion = 136,
+    InvalidPartialFill = 137,
 }
 
+/// Abort with a contract error whose code surfaces as `Error(Contract, #code)` in the HostError
 #[macro_export]
 macro_rules! panic_with_error {
     ($env:expr, $error:expr) => {
-        panic!("{:?}", $error)
+        soroban_sdk::panic_with_error!($env, $error)
     };
 }
\ No newline at end of file
@@ -1,28 +1,231 @@
-use soroban_sdk::{Address, BytesN, Env, symbol_short};
+use soroban_sdk::{Address, Bytes, BytesN, Env, IntoVal, Symbol, Val, symbol_short};
+use soroban_sdk::events::Topics;
+use crate::storage;
+use crate::types::{PayoutMemo, State};
+
+/// Version of the escrow event layout, returned by `get_event_schema_version`
+/// Bump it whenever an event's topics or data change, so indexers can tell payloads of old and
+/// new escrow code apart during an upgrade
+pub const EVENT_SCHEMA_VERSION: u32 = 3;
+
+/// First topic of every escrow event; unversioned (v1) events start with `escrow`
+const SCHEMA: Symbol = symbol_short!("v3");
+
+/// Publish an event whose data is `(swap_id, data)`, so every event of a swap carries the id
+/// that correlates it with the other chain (None for escrows deployed without one)
+fn publish<T: Topics, D: IntoVal<Env, Val>>(env: &Env, topics: T, data: D) {
+    let data: Val = data.into_val(env);
+    fusion_shared::events::publish(env, topics, (storage::get_swap_id(env), data));
+}
 
 /// Emit when escrow is created
 pub fn emit_escrow_created(env: &Env, order_hash: &BytesN<32>, maker: &Address, taker: &Address) {
     // Create a tuple of the event data
     let event_data = (order_hash.clone(), maker.clone(), taker.clone());
     
-    env.events().publish(
-        (symbol_short!("escrow"), symbol_short!("created")),
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("created")),
         event_data,
     );
 }
 
+/// Emit when a metadata blob is attached to the escrow
+pub fn emit_escrow_details(env: &Env, details: &Bytes) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("details")),
+        details.clone(),
+    );
+}
+
 /// Emit when secret is revealed
-pub fn emit_secret_revealed(env: &Env, secret: &BytesN<32>) {
-    env.events().publish(
-        (symbol_short!("escrow"), symbol_short!("withdraw")),
+/// The hashlock is a topic so relayers, who know it in advance, can filter for their swap
+pub fn emit_secret_revealed(env: &Env, hashlock: &BytesN<32>, secret: &BytesN<32>) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("withdraw"), hashlock.clone()),
         secret.clone(),
     );
 }
 
-/// Emit when escrow is cancelled
-pub fn emit_escrow_cancelled(env: &Env) {
-    env.events().publish(
-        (symbol_short!("escrow"), symbol_short!("cancel")),
-        (),
+/// Emit when a variable-length preimage is revealed (same layout as emit_secret_revealed)
+pub fn emit_preimage_revealed(env: &Env, hashlock: &BytesN<32>, preimage: &Bytes) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("withdraw"), hashlock.clone()),
+        preimage.clone(),
+    );
+}
+
+/// Emit the second secret revealed by withdraw2 (the first goes in escrow/withdraw)
+pub fn emit_second_secret_revealed(env: &Env, secret: &BytesN<32>) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("secret_b")),
+        secret.clone(),
+    );
+}
+
+/// Emit when funding is recorded by pull_funding (with the funder) or confirm_funding
+/// Signals the counterparty that it is safe to reveal the secret
+pub fn emit_escrow_funded(env: &Env, amount: i128, token: &Address, funder: Option<Address>, total_funded: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("funded")),
+        (amount, token.clone(), funder, total_funded),
+    );
+}
+
+/// Emit when withdraw_to pays a recipient other than the taker, with its memo
+pub fn emit_withdraw_to(env: &Env, payout: &PayoutMemo) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("payout")),
+        (payout.recipient.clone(), payout.muxed_id, payout.memo.clone()),
+    );
+}
+
+/// Emit when a tranche is withdrawn from a partially filled escrow
+pub fn emit_partial_withdraw(env: &Env, secret: &BytesN<32>, amount: i128, filled: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("partial")),
+        (secret.clone(), amount, filled),
+    );
+}
+
+/// Emit when escrow is cancelled, with the refunded remainder and the amount already filled
+pub fn emit_escrow_cancelled(env: &Env, refunded: i128, filled: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("cancel")),
+        (refunded, filled),
+    );
+}
+
+/// Emit when refresh_state marks a timed-out escrow as expired
+pub fn emit_escrow_expired(env: &Env) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("expired")),
+        env.ledger().timestamp(),
+    );
+}
+
+/// Emit when finalize reclaims the escrow's storage, with the terminal state it keeps
+pub fn emit_escrow_finalized(env: &Env, state: State) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("finalized")),
+        (state as u32, env.ledger().timestamp()),
+    );
+}
+
+/// Emit when sweep_surplus pays leftover balances to the sink
+pub fn emit_surplus_swept(env: &Env, sink: &Address, token_amount: i128, native_amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("swept"), sink.clone()),
+        (token_amount, native_amount),
+    );
+}
+
+/// Emit when an executor claims exclusive public_withdraw, with the lock's last ledger
+pub fn emit_execution_claimed(env: &Env, executor: &Address, until: u32) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("exec_lock"), executor.clone()),
+        until,
+    );
+}
+
+/// Emit when extend_storage extends the escrow's TTL, with whether it was due
+pub fn emit_storage_extended(env: &Env, due: bool) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), symbol_short!("extended")),
+        (due, env.ledger().sequence()),
+    );
+}
+
+/// Emit when maker and taker push the escrow's deadlines later, with the old and new timelocks
+pub fn emit_timelocks_extended(env: &Env, old_timelocks: u64, new_timelocks: u64) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "timelocks_extended")),
+        (old_timelocks, new_timelocks),
+    );
+}
+
+/// Emit when the escrow enters a new timelock stage
+pub fn emit_stage_transition(env: &Env, stage: u32, stage_start: u64) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "stage_transition")),
+        (stage, stage_start, env.ledger().timestamp()),
+    );
+}
+
+/// Emit when an inline safety-deposit transfer fails and the deposit is left claimable
+pub fn emit_deposit_failed(env: &Env, recipient: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "deposit_failed")),
+        (recipient.clone(), amount),
+    );
+}
+
+/// Emit when a withdrawal's recipient can't receive the token and the payout is held for retry_payout
+pub fn emit_payout_failed(env: &Env, recipient: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "payout_failed")),
+        (recipient.clone(), amount),
     );
-}
\ No newline at end of file
+}
+
+/// Emit when an integration hook fails; the escrow call went through without it
+pub fn emit_hook_failed(env: &Env, hook: &Address, function: &str) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "hook_failed")),
+        (hook.clone(), Symbol::new(env, function)),
+    );
+}
+
+/// Emit when retry_payout pays a held withdrawal to the taker's new target
+pub fn emit_payout_retried(env: &Env, recipient: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "payout_retried")),
+        (recipient.clone(), amount),
+    );
+}
+
+/// Emit when a third-party executor is credited a claimable share of the safety deposit
+pub fn emit_reward_earned(env: &Env, keeper: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "reward_earned")),
+        (keeper.clone(), amount),
+    );
+}
+
+/// Emit when a keeper collects its rewards
+pub fn emit_rewards_claimed(env: &Env, keeper: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "rewards_claimed")),
+        (keeper.clone(), amount),
+    );
+}
+
+/// Emit when a previously failed safety deposit is claimed
+pub fn emit_deposit_claimed(env: &Env, recipient: &Address, amount: i128) {
+    publish(
+        env,
+        (SCHEMA, symbol_short!("escrow"), Symbol::new(env, "deposit_claimed")),
+        (recipient.clone(), amount),
+    );
+}
@@ -0,0 +1,89 @@
+use soroban_sdk::{contracttype, Bytes, BytesN, Env, String};
+use crate::errors::Error;
+
+/// A 20-byte Ethereum (EVM) account or contract address
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct EvmAddress(pub BytesN<20>);
+
+impl EvmAddress {
+    /// Build an address from exactly 20 raw bytes
+    pub fn from_bytes(env: &Env, bytes: &Bytes) -> Result<Self, Error> {
+        if bytes.len() != 20 {
+            return Err(Error::InvalidEvmAddress);
+        }
+        let mut raw = [0u8; 20];
+        bytes.copy_into_slice(&mut raw);
+        Ok(EvmAddress(BytesN::from_array(env, &raw)))
+    }
+
+    /// Parse a `0x`-prefixed hex address
+    /// Mixed-case input must carry a valid EIP-55 checksum; all-lowercase or
+    /// all-uppercase input is accepted as unchecksummed
+    pub fn from_hex(env: &Env, hex: &String) -> Result<Self, Error> {
+        if hex.len() != 42 {
+            return Err(Error::InvalidEvmAddress);
+        }
+        let mut text = [0u8; 42];
+        hex.copy_into_slice(&mut text);
+        if text[0] != b'0' || (text[1] != b'x' && text[1] != b'X') {
+            return Err(Error::InvalidEvmAddress);
+        }
+
+        let digits = &text[2..];
+        let mut raw = [0u8; 20];
+        for (i, pair) in digits.chunks(2).enumerate() {
+            raw[i] = (hex_value(pair[0])? << 4) | hex_value(pair[1])?;
+        }
+
+        let address = EvmAddress(BytesN::from_array(env, &raw));
+        let has_lower = digits.iter().any(|c| c.is_ascii_lowercase());
+        let has_upper = digits.iter().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper && address.checksum_hex(env)[2..] != *digits {
+            return Err(Error::InvalidEvmAddress);
+        }
+
+        Ok(address)
+    }
+
+    /// Whether this is the zero address
+    pub fn is_zero(&self) -> bool {
+        self.0.to_array() == [0u8; 20]
+    }
+
+    /// Format as a `0x`-prefixed EIP-55 checksummed string
+    pub fn to_checksum_string(&self, env: &Env) -> String {
+        String::from_bytes(env, &self.checksum_hex(env))
+    }
+
+    /// EIP-55: uppercase each hex letter whose nibble in keccak256(lowercase hex) is >= 8
+    fn checksum_hex(&self, env: &Env) -> [u8; 42] {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+
+        let mut text = [0u8; 42];
+        text[0] = b'0';
+        text[1] = b'x';
+        for (i, byte) in self.0.to_array().iter().enumerate() {
+            text[2 + i * 2] = HEX[(byte >> 4) as usize];
+            text[3 + i * 2] = HEX[(byte & 0x0f) as usize];
+        }
+
+        let hash = env.crypto().keccak256(&Bytes::from_slice(env, &text[2..])).to_array();
+        for i in 0..40 {
+            let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+            if nibble >= 8 {
+                text[2 + i] = text[2 + i].to_ascii_uppercase();
+            }
+        }
+        text
+    }
+}
+
+fn hex_value(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidEvmAddress),
+    }
+}
@@ -32,6 +32,17 @@ impl EscrowFactory {
             amount,
             safety_deposit,
             timelocks,
+            max_secret_len: crate::SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: crate::types::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: crate::types::DEFAULT_FLAGS,
+            parts: 0,
         };
         
         // Calculate salt from immutables hash
@@ -82,6 +93,17 @@ impl EscrowFactory {
             amount,
             safety_deposit,
             timelocks,
+            max_secret_len: crate::SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: crate::types::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: crate::types::DEFAULT_FLAGS,
+            parts: 0,
         };
         
         // Calculate salt from immutables hash
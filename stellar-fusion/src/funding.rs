@@ -0,0 +1,183 @@
+use fusion_shared::trace;
+use soroban_sdk::{token::{StellarAssetClient, TokenClient}, xdr::ScErrorType, Address, Env};
+use crate::types::{EscrowBalances, Immutables, State};
+use crate::errors::Error;
+use crate::{get_native_token_address, is_native_token, panic_with_error, storage};
+
+/// Token balance the escrow must hold to be fully funded
+/// When the escrowed token is native XLM, the safety deposit shares the same balance
+pub fn required_token_balance(env: &Env, immutables: &Immutables) -> i128 {
+    if is_native_token(env, &immutables.token) {
+        immutables.amount + immutables.safety_deposit
+    } else {
+        immutables.amount
+    }
+}
+
+/// Whether the safety deposit is held in a separate native balance
+pub fn has_separate_deposit(env: &Env, immutables: &Immutables) -> bool {
+    immutables.safety_deposit > 0 && !is_native_token(env, &immutables.token)
+}
+
+/// Whether a native-token escrow's single balance covers every bucket it holds:
+/// the recorded amount, the recorded safety deposit and pending deposits
+/// Always true for other escrows and for unconfirmed funding, which settle derives from the balance
+pub fn is_native_backed(env: &Env, immutables: &Immutables) -> bool {
+    if !is_native_token(env, &immutables.token) || !storage::has_recorded_funding(env) {
+        return true;
+    }
+
+    let balance = TokenClient::new(env, &immutables.token).balance(&env.current_contract_address());
+    let owed = storage::get_funded_amount(env)
+        + storage::get_funded_deposit(env)
+        + storage::get_pending_total(env)
+        + storage::get_pending_payout(env);
+    balance >= owed
+}
+
+/// Fail closed before a payout that would spend one native bucket out of another
+pub fn ensure_native_backing(env: &Env, immutables: &Immutables) {
+    if !is_native_backed(env, immutables) {
+        panic_with_error!(env, Error::InsufficientBalance);
+    }
+}
+
+/// Whether `to` can currently receive `token`
+/// Stellar assets refuse transfers to accounts without a trustline and to deauthorized holders
+/// (authorization-required or revocable assets); native XLM and contract tokens without an
+/// `authorized` query are assumed to accept any recipient
+pub fn can_receive(env: &Env, token: &Address, to: &Address) -> bool {
+    if is_native_token(env, token) {
+        return true;
+    }
+    match StellarAssetClient::new(env, token).try_authorized(to) {
+        Ok(Ok(authorized)) => authorized,
+        // The asset contract reports a missing trustline as a contract error
+        Err(Ok(error)) => !error.is_type(ScErrorType::Contract),
+        _ => true,
+    }
+}
+
+/// Fail early with RecipientCannotReceive instead of an opaque token error mid-payout
+pub fn ensure_can_receive(env: &Env, token: &Address, to: &Address) {
+    if !can_receive(env, token, to) {
+        panic_with_error!(env, Error::RecipientCannotReceive);
+    }
+}
+
+/// Reject funding attempts on escrows that are already funded or closed
+pub fn ensure_unfunded(env: &Env) {
+    match storage::get_state(env) {
+        State::Active => {}
+        State::Funded => panic_with_error!(env, Error::AlreadyFunded),
+        _ => panic_with_error!(env, Error::InvalidState),
+    }
+}
+
+/// Get the funded amount and deposit a payout should operate on
+/// Escrows funded by pushing tokens without confirm_funding are recorded from
+/// their current balances, capped at the required amounts
+/// Must only be called on escrows that are open or expired
+pub fn settle(env: &Env, immutables: &Immutables) -> (i128, i128) {
+    if storage::has_recorded_funding(env) {
+        return (storage::get_funded_amount(env), storage::get_funded_deposit(env));
+    }
+
+    let escrow = env.current_contract_address();
+    let token_balance = TokenClient::new(env, &immutables.token).balance(&escrow);
+    let funded_amount = token_balance.min(immutables.amount);
+
+    let funded_deposit = if is_native_token(env, &immutables.token) {
+        (token_balance - funded_amount).min(immutables.safety_deposit)
+    } else if immutables.safety_deposit > 0 {
+        let native_balance = TokenClient::new(env, &get_native_token_address(env)).balance(&escrow);
+        native_balance.min(immutables.safety_deposit)
+    } else {
+        0
+    };
+    trace!(env, "settle", (token_balance, funded_amount, funded_deposit));
+
+    (funded_amount, funded_deposit)
+}
+
+/// Compare the escrow's balances with what it should hold in its current state
+/// Unconfirmed escrows should hold the full amount and deposit; funded ones their recorded
+/// funding; closed ones nothing. Pending deposits are always owed
+pub fn balances(env: &Env, immutables: &Immutables) -> EscrowBalances {
+    let escrow = env.current_contract_address();
+    let token_balance = TokenClient::new(env, &immutables.token).balance(&escrow);
+    let native_balance = if is_native_token(env, &immutables.token) {
+        token_balance
+    } else {
+        // The native asset contract may be absent in test environments
+        match TokenClient::new(env, &get_native_token_address(env)).try_balance(&escrow) {
+            Ok(Ok(balance)) => balance,
+            _ => 0,
+        }
+    };
+
+    let recorded_amount = storage::get_funded_amount(env);
+    let recorded_deposit = storage::get_funded_deposit(env);
+    let (owed_amount, owed_deposit) = match storage::get_state(env) {
+        State::Active => (immutables.amount, immutables.safety_deposit),
+        State::Funded | State::Expired if storage::has_recorded_funding(env) => (recorded_amount, recorded_deposit),
+        State::Expired => (immutables.amount, immutables.safety_deposit),
+        _ => (0, 0),
+    };
+    let owed_amount = owed_amount + storage::get_pending_payout(env);
+    let owed_deposit = owed_deposit + storage::get_pending_total(env);
+
+    let (token_surplus, native_surplus) = if is_native_token(env, &immutables.token) {
+        (token_balance - owed_amount - owed_deposit, 0)
+    } else {
+        (token_balance - owed_amount, native_balance - owed_deposit)
+    };
+
+    EscrowBalances {
+        token_balance,
+        native_balance,
+        recorded_amount,
+        recorded_deposit,
+        token_surplus,
+        native_surplus,
+    }
+}
+
+/// Amount and safety deposit the escrow holds on behalf of the swap
+/// Unconfirmed escrows that can still pay out hold the full amounts, which settle takes from
+/// their balances
+fn held(env: &Env, immutables: &Immutables) -> (i128, i128) {
+    if storage::has_recorded_funding(env) {
+        (storage::get_funded_amount(env), storage::get_funded_deposit(env))
+    } else if storage::get_state(env).is_cancellable() {
+        (immutables.amount, immutables.safety_deposit)
+    } else {
+        (0, 0)
+    }
+}
+
+/// Token balance above what the escrow holds on behalf of the swap
+pub fn token_excess(env: &Env, immutables: &Immutables) -> i128 {
+    let escrow = env.current_contract_address();
+    let balance = TokenClient::new(env, &immutables.token).balance(&escrow);
+
+    let (amount, deposit) = held(env, immutables);
+    let mut held = amount + storage::get_pending_payout(env);
+    if is_native_token(env, &immutables.token) {
+        held += deposit + storage::get_pending_total(env);
+    }
+
+    (balance - held).max(0)
+}
+
+/// Native balance above the held and pending safety deposits (separate deposit escrows only)
+pub fn native_excess(env: &Env, immutables: &Immutables) -> i128 {
+    if !has_separate_deposit(env, immutables) {
+        return 0;
+    }
+
+    let escrow = env.current_contract_address();
+    let balance = TokenClient::new(env, &get_native_token_address(env)).balance(&escrow);
+    let (_, deposit) = held(env, immutables);
+    (balance - deposit - storage::get_pending_total(env)).max(0)
+}
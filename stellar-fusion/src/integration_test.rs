@@ -20,14 +20,15 @@ mod integration_tests {
         
         // Setup token
         let token = env.register_stellar_asset_contract_v2(token_admin.clone());
-        let token_client = token::StellarAssetClient::new(&env, &token.address());
-        token_client.mint(&token_admin, &10000i128);
+        let token_admin_client = token::StellarAssetClient::new(&env, &token.address());
+        let token_client = token::TokenClient::new(&env, &token.address());
+        token_admin_client.mint(&token_admin, &10000i128);
         token_client.transfer(&token_admin, &maker, &2000i128);
         
         // Create secret and hashlock
         let secret = BytesN::from_array(&env, &[42u8; 32]);
         let secret_bytes = Bytes::from(secret.clone());
-        let hashlock = env.crypto().sha256(&secret_bytes).to_bytes();
+        let hashlock = crate::hash_secret(&env, &secret_bytes);
         
         // Setup timelock (allow immediate operations for testing)
         let timelocks = 0u64;
@@ -95,8 +96,9 @@ mod integration_tests {
         
         // Setup token and fund maker
         let token = env.register_stellar_asset_contract_v2(token_admin.clone());
-        let token_client = token::StellarAssetClient::new(&env, &token.address());
-        token_client.mint(&token_admin, &10000i128);
+        let token_admin_client = token::StellarAssetClient::new(&env, &token.address());
+        let token_client = token::TokenClient::new(&env, &token.address());
+        token_admin_client.mint(&token_admin, &10000i128);
         token_client.transfer(&token_admin, &maker, &2000i128);
         
         // Deploy escrow
@@ -192,9 +194,9 @@ mod integration_tests {
     
     // Helper function
     fn create_secret_and_hash(env: &Env) -> (BytesN<32>, BytesN<32>) {
-        let secret = BytesN::from_array(&env, &[42u8; 32]);
+        let secret = BytesN::from_array(env, &[42u8; 32]);
         let secret_bytes = Bytes::from(secret.clone());
-        let hashlock = env.crypto().sha256(&secret_bytes).to_bytes();
+        let hashlock = crate::hash_secret(env, &secret_bytes);
         (secret, hashlock)
     }
 }
\ No newline at end of file
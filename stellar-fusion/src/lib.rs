@@ -1,5 +1,7 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, Bytes};
+#![allow(clippy::too_many_arguments)]
+use fusion_shared::{hash::HashFunction, merkle, trace, EscrowHookClient, FinalityOracleClient};
+use soroban_sdk::{contract, contractimpl, symbol_short, Address, BytesN, Env, Bytes, MuxedAddress, Symbol, Vec};
 
 // Import modules
 mod types;
@@ -7,10 +9,31 @@ mod errors;
 mod events;
 mod storage;
 mod timelocks;
+mod funding;
+mod preflight;
+pub mod evm;
+pub mod testutils;
 
 use types::*;
 use errors::*;
 
+#[cfg(all(feature = "hash-keccak256", feature = "hash-sha3"))]
+compile_error!("enable at most one of the hash-keccak256 and hash-sha3 features");
+
+/// Hash used for hashlocks and the immutables hash, chosen at build time:
+/// sha256 by default, keccak256 with `hash-keccak256`, sha3-256 with `hash-sha3`
+#[cfg(not(any(feature = "hash-keccak256", feature = "hash-sha3")))]
+pub type EscrowHash = fusion_shared::hash::Sha256;
+#[cfg(feature = "hash-keccak256")]
+pub type EscrowHash = fusion_shared::hash::Keccak256;
+#[cfg(feature = "hash-sha3")]
+pub type EscrowHash = fusion_shared::hash::Sha3_256;
+
+/// Hash a secret or preimage for comparison with the hashlock
+pub(crate) fn hash_secret(env: &Env, data: &Bytes) -> BytesN<32> {
+    EscrowHash::digest(env, data)
+}
+
 // Native token address constants for different networks
 // These are placeholder addresses - replace with actual native token addresses
 #[allow(dead_code)]
@@ -18,6 +41,39 @@ const NATIVE_TOKEN_MAINNET: &str = "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RM
 #[allow(dead_code)]
 const NATIVE_TOKEN_TESTNET: &str = "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC";
 
+/// Maximum length of the metadata blob attached with deploy_with_details
+pub const MAX_DETAILS_LEN: u32 = 64;
+
+/// Length of secrets accepted by the withdraw fast path
+pub const SECRET_LEN: u32 = 32;
+
+/// Maximum preimage length an escrow can be deployed to accept
+pub const MAX_PREIMAGE_LEN: u32 = 64;
+
+/// Maximum length of a withdraw_to memo (the Stellar text memo limit)
+pub const MAX_MEMO_LEN: u32 = 28;
+
+/// Seconds after withdraw/cancel before finalize may reclaim storage
+pub const FINALIZE_GRACE_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+/// Most tranches one reveal_secrets_batch call may withdraw, bounding its proof checks
+pub const MAX_BATCH_REVEALS: u32 = 32;
+
+/// Ledgers of public_withdraw exclusivity claim_execution grants (about a minute)
+pub const EXECUTOR_LOCK_LEDGERS: u32 = 12;
+
+/// Entries get_action_log keeps; older ones are dropped first
+pub const MAX_ACTION_LOG: u32 = 64;
+
+/// Ledgers per day at the 5-second target close time
+const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// extend_storage extends the escrow's entries to this many ledgers
+pub const STORAGE_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+/// An open escrow whose estimated TTL is below this many ledgers is due for extension
+pub const STORAGE_TTL_THRESHOLD: u32 = 7 * DAY_IN_LEDGERS;
+
 #[contract]
 pub struct StellarEscrow;
 
@@ -36,154 +92,1317 @@ impl StellarEscrow {
         amount: i128,
         safety_deposit: i128,
         timelocks: u64,
+    ) -> Address {
+        Self::deploy_with_preimage_len(
+            env,
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            SECRET_LEN,
+        )
+    }
+    
+    /// Initialize a new escrow that also accepts preimages of up to `max_secret_len` bytes
+    /// via withdraw_preimage, for counterpart HTLCs that hash longer preimages
+    pub fn deploy_with_preimage_len(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        max_secret_len: u32,
     ) -> Address {
         // Verify this is the first deployment (contract not already initialized)
         if storage::is_initialized(&env) {
             panic_with_error!(&env, Error::AlreadyInitialized);
         }
         
+        if !(SECRET_LEN..=MAX_PREIMAGE_LEN).contains(&max_secret_len) {
+            panic_with_error!(&env, Error::InvalidSecretLength);
+        }
+        
         // Create immutables struct
         let immutables = Immutables {
-            order_hash: order_hash.clone(),
-            hashlock: hashlock.clone(),
-            maker: maker.clone(),
-            taker: taker.clone(),
-            token: token.clone(),
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
             amount,
             safety_deposit,
             timelocks,
+            max_secret_len,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
         };
 
-        // Store immutables
-        storage::set_immutables(&env, &immutables);
-        
-        // Initialize state
-        storage::set_state(&env, State::Active);
+        init_escrow(&env, &immutables)
+    }
+    
+    /// Initialize a new escrow locked by two distinct hashlocks, for three-party or circular
+    /// swaps where settlement needs secrets from two resolvers
+    /// Only withdraw2 can withdraw; cancellation works as usual
+    pub fn deploy_with_two_hashlocks(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock_a: BytesN<32>,
+        hashlock_b: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
         
-        // Emit creation event
-        events::emit_escrow_created(&env, &order_hash, &maker, &taker);
+        if hashlock_a == hashlock_b {
+            panic_with_error!(&env, Error::DuplicateHashlock);
+        }
         
-        // Return the contract's own address
-        env.current_contract_address()
+        let immutables = Immutables {
+            order_hash,
+            hashlock: hashlock_a,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            max_secret_len: SECRET_LEN,
+            hashlock_b: Some(hashlock_b),
+            beneficiary: None,
+            incentives: DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        init_escrow(&env, &immutables)
     }
     
+    /// Initialize a new escrow with an attached metadata blob (e.g. IPFS hash or order UID)
+    /// linking it back to the off-chain order record
+    pub fn deploy_with_details(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        details: Bytes,
+    ) -> Address {
+        if details.len() > MAX_DETAILS_LEN {
+            panic_with_error!(&env, Error::DetailsTooLong);
+        }
+
+        let escrow = Self::deploy(
+            env.clone(),
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+        );
+
+        storage::set_details(&env, &details);
+        events::emit_escrow_details(&env, &details);
+
+        escrow
+    }
+
+    /// Initialize a destination escrow whose withdrawals pay `beneficiary` (e.g. the resolver's
+    /// treasury) instead of the taker; the taker keeps signing as the resolver's operating key
+    /// The safety deposit still goes to the parties as usual
+    pub fn deploy_with_beneficiary(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        beneficiary: Address,
+    ) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+
+        let immutables = Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            max_secret_len: SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: Some(beneficiary),
+            incentives: DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        init_escrow(&env, &immutables)
+    }
+
+    /// Initialize an escrow whose safety deposit is split per action between the executor,
+    /// the maker and a protocol address (each split must sum to 10_000 bps)
+    pub fn deploy_with_incentives(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        incentives: DepositIncentives,
+    ) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+
+        if !incentives.is_valid() {
+            panic_with_error!(&env, Error::InvalidIncentives);
+        }
+
+        let immutables = Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            max_secret_len: SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: DepositPolicy::Split(incentives),
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        init_escrow(&env, &immutables)
+    }
+
+    /// Initialize an escrow for a swap sourced from a probabilistic-finality chain
+    /// Withdrawals also require `finality_oracle`'s `is_final(order_hash)` to return true, so
+    /// light-client or attestation oracles can vouch for the source lock
+    pub fn deploy_with_finality_oracle(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        finality_oracle: Address,
+    ) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+
+        let immutables = Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            max_secret_len: SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: Some(finality_oracle),
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        init_escrow(&env, &immutables)
+    }
+
+    /// Initialize an escrow that calls integration hooks around its funds, like LOP interactions
+    /// `pre_funding_hook`'s `before_funding` runs before funding is accepted and
+    /// `post_payout_hook`'s `after_payout` after each withdrawal payout (e.g. to convert the received
+    /// asset or notify another contract). A failing hook is reported, never reverts the escrow call
+    pub fn deploy_with_hooks(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+        pre_funding_hook: Option<Address>,
+        post_payout_hook: Option<Address>,
+    ) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+
+        let immutables = Immutables {
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+            max_secret_len: SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook,
+            post_payout_hook,
+            swap_id: None,
+            flags: DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        init_escrow(&env, &immutables)
+    }
+
+    /// Initialize a source-chain escrow holding the maker's funds (Stellar → EVM swaps)
+    /// Same parameters as deploy; differs in who may cancel (see get_side)
+    pub fn deploy_src(
+        env: Env,
+        order_hash: BytesN<32>,
+        hashlock: BytesN<32>,
+        maker: Address,
+        taker: Address,
+        token: Address,
+        amount: i128,
+        safety_deposit: i128,
+        timelocks: u64,
+    ) -> Address {
+        let escrow = Self::deploy(
+            env.clone(),
+            order_hash,
+            hashlock,
+            maker,
+            taker,
+            token,
+            amount,
+            safety_deposit,
+            timelocks,
+        );
+        storage::set_side(&env, EscrowSide::Src);
+        escrow
+    }
+
+    /// Initialize an escrow from v2 immutables, which carry the side, flags, receiver and fee
+    /// policy that v1 deploys take through set_flags and the deploy_* variants
+    /// `version` must be IMMUTABLES_V2_VERSION; set_skew_tolerance and set_swap_id still apply
+    pub fn deploy_v2(env: Env, immutables: ImmutablesV2) -> Address {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        if immutables.version != IMMUTABLES_V2_VERSION {
+            panic_with_error!(&env, Error::InvalidImmutablesVersion);
+        }
+        if immutables.flags & !ALL_FLAGS != 0 {
+            panic_with_error!(&env, Error::InvalidFlags);
+        }
+        if matches!(&immutables.fees, DepositPolicy::Split(incentives) if !incentives.is_valid()) {
+            panic_with_error!(&env, Error::InvalidIncentives);
+        }
+
+        storage::set_pending_flags(&env, immutables.flags);
+        storage::set_side(&env, immutables.side);
+        storage::set_immutables_version(&env, IMMUTABLES_V2_VERSION);
+        init_escrow(&env, &immutables.to_v1())
+    }
+
+    /// Set the clock-skew tolerance (seconds) recorded in the immutables by the next deploy call
+    /// The factory calls this in the same transaction as deploy; cancellation stages then open
+    /// `skew_tolerance` seconds after their timelock
+    pub fn set_skew_tolerance(env: Env, skew_tolerance: u32) {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        storage::set_pending_skew(&env, skew_tolerance);
+    }
+
+    /// Set the swap id recorded in the immutables by the next deploy call
+    /// The factory calls this in the same transaction as deploy when it knows the swap's route
+    /// (`fusion_shared::swap_id`); every event then carries the id
+    pub fn set_swap_id(env: Env, swap_id: BytesN<32>) {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        storage::set_pending_swap_id(&env, &swap_id);
+    }
+
+    /// Set the behavior flags (types::FLAG_*) recorded in the immutables by the next deploy call
+    /// Like set_skew_tolerance, called by the deployer in the same transaction as deploy; escrows
+    /// deployed without it get DEFAULT_FLAGS. Unknown bits fail with InvalidFlags
+    pub fn set_flags(env: Env, flags: u32) {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        if flags & !ALL_FLAGS != 0 {
+            panic_with_error!(&env, Error::InvalidFlags);
+        }
+        storage::set_pending_flags(&env, flags);
+    }
+
+    /// Set the parts count recorded in the immutables by the next deploy call, for a multi-secret
+    /// escrow whose hashlock is the Merkle root of `parts + 1` secrets
+    /// Like set_flags, called by the deployer in the same transaction as deploy; each fill must
+    /// then use the secret of the part it ends in. A zero count fails with InvalidPartialFill
+    pub fn set_parts(env: Env, parts: u32) {
+        if storage::is_initialized(&env) {
+            panic_with_error!(&env, Error::AlreadyInitialized);
+        }
+        if parts == 0 {
+            panic_with_error!(&env, Error::InvalidPartialFill);
+        }
+        storage::set_pending_parts(&env, parts);
+    }
+
+    /// Get the behavior flags the escrow was deployed with
+    pub fn get_flags(env: Env) -> u32 {
+        storage::get_immutables_field(&env, "flags")
+    }
+
+    /// Get the cross-chain swap id, if the deployer recorded one
+    pub fn get_swap_id(env: Env) -> Option<BytesN<32>> {
+        storage::get_swap_id(&env)
+    }
+
+    /// Get the swap leg this escrow holds
+    pub fn get_side(env: Env) -> EscrowSide {
+        storage::get_side(&env)
+    }
+
+    /// Get the metadata blob attached at deployment, if any
+    pub fn get_details(env: Env) -> Option<Bytes> {
+        storage::get_details(&env)
+    }
+
     /// Get the hash of the current escrow's immutables
-    /// This can be used by external contracts to verify the escrow address
+    /// This can be used by external contracts to verify the escrow address; escrows deployed
+    /// with deploy_v2 hash with their version byte and side (Immutables::hash_v2)
     pub fn get_immutables_hash(env: Env) -> BytesN<32> {
         let immutables = storage::get_immutables(&env);
-        immutables.hash(&env)
+        match storage::get_immutables_version(&env) {
+            IMMUTABLES_V2_VERSION => immutables.hash_v2(&env, storage::get_side(&env)),
+            _ => immutables.hash(&env),
+        }
+    }
+
+    /// Get the immutables in the v2 layout, whichever version the escrow was deployed with
+    /// v1 escrows report version 1, so decoders know which hash get_immutables_hash returns
+    pub fn get_immutables_v2(env: Env) -> ImmutablesV2 {
+        let immutables = storage::get_immutables(&env);
+        ImmutablesV2 {
+            version: storage::get_immutables_version(&env),
+            ..ImmutablesV2::from_v1(&immutables, storage::get_side(&env))
+        }
+    }
+
+    /// Get the immutables version: IMMUTABLES_V2_VERSION for deploy_v2 escrows, else v1
+    pub fn get_immutables_version(env: Env) -> u32 {
+        storage::get_immutables_version(&env)
+    }
+
+    /// Fund the escrow by pulling tokens from `from` using a prior token approval (`from` only)
+    /// The approval must cover the exact escrow amount (plus the safety deposit when
+    /// the escrowed token is native XLM)
+    pub fn pull_funding(env: Env, from: Address) {
+        from.require_auth();
+        funding::ensure_unfunded(&env);
+
+        let immutables = storage::get_immutables(&env);
+        funding::ensure_can_receive(&env, &immutables.token, &immutables.payout_recipient());
+        let escrow = env.current_contract_address();
+        let token = soroban_sdk::token::TokenClient::new(&env, &immutables.token);
+
+        // Tokens pushed beforehand must be refunded first (see refund_excess)
+        if token.balance(&escrow) > 0 {
+            panic_with_error!(&env, Error::OverFunded);
+        }
+
+        let required = funding::required_token_balance(&env, &immutables);
+        if token.allowance(&from, &escrow) < required {
+            panic_with_error!(&env, Error::InsufficientBalance);
+        }
+        call_pre_funding_hook(&env, &immutables);
+        token.transfer_from(&escrow, &from, &escrow, &required);
+
+        if funding::has_separate_deposit(&env, &immutables) {
+            let native = soroban_sdk::token::TokenClient::new(&env, &get_native_token_address(&env));
+            if native.allowance(&from, &escrow) < immutables.safety_deposit {
+                panic_with_error!(&env, Error::InsufficientBalance);
+            }
+            native.transfer_from(&escrow, &from, &escrow, &immutables.safety_deposit);
+        }
+
+        storage::set_funding(&env, immutables.amount, immutables.safety_deposit);
+        storage::set_state(&env, State::Funded);
+        log_action(&env, symbol_short!("fund"), &from, required);
+        events::emit_escrow_funded(&env, required, &immutables.token, Some(from), immutables.amount);
+    }
+
+    /// Confirm funding pushed directly to the escrow address
+    /// The escrow must hold exactly the required amount and safety deposit
+    pub fn confirm_funding(env: Env) {
+        funding::ensure_unfunded(&env);
+
+        let immutables = storage::get_immutables(&env);
+        funding::ensure_can_receive(&env, &immutables.token, &immutables.payout_recipient());
+        let escrow = env.current_contract_address();
+
+        let token_balance = soroban_sdk::token::TokenClient::new(&env, &immutables.token).balance(&escrow);
+        let required = funding::required_token_balance(&env, &immutables);
+        trace!(&env, "balance", (immutables.token.clone(), token_balance, required));
+        if token_balance < required {
+            panic_with_error!(&env, Error::InsufficientBalance);
+        }
+        if token_balance > required {
+            panic_with_error!(&env, Error::OverFunded);
+        }
+
+        if funding::has_separate_deposit(&env, &immutables) {
+            let native_balance = soroban_sdk::token::TokenClient::new(&env, &get_native_token_address(&env)).balance(&escrow);
+            trace!(&env, "balance", (get_native_token_address(&env), native_balance, immutables.safety_deposit));
+            if native_balance < immutables.safety_deposit {
+                panic_with_error!(&env, Error::InsufficientBalance);
+            }
+            if native_balance > immutables.safety_deposit {
+                panic_with_error!(&env, Error::OverFunded);
+            }
+        }
+
+        call_pre_funding_hook(&env, &immutables);
+        storage::set_funding(&env, immutables.amount, immutables.safety_deposit);
+        storage::set_state(&env, State::Funded);
+        log_action(&env, symbol_short!("fund"), &immutables.maker, token_balance);
+        events::emit_escrow_funded(&env, token_balance, &immutables.token, None, immutables.amount);
+    }
+
+    /// Return any balance above the funded amounts to the maker
+    /// Returns the refunded token amount
+    pub fn refund_excess(env: Env) -> i128 {
+        let immutables = storage::get_immutables(&env);
+
+        let token_excess = funding::token_excess(&env, &immutables);
+        if token_excess > 0 {
+            transfer_tokens(&env, &immutables.token, &immutables.maker, token_excess);
+        }
+
+        let native_excess = funding::native_excess(&env, &immutables);
+        if native_excess > 0 {
+            transfer_native(&env, &immutables.maker, native_excess);
+        }
+        if token_excess > 0 || native_excess > 0 {
+            log_action(&env, symbol_short!("refund"), &immutables.maker, token_excess);
+        }
+
+        token_excess
+    }
+
+    /// Get the funded token amount
+    pub fn get_funded_amount(env: Env) -> i128 {
+        storage::get_funded_amount(&env)
+    }
+
+    /// Get the funded safety deposit
+    pub fn get_funded_deposit(env: Env) -> i128 {
+        storage::get_funded_deposit(&env)
+    }
+
+    /// Get the total amount withdrawn so far
+    pub fn get_filled_amount(env: Env) -> i128 {
+        storage::get_filled_amount(&env)
+    }
+
+    /// Withdraw funds by revealing the secret
+    /// `unwrap_native` needs FLAG_UNWRAP_NATIVE
+    pub fn withdraw(env: Env, secret: BytesN<32>, unwrap_native: bool) {
+        require_not_executed(&env);
+        // Verify state is active
+        let state = storage::get_state(&env);
+        if !state.is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        // Get immutables
+        let immutables = storage::get_immutables(&env);
+        if unwrap_native {
+            require_flag(&env, &immutables, FLAG_UNWRAP_NATIVE);
+        }
+        require_single_hashlock(&env, &immutables);
+        
+        // Verify secret hash matches
+        let secret_bytes = Bytes::from(secret.clone());
+        let secret_hash = hash_secret(&env, &secret_bytes);
+        trace!(&env, "hash", (secret_hash.clone(), immutables.hashlock.clone()));
+        if secret_hash != immutables.hashlock {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        settle_withdrawal(&env, &immutables, &immutables.payout_recipient().into());
+        
+        // Emit event
+        storage::set_revealed_secret(&env, &secret.clone().into());
+        events::emit_secret_revealed(&env, &immutables.hashlock, &secret);
+    }
+
+    /// Withdraw by revealing the secret, paying a recipient chosen by the taker (taker only)
+    /// A muxed (M...) recipient carries its memo ID into the token transfer, as exchange
+    /// deposits require; `memo` is recorded and emitted for references that can't be attached
+    pub fn withdraw_to(env: Env, secret: BytesN<32>, recipient: MuxedAddress, memo: Option<Bytes>) {
+        require_not_executed(&env);
+        if !storage::get_state(&env).is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        immutables.taker.require_auth();
+
+        if memo.as_ref().is_some_and(|memo| memo.len() > MAX_MEMO_LEN) {
+            panic_with_error!(&env, Error::MemoTooLong);
+        }
+
+        // A designated beneficiary can't be redirected (memo IDs are still allowed)
+        if immutables.beneficiary.as_ref().is_some_and(|beneficiary| *beneficiary != recipient.address()) {
+            panic_with_error!(&env, Error::BeneficiaryMismatch);
+        }
+
+        require_single_hashlock(&env, &immutables);
+        let secret_hash = hash_secret(&env, &Bytes::from(secret.clone()));
+        trace!(&env, "hash", (secret_hash.clone(), immutables.hashlock.clone()));
+        if secret_hash != immutables.hashlock {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        // The taker chose the recipient, so it is checked up front rather than held for retry_payout
+        funding::ensure_can_receive(&env, &immutables.token, &recipient.address());
+        settle_withdrawal(&env, &immutables, &recipient);
+
+        let payout = PayoutMemo {
+            recipient: recipient.address(),
+            muxed_id: recipient.id(),
+            memo,
+        };
+        storage::set_payout_memo(&env, &payout);
+        events::emit_withdraw_to(&env, &payout);
+        storage::set_revealed_secret(&env, &secret.clone().into());
+        events::emit_secret_revealed(&env, &immutables.hashlock, &secret);
+    }
+
+    /// Get the recipient and memo of the last withdraw_to payout
+    pub fn get_payout_memo(env: Env) -> Option<PayoutMemo> {
+        storage::get_payout_memo(&env)
+    }
+
+    /// Withdraw a two-hashlock escrow by revealing both secrets
+    pub fn withdraw2(env: Env, secret_a: BytesN<32>, secret_b: BytesN<32>) {
+        require_not_executed(&env);
+        if !storage::get_state(&env).is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        let hashlock_b = match &immutables.hashlock_b {
+            Some(hashlock_b) => hashlock_b.clone(),
+            None => panic_with_error!(&env, Error::InvalidSecret),
+        };
+
+        let hash_a = hash_secret(&env, &Bytes::from(secret_a.clone()));
+        let hash_b = hash_secret(&env, &Bytes::from(secret_b.clone()));
+        if hash_a != immutables.hashlock || hash_b != hashlock_b {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        settle_withdrawal(&env, &immutables, &immutables.payout_recipient().into());
+
+        storage::set_revealed_secret(&env, &secret_a.clone().into());
+        storage::set_revealed_secret_b(&env, &secret_b);
+        events::emit_secret_revealed(&env, &immutables.hashlock, &secret_a);
+        events::emit_second_secret_revealed(&env, &secret_b);
+    }
+
+    /// Withdraw funds by revealing a variable-length preimage
+    /// The preimage may be up to the escrow's max_secret_len bytes
+    pub fn withdraw_preimage(env: Env, preimage: Bytes) {
+        require_not_executed(&env);
+        if !storage::get_state(&env).is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        
+        if preimage.is_empty() || preimage.len() > immutables.max_secret_len {
+            panic_with_error!(&env, Error::InvalidSecretLength);
+        }
+        require_single_hashlock(&env, &immutables);
+        if hash_secret(&env, &preimage) != immutables.hashlock {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        settle_withdrawal(&env, &immutables, &immutables.payout_recipient().into());
+        storage::set_revealed_secret(&env, &preimage);
+        events::emit_preimage_revealed(&env, &immutables.hashlock, &preimage);
+    }
+
+    /// Withdraw a tranche of a funded escrow by revealing the secret
+    /// The escrow stays open until the whole funded amount is filled, at which point
+    /// the safety deposit is returned to the maker as in withdraw
+    pub fn withdraw_partial(env: Env, secret: BytesN<32>, amount: i128) {
+        require_not_executed(&env);
+        // Partial fills operate on recorded funding only
+        if storage::get_state(&env) != State::Funded {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+
+        require_single_hashlock(&env, &immutables);
+
+        let secret_bytes = Bytes::from(secret.clone());
+        let secret_hash = hash_secret(&env, &secret_bytes);
+        trace!(&env, "hash", (secret_hash.clone(), immutables.hashlock.clone()));
+        if secret_hash != immutables.hashlock {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        if !timelocks::can_withdraw(&env, &storage::get_timelocks(&env, &immutables), false) {
+            panic_with_error!(&env, Error::TimelockNotExpired);
+        }
+        require_final(&env, &immutables);
+
+        fill_tranche(&env, &immutables, &secret, amount);
+    }
+
+    /// Withdraw a tranche of a multi-secret escrow, whose hashlock is the Merkle root of one
+    /// secret per fill (see fusion_shared::merkle); `proof` shows the secret's leaf at `index`
+    /// Each index can be used once, and must be the part the tranche ends in (see set_parts)
+    pub fn withdraw_partial_with_proof(
+        env: Env,
+        secret: BytesN<32>,
+        index: u64,
+        proof: Vec<BytesN<32>>,
+        amount: i128,
+    ) {
+        require_not_executed(&env);
+        if storage::get_state(&env) != State::Funded {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+
+        require_single_hashlock(&env, &immutables);
+
+        let secret_hash = hash_secret(&env, &Bytes::from(secret.clone()));
+        let leaf = merkle::secret_leaf(&env, index, &secret_hash);
+        trace!(&env, "leaf", (index, leaf.clone(), immutables.hashlock.clone()));
+        if !merkle::verify_proof(&env, &immutables.hashlock, &leaf, &proof) {
+            panic_with_error!(&env, Error::InvalidSecret);
+        }
+
+        if storage::get_used_indices(&env).contains(index) {
+            panic_with_error!(&env, Error::SecretAlreadyUsed);
+        }
+        require_part_boundary(&env, &immutables, storage::get_filled_amount(&env), amount, index);
+
+        if !timelocks::can_withdraw(&env, &storage::get_timelocks(&env, &immutables), false) {
+            panic_with_error!(&env, Error::TimelockNotExpired);
+        }
+        require_final(&env, &immutables);
+
+        storage::add_used_index(&env, index);
+        fill_tranche(&env, &immutables, &secret, amount);
     }
 
-    /// Withdraw funds by revealing the secret
-    pub fn withdraw(env: Env, secret: BytesN<32>, _unwrap_native: bool) {
-        // Verify state is active
-        let state = storage::get_state(&env);
-        if state != State::Active {
+    /// Withdraw several tranches of a multi-secret escrow in one call, e.g. a resolver settling
+    /// every fill of a finished auction; `root` must be the escrow's hashlock
+    /// Each reveal is checked as in withdraw_partial_with_proof and emits its own `escrow/partial`
+    /// event; the tranches are paid in one transfer. Returns the total withdrawn
+    pub fn reveal_secrets_batch(env: Env, root: BytesN<32>, reveals: Vec<SecretReveal>) -> i128 {
+        require_not_executed(&env);
+        if storage::get_state(&env) != State::Funded {
             panic_with_error!(&env, Error::InvalidState);
         }
+        if reveals.len() > MAX_BATCH_REVEALS {
+            panic_with_error!(&env, Error::BatchTooLarge);
+        }
 
-        // Get immutables
         let immutables = storage::get_immutables(&env);
-        
-        // Verify secret hash matches
-        let secret_bytes = Bytes::from(secret.clone());
-        let secret_hash = env.crypto().sha256(&secret_bytes);
-        if secret_hash.to_bytes() != immutables.hashlock {
+
+        require_single_hashlock(&env, &immutables);
+        if root != immutables.hashlock {
             panic_with_error!(&env, Error::InvalidSecret);
         }
 
-        // Check timelock
-        if !timelocks::can_withdraw(&env, &immutables.timelocks, false) {
+        if !timelocks::can_withdraw(&env, &storage::get_timelocks(&env, &immutables), false) {
             panic_with_error!(&env, Error::TimelockNotExpired);
         }
+        require_final(&env, &immutables);
 
-        // Transfer tokens to taker
-        transfer_tokens(&env, &immutables.token, &immutables.taker, immutables.amount);
-        
-        // Return safety deposit to maker if any
-        if immutables.safety_deposit > 0 {
-            transfer_native(&env, &immutables.maker, immutables.safety_deposit);
+        let filled_before = storage::get_filled_amount(&env);
+        let mut used = storage::get_used_indices(&env);
+        let mut total: i128 = 0;
+        for reveal in reveals.iter() {
+            let secret_hash = hash_secret(&env, &Bytes::from(reveal.secret.clone()));
+            let leaf = merkle::secret_leaf(&env, reveal.index, &secret_hash);
+            trace!(&env, "leaf", (reveal.index, leaf.clone(), root.clone()));
+            if !merkle::verify_proof(&env, &root, &leaf, &reveal.proof) {
+                panic_with_error!(&env, Error::InvalidSecret);
+            }
+            if used.contains(reveal.index) {
+                panic_with_error!(&env, Error::SecretAlreadyUsed);
+            }
+            if reveal.amount <= 0 {
+                panic_with_error!(&env, Error::InvalidAmount);
+            }
+            require_part_boundary(&env, &immutables, filled_before + total, reveal.amount, reveal.index);
+            used.push_back(reveal.index);
+            total = total.checked_add(reveal.amount).unwrap_or_else(|| panic_with_error!(&env, Error::InvalidAmount));
         }
 
-        // Update state
-        storage::set_state(&env, State::Withdrawn);
-        
-        // Emit event
-        events::emit_secret_revealed(&env, &secret);
+        settle_tranches(&env, &immutables, total);
+        storage::set_used_indices(&env, &used);
+
+        let mut filled = filled_before;
+        for reveal in reveals.iter() {
+            filled += reveal.amount;
+            events::emit_partial_withdraw(&env, &reveal.secret, reveal.amount, filled);
+        }
+        if let Some(last) = reveals.last() {
+            storage::set_revealed_secret(&env, &last.secret.into());
+        }
+        total
+    }
+
+    /// Get the last secret (or preimage) revealed by a withdrawal, so the other leg's resolver can
+    /// read it instead of parsing events; for multi-secret escrows, the latest fill's secret
+    pub fn get_revealed_secret(env: Env) -> Option<Bytes> {
+        storage::get_revealed_secret(&env)
+    }
+
+    /// Get the second secret revealed by withdraw2
+    pub fn get_revealed_secret_b(env: Env) -> Option<BytesN<32>> {
+        storage::get_revealed_secret_b(&env)
+    }
+
+    /// Get the fill indices withdrawn from a multi-secret escrow, in withdrawal order
+    pub fn get_revealed_indices(env: Env) -> Vec<u64> {
+        storage::get_used_indices(&env)
+    }
+
+    /// Check a sorted-pair keccak256 Merkle proof, as used by withdraw_partial_with_proof
+    pub fn verify_proof(env: Env, root: BytesN<32>, leaf: BytesN<32>, proof: Vec<BytesN<32>>) -> bool {
+        merkle::verify_proof(&env, &root, &leaf, &proof)
     }
 
     /// Cancel escrow and return funds
     pub fn cancel(env: Env, caller: Address) {
+        require_not_executed(&env);
         // Require authentication from the caller
         caller.require_auth();
         
-        // Verify state is active
+        // Verify state is active (or expired)
         let state = storage::get_state(&env);
-        if state != State::Active {
+        if !state.is_cancellable() {
             panic_with_error!(&env, Error::InvalidState);
         }
 
         // Get immutables
         let immutables = storage::get_immutables(&env);
+        if caller != immutables.maker && caller != immutables.taker {
+            require_flag(&env, &immutables, FLAG_ALLOW_PUBLIC_ACTIONS);
+        }
         
         // Check if cancellation is allowed
-        if !timelocks::can_cancel(&env, &immutables.timelocks, &caller, &immutables.maker, &immutables.taker) {
+        if !timelocks::can_cancel(&env, &storage::get_timelocks(&env, &immutables), immutables.skew_tolerance, storage::get_side(&env), &caller, &immutables.maker, &immutables.taker) {
             panic_with_error!(&env, Error::CannotCancel);
         }
 
-        // Return tokens to maker
-        transfer_tokens(&env, &immutables.token, &immutables.maker, immutables.amount);
+        // Return the unfilled remainder to maker; filled tranches stay with the taker
+        mark_executed(&env, EXECUTED_CANCEL);
+        funding::ensure_native_backing(&env, &immutables);
+        let (amount, deposit) = funding::settle(&env, &immutables);
+        if amount > 0 {
+            funding::ensure_can_receive(&env, &immutables.token, &immutables.maker);
+            transfer_tokens(&env, &immutables.token, &immutables.maker, amount);
+        }
         
-        // Return safety deposit to taker if any
-        if immutables.safety_deposit > 0 {
-            transfer_native(&env, &immutables.taker, immutables.safety_deposit);
+        // Safety deposit goes to the taker by default
+        if deposit > 0 {
+            let action = if timelocks::is_public_cancellation(&env, &storage::get_timelocks(&env, &immutables), immutables.skew_tolerance, storage::get_side(&env)) {
+                DepositAction::PublicCancel
+            } else {
+                DepositAction::Cancel
+            };
+            pay_deposit_for(&env, &immutables, action, &caller, deposit);
         }
 
         // Update state
+        storage::set_funding(&env, 0, 0);
         storage::set_state(&env, State::Cancelled);
+        record_receipt(&env, &immutables.maker, amount, deposit, &caller);
+        log_action(&env, symbol_short!("cancel"), &caller, amount);
         
         // Emit event
-        events::emit_escrow_cancelled(&env);
+        events::emit_escrow_cancelled(&env, amount, storage::get_filled_amount(&env));
+    }
+
+    /// Cancel an escrow that was never funded (maker only), without waiting for its cancellation
+    /// timelock; emits `escrow/cancel` with nothing refunded
+    /// Tokens sent without confirm_funding belong to whoever sent them, so the escrow must be empty
+    pub fn abort(env: Env) {
+        require_not_executed(&env);
+        if storage::get_state(&env) != State::Active {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        immutables.maker.require_auth();
+
+        let balances = funding::balances(&env, &immutables);
+        if balances.token_balance > 0 || balances.native_balance > 0 {
+            panic_with_error!(&env, Error::EscrowNotEmpty);
+        }
+
+        mark_executed(&env, EXECUTED_CANCEL);
+        storage::set_state(&env, State::Cancelled);
+        log_action(&env, symbol_short!("abort"), &immutables.maker, 0);
+        events::emit_escrow_cancelled(&env, 0, 0);
+    }
+
+    /// Push the escrow's deadlines later by mutual consent of maker and taker, e.g. to rescue a
+    /// swap delayed by network congestion before it cancels; only while the escrow is open
+    /// Every stage must stay or move later (`TimelocksNotLater`). The immutables, and so the escrow
+    /// address and hash, keep the deployed timelocks; get_timelocks returns the ones in force
+    pub fn extend_timelocks(env: Env, new_timelocks: u64) {
+        if !storage::get_state(&env).is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        immutables.maker.require_auth();
+        immutables.taker.require_auth();
+
+        let current = storage::get_timelocks(&env, &immutables);
+        let later = (0..timelocks::STAGE_COUNT)
+            .all(|stage| timelocks::get_timelock(new_timelocks, stage) >= timelocks::get_timelock(current, stage));
+        if !later || new_timelocks == current {
+            panic_with_error!(&env, Error::TimelocksNotLater);
+        }
+
+        storage::set_timelocks(&env, new_timelocks);
+        // Stages the extension moved back into the future are reported again by ping
+        storage::set_reported_stages(&env, storage::get_reported_stages(&env) & timelocks::reached_stages(&env, new_timelocks));
+        log_action(&env, symbol_short!("extend"), &immutables.maker, 0);
+        events::emit_timelocks_extended(&env, current, new_timelocks);
+    }
+
+    /// Get the timelocks in force: the immutables' unless extend_timelocks moved them
+    pub fn get_timelocks(env: Env) -> u64 {
+        storage::get_current_timelocks(&env)
+    }
+
+    /// Claim EXECUTOR_LOCK_LEDGERS of exclusive public_withdraw once the public window opens
+    /// Lets an executor commit before revealing the secret, so copies of its transaction can't
+    /// front-run it. Only the first claim counts; afterwards public_withdraw is open to everyone
+    /// again. Returns the last ledger of the lock
+    pub fn claim_execution(env: Env, caller: Address) -> u32 {
+        caller.require_auth();
+
+        if !storage::get_state(&env).is_open() {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+        let immutables = storage::get_immutables(&env);
+        if !timelocks::can_withdraw(&env, &storage::get_timelocks(&env, &immutables), true) {
+            panic_with_error!(&env, Error::TimelockNotExpired);
+        }
+        if storage::get_executor_lock(&env).is_some() {
+            panic_with_error!(&env, Error::ExecutionClaimed);
+        }
+
+        let until = env.ledger().sequence() + EXECUTOR_LOCK_LEDGERS;
+        storage::set_executor_lock(&env, &caller, until);
+        log_action(&env, symbol_short!("claim_exe"), &caller, 0);
+        events::emit_execution_claimed(&env, &caller, until);
+        until
+    }
+
+    /// Get the claim_execution lock: the executor and the last ledger of its exclusivity
+    pub fn get_execution_lock(env: Env) -> Option<(Address, u32)> {
+        storage::get_executor_lock(&env)
     }
 
     /// Public withdrawal after timelock expiry
+    /// While a claim_execution lock is live only its executor may call this
     pub fn public_withdraw(env: Env, secret: BytesN<32>, caller: Address) {
+        require_not_executed(&env);
         // Require authentication from the caller
         caller.require_auth();
         
+        if let Some((executor, until)) = storage::get_executor_lock(&env) {
+            if executor != caller && env.ledger().sequence() <= until {
+                panic_with_error!(&env, Error::ExecutorLocked);
+            }
+        }
+        
         // Similar to withdraw but with public timelock check
         let state = storage::get_state(&env);
-        if state != State::Active {
+        if !state.is_open() {
             panic_with_error!(&env, Error::InvalidState);
         }
 
         let immutables = storage::get_immutables(&env);
+        require_flag(&env, &immutables, FLAG_ALLOW_PUBLIC_ACTIONS);
+        require_single_hashlock(&env, &immutables);
         
         // Verify secret
         let secret_bytes = Bytes::from(secret.clone());
-        let secret_hash = env.crypto().sha256(&secret_bytes);
-        if secret_hash.to_bytes() != immutables.hashlock {
+        let secret_hash = hash_secret(&env, &secret_bytes);
+        trace!(&env, "hash", (secret_hash.clone(), immutables.hashlock.clone()));
+        if secret_hash != immutables.hashlock {
             panic_with_error!(&env, Error::InvalidSecret);
         }
 
         // Check public withdrawal timelock
-        if !timelocks::can_withdraw(&env, &immutables.timelocks, true) {
+        if !timelocks::can_withdraw(&env, &storage::get_timelocks(&env, &immutables), true) {
             panic_with_error!(&env, Error::TimelockNotExpired);
         }
+        require_final(&env, &immutables);
 
         // Anyone can call this after public timelock
         
-        // Transfer tokens to caller
-        transfer_tokens(&env, &immutables.token, &caller, immutables.amount);
+        // Transfer funded tokens to caller
+        mark_executed(&env, EXECUTED_PUBLIC_WITHDRAW);
+        funding::ensure_native_backing(&env, &immutables);
+        let (amount, deposit) = funding::settle(&env, &immutables);
+        if amount > 0 {
+            funding::ensure_can_receive(&env, &immutables.token, &caller);
+            transfer_tokens(&env, &immutables.token, &caller, amount);
+            storage::add_filled_amount(&env, amount);
+            call_post_payout_hook(&env, &immutables, &caller, amount);
+        }
         
-        // Safety deposit goes to original parties
-        if immutables.safety_deposit > 0 {
-            transfer_native(&env, &immutables.maker, immutables.safety_deposit / 2);
-            transfer_native(&env, &immutables.taker, immutables.safety_deposit / 2);
+        // Safety deposit goes to original parties by default
+        if deposit > 0 {
+            pay_deposit_for(&env, &immutables, DepositAction::PublicWithdraw, &caller, deposit);
         }
 
+        storage::set_funding(&env, 0, 0);
         storage::set_state(&env, State::Withdrawn);
-        events::emit_secret_revealed(&env, &secret);
+        record_receipt(&env, &caller, amount, deposit, &caller);
+        log_action(&env, symbol_short!("public_wd"), &caller, amount);
+        storage::set_revealed_secret(&env, &secret.clone().into());
+        events::emit_secret_revealed(&env, &immutables.hashlock, &secret);
+    }
+
+    /// Pay a withdrawal held because its recipient couldn't receive the token to `new_target` (taker only)
+    /// Escrows with a beneficiary only pay the beneficiary (a memo ID may be added), once it can
+    /// receive again. Returns the paid amount
+    pub fn retry_payout(env: Env, new_target: MuxedAddress) -> i128 {
+        let amount = storage::get_pending_payout(&env);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        immutables.taker.require_auth();
+
+        let recipient = new_target.address();
+        if immutables.beneficiary.as_ref().is_some_and(|beneficiary| *beneficiary != recipient) {
+            panic_with_error!(&env, Error::BeneficiaryMismatch);
+        }
+        funding::ensure_can_receive(&env, &immutables.token, &recipient);
+
+        storage::set_pending_payout(&env, 0);
+        transfer_tokens(&env, &immutables.token, new_target, amount);
+        storage::add_filled_amount(&env, amount);
+        log_action(&env, symbol_short!("retry"), &immutables.taker, amount);
+        events::emit_payout_retried(&env, &recipient, amount);
+        call_post_payout_hook(&env, &immutables, &recipient, amount);
+
+        amount
+    }
+
+    /// Get the withdrawn amount awaiting retry_payout (0 if none)
+    pub fn get_pending_payout(env: Env) -> i128 {
+        storage::get_pending_payout(&env)
+    }
+
+    /// Pay out a safety deposit whose inline transfer failed during withdraw or cancel
+    /// Anyone may call; the deposit always goes to the recorded recipient
+    /// Returns the claimed amount
+    pub fn claim_deposit(env: Env, recipient: Address) -> i128 {
+        let amount = storage::get_pending_deposit(&env, &recipient);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        storage::set_pending_deposit(&env, &recipient, 0);
+        transfer_native(&env, &recipient, amount);
+        log_action(&env, symbol_short!("claim_dep"), &recipient, amount);
+        events::emit_deposit_claimed(&env, &recipient, amount);
+
+        amount
+    }
+
+    /// Collect the deposit shares credited to `caller` as a third-party executor (caller only)
+    /// Rewards are accrued instead of paid inline so a keeper's missing trustline can't block
+    /// settlement, and can be collected from many escrows at once through the resolver
+    pub fn claim_rewards(env: Env, caller: Address) -> i128 {
+        caller.require_auth();
+
+        let amount = storage::get_reward(&env, &caller);
+        if amount <= 0 {
+            panic_with_error!(&env, Error::InvalidAmount);
+        }
+
+        storage::set_reward(&env, &caller, 0);
+        transfer_native(&env, &caller, amount);
+        log_action(&env, symbol_short!("claim_rwd"), &caller, amount);
+        events::emit_rewards_claimed(&env, &caller, amount);
+
+        amount
+    }
+
+    /// Get the rewards `keeper` can collect with claim_rewards
+    pub fn get_rewards(env: Env, keeper: Address) -> i128 {
+        storage::get_reward(&env, &keeper)
+    }
+
+    /// Get the escrow's balances and surplus/deficit against what it should hold
+    pub fn get_balances(env: Env) -> EscrowBalances {
+        funding::balances(&env, &storage::get_immutables(&env))
+    }
+
+    /// Choose where sweep_surplus sends leftover balances instead of the maker (maker only)
+    pub fn set_surplus_sink(env: Env, sink: Address) {
+        let immutables = storage::get_immutables(&env);
+        immutables.maker.require_auth();
+
+        storage::set_surplus_sink(&env, &sink);
+        log_action(&env, symbol_short!("set_sink"), &immutables.maker, 0);
+    }
+
+    /// Get the address sweep_surplus pays (the maker unless set_surplus_sink chose another)
+    pub fn get_surplus_sink(env: Env) -> Address {
+        storage::get_surplus_sink(&env).unwrap_or_else(|| storage::get_immutables(&env).maker)
+    }
+
+    /// Send token and native balances the escrow doesn't owe anyone to the surplus sink (anyone)
+    /// Only once the escrow was withdrawn or cancelled and before finalize; held payouts and
+    /// pending deposits stay. Returns the (token, native) amounts swept
+    pub fn sweep_surplus(env: Env) -> (i128, i128) {
+        if !storage::get_state(&env).is_terminal() || storage::is_finalized(&env) {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let immutables = storage::get_immutables(&env);
+        let sink = storage::get_surplus_sink(&env).unwrap_or_else(|| immutables.maker.clone());
+        let balances = funding::balances(&env, &immutables);
+        let token_surplus = balances.token_surplus.max(0);
+        let native_surplus = balances.native_surplus.max(0);
+        if token_surplus > 0 {
+            transfer_tokens(&env, &immutables.token, &sink, token_surplus);
+        }
+        if native_surplus > 0 {
+            transfer_native(&env, &sink, native_surplus);
+        }
+
+        log_action(&env, symbol_short!("sweep"), &sink, token_surplus);
+        events::emit_surplus_swept(&env, &sink, token_surplus, native_surplus);
+        (token_surplus, native_surplus)
+    }
+
+    /// Pre-flight withdraw without moving funds, for relayers simulating before submitting
+    /// Runs the state, secret, timelock and balance checks and reports the first failure
+    pub fn dry_run_withdraw(env: Env, secret: BytesN<32>) -> DryRunResult {
+        preflight::to_result(preflight::check_withdraw(&env, &secret))
+    }
+
+    /// Pre-flight cancel for `caller` without moving funds
+    /// Runs the state, timelock/caller and balance checks and reports the first failure
+    pub fn dry_run_cancel(env: Env, caller: Address) -> DryRunResult {
+        preflight::to_result(preflight::check_cancel(&env, &caller))
+    }
+
+    /// Get the safety deposit awaiting claim_deposit for `recipient`
+    pub fn get_pending_deposit(env: Env, recipient: Address) -> i128 {
+        storage::get_pending_deposit(&env, &recipient)
+    }
+
+    /// Mark the escrow Expired if it timed out (public cancellation reached) while still open
+    /// Expired escrows reject withdrawals and funding; cancel returns the funds
+    /// Returns the resulting state
+    pub fn refresh_state(env: Env) -> State {
+        let state = storage::get_state(&env);
+        if !state.is_open() {
+            return state;
+        }
+
+        let immutables = storage::get_immutables(&env);
+        if !timelocks::is_expired(&env, &storage::get_timelocks(&env, &immutables), immutables.skew_tolerance) {
+            return state;
+        }
+
+        storage::set_state(&env, State::Expired);
+        log_action(&env, symbol_short!("expire"), &env.current_contract_address(), 0);
+        events::emit_escrow_expired(&env);
+        State::Expired
+    }
+
+    /// Reclaim storage rent once the escrow has been withdrawn or cancelled (anyone)
+    /// Callable FINALIZE_GRACE_PERIOD after the terminal transition and only once every
+    /// failed deposit, keeper reward and held payout has been claimed; removes all persistent entries but the final state, receipt and action log
+    pub fn finalize(env: Env) {
+        let state = storage::get_state(&env);
+        if !state.is_terminal() || storage::is_finalized(&env) {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+        if storage::get_pending_total(&env) > 0 || storage::get_pending_payout(&env) > 0 {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+
+        let terminal_at = storage::get_terminal_at(&env).unwrap_or(0);
+        if env.ledger().timestamp() < terminal_at + FINALIZE_GRACE_PERIOD {
+            panic_with_error!(&env, Error::FinalizeTooEarly);
+        }
+
+        storage::clear_for_finalize(&env);
+        log_action(&env, symbol_short!("finalize"), &env.current_contract_address(), 0);
+        events::emit_escrow_finalized(&env, state);
+    }
+
+    /// Get the payout record of the withdrawal or cancellation that closed the escrow
+    /// None while open and for aborted escrows; kept by finalize
+    pub fn get_receipt(env: Env) -> Option<Receipt> {
+        storage::get_receipt(&env)
+    }
+
+    /// Get the state-changing calls made on the escrow, oldest first (the last MAX_ACTION_LOG)
+    /// Each entry records the action, caller, ledger and amount moved; kept by finalize, so
+    /// disputes can be settled from contract state after RPC nodes drop the events
+    pub fn get_action_log(env: Env) -> Vec<ActionLogEntry> {
+        storage::get_action_log(&env)
+    }
+
+    /// Ledger timestamp at which the escrow was withdrawn or cancelled (None while open or once finalized)
+    pub fn get_terminal_at(env: Env) -> Option<u64> {
+        storage::get_terminal_at(&env)
+    }
+
+    /// Extend the escrow's storage TTL (anyone, e.g. a keeper collecting the factory's bounty)
+    /// Returns whether the escrow was due: funded and not yet withdrawn or cancelled, with an
+    /// estimated TTL below STORAGE_TTL_THRESHOLD. Unfunded escrows cost nothing to deploy, so they
+    /// are extended but never due. Entries keyed by recipient (pending deposits, rewards) are
+    /// not extended
+    pub fn extend_storage(env: Env) -> bool {
+        if !storage::is_initialized(&env) || storage::is_finalized(&env) {
+            panic_with_error!(&env, Error::InvalidState);
+        }
+        let due = storage::get_state(&env) == State::Funded && storage_ttl(&env) < STORAGE_TTL_THRESHOLD;
+        storage::extend_storage(&env, STORAGE_TTL_EXTEND_TO);
+        events::emit_storage_extended(&env, due);
+        due
+    }
+
+    /// Estimated ledgers until the escrow's storage expires, counted from the last
+    /// extend_storage; entries written since then live at least the network's minimum TTL
+    pub fn get_storage_ttl(env: Env) -> u32 {
+        storage_ttl(&env)
+    }
+
+    /// Whether finalize has reclaimed the escrow's storage
+    pub fn is_finalized(env: Env) -> bool {
+        storage::is_finalized(&env)
+    }
+
+    /// Report timelock stages entered since the last ping (keeper-callable)
+    /// Emits one stage_transition event per newly reached stage and returns how many were emitted
+    pub fn ping(env: Env) -> u32 {
+        let timelocks = storage::get_timelocks(&env, &storage::get_immutables(&env));
+
+        let reached = timelocks::reached_stages(&env, timelocks);
+        let new_stages = reached & !storage::get_reported_stages(&env);
+        if new_stages == 0 {
+            return 0;
+        }
+
+        for stage in 0..timelocks::STAGE_COUNT {
+            if new_stages & (1 << stage) != 0 {
+                let stage_start = timelocks::get_timelock(timelocks, stage) as u64;
+                events::emit_stage_transition(&env, stage as u32, stage_start);
+            }
+        }
+
+        storage::set_reported_stages(&env, reached);
+        new_stages.count_ones()
+    }
+
+    /// Version of the event layout this escrow code emits; its events' first topic is the matching
+    /// `events::SCHEMA` symbol
+    pub fn get_event_schema_version(_env: Env) -> u32 {
+        events::EVENT_SCHEMA_VERSION
     }
 
     /// Get current escrow state
@@ -191,31 +1410,375 @@ impl StellarEscrow {
         storage::get_state(&env)
     }
 
+    /// Get the executed-actions bitmap: which payout paths ran (EXECUTED_* bits)
+    pub fn get_executed_actions(env: Env) -> u32 {
+        storage::get_executed(&env)
+    }
+
     /// Get escrow immutables
     pub fn get_immutables(env: Env) -> Immutables {
         storage::get_immutables(&env)
     }
+
+    /// Get the hashlock, without the rest of the immutables
+    pub fn get_hashlock(env: Env) -> BytesN<32> {
+        storage::get_immutables_field(&env, "hashlock")
+    }
+
+    /// Get the escrowed amount, without the rest of the immutables
+    pub fn get_amount(env: Env) -> i128 {
+        storage::get_immutables_field(&env, "amount")
+    }
+
+    /// Get (maker, taker), without the rest of the immutables
+    pub fn get_parties(env: Env) -> (Address, Address) {
+        storage::get_parties(&env)
+    }
+
+    /// Get escrow immutables ABI-encoded as the EVM escrow factory expects
+    /// keccak256 of the result equals the EVM-side immutables hash of the same swap
+    pub fn get_immutables_evm(env: Env) -> Bytes {
+        storage::get_immutables(&env).evm_abi(&env)
+    }
 }
 
 // Helper functions
-fn transfer_tokens(env: &Env, token: &Address, to: &Address, amount: i128) {
+
+/// Ledgers left of the last extend_storage's extension; 0 if there was none
+fn storage_ttl(env: &Env) -> u32 {
+    storage::get_ttl_extended_at(env)
+        .map_or(0, |extended_at| (extended_at + STORAGE_TTL_EXTEND_TO).saturating_sub(env.ledger().sequence()))
+}
+
+/// Withdraw the tranche `secret` unlocked and record the secret
+fn fill_tranche(env: &Env, immutables: &Immutables, secret: &BytesN<32>, amount: i128) {
+    settle_tranches(env, immutables, amount);
+    storage::set_revealed_secret(env, &secret.clone().into());
+    events::emit_partial_withdraw(env, secret, amount, storage::get_filled_amount(env));
+}
+
+/// Check that a multi-secret tranche of `amount` after `filled` uses the secret of its part, as in
+/// 1inch's MerkleStorageInvalidator: the amount splits into `parts` equal parts, a fill uses the
+/// index of the part its last unit falls in, and the fill completing the escrow uses `parts`
+/// A fill ending in the same part as the previous one has no secret left and is rejected
+fn require_part_boundary(env: &Env, immutables: &Immutables, filled: i128, amount: i128, index: u64) {
+    if immutables.parts == 0 {
+        panic_with_error!(env, Error::InvalidPartialFill);
+    }
+    let end = filled.checked_add(amount).filter(|end| amount > 0 && *end <= immutables.amount);
+    let Some(end) = end else { panic_with_error!(env, Error::InvalidAmount) };
+    let part_of = |filled: i128| {
+        (filled - 1)
+            .checked_mul(immutables.parts as i128)
+            .map(|scaled| scaled / immutables.amount)
+            .unwrap_or_else(|| panic_with_error!(env, Error::InvalidAmount))
+    };
+
+    let expected = if end == immutables.amount {
+        immutables.parts as i128
+    } else if filled > 0 && part_of(filled) == part_of(end) {
+        panic_with_error!(env, Error::InvalidPartialFill)
+    } else {
+        part_of(end)
+    };
+    if index as i128 != expected {
+        panic_with_error!(env, Error::InvalidPartialFill);
+    }
+}
+
+/// Pay `amount` of the recorded funding to the payout recipient, closing the escrow once it is all filled
+fn settle_tranches(env: &Env, immutables: &Immutables, amount: i128) {
+    let remaining = storage::get_funded_amount(env);
+    if amount <= 0 || amount > remaining {
+        panic_with_error!(env, Error::InvalidAmount);
+    }
+    require_flag(env, immutables, FLAG_ALLOW_PARTIAL_FILLS);
+    require_taker_auth(immutables);
+    let closing = amount == remaining;
+    mark_executed(env, if closing { EXECUTED_PARTIAL | EXECUTED_WITHDRAW } else { EXECUTED_PARTIAL });
+    funding::ensure_native_backing(env, immutables);
+    funding::ensure_can_receive(env, &immutables.token, &immutables.payout_recipient());
+
+    transfer_tokens(env, &immutables.token, immutables.payout_recipient(), amount);
+    storage::add_filled_amount(env, amount);
+    call_post_payout_hook(env, immutables, &immutables.payout_recipient(), amount);
+    log_action(env, symbol_short!("partial"), &immutables.taker, amount);
+
+    let deposit = storage::get_funded_deposit(env);
+    if closing {
+        if deposit > 0 {
+            pay_deposit_for(env, immutables, DepositAction::Withdraw, &immutables.taker, deposit);
+        }
+        storage::set_funding(env, 0, 0);
+        storage::set_state(env, State::Withdrawn);
+        record_receipt(env, &immutables.payout_recipient(), amount, deposit, &immutables.taker);
+    } else {
+        storage::set_funding(env, remaining - amount, deposit);
+    }
+}
+
+/// Store the immutables of a new escrow and mark it Active
+/// Rejects parameters that would make payouts meaningless: a maker paying itself, nothing
+/// escrowed, a negative deposit, or an all-zero hashlock or order hash (an unset value)
+fn init_escrow(env: &Env, immutables: &Immutables) -> Address {
+    if immutables.maker == immutables.taker {
+        panic_with_error!(env, Error::MakerIsTaker);
+    }
+
+    // Native-token escrows hold amount and deposit in one balance; their sum must be representable
+    let total = immutables.amount.checked_add(immutables.safety_deposit);
+    if immutables.amount <= 0 || immutables.safety_deposit < 0 || total.is_none() {
+        panic_with_error!(env, Error::InvalidAmount);
+    }
+
+    let zero = BytesN::from_array(env, &[0u8; 32]);
+    if immutables.hashlock == zero || immutables.hashlock_b.as_ref() == Some(&zero) {
+        panic_with_error!(env, Error::ZeroHashlock);
+    }
+    if immutables.order_hash == zero {
+        panic_with_error!(env, Error::ZeroOrderHash);
+    }
+    
+    // Fold in the skew tolerance, swap id, flags and parts the deployer set just before initialization
+    let immutables = &Immutables {
+        skew_tolerance: storage::take_pending_skew(env),
+        swap_id: storage::take_pending_swap_id(env),
+        flags: storage::take_pending_flags(env),
+        parts: storage::take_pending_parts(env),
+        ..immutables.clone()
+    };
+
+    // Store immutables
+    storage::set_immutables(env, immutables);
+    log_action(env, symbol_short!("deploy"), &immutables.maker, immutables.amount);
+    if let Some(swap_id) = &immutables.swap_id {
+        storage::set_swap_id(env, swap_id);
+    }
+    
+    // Initialize state
+    storage::set_state(env, State::Active);
+    storage::extend_storage(env, STORAGE_TTL_EXTEND_TO);
+    
+    // Emit creation event
+    events::emit_escrow_created(env, &immutables.order_hash, &immutables.maker, &immutables.taker);
+    
+    // Return the contract's own address
+    env.current_contract_address()
+}
+
+/// Whether the escrow's finality oracle (if any) reports the order final
+pub(crate) fn is_final(env: &Env, immutables: &Immutables) -> bool {
+    immutables.finality_oracle.as_ref().is_none_or(|oracle| {
+        FinalityOracleClient::new(env, oracle).is_final(&immutables.order_hash)
+    })
+}
+
+/// Reject withdrawals until the finality oracle confirms the order
+fn require_final(env: &Env, immutables: &Immutables) {
+    if !is_final(env, immutables) {
+        panic_with_error!(env, Error::NotFinal);
+    }
+}
+
+/// Reject single-secret withdrawals on escrows that also need a second secret
+fn require_single_hashlock(env: &Env, immutables: &Immutables) {
+    if immutables.hashlock_b.is_some() {
+        panic_with_error!(env, Error::SecondSecretRequired);
+    }
+}
+
+/// Fail with FlagDisabled unless the escrow was deployed with `flag`
+fn require_flag(env: &Env, immutables: &Immutables, flag: u32) {
+    if !immutables.has_flag(flag) {
+        panic_with_error!(env, Error::FlagDisabled);
+    }
+}
+
+/// Require the taker's authorization for a withdrawal when FLAG_REQUIRE_TAKER_AUTH is set
+fn require_taker_auth(immutables: &Immutables) {
+    if immutables.has_flag(FLAG_REQUIRE_TAKER_AUTH) {
+        immutables.taker.require_auth();
+    }
+}
+
+/// Fail with AlreadyExecuted once a withdrawal or cancellation has completed
+/// Checked first by every payout entrypoint, ahead of the state checks
+fn require_not_executed(env: &Env) {
+    if storage::get_executed(env) & EXECUTED_TERMINAL != 0 {
+        panic_with_error!(env, Error::AlreadyExecuted);
+    }
+}
+
+/// Record a payout path in the executed-actions bitmap, re-checking it where funds move so a
+/// payout path can't run after another has completed, whichever entrypoint reaches it
+fn mark_executed(env: &Env, action: u32) {
+    require_not_executed(env);
+    storage::add_executed(env, action);
+}
+
+/// Pay the funded amount to `recipient` and return the deposit to the maker once the secret is verified
+/// If the recipient can't receive the token (missing trustline, deauthorized), the withdrawal still
+/// completes and records the secret; the amount is held for the taker's retry_payout
+fn settle_withdrawal(env: &Env, immutables: &Immutables, recipient: &MuxedAddress) {
+    // Check timelock
+    if !timelocks::can_withdraw(env, &storage::get_timelocks(env, immutables), false) {
+        panic_with_error!(env, Error::TimelockNotExpired);
+    }
+    require_final(env, immutables);
+    require_taker_auth(immutables);
+    mark_executed(env, EXECUTED_WITHDRAW);
+
+    // Transfer funded tokens to the recipient (the payout recipient unless withdraw_to chose another)
+    funding::ensure_native_backing(env, immutables);
+    let (amount, deposit) = funding::settle(env, immutables);
+    if amount > 0 && !funding::can_receive(env, &immutables.token, &recipient.address()) {
+        storage::set_pending_payout(env, amount);
+        events::emit_payout_failed(env, &recipient.address(), amount);
+    } else if amount > 0 {
+        transfer_tokens(env, &immutables.token, recipient, amount);
+        storage::add_filled_amount(env, amount);
+        call_post_payout_hook(env, immutables, &recipient.address(), amount);
+    }
+    
+    // Return safety deposit to maker by default
+    if deposit > 0 {
+        pay_deposit_for(env, immutables, DepositAction::Withdraw, &immutables.taker, deposit);
+    }
+
+    // Update state
+    storage::set_funding(env, 0, 0);
+    storage::set_state(env, State::Withdrawn);
+    record_receipt(env, &recipient.address(), amount, deposit, &immutables.taker);
+    log_action(env, symbol_short!("withdraw"), &immutables.taker, amount);
+}
+
+/// Let the pre-funding hook (if any) see the funding the escrow is about to accept
+fn call_pre_funding_hook(env: &Env, immutables: &Immutables) {
+    if let Some(hook) = &immutables.pre_funding_hook {
+        let escrow = env.current_contract_address();
+        let result = EscrowHookClient::new(env, hook)
+            .try_before_funding(&escrow, &immutables.order_hash, &immutables.token, &immutables.amount);
+        if !matches!(result, Ok(Ok(()))) {
+            events::emit_hook_failed(env, hook, "before_funding");
+        }
+    }
+}
+
+/// Notify the post-payout hook (if any) of tokens paid to `recipient`
+fn call_post_payout_hook(env: &Env, immutables: &Immutables, recipient: &Address, amount: i128) {
+    if let Some(hook) = &immutables.post_payout_hook {
+        let escrow = env.current_contract_address();
+        let result = EscrowHookClient::new(env, hook)
+            .try_after_payout(&escrow, &immutables.order_hash, &immutables.token, recipient, &amount);
+        if !matches!(result, Ok(Ok(()))) {
+            events::emit_hook_failed(env, hook, "after_payout");
+        }
+    }
+}
+
+/// Append a state-changing call to the action log
+fn log_action(env: &Env, action: Symbol, caller: &Address, amount: i128) {
+    storage::append_action(env, ActionLogEntry {
+        action,
+        caller: caller.clone(),
+        ledger: env.ledger().sequence(),
+        amount,
+    }, MAX_ACTION_LOG);
+}
+
+/// Store the Receipt of the call closing the escrow
+fn record_receipt(env: &Env, recipient: &Address, amount: i128, fee_paid: i128, executor: &Address) {
+    storage::set_receipt(env, &Receipt {
+        recipient: recipient.clone(),
+        amount,
+        fee_paid,
+        executor: executor.clone(),
+        timestamp: env.ledger().timestamp(),
+        tx_context: env.ledger().sequence(),
+    });
+}
+
+fn transfer_tokens(env: &Env, token: &Address, to: impl Into<MuxedAddress>, amount: i128) {
+    let to: MuxedAddress = to.into();
     // For native XLM
     if is_native_token(env, token) {
-        transfer_native(env, to, amount);
+        transfer_native(env, &to, amount);
     } else {
         // For other tokens, use token contract
         let client = soroban_sdk::token::TokenClient::new(env, token);
-        client.transfer(&env.current_contract_address(), to, &amount);
+        client.transfer(&env.current_contract_address(), &to, &amount);
     }
 }
 
-pub(crate) fn transfer_native(env: &Env, to: &Address, amount: i128) {
+pub(crate) fn transfer_native(env: &Env, to: impl Into<MuxedAddress>, amount: i128) {
+    let to: MuxedAddress = to.into();
     // In Stellar/Soroban, native XLM is handled through the Stellar Asset Contract (SAC)
     // The native token has a special address that can be obtained through the SDK
     // For now, we'll use the token client with the native token address
     let native_token = get_native_token_address(env);
     let client = soroban_sdk::token::TokenClient::new(env, &native_token);
-    client.transfer(&env.current_contract_address(), to, &amount);
+    client.transfer(&env.current_contract_address(), &to, &amount);
+}
+
+/// Split a released safety deposit according to the escrow's DepositPolicy
+fn pay_deposit_for(env: &Env, immutables: &Immutables, action: DepositAction, executor: &Address, deposit: i128) {
+    let incentives = match &immutables.incentives {
+        DepositPolicy::Split(incentives) => incentives,
+        DepositPolicy::Default => {
+            match action {
+                DepositAction::Withdraw => pay_deposit(env, &immutables.maker, deposit),
+                DepositAction::PublicWithdraw => {
+                    let (maker_share, taker_share) = halve_deposit(deposit);
+                    pay_deposit(env, &immutables.maker, maker_share);
+                    pay_deposit(env, &immutables.taker, taker_share);
+                }
+                DepositAction::Cancel | DepositAction::PublicCancel => pay_deposit(env, &immutables.taker, deposit),
+            }
+            return;
+        }
+    };
+
+    let split = match action {
+        DepositAction::Withdraw => &incentives.withdraw,
+        DepositAction::PublicWithdraw => &incentives.public_withdraw,
+        DepositAction::Cancel => &incentives.cancel,
+        DepositAction::PublicCancel => &incentives.public_cancel,
+    };
+    let [executor_share, mut maker_share, mut protocol_share] = incentives.shares(split, deposit);
+    if !immutables.has_flag(FLAG_ENABLE_FEES) {
+        maker_share += protocol_share;
+        protocol_share = 0;
+    }
+    trace!(env, "split", (action as u32, executor_share, maker_share, protocol_share));
+
+    // Third-party executors (keepers) accrue their share for claim_rewards
+    let is_keeper = *executor != immutables.maker && *executor != immutables.taker;
+    if is_keeper && executor_share > 0 {
+        storage::set_reward(env, executor, storage::get_reward(env, executor) + executor_share);
+        events::emit_reward_earned(env, executor, executor_share);
+    }
+    let inline_executor_share = if is_keeper { 0 } else { executor_share };
+
+    for (recipient, amount) in [
+        (executor, inline_executor_share),
+        (&immutables.maker, maker_share),
+        (&incentives.protocol, protocol_share),
+    ] {
+        if amount > 0 {
+            pay_deposit(env, recipient, amount);
+        }
+    }
+}
+
+/// Transfer a safety deposit without letting a failing recipient block the payout
+/// If the native transfer fails, the deposit is recorded for claim_deposit instead
+fn pay_deposit(env: &Env, to: &Address, amount: i128) {
+    let client = soroban_sdk::token::TokenClient::new(env, &get_native_token_address(env));
+    let result = client.try_transfer(&env.current_contract_address(), to, &amount);
+    if !matches!(result, Ok(Ok(()))) {
+        storage::set_pending_deposit(env, to, storage::get_pending_deposit(env, to) + amount);
+        events::emit_deposit_failed(env, to, amount);
+    }
 }
 
 pub(crate) fn is_native_token(env: &Env, token: &Address) -> bool {
@@ -241,4 +1804,13 @@ pub(crate) fn get_native_token_address(env: &Env) -> Address {
 mod test;
 
 #[cfg(test)]
-mod integration_test;
\ No newline at end of file
+mod integration_test;
+
+#[cfg(test)]
+mod bench_test;
+
+#[cfg(test)]
+mod conformance_test;
+
+#[cfg(test)]
+mod soak_test;
\ No newline at end of file
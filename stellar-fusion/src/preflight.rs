@@ -0,0 +1,84 @@
+use soroban_sdk::{token::TokenClient, Address, Bytes, BytesN, Env};
+use crate::errors::Error;
+use crate::types::{DryRunResult, Immutables, EXECUTED_TERMINAL, FLAG_ALLOW_PUBLIC_ACTIONS};
+use crate::{funding, storage, timelocks};
+
+/// Run the withdraw checks in the order withdraw performs them, without moving funds
+pub fn check_withdraw(env: &Env, secret: &BytesN<32>) -> Result<(i128, i128), Error> {
+    check_not_executed(env)?;
+    if !storage::get_state(env).is_open() {
+        return Err(Error::InvalidState);
+    }
+
+    let immutables = storage::get_immutables(env);
+    if immutables.hashlock_b.is_some() {
+        return Err(Error::SecondSecretRequired);
+    }
+    let secret_hash = crate::hash_secret(env, &Bytes::from(secret.clone()));
+    if secret_hash != immutables.hashlock {
+        return Err(Error::InvalidSecret);
+    }
+
+    if !timelocks::can_withdraw(env, &storage::get_timelocks(env, &immutables), false) {
+        return Err(Error::TimelockNotExpired);
+    }
+    if !crate::is_final(env, &immutables) {
+        return Err(Error::NotFinal);
+    }
+
+    check_payout(env, &immutables)
+}
+
+/// Run the cancel checks in the order cancel performs them, without moving funds
+/// Authorization of `caller` is not checked; the real call requires it
+pub fn check_cancel(env: &Env, caller: &Address) -> Result<(i128, i128), Error> {
+    check_not_executed(env)?;
+    if !storage::get_state(env).is_cancellable() {
+        return Err(Error::InvalidState);
+    }
+
+    let immutables = storage::get_immutables(env);
+    if *caller != immutables.maker && *caller != immutables.taker && !immutables.has_flag(FLAG_ALLOW_PUBLIC_ACTIONS) {
+        return Err(Error::FlagDisabled);
+    }
+    if !timelocks::can_cancel(env, &storage::get_timelocks(env, &immutables), immutables.skew_tolerance, storage::get_side(env), caller, &immutables.maker, &immutables.taker) {
+        return Err(Error::CannotCancel);
+    }
+
+    let (amount, deposit) = check_payout(env, &immutables)?;
+    if amount > 0 && !funding::can_receive(env, &immutables.token, &immutables.maker) {
+        return Err(Error::RecipientCannotReceive);
+    }
+    Ok((amount, deposit))
+}
+
+fn check_not_executed(env: &Env) -> Result<(), Error> {
+    if storage::get_executed(env) & EXECUTED_TERMINAL != 0 {
+        return Err(Error::AlreadyExecuted);
+    }
+    Ok(())
+}
+
+/// Amount and deposit the payout would move
+/// Only a short token balance fails the call; a failed deposit transfer becomes claimable instead
+fn check_payout(env: &Env, immutables: &Immutables) -> Result<(i128, i128), Error> {
+    if !funding::is_native_backed(env, immutables) {
+        return Err(Error::InsufficientBalance);
+    }
+    let (amount, deposit) = funding::settle(env, immutables);
+
+    let balance = TokenClient::new(env, &immutables.token).balance(&env.current_contract_address());
+    if amount > 0 && balance < amount {
+        return Err(Error::InsufficientBalance);
+    }
+
+    Ok((amount, deposit))
+}
+
+/// Describe a check's outcome for simulation callers
+pub fn to_result(result: Result<(i128, i128), Error>) -> DryRunResult {
+    match result {
+        Ok((amount, deposit)) => DryRunResult { success: true, error: None, amount, deposit },
+        Err(error) => DryRunResult { success: false, error: Some(error as u32), amount: 0, deposit: 0 },
+    }
+}
@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod soak_tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use crate::testutils::{register_native_token, secret_and_hashlock, MockNativeTokenClient};
+    use crate::timelocks::pack_timelocks;
+    use crate::types::{DST_CANCELLATION_TIMELOCK, DST_PUBLIC_WITHDRAWAL_TIMELOCK, DST_WITHDRAWAL_TIMELOCK};
+    use crate::{StellarEscrow, StellarEscrowClient, State};
+    use soroban_sdk::testutils::{Address as _, Ledger};
+    use soroban_sdk::{token, Address, BytesN, Env};
+
+    // Override with SOAK_SEED / SOAK_ESCROWS to reproduce a failure or soak longer
+    const DEFAULT_SEED: u64 = 0x5eed_f051_0417;
+    const DEFAULT_ESCROWS: usize = 200;
+    // Invocations per escrow, and how often the global invariants are checked
+    const STEPS_PER_ESCROW: usize = 6;
+    const CONSERVATION_INTERVAL: usize = 50;
+
+    const MAKERS: usize = 4;
+    const TAKERS: usize = 4;
+    const OUTSIDERS: usize = 3;
+    const INITIAL_BALANCE: i128 = 1_000_000_000;
+
+    /// xorshift64*: deterministic, so a seed fully reproduces a schedule
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.below(items.len() as u64) as usize]
+        }
+
+        fn chance(&mut self, percent: u64) -> bool {
+            self.below(100) < percent
+        }
+    }
+
+    fn env_var(name: &str) -> Option<u64> {
+        std::env::var(name).ok()?.parse().ok()
+    }
+
+    /// One escrow of the schedule and what the model expects of it
+    struct Swap<'a> {
+        client: StellarEscrowClient<'a>,
+        maker: Address,
+        taker: Address,
+        secret: BytesN<32>,
+        hashlock: BytesN<32>,
+        amount: i128,
+        deposit: i128,
+        timelocks: u64,
+        deployed: bool,
+        funded: bool,
+        closed: bool,
+    }
+
+    struct World<'a> {
+        env: &'a Env,
+        token: token::TokenClient<'a>,
+        native: MockNativeTokenClient<'a>,
+        makers: Vec<Address>,
+        takers: Vec<Address>,
+        outsiders: Vec<Address>,
+        swaps: Vec<Swap<'a>>,
+    }
+
+    impl World<'_> {
+        fn accounts(&self) -> impl Iterator<Item = &Address> {
+            self.makers.iter().chain(&self.takers).chain(&self.outsiders)
+                .chain(self.swaps.iter().map(|swap| &swap.client.address))
+        }
+
+        /// Tokens and native XLM are only moved, never created or burnt, by escrow calls
+        fn assert_conserved(&self, step: usize) {
+            let parties = (MAKERS + TAKERS + OUTSIDERS) as i128;
+            let tokens: i128 = self.accounts().map(|account| self.token.balance(account)).sum();
+            let native: i128 = self.accounts().map(|account| self.native.balance(account)).sum();
+            assert_eq!(tokens, parties * INITIAL_BALANCE, "token supply changed by step {step}");
+            assert_eq!(native, parties * INITIAL_BALANCE, "native supply changed by step {step}");
+        }
+
+        /// An open funded escrow holds exactly its funding; a closed one holds nothing
+        fn assert_escrow(&self, index: usize) {
+            let swap = &self.swaps[index];
+            if !swap.deployed {
+                return;
+            }
+            let escrow = &swap.client.address;
+            let state = swap.client.get_state();
+            assert_eq!(swap.closed, matches!(state, State::Withdrawn | State::Cancelled), "escrow {index} in {state:?}");
+            let (tokens, native) = if swap.funded && !swap.closed { (swap.amount, swap.deposit) } else { (0, 0) };
+            assert_eq!(self.token.balance(escrow), tokens, "escrow {index} token balance");
+            assert_eq!(self.native.balance(escrow), native, "escrow {index} native balance");
+            assert_eq!(swap.client.get_funded_amount(), tokens, "escrow {index} funded amount");
+        }
+    }
+
+    /// Random stage offsets in timelock order, each within the 8-bit packing
+    fn random_timelocks(rng: &mut Rng) -> u64 {
+        let mut offsets = [0u32; 7];
+        // Src stages 0-3 and dst stages 4-6 each increase from their own start
+        let (src, dst) = offsets.split_at_mut(4);
+        for stages in [src, dst] {
+            let mut time = 1 + rng.below(30) as u32;
+            for offset in stages.iter_mut() {
+                *offset = time;
+                time += 1 + rng.below(40) as u32;
+            }
+        }
+        pack_timelocks(offsets)
+    }
+
+    fn stage_time(timelocks: u64, stage: u8) -> u64 {
+        crate::timelocks::get_timelock(timelocks, stage) as u64
+    }
+
+    fn soak(seed: u64, escrows: usize) {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.cost_estimate().budget().reset_unlimited();
+        let mut rng = Rng(seed ^ 0x9e37_79b9_7f4a_7c15);
+
+        let token_address = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let mut world = World {
+            env: &env,
+            token: token::TokenClient::new(&env, &token_address),
+            native: register_native_token(&env),
+            makers: (0..MAKERS).map(|_| Address::generate(&env)).collect(),
+            takers: (0..TAKERS).map(|_| Address::generate(&env)).collect(),
+            outsiders: (0..OUTSIDERS).map(|_| Address::generate(&env)).collect(),
+            swaps: Vec::new(),
+        };
+        let minter = token::StellarAssetClient::new(&env, &token_address);
+        for account in world.makers.iter().chain(&world.takers).chain(&world.outsiders) {
+            minter.mint(account, &INITIAL_BALANCE);
+            world.native.mint(account, &INITIAL_BALANCE);
+        }
+
+        for index in 0..escrows {
+            let (secret, hashlock) = secret_and_hashlock(&env, (index % 251) as u8);
+            let swap = Swap {
+                client: StellarEscrowClient::new(&env, &env.register(StellarEscrow, ())),
+                maker: rng.pick(&world.makers).clone(),
+                taker: rng.pick(&world.takers).clone(),
+                secret,
+                hashlock,
+                amount: 1 + rng.below(1_000_000) as i128,
+                deposit: if rng.chance(50) { rng.below(10_000) as i128 } else { 0 },
+                timelocks: random_timelocks(&mut rng),
+                deployed: false,
+                funded: false,
+                closed: false,
+            };
+            world.swaps.push(swap);
+        }
+
+        for step in 0..escrows * STEPS_PER_ESCROW {
+            let index = rng.below(escrows as u64) as usize;
+            let now = env.ledger().timestamp();
+            let outsider = rng.pick(&world.outsiders).clone();
+            let swap = &mut world.swaps[index];
+
+            match rng.below(8) {
+                // Deploy, or re-deploy (always rejected)
+                0 => {
+                    let order_hash = BytesN::from_array(&env, &[(index % 255) as u8 + 1; 32]);
+                    let result = swap.client.try_deploy(
+                        &order_hash,
+                        &swap.hashlock,
+                        &swap.maker,
+                        &swap.taker,
+                        &token_address,
+                        &swap.amount,
+                        &swap.deposit,
+                        &swap.timelocks,
+                    );
+                    assert_eq!(result.is_ok(), !swap.deployed, "deploy of escrow {index}");
+                    swap.deployed = true;
+                }
+                // Fund by pushing to the escrow and confirming
+                1 | 2 if swap.deployed => {
+                    let escrow = swap.client.address.clone();
+                    if !swap.funded && !swap.closed {
+                        world.token.transfer(&swap.maker, &escrow, &swap.amount);
+                        if swap.deposit > 0 {
+                            world.native.transfer(&swap.maker, &escrow, &swap.deposit);
+                        }
+                    }
+                    let result = swap.client.try_confirm_funding();
+                    assert_eq!(result.is_ok(), !swap.funded && !swap.closed, "funding of escrow {index}");
+                    swap.funded = true;
+                }
+                // Adversarial funding: pulling from an outsider without an approval
+                3 if swap.deployed => {
+                    assert!(swap.client.try_pull_funding(&outsider).is_err(), "unapproved pull of escrow {index}");
+                }
+                // Withdraw by the taker, sometimes with a wrong secret
+                // Unconfirmed escrows settle from their balances (nothing here), so funding isn't required
+                4 | 5 if swap.deployed => {
+                    let wrong = rng.chance(30);
+                    let secret = if wrong { BytesN::from_array(&env, &[0xee; 32]) } else { swap.secret.clone() };
+                    let result = swap.client.try_withdraw(&secret, &false);
+                    let open = now >= stage_time(swap.timelocks, DST_WITHDRAWAL_TIMELOCK);
+                    let expected = !swap.closed && !wrong && open;
+                    assert_eq!(result.is_ok(), expected, "withdraw of escrow {index} at {now}");
+                    swap.closed |= expected;
+                }
+                // Public withdrawal by an outsider
+                6 if swap.deployed => {
+                    let result = swap.client.try_public_withdraw(&swap.secret, &outsider);
+                    let open = now >= stage_time(swap.timelocks, DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+                    let expected = !swap.closed && open;
+                    assert_eq!(result.is_ok(), expected, "public withdraw of escrow {index} at {now}");
+                    swap.closed |= expected;
+                }
+                // Cancellation by an outsider, only allowed once public cancellation opens
+                7 if swap.deployed => {
+                    let result = swap.client.try_cancel(&outsider);
+                    let open = now >= stage_time(swap.timelocks, DST_CANCELLATION_TIMELOCK);
+                    let expected = !swap.closed && open;
+                    assert_eq!(result.is_ok(), expected, "outsider cancel of escrow {index} at {now}");
+                    swap.closed |= expected;
+                }
+                _ => {}
+            }
+
+            // Time only moves forward, in random jumps
+            if rng.chance(10) {
+                let jump = 1 + rng.below(3);
+                env.ledger().with_mut(|li| li.timestamp += jump);
+            }
+
+            world.assert_escrow(index);
+            if step % CONSERVATION_INTERVAL == 0 {
+                world.assert_conserved(step);
+            }
+        }
+
+        world.assert_conserved(escrows * STEPS_PER_ESCROW);
+        for index in 0..escrows {
+            world.assert_escrow(index);
+        }
+        let settled = world.swaps.iter().filter(|swap| swap.closed).count();
+        std::println!("soak seed {seed}: {escrows} escrows, {settled} settled by ledger time {}", world.env.ledger().timestamp());
+    }
+
+    #[test]
+    fn test_soak_randomized_schedule() {
+        let seed = env_var("SOAK_SEED").unwrap_or(DEFAULT_SEED);
+        let escrows = env_var("SOAK_ESCROWS").map_or(DEFAULT_ESCROWS, |escrows| escrows as usize);
+        soak(seed, escrows);
+    }
+}
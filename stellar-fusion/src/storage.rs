@@ -1,13 +1,70 @@
-use soroban_sdk::Env;
-use crate::types::{Immutables, State};
+use soroban_sdk::{Address, Bytes, BytesN, Env, Map, Symbol, TryFromVal, Val, Vec};
+use crate::types::{ActionLogEntry, EscrowSide, Immutables, PayoutMemo, Receipt, State, DEFAULT_FLAGS, IMMUTABLES_V1_VERSION};
 
 /// Storage keys
 const IMMUTABLES_KEY: &str = "immutables";
 const STATE_KEY: &str = "state";
+const FUNDED_AMOUNT_KEY: &str = "funded_amt";
+const FUNDED_DEPOSIT_KEY: &str = "funded_dep";
+const FILLED_KEY: &str = "filled";
+const STAGES_KEY: &str = "stages";
+const DETAILS_KEY: &str = "details";
+const PENDING_DEPOSIT_KEY: &str = "pending_dep";
+const PENDING_TOTAL_KEY: &str = "pending_tot";
+const REWARD_KEY: &str = "reward";
+const PENDING_SKEW_KEY: &str = "pending_skw";
+const TERMINAL_AT_KEY: &str = "terminal_at";
+const FINALIZED_KEY: &str = "finalized";
+const PAYOUT_MEMO_KEY: &str = "payout_memo";
+const SIDE_KEY: &str = "side";
+const USED_INDICES_KEY: &str = "used_idx";
+const REVEALED_KEY: &str = "revealed";
+const REVEALED_B_KEY: &str = "revealed_b";
+const PENDING_PAYOUT_KEY: &str = "pending_pay";
+const TTL_EXTENDED_KEY: &str = "ttl_ext";
+const EXECUTOR_LOCK_KEY: &str = "exec_lock";
+const SURPLUS_SINK_KEY: &str = "sweep_sink";
+const RECEIPT_KEY: &str = "receipt";
+const TIMELOCKS_KEY: &str = "timelocks";
+const PENDING_SWAP_ID_KEY: &str = "pending_sid";
+const SWAP_ID_KEY: &str = "swap_id";
+const EXECUTED_KEY: &str = "executed";
+const PENDING_FLAGS_KEY: &str = "pending_flg";
+const PENDING_PARTS_KEY: &str = "pending_prt";
+const IMMUTABLES_VERSION_KEY: &str = "imm_ver";
+const ACTION_LOG_KEY: &str = "action_log";
+
+/// Persistent entries with a fixed key; entries keyed by recipient can't be enumerated
+const FIXED_KEYS: [&str; 23] = [
+    IMMUTABLES_KEY,
+    STATE_KEY,
+    FUNDED_AMOUNT_KEY,
+    FUNDED_DEPOSIT_KEY,
+    FILLED_KEY,
+    STAGES_KEY,
+    DETAILS_KEY,
+    PENDING_TOTAL_KEY,
+    TERMINAL_AT_KEY,
+    PAYOUT_MEMO_KEY,
+    SIDE_KEY,
+    USED_INDICES_KEY,
+    REVEALED_KEY,
+    REVEALED_B_KEY,
+    PENDING_PAYOUT_KEY,
+    TTL_EXTENDED_KEY,
+    EXECUTOR_LOCK_KEY,
+    SURPLUS_SINK_KEY,
+    TIMELOCKS_KEY,
+    RECEIPT_KEY,
+    EXECUTED_KEY,
+    IMMUTABLES_VERSION_KEY,
+    ACTION_LOG_KEY,
+];
 
 /// Check if contract is initialized
+/// Finalized escrows stay initialized so they can't be redeployed
 pub fn is_initialized(env: &Env) -> bool {
-    env.storage().persistent().has(&IMMUTABLES_KEY)
+    env.storage().persistent().has(&IMMUTABLES_KEY) || is_finalized(env)
 }
 
 /// Set immutables in storage
@@ -23,9 +80,111 @@ pub fn get_immutables(env: &Env) -> Immutables {
         .expect("Immutables not initialized")
 }
 
+/// One field of the stored immutables
+/// Reads the stored entry as a host map and converts only `name`, so views polled by monitors
+/// don't decode the whole struct
+pub fn get_immutables_field<T: TryFromVal<Env, Val>>(env: &Env, name: &str) -> T {
+    immutables_field(env, &get_immutables_map(env), name)
+}
+
+/// The immutables' (maker, taker), from a single read of the stored entry
+pub fn get_parties(env: &Env) -> (Address, Address) {
+    let immutables = get_immutables_map(env);
+    (immutables_field(env, &immutables, "maker"), immutables_field(env, &immutables, "taker"))
+}
+
+fn get_immutables_map(env: &Env) -> Map<Symbol, Val> {
+    env.storage()
+        .persistent()
+        .get(&IMMUTABLES_KEY)
+        .expect("Immutables not initialized")
+}
+
+fn immutables_field<T: TryFromVal<Env, Val>>(env: &Env, immutables: &Map<Symbol, Val>, name: &str) -> T {
+    let field = immutables.get(Symbol::new(env, name)).expect("Immutables field");
+    T::try_from_val(env, &field).unwrap_or_else(|_| panic!("Immutables field type"))
+}
+
 /// Set state in storage
+/// Entering a terminal state records when, for finalize's grace period
 pub fn set_state(env: &Env, state: State) {
     env.storage().persistent().set(&STATE_KEY, &state);
+    if state.is_terminal() {
+        env.storage().persistent().set(&TERMINAL_AT_KEY, &env.ledger().timestamp());
+    }
+}
+
+/// Get the ledger timestamp at which the escrow reached a terminal state
+pub fn get_terminal_at(env: &Env) -> Option<u64> {
+    env.storage().persistent().get(&TERMINAL_AT_KEY)
+}
+
+/// Whether finalize has removed the escrow's storage
+pub fn is_finalized(env: &Env) -> bool {
+    env.storage()
+        .persistent()
+        .get(&FINALIZED_KEY)
+        .unwrap_or(false)
+}
+
+/// Remove every persistent entry except the terminal state, leaving a finalized marker
+pub fn clear_for_finalize(env: &Env) {
+    let storage = env.storage().persistent();
+    for key in [
+        IMMUTABLES_KEY,
+        FUNDED_AMOUNT_KEY,
+        FUNDED_DEPOSIT_KEY,
+        FILLED_KEY,
+        STAGES_KEY,
+        DETAILS_KEY,
+        PENDING_TOTAL_KEY,
+        TERMINAL_AT_KEY,
+        PAYOUT_MEMO_KEY,
+        SIDE_KEY,
+        USED_INDICES_KEY,
+        REVEALED_KEY,
+        REVEALED_B_KEY,
+        PENDING_PAYOUT_KEY,
+        TTL_EXTENDED_KEY,
+        EXECUTOR_LOCK_KEY,
+        SURPLUS_SINK_KEY,
+        TIMELOCKS_KEY,
+        IMMUTABLES_VERSION_KEY,
+    ] {
+        storage.remove(&key);
+    }
+    storage.set(&FINALIZED_KEY, &true);
+}
+
+/// Extend the instance and every fixed-key entry to `extend_to` ledgers, recording the ledger
+pub fn extend_storage(env: &Env, extend_to: u32) {
+    let storage = env.storage().persistent();
+    storage.set(&TTL_EXTENDED_KEY, &env.ledger().sequence());
+    for key in FIXED_KEYS {
+        if storage.has(&key) {
+            storage.extend_ttl(&key, extend_to, extend_to);
+        }
+    }
+    env.storage().instance().extend_ttl(extend_to, extend_to);
+}
+
+/// Ledger sequence of the last extend_storage
+pub fn get_ttl_extended_at(env: &Env) -> Option<u32> {
+    env.storage().persistent().get(&TTL_EXTENDED_KEY)
+}
+
+/// Get the executed-actions bitmap (see types::EXECUTED_TERMINAL)
+/// Kept by finalize, so a finalized escrow stays blocked
+pub fn get_executed(env: &Env) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&EXECUTED_KEY)
+        .unwrap_or(0)
+}
+
+/// Add `actions` to the executed-actions bitmap
+pub fn add_executed(env: &Env, actions: u32) {
+    env.storage().persistent().set(&EXECUTED_KEY, &(get_executed(env) | actions));
 }
 
 /// Get state from storage
@@ -34,4 +193,312 @@ pub fn get_state(env: &Env) -> State {
         .persistent()
         .get(&STATE_KEY)
         .unwrap_or(State::Active)
-}
\ No newline at end of file
+}
+
+/// Set the funded token amount and safety deposit
+pub fn set_funding(env: &Env, amount: i128, deposit: i128) {
+    env.storage().persistent().set(&FUNDED_AMOUNT_KEY, &amount);
+    env.storage().persistent().set(&FUNDED_DEPOSIT_KEY, &deposit);
+}
+
+/// Whether funding was recorded by pull_funding/confirm_funding
+pub fn has_recorded_funding(env: &Env) -> bool {
+    env.storage().persistent().has(&FUNDED_AMOUNT_KEY)
+}
+
+/// Get the funded token amount
+pub fn get_funded_amount(env: &Env) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&FUNDED_AMOUNT_KEY)
+        .unwrap_or(0)
+}
+
+/// Get the funded safety deposit
+pub fn get_funded_deposit(env: &Env) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&FUNDED_DEPOSIT_KEY)
+        .unwrap_or(0)
+}
+
+/// Get the total amount paid out to takers so far (fill ledger)
+pub fn get_filled_amount(env: &Env) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&FILLED_KEY)
+        .unwrap_or(0)
+}
+
+/// Record a payout in the fill ledger
+pub fn add_filled_amount(env: &Env, amount: i128) {
+    let filled = get_filled_amount(env) + amount;
+    env.storage().persistent().set(&FILLED_KEY, &filled);
+}
+
+/// Get the bitmap of timelock stages already reported by ping
+pub fn get_reported_stages(env: &Env) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&STAGES_KEY)
+        .unwrap_or(0)
+}
+
+/// Set the bitmap of timelock stages already reported by ping
+pub fn set_reported_stages(env: &Env, stages: u32) {
+    env.storage().persistent().set(&STAGES_KEY, &stages);
+}
+
+/// Set the metadata blob attached at deployment
+pub fn set_details(env: &Env, details: &Bytes) {
+    env.storage().persistent().set(&DETAILS_KEY, details);
+}
+
+/// Get the metadata blob attached at deployment
+pub fn get_details(env: &Env) -> Option<Bytes> {
+    env.storage().persistent().get(&DETAILS_KEY)
+}
+
+/// Record the destination and memo of a withdraw_to payout
+pub fn set_payout_memo(env: &Env, memo: &PayoutMemo) {
+    env.storage().persistent().set(&PAYOUT_MEMO_KEY, memo);
+}
+
+/// Get the destination and memo of the last withdraw_to payout
+pub fn get_payout_memo(env: &Env) -> Option<PayoutMemo> {
+    env.storage().persistent().get(&PAYOUT_MEMO_KEY)
+}
+
+/// Record the secret or preimage revealed by a withdrawal
+pub fn set_revealed_secret(env: &Env, secret: &Bytes) {
+    env.storage().persistent().set(&REVEALED_KEY, secret);
+}
+
+/// Get the last secret or preimage revealed by a withdrawal
+pub fn get_revealed_secret(env: &Env) -> Option<Bytes> {
+    env.storage().persistent().get(&REVEALED_KEY)
+}
+
+/// Record the second secret revealed by withdraw2
+pub fn set_revealed_secret_b(env: &Env, secret: &BytesN<32>) {
+    env.storage().persistent().set(&REVEALED_B_KEY, secret);
+}
+
+/// Get the second secret revealed by withdraw2
+pub fn get_revealed_secret_b(env: &Env) -> Option<BytesN<32>> {
+    env.storage().persistent().get(&REVEALED_B_KEY)
+}
+
+/// Get the indices of the multi-secret fills already withdrawn
+pub fn get_used_indices(env: &Env) -> Vec<u64> {
+    env.storage()
+        .persistent()
+        .get(&USED_INDICES_KEY)
+        .unwrap_or(Vec::new(env))
+}
+
+/// Mark a multi-secret fill index as withdrawn
+pub fn add_used_index(env: &Env, index: u64) {
+    let mut indices = get_used_indices(env);
+    indices.push_back(index);
+    env.storage().persistent().set(&USED_INDICES_KEY, &indices);
+}
+
+/// Replace the withdrawn multi-secret fill indices (reveal_secrets_batch)
+pub fn set_used_indices(env: &Env, indices: &Vec<u64>) {
+    env.storage().persistent().set(&USED_INDICES_KEY, indices);
+}
+
+/// Record the payout of the call that closed the escrow
+pub fn set_receipt(env: &Env, receipt: &Receipt) {
+    env.storage().persistent().set(&RECEIPT_KEY, receipt);
+}
+
+pub fn get_receipt(env: &Env) -> Option<Receipt> {
+    env.storage().persistent().get(&RECEIPT_KEY)
+}
+
+/// Append to the action log, dropping the oldest entry beyond `max_entries`
+pub fn append_action(env: &Env, entry: ActionLogEntry, max_entries: u32) {
+    let mut log = get_action_log(env);
+    if log.len() >= max_entries {
+        log.pop_front();
+    }
+    log.push_back(entry);
+    env.storage().persistent().set(&ACTION_LOG_KEY, &log);
+}
+
+pub fn get_action_log(env: &Env) -> Vec<ActionLogEntry> {
+    env.storage()
+        .persistent()
+        .get(&ACTION_LOG_KEY)
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+/// Record the swap leg of an escrow deployed with deploy_src
+pub fn set_side(env: &Env, side: EscrowSide) {
+    env.storage().persistent().set(&SIDE_KEY, &side);
+}
+
+/// Get the swap leg of the escrow (Dst unless deployed with deploy_src)
+pub fn get_side(env: &Env) -> EscrowSide {
+    env.storage()
+        .persistent()
+        .get(&SIDE_KEY)
+        .unwrap_or(EscrowSide::Dst)
+}
+
+/// Record that the immutables follow the v2 hash (deploy_v2)
+pub fn set_immutables_version(env: &Env, version: u32) {
+    env.storage().persistent().set(&IMMUTABLES_VERSION_KEY, &version);
+}
+
+/// Get the immutables version (IMMUTABLES_V1_VERSION unless deployed with deploy_v2)
+pub fn get_immutables_version(env: &Env) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&IMMUTABLES_VERSION_KEY)
+        .unwrap_or(IMMUTABLES_V1_VERSION)
+}
+
+/// Get the safety deposit owed to `recipient` after a failed inline transfer
+pub fn get_pending_deposit(env: &Env, recipient: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&(PENDING_DEPOSIT_KEY, recipient.clone()))
+        .unwrap_or(0)
+}
+
+/// Set the safety deposit owed to `recipient`, keeping the running total in sync
+pub fn set_pending_deposit(env: &Env, recipient: &Address, amount: i128) {
+    let total = get_pending_total(env) - get_pending_deposit(env, recipient) + amount;
+    env.storage().persistent().set(&(PENDING_DEPOSIT_KEY, recipient.clone()), &amount);
+    env.storage().persistent().set(&PENDING_TOTAL_KEY, &total);
+}
+
+/// Get the withdrawn amount held for retry_payout after the recipient couldn't receive it
+pub fn get_pending_payout(env: &Env) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&PENDING_PAYOUT_KEY)
+        .unwrap_or(0)
+}
+
+/// Set the withdrawn amount held for retry_payout
+pub fn set_pending_payout(env: &Env, amount: i128) {
+    env.storage().persistent().set(&PENDING_PAYOUT_KEY, &amount);
+}
+
+/// Get the public_withdraw executor lock: the claimant and the last ledger of its exclusivity
+pub fn get_executor_lock(env: &Env) -> Option<(Address, u32)> {
+    env.storage().persistent().get(&EXECUTOR_LOCK_KEY)
+}
+
+pub fn set_executor_lock(env: &Env, executor: &Address, until: u32) {
+    env.storage().persistent().set(&EXECUTOR_LOCK_KEY, &(executor.clone(), until));
+}
+
+/// Timelocks in force: those agreed by extend_timelocks, else the immutables' own
+pub fn get_timelocks(env: &Env, immutables: &Immutables) -> u64 {
+    env.storage().persistent().get(&TIMELOCKS_KEY).unwrap_or(immutables.timelocks)
+}
+
+/// get_timelocks without the immutables at hand, reading only their timelocks field
+pub fn get_current_timelocks(env: &Env) -> u64 {
+    env.storage().persistent().get(&TIMELOCKS_KEY)
+        .unwrap_or_else(|| get_immutables_field(env, "timelocks"))
+}
+
+pub fn set_timelocks(env: &Env, timelocks: u64) {
+    env.storage().persistent().set(&TIMELOCKS_KEY, &timelocks);
+}
+
+/// Get the address sweep_surplus pays, if the maker chose one
+pub fn get_surplus_sink(env: &Env) -> Option<Address> {
+    env.storage().persistent().get(&SURPLUS_SINK_KEY)
+}
+
+pub fn set_surplus_sink(env: &Env, sink: &Address) {
+    env.storage().persistent().set(&SURPLUS_SINK_KEY, sink);
+}
+
+/// Record the skew tolerance for the upcoming initialization
+pub fn set_pending_skew(env: &Env, skew_tolerance: u32) {
+    env.storage().instance().set(&PENDING_SKEW_KEY, &skew_tolerance);
+}
+
+/// Take the skew tolerance set before initialization (0 if none)
+pub fn take_pending_skew(env: &Env) -> u32 {
+    let skew_tolerance = env.storage().instance().get(&PENDING_SKEW_KEY).unwrap_or(0);
+    env.storage().instance().remove(&PENDING_SKEW_KEY);
+    skew_tolerance
+}
+
+/// Record the swap id for the upcoming initialization
+pub fn set_pending_swap_id(env: &Env, swap_id: &BytesN<32>) {
+    env.storage().instance().set(&PENDING_SWAP_ID_KEY, swap_id);
+}
+
+/// Take the swap id set before initialization, if any
+pub fn take_pending_swap_id(env: &Env) -> Option<BytesN<32>> {
+    let swap_id = env.storage().instance().get(&PENDING_SWAP_ID_KEY);
+    env.storage().instance().remove(&PENDING_SWAP_ID_KEY);
+    swap_id
+}
+
+/// Record the flags for the upcoming initialization
+pub fn set_pending_flags(env: &Env, flags: u32) {
+    env.storage().instance().set(&PENDING_FLAGS_KEY, &flags);
+}
+
+/// Take the flags set before initialization (DEFAULT_FLAGS if none)
+pub fn take_pending_flags(env: &Env) -> u32 {
+    let flags = env.storage().instance().get(&PENDING_FLAGS_KEY).unwrap_or(DEFAULT_FLAGS);
+    env.storage().instance().remove(&PENDING_FLAGS_KEY);
+    flags
+}
+
+/// Record the parts count for the upcoming initialization
+pub fn set_pending_parts(env: &Env, parts: u32) {
+    env.storage().instance().set(&PENDING_PARTS_KEY, &parts);
+}
+
+/// Take the parts count set before initialization (0 if none)
+pub fn take_pending_parts(env: &Env) -> u32 {
+    let parts = env.storage().instance().get(&PENDING_PARTS_KEY).unwrap_or(0);
+    env.storage().instance().remove(&PENDING_PARTS_KEY);
+    parts
+}
+
+/// The immutables' swap id, kept in instance storage so every event can carry it cheaply
+/// (and it outlives finalize)
+pub fn get_swap_id(env: &Env) -> Option<BytesN<32>> {
+    env.storage().instance().get(&SWAP_ID_KEY)
+}
+
+pub fn set_swap_id(env: &Env, swap_id: &BytesN<32>) {
+    env.storage().instance().set(&SWAP_ID_KEY, swap_id);
+}
+
+/// Get the keeper reward `keeper` can collect with claim_rewards
+pub fn get_reward(env: &Env, keeper: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&(REWARD_KEY, keeper.clone()))
+        .unwrap_or(0)
+}
+
+/// Set the keeper reward owed to `keeper`; rewards count towards the pending total
+pub fn set_reward(env: &Env, keeper: &Address, amount: i128) {
+    let total = get_pending_total(env) - get_reward(env, keeper) + amount;
+    env.storage().persistent().set(&(REWARD_KEY, keeper.clone()), &amount);
+    env.storage().persistent().set(&PENDING_TOTAL_KEY, &total);
+}
+
+/// Get the total of all safety deposits awaiting claim_deposit and rewards awaiting claim_rewards
+pub fn get_pending_total(env: &Env) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&PENDING_TOTAL_KEY)
+        .unwrap_or(0)
+}
@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test {
     use crate::{StellarEscrow, StellarEscrowClient, State};
     use soroban_sdk::{testutils::Address as _, Address, Env, BytesN, Bytes, token};
@@ -6,17 +7,17 @@ mod test {
     
     // Helper function to create a secret and its hash
     fn create_secret_and_hash(env: &Env) -> (BytesN<32>, BytesN<32>) {
-        let secret = BytesN::from_array(&env, &[42u8; 32]);
+        let secret = BytesN::from_array(env, &[42u8; 32]);
         let secret_bytes = Bytes::from(secret.clone());
-        let hashlock = env.crypto().sha256(&secret_bytes);
-        (secret, hashlock.to_bytes())
+        let hashlock = crate::hash_secret(env, &secret_bytes);
+        (secret, hashlock)
     }
     
     // Helper function to setup token with balances
     fn setup_token(env: &Env, admin: &Address, initial_balance: i128) -> Address {
         let token = env.register_stellar_asset_contract_v2(admin.clone());
-        let token_client = token::StellarAssetClient::new(&env, &token.address());
-        token_client.mint(&admin, &initial_balance);
+        let token_client = token::StellarAssetClient::new(env, &token.address());
+        token_client.mint(admin, &initial_balance);
         token.address()
     }
 
@@ -85,7 +86,7 @@ mod test {
         
         // Setup token with balance
         let token = setup_token(&env, &token_admin, 10000i128);
-        let token_client = token::StellarAssetClient::new(&env, &token);
+        let token_client = token::TokenClient::new(&env, &token);
         
         // Transfer tokens to maker
         token_client.transfer(&token_admin, &maker, &2000i128);
@@ -125,7 +126,7 @@ mod test {
     }
     
     #[test]
-    #[should_panic(expected = "InvalidSecret")]
+    #[should_panic(expected = "Error(Contract, #102)")] // InvalidSecret
     fn test_withdraw_with_wrong_secret() {
         let env = Env::default();
         env.mock_all_auths();
@@ -173,7 +174,7 @@ mod test {
         
         // Setup token
         let token = setup_token(&env, &token_admin, 10000i128);
-        let token_client = token::StellarAssetClient::new(&env, &token);
+        let token_client = token::TokenClient::new(&env, &token);
         
         // Transfer tokens to maker
         token_client.transfer(&token_admin, &maker, &2000i128);
@@ -209,7 +210,7 @@ mod test {
     }
     
     #[test]
-    #[should_panic(expected = "InvalidState")]
+    #[should_panic(expected = "Error(Contract, #133)")] // AlreadyExecuted
     fn test_withdraw_after_cancel() {
         let env = Env::default();
         env.mock_all_auths();
@@ -224,7 +225,7 @@ mod test {
         
         // Setup token
         let token = setup_token(&env, &token_admin, 10000i128);
-        let token_client = token::StellarAssetClient::new(&env, &token);
+        let token_client = token::TokenClient::new(&env, &token);
         
         // Transfer tokens to maker
         token_client.transfer(&token_admin, &maker, &2000i128);
@@ -253,7 +254,7 @@ mod test {
     }
     
     #[test]
-    #[should_panic(expected = "InvalidState")]
+    #[should_panic(expected = "Error(Contract, #133)")] // AlreadyExecuted
     fn test_cancel_after_withdraw() {
         let env = Env::default();
         env.mock_all_auths();
@@ -268,7 +269,7 @@ mod test {
         
         // Setup token
         let token = setup_token(&env, &token_admin, 10000i128);
-        let token_client = token::StellarAssetClient::new(&env, &token);
+        let token_client = token::TokenClient::new(&env, &token);
         
         // Transfer tokens to maker
         token_client.transfer(&token_admin, &maker, &2000i128);
@@ -311,7 +312,7 @@ mod test {
         
         // Setup token
         let token = setup_token(&env, &token_admin, 10000i128);
-        let token_client = token::StellarAssetClient::new(&env, &token);
+        let token_client = token::TokenClient::new(&env, &token);
         
         // Transfer tokens to maker
         token_client.transfer(&token_admin, &maker, &2000i128);
@@ -385,6 +386,17 @@ mod test {
             amount: 1000i128,
             safety_deposit: 100i128,
             timelocks: 12345u64,
+            max_secret_len: 32,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: crate::types::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: crate::types::DEFAULT_FLAGS,
+            parts: 0,
         };
         
         // Same immutables should produce same hash
@@ -398,6 +410,61 @@ mod test {
         let hash3 = immutables2.hash(&env);
         assert_ne!(hash1, hash3);
     }
+
+    #[test]
+    fn test_optional_immutables_fields_do_not_collide() {
+        let env = Env::default();
+        let contract = Address::generate(&env);
+        let base = crate::types::Immutables {
+            order_hash: BytesN::from_array(&env, &[1u8; 32]),
+            hashlock: BytesN::from_array(&env, &[2u8; 32]),
+            maker: Address::generate(&env),
+            taker: Address::generate(&env),
+            token: Address::generate(&env),
+            amount: 1000i128,
+            safety_deposit: 100i128,
+            timelocks: 12345u64,
+            max_secret_len: 32,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: crate::types::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: crate::types::DEFAULT_FLAGS,
+            parts: 0,
+        };
+
+        // The same address in each optional address field
+        let mut hashes = soroban_sdk::Vec::<BytesN<32>>::new(&env);
+        for field in 0..4 {
+            let mut immutables = base.clone();
+            match field {
+                0 => immutables.beneficiary = Some(contract.clone()),
+                1 => immutables.finality_oracle = Some(contract.clone()),
+                2 => immutables.pre_funding_hook = Some(contract.clone()),
+                _ => immutables.post_payout_hook = Some(contract.clone()),
+            }
+            let hash = immutables.hash(&env);
+            assert!(!hashes.contains(&hash));
+            hashes.push_back(hash);
+        }
+
+        // The same 32 bytes as a second hashlock or a swap id, and the same u32 as a skew or flags
+        let mut with_hashlock_b = base.clone();
+        with_hashlock_b.hashlock_b = Some(BytesN::from_array(&env, &[3u8; 32]));
+        let mut with_swap_id = base.clone();
+        with_swap_id.swap_id = Some(BytesN::from_array(&env, &[3u8; 32]));
+        assert_ne!(with_hashlock_b.hash(&env), with_swap_id.hash(&env));
+
+        let mut with_skew = base.clone();
+        with_skew.skew_tolerance = 7;
+        let mut with_flags = base.clone();
+        with_flags.flags = 7;
+        assert_ne!(with_skew.hash(&env), with_flags.hash(&env));
+    }
     
     #[test]
     fn test_safety_deposit() {
@@ -433,4 +500,2321 @@ mod test {
         let immutables = client.get_immutables();
         assert_eq!(immutables.safety_deposit, safety_deposit);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pull_funding() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (secret, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Maker approves the escrow, then pulls
+        let expiration = env.ledger().sequence() + 100;
+        token_client.approve(&maker, &contract_id, &1000i128, &expiration);
+        client.pull_funding(&maker);
+        
+        assert_eq!(client.get_state(), State::Funded);
+        assert_eq!(token_client.balance(&contract_id), 1000i128);
+        assert_eq!(token_client.balance(&maker), 1000i128);
+        
+        // Funded escrows can be withdrawn as usual
+        client.withdraw(&secret, &false);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(token_client.balance(&taker), 1000i128);
+    }
+    
+    #[test]
+    fn test_pull_funding_needs_the_funders_auth() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &taker, &1000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // The taker approved the escrow for some other purpose; a third party can't spend it
+        let expiration = env.ledger().sequence() + 100;
+        token_client.approve(&taker, &contract_id, &1000i128, &expiration);
+        env.set_auths(&[]);
+        assert!(client.try_pull_funding(&taker).is_err());
+        assert_eq!(token_client.balance(&taker), 1000i128);
+        assert_eq!(client.get_state(), State::Active);
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #109)")] // InsufficientBalance
+    fn test_pull_funding_insufficient_allowance() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Approval short of the escrow amount
+        let expiration = env.ledger().sequence() + 100;
+        token_client.approve(&maker, &contract_id, &999i128, &expiration);
+        client.pull_funding(&maker);
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #111)")] // OverFunded
+    fn test_pull_funding_rejects_overfunding() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &3000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Tokens already pushed to the escrow, then pulled again
+        token_client.transfer(&maker, &contract_id, &1000i128);
+        let expiration = env.ledger().sequence() + 100;
+        token_client.approve(&maker, &contract_id, &1000i128, &expiration);
+        client.pull_funding(&maker);
+    }
+
+    #[test]
+    fn test_refund_excess_keeps_unconfirmed_push_funding() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (secret, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Pushed without confirm_funding: the locked amount is not excess
+        token_client.transfer(&maker, &contract_id, &1000i128);
+        assert_eq!(client.refund_excess(), 0i128);
+        assert_eq!(token_client.balance(&contract_id), 1000i128);
+        
+        // Only what exceeds the escrow amount is
+        token_client.transfer(&token_admin, &contract_id, &250i128);
+        assert_eq!(client.refund_excess(), 250i128);
+        assert_eq!(token_client.balance(&maker), 1250i128);
+        
+        client.withdraw(&secret, &false);
+        assert_eq!(token_client.balance(&taker), 1000i128);
+    }
+
+    #[test]
+    fn test_confirm_funding_and_refund_excess() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (secret, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Push the exact amount and confirm it
+        token_client.transfer(&maker, &contract_id, &1000i128);
+        client.confirm_funding();
+        assert_eq!(client.get_state(), State::Funded);
+        assert_eq!(client.get_funded_amount(), 1000i128);
+        
+        // Stray tokens sent after funding are refundable to the maker
+        token_client.transfer(&token_admin, &contract_id, &250i128);
+        assert_eq!(client.refund_excess(), 250i128);
+        assert_eq!(token_client.balance(&maker), 1250i128);
+        assert_eq!(token_client.balance(&contract_id), 1000i128);
+        
+        // Withdraw pays exactly the funded amount
+        client.withdraw(&secret, &false);
+        assert_eq!(token_client.balance(&taker), 1000i128);
+        assert_eq!(client.get_funded_amount(), 0i128);
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #111)")] // OverFunded
+    fn test_confirm_funding_rejects_overfunding() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        token_client.transfer(&maker, &contract_id, &1500i128);
+        client.confirm_funding();
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #112)")] // AlreadyFunded
+    fn test_double_funding_rejected() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &3000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        let expiration = env.ledger().sequence() + 100;
+        token_client.approve(&maker, &contract_id, &2000i128, &expiration);
+        client.pull_funding(&maker);
+        client.pull_funding(&maker);
+    }
+    
+    #[test]
+    fn test_cancel_refunds_funded_amount_only() {
+        let env = Env::default();
+        env.mock_all_auths();
+        
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token_admin = Address::generate(&env);
+        
+        let token = setup_token(&env, &token_admin, 10000i128);
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&token_admin, &maker, &2000i128);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+        
+        // Under-funded push: only what was sent is refunded on cancel
+        token_client.transfer(&maker, &contract_id, &400i128);
+        client.cancel(&maker);
+        
+        assert_eq!(client.get_state(), State::Cancelled);
+        assert_eq!(token_client.balance(&maker), 2000i128);
+        assert_eq!(token_client.balance(&contract_id), 0i128);
+    }
+
+    #[test]
+    fn test_funded_fixture_withdraw_at_stage() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 500i128);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_state(), State::Funded);
+        
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 500i128);
+    }
+
+    #[test]
+    fn test_cancel_after_partial_fill_refunds_remainder() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let token_client = fixture.token_client(&env);
+        
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_partial(&fixture.secret, &300i128);
+        assert_eq!(client.get_state(), State::Funded);
+        assert_eq!(client.get_filled_amount(), 300i128);
+        assert_eq!(client.get_funded_amount(), 700i128);
+        
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        client.cancel(&fixture.maker);
+        
+        assert_eq!(client.get_state(), State::Cancelled);
+        assert_eq!(token_client.balance(&fixture.taker), 300i128);
+        assert_eq!(token_client.balance(&fixture.maker), 700i128);
+        assert_eq!(token_client.balance(&fixture.escrow), 0i128);
+    }
+    
+    #[test]
+    fn test_partial_fills_complete_escrow() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_partial(&fixture.secret, &400i128);
+        client.withdraw_partial(&fixture.secret, &600i128);
+        
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(client.get_filled_amount(), 1000i128);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #105)")] // InvalidAmount
+    fn test_partial_fill_exceeding_remainder() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_partial(&fixture.secret, &600i128);
+        client.withdraw_partial(&fixture.secret, &600i128);
+    }
+
+    #[test]
+    fn test_ping_reports_each_stage_once() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        
+        // Default timelocks start stages 0 and 4 at t=10
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        assert_eq!(client.ping(), 2);
+        assert_eq!(client.ping(), 0);
+        
+        // Stages 1, 2, 5 and 6 start by t=30
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        assert_eq!(client.ping(), 4);
+        assert_eq!(client.ping(), 0);
+    }
+
+    #[test]
+    fn test_evm_address_checksum() {
+        use crate::evm::EvmAddress;
+        use soroban_sdk::String;
+        
+        let env = Env::default();
+        
+        // EIP-55 reference vector
+        let checksummed = String::from_str(&env, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        let address = EvmAddress::from_hex(&env, &checksummed).unwrap();
+        assert_eq!(address.to_checksum_string(&env), checksummed);
+        assert!(!address.is_zero());
+        
+        // Unchecksummed lowercase input parses to the same address
+        let lowercase = String::from_str(&env, "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(EvmAddress::from_hex(&env, &lowercase).unwrap(), address);
+        
+        // Wrong checksum, bad length and non-hex input are rejected
+        let bad_checksum = String::from_str(&env, "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert!(EvmAddress::from_hex(&env, &bad_checksum).is_err());
+        assert!(EvmAddress::from_hex(&env, &String::from_str(&env, "0x5aAeb6")).is_err());
+        let non_hex = String::from_str(&env, "0xzaaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert!(EvmAddress::from_hex(&env, &non_hex).is_err());
+        
+        // Raw bytes must be exactly 20 long
+        assert!(EvmAddress::from_bytes(&env, &Bytes::from_array(&env, &[0u8; 20])).unwrap().is_zero());
+        assert!(EvmAddress::from_bytes(&env, &Bytes::from_array(&env, &[0u8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_deploy_with_details() {
+        let env = Env::default();
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        let details = Bytes::from_slice(&env, b"ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3ef");
+        assert!(details.len() <= crate::MAX_DETAILS_LEN);
+        
+        // No details before deployment
+        assert_eq!(client.get_details(), None);
+        
+        client.deploy_with_details(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &1000i128,
+            &0i128,
+            &0u64,
+            &details,
+        );
+        
+        assert_eq!(client.get_state(), State::Active);
+        assert_eq!(client.get_details(), Some(details));
+    }
+    
+    #[test]
+    #[should_panic(expected = "Error(Contract, #114)")] // DetailsTooLong
+    fn test_deploy_with_details_too_long() {
+        let env = Env::default();
+        let contract_id = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &contract_id);
+        
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy_with_details(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &1000i128,
+            &0i128,
+            &0u64,
+            &Bytes::from_array(&env, &[7u8; 65]),
+        );
+    }
+
+    #[test]
+    fn test_withdraw_pays_deposit_inline() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(&env, 1000i128, 100i128);
+        let client = fixture.client(&env);
+        let native = crate::testutils::MockNativeTokenClient::new(&env, &crate::get_native_token_address(&env));
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+        assert_eq!(native.balance(&fixture.maker), 100i128);
+        assert_eq!(client.get_pending_deposit(&fixture.maker), 0i128);
+    }
+
+    #[test]
+    fn test_failed_deposit_transfer_is_claimable() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(&env, 1000i128, 100i128);
+        let client = fixture.client(&env);
+        let native = crate::testutils::MockNativeTokenClient::new(&env, &crate::get_native_token_address(&env));
+        native.set_blocked(&fixture.maker, &true);
+
+        // The token payout goes through even though the deposit transfer fails
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+        assert_eq!(native.balance(&fixture.maker), 0i128);
+        assert_eq!(client.get_pending_deposit(&fixture.maker), 100i128);
+
+        // Pending deposits are not treated as excess
+        client.refund_excess();
+        assert_eq!(native.balance(&fixture.escrow), 100i128);
+
+        native.set_blocked(&fixture.maker, &false);
+        assert_eq!(client.claim_deposit(&fixture.maker), 100i128);
+        assert_eq!(native.balance(&fixture.maker), 100i128);
+        assert_eq!(client.get_pending_deposit(&fixture.maker), 0i128);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #105)")] // InvalidAmount
+    fn test_claim_deposit_without_pending() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        fixture.client(&env).claim_deposit(&fixture.maker);
+    }
+
+    fn deploy_with_preimage<'a>(env: &'a Env, preimage: &[u8], max_secret_len: u32) -> (StellarEscrowClient<'a>, Address, Address) {
+        env.mock_all_auths();
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(env, &escrow);
+        let maker = Address::generate(env);
+        let taker = Address::generate(env);
+        let token = setup_token(env, &Address::generate(env), 0);
+        token::StellarAssetClient::new(env, &token).mint(&escrow, &1000i128);
+
+        let hashlock = crate::hash_secret(env, &Bytes::from_slice(env, preimage));
+        client.deploy_with_preimage_len(
+            &BytesN::from_array(env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &crate::testutils::default_timelocks(),
+            &max_secret_len,
+        );
+        (client, token, taker)
+    }
+
+    #[test]
+    fn test_withdraw_with_64_byte_preimage() {
+        let env = Env::default();
+        let (client, token, taker) = deploy_with_preimage(&env, &[9u8; 64], 64);
+        assert_eq!(client.get_immutables().max_secret_len, 64);
+
+        crate::testutils::advance_to_stage(&env, crate::testutils::default_timelocks(), crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_preimage(&Bytes::from_slice(&env, &[9u8; 64]));
+
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(token::TokenClient::new(&env, &token).balance(&taker), 1000i128);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #115)")] // InvalidSecretLength
+    fn test_preimage_longer_than_allowed() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        fixture.client(&env).withdraw_preimage(&Bytes::from_slice(&env, &[9u8; 64]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #115)")] // InvalidSecretLength
+    fn test_deploy_rejects_preimage_len_above_max() {
+        let env = Env::default();
+        deploy_with_preimage(&env, &[9u8; 65], crate::MAX_PREIMAGE_LEN + 1);
+    }
+
+    #[test]
+    fn test_refresh_state_marks_expiry() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        assert_eq!(client.refresh_state(), State::Funded);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        assert_eq!(client.refresh_state(), State::Expired);
+        assert_eq!(client.get_state(), State::Expired);
+
+        // Cancel still returns the recorded funding
+        client.cancel(&fixture.maker);
+        assert_eq!(client.get_state(), State::Cancelled);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.maker), 1000i128);
+        assert_eq!(client.refresh_state(), State::Cancelled);
+    }
+
+    #[test]
+    fn test_extend_timelocks_by_mutual_consent() {
+        use crate::timelocks::pack_timelocks;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let hash = client.get_immutables_hash();
+
+        // Moving any stage earlier is rejected
+        let earlier = pack_timelocks([10, 20, 30, 40, 5, 20, 60]);
+        let result = client.try_extend_timelocks(&earlier);
+        assert_eq!(result.err().unwrap().unwrap(), crate::errors::Error::TimelocksNotLater.into());
+        let result = client.try_extend_timelocks(&fixture.timelocks);
+        assert_eq!(result.err().unwrap().unwrap(), crate::errors::Error::TimelocksNotLater.into());
+
+        let extended = pack_timelocks([10, 20, 30, 40, 10, 20, 60]);
+        client.extend_timelocks(&extended);
+        let auths = env.auths();
+        assert_eq!((auths.len(), &auths[0].0, &auths[1].0), (2, &fixture.maker, &fixture.taker));
+        assert_eq!(client.get_timelocks(), extended);
+        assert_eq!(client.get_immutables().timelocks, fixture.timelocks);
+        assert_eq!(client.get_immutables_hash(), hash);
+
+        // The original cancellation deadline no longer lets anyone cancel
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        assert!(client.try_cancel(&Address::generate(&env)).is_err());
+        assert_eq!(client.refresh_state(), State::Funded);
+        client.withdraw(&fixture.secret, &false);
+
+        let result = client.try_extend_timelocks(&pack_timelocks([10, 20, 30, 40, 10, 20, 90]));
+        assert_eq!(result.err().unwrap().unwrap(), crate::errors::Error::InvalidState.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #101)")] // InvalidState
+    fn test_withdraw_after_expiry() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        client.refresh_state();
+        client.withdraw(&fixture.secret, &false);
+    }
+
+    #[test]
+    fn test_conforms_to_shared_escrow_interface() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fusion_shared::EscrowClient::new(&env, &fixture.escrow);
+
+        assert_eq!(client.get_state(), fusion_shared::State::Funded);
+        assert_eq!(client.get_immutables().hashlock, fixture.hashlock);
+        assert_eq!(client.get_funded_amount(), 1000i128);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.get_state(), fusion_shared::State::Withdrawn);
+        assert_eq!(client.get_filled_amount(), 1000i128);
+    }
+
+    #[test]
+    fn test_finalize_reclaims_storage_after_grace_period() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+
+        let withdrawn_at = env.ledger().timestamp();
+        assert_eq!(client.get_terminal_at(), Some(withdrawn_at));
+        env.ledger().with_mut(|li| li.timestamp = withdrawn_at + crate::FINALIZE_GRACE_PERIOD);
+        client.finalize();
+
+        assert!(client.is_finalized());
+        assert_eq!(client.get_terminal_at(), None);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(client.get_filled_amount(), 0);
+        assert!(client.try_get_immutables().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #116)")] // FinalizeTooEarly
+    fn test_finalize_before_grace_period() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        client.cancel(&fixture.maker);
+        client.finalize();
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #101)")] // InvalidState
+    fn test_finalize_open_escrow() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        fixture.client(&env).finalize();
+    }
+
+    #[test]
+    fn test_withdraw_to_muxed_recipient_with_memo() {
+        use soroban_sdk::{testutils::MuxedAddress as _, MuxedAddress};
+
+        let env = Env::default();
+        env.mock_all_auths();
+
+        // Muxed accounts need a trustline for Stellar assets, so escrow the mock native token
+        let native = crate::testutils::register_native_token(&env);
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let (secret, hashlock) = crate::testutils::secret_and_hashlock(&env, 42);
+        let timelocks = crate::testutils::default_timelocks();
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &native.address,
+            &1000i128,
+            &0i128,
+            &timelocks,
+        );
+        native.mint(&escrow, &1000i128);
+        client.confirm_funding();
+
+        let exchange = MuxedAddress::generate(&env);
+        let reference = Bytes::from_slice(&env, b"INV-2024-0042");
+        crate::testutils::advance_to_stage(&env, timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_to(&secret, &exchange, &Some(reference.clone()));
+
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(native.balance(&exchange.address()), 1000i128);
+        assert_eq!(native.balance(&taker), 0);
+
+        let payout = client.get_payout_memo().unwrap();
+        assert_eq!(payout.recipient, exchange.address());
+        assert_eq!(payout.muxed_id, exchange.id());
+        assert_eq!(payout.memo, Some(reference));
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #117)")] // MemoTooLong
+    fn test_withdraw_to_memo_too_long() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let memo = Bytes::from_slice(&env, &[b'x'; 29]);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        fixture.client(&env).withdraw_to(&fixture.secret, &fixture.taker, &Some(memo));
+    }
+
+    #[test]
+    fn test_dry_run_withdraw_reports_failures() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let wrong_secret = BytesN::from_array(&env, &[7u8; 32]);
+
+        let early = client.dry_run_withdraw(&fixture.secret);
+        assert!(!early.success);
+        assert_eq!(early.error, Some(crate::errors::Error::TimelockNotExpired as u32));
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        let wrong = client.dry_run_withdraw(&wrong_secret);
+        assert_eq!(wrong.error, Some(crate::errors::Error::InvalidSecret as u32));
+
+        let ok = client.dry_run_withdraw(&fixture.secret);
+        assert!(ok.success);
+        assert_eq!((ok.error, ok.amount, ok.deposit), (None, 1000i128, 0));
+
+        // Dry runs don't change state
+        assert_eq!(client.get_state(), State::Funded);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.dry_run_withdraw(&fixture.secret).error, Some(crate::errors::Error::AlreadyExecuted as u32));
+    }
+
+    #[test]
+    fn test_dry_run_cancel_checks_timelocks() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let stranger = Address::generate(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        assert_eq!(client.dry_run_cancel(&fixture.maker).error, Some(crate::errors::Error::CannotCancel as u32));
+
+        // Public cancellation lets anyone cancel
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        let ok = client.dry_run_cancel(&stranger);
+        assert!(ok.success);
+        assert_eq!(ok.amount, 1000i128);
+    }
+
+    #[test]
+    fn test_get_balances_reports_deficit_and_surplus() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        let maker = Address::generate(&env);
+        let taker = Address::generate(&env);
+        let token = setup_token(&env, &maker, 2000i128);
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &taker,
+            &token,
+            &1000i128,
+            &0i128,
+            &crate::testutils::default_timelocks(),
+        );
+
+        let token_client = token::TokenClient::new(&env, &token);
+        token_client.transfer(&maker, &escrow, &600i128);
+        let balances = client.get_balances();
+        assert_eq!(balances.token_balance, 600i128);
+        assert_eq!(balances.recorded_amount, 0);
+        assert_eq!(balances.token_surplus, -400i128);
+
+        token_client.transfer(&maker, &escrow, &400i128);
+        client.confirm_funding();
+        token_client.transfer(&maker, &escrow, &100i128);
+        let balances = client.get_balances();
+        assert_eq!(balances.recorded_amount, 1000i128);
+        assert_eq!(balances.token_surplus, 100i128);
+        assert_eq!(balances.native_surplus, 0);
+    }
+
+    fn deploy_two_hashlock_escrow(env: &Env) -> (crate::testutils::EscrowFixture, BytesN<32>) {
+        let fixture = crate::testutils::create_funded_escrow(env, 1000i128);
+        let (secret_b, hashlock_b) = crate::testutils::secret_and_hashlock(env, 43);
+
+        // Redeploy over a fresh contract with both hashlocks, funded the same way
+        let fixture = crate::testutils::redeploy_escrow(env, fixture, 0, |client, fixture| {
+            client.deploy_with_two_hashlocks(
+                &fixture.order_hash,
+                &fixture.hashlock,
+                &hashlock_b,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &fixture.amount,
+                &0i128,
+                &fixture.timelocks,
+            );
+        });
+
+        (fixture, secret_b)
+    }
+
+    #[test]
+    fn test_withdraw2_requires_both_secrets() {
+        let env = Env::default();
+        let (fixture, secret_b) = deploy_two_hashlock_escrow(&env);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        assert!(client.try_withdraw(&fixture.secret, &false).is_err());
+        assert!(client.try_withdraw2(&fixture.secret, &fixture.secret).is_err());
+
+        client.withdraw2(&fixture.secret, &secret_b);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #118)")] // SecondSecretRequired
+    fn test_single_secret_withdraw_on_two_hashlock_escrow() {
+        let env = Env::default();
+        let (fixture, _) = deploy_two_hashlock_escrow(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        fixture.client(&env).withdraw(&fixture.secret, &false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #119)")] // DuplicateHashlock
+    fn test_two_hashlocks_must_differ() {
+        let env = Env::default();
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        let (_, hashlock) = create_secret_and_hash(&env);
+        let party = Address::generate(&env);
+        client.deploy_with_two_hashlocks(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &hashlock,
+            &party,
+            &party,
+            &party,
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+    }
+
+    #[test]
+    fn test_confirm_funding_emits_funded_event() {
+        use soroban_sdk::{symbol_short, testutils::Events, IntoVal, TryIntoVal, Val, Vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+
+        let topics: Vec<Val> = (symbol_short!("v3"), symbol_short!("escrow"), symbol_short!("funded")).into_val(&env);
+        let (contract, _, data) = env
+            .events()
+            .all()
+            .iter()
+            .find(|(_, event_topics, _)| *event_topics == topics)
+            .expect("funded event");
+        let (swap_id, (amount, token, funder, total)): (Option<BytesN<32>>, (i128, Address, Option<Address>, i128)) =
+            data.try_into_val(&env).unwrap();
+
+        assert_eq!(contract, fixture.escrow);
+        assert_eq!(swap_id, None);
+        assert_eq!((amount, token, funder, total), (1000i128, fixture.token, None, 1000i128));
+    }
+
+    // SRC_CANCELLATION 30, SRC_PUBLIC_CANCELLATION 40, DST_CANCELLATION 50
+    fn deploy_sided_escrow(env: &Env, side: crate::types::EscrowSide) -> crate::testutils::EscrowFixture {
+        let fixture = crate::testutils::create_funded_escrow(env, 1000i128);
+        let timelocks = crate::timelocks::pack_timelocks([10, 20, 30, 40, 10, 20, 50]);
+
+        let fixture = crate::testutils::redeploy_escrow(
+            env,
+            crate::testutils::EscrowFixture { timelocks, ..fixture },
+            0,
+            |client, fixture| {
+                let deploy = match side {
+                    crate::types::EscrowSide::Src => StellarEscrowClient::deploy_src,
+                    crate::types::EscrowSide::Dst => StellarEscrowClient::deploy,
+                };
+                deploy(
+                    client,
+                    &fixture.order_hash,
+                    &fixture.hashlock,
+                    &fixture.maker,
+                    &fixture.taker,
+                    &fixture.token,
+                    &fixture.amount,
+                    &0i128,
+                    &fixture.timelocks,
+                );
+            },
+        );
+        assert_eq!(fixture.client(env).get_side(), side);
+
+        fixture
+    }
+
+    fn cancel_error(client: &StellarEscrowClient, caller: &Address) -> Option<u32> {
+        match client.try_cancel(caller) {
+            Ok(_) => None,
+            Err(Ok(error)) => Some(error.get_code()),
+            Err(Err(_)) => panic!("cancel failed without a contract error"),
+        }
+    }
+
+    #[test]
+    fn test_dst_private_cancellation_is_taker_only() {
+        let env = Env::default();
+        let fixture = deploy_sided_escrow(&env, crate::types::EscrowSide::Dst);
+        let client = fixture.client(&env);
+        let stranger = Address::generate(&env);
+
+        env.ledger().with_mut(|li| li.timestamp = 30);
+        assert_eq!(cancel_error(&client, &fixture.maker), Some(crate::errors::Error::CannotCancel as u32));
+        assert_eq!(cancel_error(&client, &stranger), Some(crate::errors::Error::CannotCancel as u32));
+        assert_eq!(client.dry_run_cancel(&fixture.maker).error, Some(crate::errors::Error::CannotCancel as u32));
+
+        // SRC_PUBLIC_CANCELLATION doesn't open dst escrows
+        env.ledger().with_mut(|li| li.timestamp = 40);
+        assert_eq!(cancel_error(&client, &fixture.maker), Some(crate::errors::Error::CannotCancel as u32));
+
+        assert_eq!(cancel_error(&client, &fixture.taker), None);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.maker), 1000i128);
+    }
+
+    #[test]
+    fn test_dst_public_cancellation_opens_to_anyone() {
+        let env = Env::default();
+        let fixture = deploy_sided_escrow(&env, crate::types::EscrowSide::Dst);
+        let client = fixture.client(&env);
+
+        env.ledger().with_mut(|li| li.timestamp = 50);
+        assert_eq!(cancel_error(&client, &Address::generate(&env)), None);
+        assert_eq!(client.get_state(), State::Cancelled);
+    }
+
+    #[test]
+    fn test_src_private_cancellation_allows_maker_and_taker() {
+        let env = Env::default();
+        for caller in 0..2 {
+            let fixture = deploy_sided_escrow(&env, crate::types::EscrowSide::Src);
+            let client = fixture.client(&env);
+            let caller = if caller == 0 { &fixture.maker } else { &fixture.taker };
+
+            env.ledger().with_mut(|li| li.timestamp = 20);
+            assert_eq!(cancel_error(&client, caller), Some(crate::errors::Error::CannotCancel as u32));
+
+            env.ledger().with_mut(|li| li.timestamp = 30);
+            assert_eq!(cancel_error(&client, &Address::generate(&env)), Some(crate::errors::Error::CannotCancel as u32));
+            assert_eq!(cancel_error(&client, caller), None);
+            assert_eq!(client.get_state(), State::Cancelled);
+            env.ledger().with_mut(|li| li.timestamp = 0);
+        }
+    }
+
+    #[test]
+    fn test_src_public_cancellation_opens_to_anyone() {
+        let env = Env::default();
+        let fixture = deploy_sided_escrow(&env, crate::types::EscrowSide::Src);
+        let client = fixture.client(&env);
+
+        env.ledger().with_mut(|li| li.timestamp = 40);
+        assert_eq!(cancel_error(&client, &Address::generate(&env)), None);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.maker), 1000i128);
+    }
+
+    #[test]
+    fn test_withdraw_partial_with_proof() {
+        use fusion_shared::merkle;
+        use soroban_sdk::vec;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let secrets: [(BytesN<32>, BytesN<32>); 2] =
+            core::array::from_fn(|i| crate::testutils::secret_and_hashlock(&env, i as u8 + 1));
+        let leaves: [BytesN<32>; 2] =
+            core::array::from_fn(|i| merkle::secret_leaf(&env, i as u64, &secrets[i].1));
+        let root = merkle::hash_pair(&env, &leaves[0], &leaves[1]);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.set_parts(&1u32);
+        client.deploy(
+            &fixture.order_hash,
+            &root,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+
+        assert!(client.verify_proof(&root, &leaves[0], &vec![&env, leaves[1].clone()]));
+        // Wrong index for the secret
+        assert!(client
+            .try_withdraw_partial_with_proof(&secrets[0].0, &1u64, &vec![&env, leaves[0].clone()], &400i128)
+            .is_err());
+
+        client.withdraw_partial_with_proof(&secrets[0].0, &0u64, &vec![&env, leaves[1].clone()], &400i128);
+        let reused = client.try_withdraw_partial_with_proof(&secrets[0].0, &0u64, &vec![&env, leaves[1].clone()], &100i128);
+        assert_eq!(
+            reused.err().unwrap().unwrap().get_code(),
+            crate::errors::Error::SecretAlreadyUsed as u32
+        );
+        assert_eq!(client.get_state(), State::Funded);
+
+        client.withdraw_partial_with_proof(&secrets[1].0, &1u64, &vec![&env, leaves[0].clone()], &600i128);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(client.get_revealed_indices(), vec![&env, 0u64, 1u64]);
+        assert_eq!(client.get_revealed_secret(), Some(Bytes::from(secrets[1].0.clone())));
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn test_trace_events_report_timelock_checks() {
+        use soroban_sdk::{symbol_short, testutils::Events, IntoVal, Val, Vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+
+        let topics: Vec<Val> = (symbol_short!("trace"), symbol_short!("timelock")).into_val(&env);
+        let traced = env.events().all().iter().any(|(contract, event_topics, _)| {
+            contract == fixture.escrow && event_topics == topics
+        });
+        assert!(traced);
+    }
+
+    fn deploy_native_escrow(env: &Env) -> (crate::testutils::EscrowFixture, crate::testutils::MockNativeTokenClient<'_>) {
+        let fixture = crate::testutils::create_funded_escrow(env, 1000i128);
+        let native = crate::testutils::register_native_token(env);
+
+        // Amount and deposit both come from the one native balance
+        let fixture = crate::testutils::redeploy_escrow(
+            env,
+            crate::testutils::EscrowFixture { token: native.address.clone(), ..fixture },
+            100i128,
+            |client, fixture| {
+                client.deploy(
+                    &fixture.order_hash,
+                    &fixture.hashlock,
+                    &fixture.maker,
+                    &fixture.taker,
+                    &fixture.token,
+                    &fixture.amount,
+                    &100i128,
+                    &fixture.timelocks,
+                );
+            },
+        );
+
+        (fixture, native)
+    }
+
+    #[test]
+    fn test_native_escrow_pays_amount_and_deposit_from_one_balance() {
+        let env = Env::default();
+        let (fixture, native) = deploy_native_escrow(&env);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_funded_amount(), 1000i128);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+
+        assert_eq!(native.balance(&fixture.taker), 1000i128);
+        assert_eq!(native.balance(&fixture.maker), 100i128);
+        assert_eq!(native.balance(&fixture.escrow), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #109)")] // InsufficientBalance
+    fn test_native_escrow_fails_closed_when_balance_misses_a_bucket() {
+        let env = Env::default();
+        let (fixture, native) = deploy_native_escrow(&env);
+        let client = fixture.client(&env);
+
+        // Lumens leave the escrow after confirmation: the amount alone is still covered
+        native.transfer(&fixture.escrow, Address::generate(&env), &50i128);
+        assert!(!client.dry_run_cancel(&fixture.taker).success);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #105)")] // InvalidAmount
+    fn test_deploy_rejects_negative_safety_deposit() {
+        let env = Env::default();
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        let (_, hashlock) = create_secret_and_hash(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &Address::generate(&env),
+            &1000i128,
+            &-1i128,
+            &0u64,
+        );
+    }
+
+    #[test]
+    fn test_withdraw_stores_revealed_secret() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_revealed_secret(), None);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+
+        assert_eq!(client.get_revealed_secret(), Some(Bytes::from(fixture.secret.clone())));
+        assert_eq!(client.get_revealed_secret_b(), None);
+        assert!(client.get_revealed_indices().is_empty());
+    }
+
+    #[test]
+    fn test_withdrawals_pay_designated_beneficiary() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let treasury = Address::generate(&env);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.deploy_with_beneficiary(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+            &treasury,
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        assert_eq!(client.get_immutables().beneficiary, Some(treasury.clone()));
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+
+        // withdraw_to can't redirect the payout away from the beneficiary
+        let other = Address::generate(&env);
+        let result = client.try_withdraw_to(&fixture.secret, &other, &None);
+        assert_eq!(result.err().unwrap().unwrap().get_code(), crate::errors::Error::BeneficiaryMismatch as u32);
+
+        client.withdraw(&fixture.secret, &false);
+        let token_client = fixture.token_client(&env);
+        assert_eq!(token_client.balance(&treasury), 1000);
+        assert_eq!(token_client.balance(&fixture.taker), 0);
+    }
+
+    fn split(executor_bps: u32, maker_bps: u32, protocol_bps: u32) -> crate::types::DepositSplit {
+        crate::types::DepositSplit { executor_bps, maker_bps, protocol_bps }
+    }
+
+    fn deploy_incentivized_escrow(env: &Env, incentives: &crate::types::DepositIncentives) -> crate::testutils::EscrowFixture {
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(env, 1000i128, 1);
+
+        crate::testutils::redeploy_escrow(env, fixture, 1000i128, |client, fixture| {
+            client.deploy_with_incentives(
+                &fixture.order_hash,
+                &fixture.hashlock,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &fixture.amount,
+                &1000i128,
+                &fixture.timelocks,
+                incentives,
+            );
+        })
+    }
+
+    #[test]
+    fn test_public_withdraw_splits_deposit_per_incentives() {
+        let env = Env::default();
+        let protocol = Address::generate(&env);
+        let incentives = crate::types::DepositIncentives {
+            withdraw: split(0, 10_000, 0),
+            public_withdraw: split(6_000, 3_000, 1_000),
+            cancel: split(0, 0, 10_000),
+            public_cancel: split(5_000, 5_000, 0),
+            protocol: protocol.clone(),
+            remainder: crate::types::DepositParty::Maker,
+            dust_threshold: 0,
+        };
+        let fixture = deploy_incentivized_escrow(&env, &incentives);
+        let keeper = Address::generate(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        let client = fixture.client(&env);
+        client.public_withdraw(&fixture.secret, &keeper);
+
+        let native = crate::testutils::MockNativeTokenClient::new(&env, &crate::get_native_token_address(&env));
+        assert_eq!(native.balance(&fixture.maker), 300);
+        assert_eq!(native.balance(&protocol), 100);
+        assert_eq!(native.balance(&fixture.taker), 0);
+
+        // The keeper's share is accrued, not paid inline
+        assert_eq!(native.balance(&keeper), 0);
+        assert_eq!(client.get_rewards(&keeper), 600);
+        assert_eq!(client.get_balances().native_surplus, 0);
+        assert_eq!(client.claim_rewards(&keeper), 600);
+        assert_eq!(native.balance(&keeper), 600);
+        assert_eq!(client.get_rewards(&keeper), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #122)")] // InvalidIncentives
+    fn test_incentive_splits_must_sum_to_whole_deposit() {
+        let env = Env::default();
+        let incentives = crate::types::DepositIncentives {
+            withdraw: split(0, 10_000, 0),
+            public_withdraw: split(6_000, 3_000, 0),
+            cancel: split(0, 0, 10_000),
+            public_cancel: split(5_000, 5_000, 0),
+            protocol: Address::generate(&env),
+            remainder: crate::types::DepositParty::Maker,
+            dust_threshold: 0,
+        };
+        deploy_incentivized_escrow(&env, &incentives);
+    }
+
+    #[test]
+    fn test_deposit_shares_round_to_remainder_party_and_skip_dust() {
+        use crate::types::DepositParty;
+
+        let env = Env::default();
+        let thirds = split(3_333, 3_333, 3_334);
+        let mut incentives = crate::types::DepositIncentives {
+            withdraw: thirds.clone(),
+            public_withdraw: thirds.clone(),
+            cancel: thirds.clone(),
+            public_cancel: thirds.clone(),
+            protocol: Address::generate(&env),
+            remainder: DepositParty::Maker,
+            dust_threshold: 0,
+        };
+
+        // A single stroop rounds to nothing for everyone but the remainder party
+        assert_eq!(incentives.shares(&thirds, 0), [0, 0, 0]);
+        assert_eq!(incentives.shares(&thirds, 1), [0, 1, 0]);
+        incentives.remainder = DepositParty::Protocol;
+        assert_eq!(incentives.shares(&thirds, 1), [0, 0, 1]);
+        incentives.remainder = DepositParty::Executor;
+        assert_eq!(incentives.shares(&thirds, 10), [4, 3, 3]);
+
+        // Shares below the threshold are folded into the remainder party's
+        incentives.dust_threshold = 4;
+        assert_eq!(incentives.shares(&thirds, 10), [10, 0, 0]);
+        assert_eq!(incentives.shares(&thirds, 15), [6, 4, 5]);
+        incentives.remainder = DepositParty::Protocol;
+        assert_eq!(incentives.shares(&split(10_000, 0, 0), 3), [0, 0, 3]);
+
+        // Shares always sum to the deposit, even at the top of the range
+        let whole = incentives.shares(&thirds, i128::MAX);
+        assert_eq!(whole.iter().try_fold(0i128, |sum, share| sum.checked_add(*share)), Some(i128::MAX));
+
+        assert!(incentives.is_valid());
+        incentives.dust_threshold = -1;
+        assert!(!incentives.is_valid());
+
+        assert_eq!(crate::types::halve_deposit(0), (0, 0));
+        assert_eq!(crate::types::halve_deposit(1), (1, 0));
+        assert_eq!(crate::types::halve_deposit(7), (4, 3));
+    }
+
+    #[test]
+    fn test_public_withdraw_pays_odd_deposit_stroop_to_maker() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(&env, 1000i128, 3);
+        let keeper = Address::generate(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        let client = fixture.client(&env);
+        client.public_withdraw(&fixture.secret, &keeper);
+
+        let native = crate::testutils::MockNativeTokenClient::new(&env, &crate::get_native_token_address(&env));
+        assert_eq!(native.balance(&fixture.maker), 2);
+        assert_eq!(native.balance(&fixture.taker), 1);
+        assert_eq!(native.balance(&fixture.escrow), 0);
+    }
+
+    #[test]
+    fn test_skew_tolerance_delays_public_cancellation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.set_skew_tolerance(&60);
+        client.deploy(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        assert_eq!(client.get_immutables().skew_tolerance, 60);
+
+        // Within the tolerance a third party still can't cancel
+        let keeper = Address::generate(&env);
+        let public_cancel = crate::timelocks::get_timelock(fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK) as u64;
+        env.ledger().with_mut(|li| li.timestamp = public_cancel + 59);
+        assert!(client.try_cancel(&keeper).is_err());
+
+        env.ledger().with_mut(|li| li.timestamp = public_cancel + 60);
+        client.cancel(&keeper);
+        assert_eq!(client.get_state(), State::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #107)")] // AlreadyInitialized
+    fn test_skew_tolerance_fixed_after_initialization() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        fixture.client(&env).set_skew_tolerance(&60);
+    }
+
+    #[test]
+    fn test_swap_id_is_hashed_and_carried_by_events() {
+        use soroban_sdk::{symbol_short, testutils::Events, IntoVal, TryIntoVal, Val, Vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let swap_id = BytesN::from_array(&env, &[7u8; 32]);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.set_swap_id(&swap_id);
+        client.deploy(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+        let topics: Vec<Val> = (symbol_short!("v3"), symbol_short!("escrow"), symbol_short!("created")).into_val(&env);
+        let (_, _, data) = env.events().all().iter()
+            .find(|(contract, event_topics, _)| *contract == escrow && *event_topics == topics)
+            .expect("created event");
+        let (event_swap_id, _): (Option<BytesN<32>>, Val) = data.try_into_val(&env).unwrap();
+        assert_eq!(event_swap_id, Some(swap_id.clone()));
+
+        assert_eq!(client.get_swap_id(), Some(swap_id.clone()));
+        assert_eq!(client.get_immutables().swap_id, Some(swap_id));
+        // The id is part of the hash, so it can't be swapped out of the salt
+        assert_ne!(client.get_immutables_hash(), fixture.client(&env).get_immutables_hash());
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #107)")] // AlreadyInitialized
+    fn test_swap_id_fixed_after_initialization() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        fixture.client(&env).set_swap_id(&BytesN::from_array(&env, &[7u8; 32]));
+    }
+
+    /// Escrow over a revocable asset whose taker has been deauthorized, with the maker's tokens
+    /// pushed to it but funding not yet confirmed
+    fn escrow_with_deauthorized_taker(env: &Env) -> (StellarEscrowClient<'_>, crate::testutils::EscrowFixture) {
+        use soroban_sdk::testutils::IssuerFlags;
+
+        env.mock_all_auths();
+        let fixture = crate::testutils::create_funded_escrow(env, 1000i128);
+        let asset = env.register_stellar_asset_contract_v2(fixture.token_admin.clone());
+        asset.issuer().set_flag(IssuerFlags::RevocableFlag);
+        let sac = token::StellarAssetClient::new(env, &asset.address());
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(env, &escrow);
+        client.deploy(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &asset.address(),
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+        sac.mint(&escrow, &1000i128);
+        sac.set_authorized(&fixture.taker, &false);
+        (client, crate::testutils::EscrowFixture { escrow, token: asset.address(), ..fixture })
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #123)")] // RecipientCannotReceive
+    fn test_funding_rejects_recipient_that_cannot_receive() {
+        let env = Env::default();
+        let (client, _) = escrow_with_deauthorized_taker(&env);
+        client.confirm_funding();
+    }
+
+    #[test]
+    fn test_withdraw_holds_payout_for_retry() {
+        let env = Env::default();
+        let (client, fixture) = escrow_with_deauthorized_taker(&env);
+        let token = fixture.token_client(&env);
+
+        // The secret is still accepted; the payout waits for the taker
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(client.get_revealed_secret(), Some(fixture.secret.clone().into()));
+        assert_eq!(client.get_pending_payout(), 1000i128);
+        assert_eq!(client.refund_excess(), 0);
+
+        // Another unreachable target fails early; a reachable one is paid
+        let deauthorized = Address::generate(&env);
+        token::StellarAssetClient::new(&env, &fixture.token).set_authorized(&deauthorized, &false);
+        assert!(client.try_retry_payout(&deauthorized).is_err());
+
+        let wallet = Address::generate(&env);
+        assert_eq!(client.retry_payout(&wallet), 1000i128);
+        assert_eq!(token.balance(&wallet), 1000i128);
+        assert_eq!(client.get_pending_payout(), 0);
+        assert_eq!(client.get_filled_amount(), 1000i128);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error(Contract, #124)")] // MakerIsTaker
+    fn test_deploy_rejects_maker_as_taker() {
+        let env = Env::default();
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        let (_, hashlock) = create_secret_and_hash(&env);
+        let maker = Address::generate(&env);
+        client.deploy(
+            &BytesN::from_array(&env, &[1u8; 32]),
+            &hashlock,
+            &maker,
+            &maker,
+            &Address::generate(&env),
+            &1000i128,
+            &0i128,
+            &0u64,
+        );
+    }
+
+    #[test]
+    fn test_deploy_rejects_zero_amount_and_unset_hashes() {
+        let env = Env::default();
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        let (_, hashlock) = create_secret_and_hash(&env);
+        let order_hash = BytesN::from_array(&env, &[1u8; 32]);
+        let zero = BytesN::from_array(&env, &[0u8; 32]);
+        let (maker, taker, token) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+
+        let deploy = |order_hash: &BytesN<32>, hashlock: &BytesN<32>, amount: i128| {
+            client.try_deploy(order_hash, hashlock, &maker, &taker, &token, &amount, &0i128, &0u64)
+        };
+        let error = |code: crate::Error| Err(Ok(soroban_sdk::Error::from_contract_error(code as u32)));
+        assert_eq!(deploy(&order_hash, &hashlock, 0).map(|_| ()), error(crate::Error::InvalidAmount));
+        assert_eq!(deploy(&order_hash, &zero, 1000).map(|_| ()), error(crate::Error::ZeroHashlock));
+        assert_eq!(deploy(&zero, &hashlock, 1000).map(|_| ()), error(crate::Error::ZeroOrderHash));
+        assert!(deploy(&order_hash, &hashlock, 1000).is_ok());
+    }
+
+    #[test]
+    fn test_withdraw_event_has_hashlock_topic() {
+        use soroban_sdk::{symbol_short, testutils::Events, IntoVal, Val, Vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        fixture.client(&env).withdraw(&fixture.secret, &false);
+
+        let topics: Vec<Val> = (symbol_short!("v3"), symbol_short!("escrow"), symbol_short!("withdraw"), fixture.hashlock.clone()).into_val(&env);
+        let event = env.events().all().iter().find(|(contract, event_topics, _)| {
+            *contract == fixture.escrow && *event_topics == topics
+        });
+        let (_, _, data) = event.expect("withdraw event filtered by hashlock");
+        let (_, secret): (Option<BytesN<32>>, BytesN<32>) = soroban_sdk::FromVal::from_val(&env, &data);
+        assert_eq!(secret, fixture.secret);
+    }
+
+    #[test]
+    fn test_field_getters_match_immutables() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let immutables = client.get_immutables();
+
+        assert_eq!(client.get_hashlock(), immutables.hashlock);
+        assert_eq!(client.get_amount(), immutables.amount);
+        assert_eq!(client.get_parties(), (immutables.maker, immutables.taker));
+        assert_eq!(client.get_timelocks(), immutables.timelocks);
+    }
+
+    #[test]
+    fn test_get_immutables_evm_matches_salt() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let immutables = client.get_immutables();
+
+        let encoded = client.get_immutables_evm();
+        assert_eq!(encoded.len(), 8 * 32);
+        let hash: BytesN<32> = env.crypto().keccak256(&encoded).into();
+        let salt = fusion_shared::conversion::derive_salt(
+            &env,
+            &immutables.order_hash,
+            &immutables.hashlock,
+            &immutables.maker,
+            &immutables.taker,
+            &immutables.token,
+            immutables.amount,
+            immutables.safety_deposit,
+            immutables.timelocks,
+        );
+        assert_eq!(hash, salt);
+    }
+
+    #[test]
+    fn test_extend_storage_never_reports_an_unfunded_escrow_due() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        client.deploy(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+
+        let elapsed = crate::STORAGE_TTL_EXTEND_TO - crate::STORAGE_TTL_THRESHOLD + 1;
+        env.ledger().with_mut(|ledger| ledger.sequence_number += elapsed);
+        assert!(!client.extend_storage());
+        assert_eq!(client.get_storage_ttl(), crate::STORAGE_TTL_EXTEND_TO);
+    }
+
+    #[test]
+    fn test_extend_storage_reports_due_escrows() {
+        use soroban_sdk::testutils::storage::Persistent;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        // Freshly deployed: extended at init, not due
+        assert_eq!(client.get_storage_ttl(), crate::STORAGE_TTL_EXTEND_TO);
+        assert!(!client.extend_storage());
+
+        let elapsed = crate::STORAGE_TTL_EXTEND_TO - crate::STORAGE_TTL_THRESHOLD + 1;
+        env.ledger().with_mut(|ledger| ledger.sequence_number += elapsed);
+        assert!(client.get_storage_ttl() < crate::STORAGE_TTL_THRESHOLD);
+        assert!(client.extend_storage());
+        assert_eq!(client.get_storage_ttl(), crate::STORAGE_TTL_EXTEND_TO);
+        env.as_contract(&fixture.escrow, || {
+            assert_eq!(env.storage().persistent().get_ttl(&"immutables"), crate::STORAGE_TTL_EXTEND_TO);
+        });
+    }
+
+    #[test]
+    fn test_claim_execution_locks_public_withdraw() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let (executor, copycat) = (Address::generate(&env), Address::generate(&env));
+        let error = |code: crate::Error| Err(Ok(soroban_sdk::Error::from_contract_error(code as u32)));
+
+        // Not before the public window opens
+        assert_eq!(client.try_claim_execution(&executor).map(|_| ()), error(crate::Error::TimelockNotExpired));
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        let until = client.claim_execution(&executor);
+        assert_eq!(client.get_execution_lock(), Some((executor.clone(), until)));
+        assert_eq!(client.try_claim_execution(&copycat).map(|_| ()), error(crate::Error::ExecutionClaimed));
+        assert_eq!(client.try_public_withdraw(&fixture.secret, &copycat).map(|_| ()), error(crate::Error::ExecutorLocked));
+
+        // Open to everyone again once the lock lapses
+        env.ledger().with_mut(|ledger| ledger.sequence_number = until + 1);
+        client.public_withdraw(&fixture.secret, &copycat);
+        assert_eq!(client.get_state(), State::Withdrawn);
+    }
+
+    #[test]
+    fn test_sweep_surplus_after_withdraw() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        let token = token::StellarAssetClient::new(&env, &fixture.token);
+        let sink = Address::generate(&env);
+
+        // Nothing to sweep while the escrow is open
+        token.mint(&fixture.escrow, &40);
+        assert!(client.try_sweep_surplus().is_err());
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        client.set_surplus_sink(&sink);
+        assert_eq!(client.get_surplus_sink(), sink);
+
+        assert_eq!(client.sweep_surplus(), (40, 0));
+        assert_eq!(token::TokenClient::new(&env, &fixture.token).balance(&sink), 40);
+        assert_eq!(client.sweep_surplus(), (0, 0));
+    }
+
+    #[test]
+    fn test_finality_oracle_gates_withdrawals() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let oracle = env.register(crate::testutils::MockFinalityOracle, ());
+        let oracle_client = crate::testutils::MockFinalityOracleClient::new(&env, &oracle);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.deploy_with_finality_oracle(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+            &oracle,
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        assert_eq!(client.get_immutables().finality_oracle, Some(oracle.clone()));
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+
+        // Not final yet: neither withdraw nor its dry run go through
+        let not_final = crate::errors::Error::NotFinal as u32;
+        assert_eq!(client.try_withdraw(&fixture.secret, &false).err().unwrap().unwrap().get_code(), not_final);
+        assert_eq!(client.dry_run_withdraw(&fixture.secret).error, Some(not_final));
+
+        oracle_client.set_final(&fixture.order_hash, &true);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000);
+    }
+
+    #[test]
+    fn test_reveal_secrets_batch() {
+        use crate::types::SecretReveal;
+        use fusion_shared::merkle;
+        use soroban_sdk::vec;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let secrets: [(BytesN<32>, BytesN<32>); 4] =
+            core::array::from_fn(|i| crate::testutils::secret_and_hashlock(&env, i as u8 + 1));
+        let leaves: [BytesN<32>; 4] =
+            core::array::from_fn(|i| merkle::secret_leaf(&env, i as u64, &secrets[i].1));
+        let pairs = [merkle::hash_pair(&env, &leaves[0], &leaves[1]), merkle::hash_pair(&env, &leaves[2], &leaves[3])];
+        let root = merkle::hash_pair(&env, &pairs[0], &pairs[1]);
+        let reveal = |i: usize, amount: i128| SecretReveal {
+            index: i as u64,
+            secret: secrets[i].0.clone(),
+            proof: vec![&env, leaves[i ^ 1].clone(), pairs[1 - i / 2].clone()],
+            amount,
+        };
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.set_parts(&3u32);
+        client.deploy(
+            &fixture.order_hash,
+            &root,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &0i128,
+            &fixture.timelocks,
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+
+        // A repeated index fails the whole batch
+        let repeated = client.try_reveal_secrets_batch(&root, &vec![&env, reveal(0, 100), reveal(0, 100)]);
+        assert_eq!(repeated.err().unwrap().unwrap().get_code(), crate::errors::Error::SecretAlreadyUsed as u32);
+
+        assert_eq!(client.reveal_secrets_batch(&root, &vec![&env, reveal(0, 250), reveal(1, 250), reveal(2, 250)]), 750);
+        assert_eq!(client.get_state(), State::Funded);
+        assert_eq!(client.get_filled_amount(), 750);
+        assert_eq!(client.get_revealed_indices(), vec![&env, 0u64, 1u64, 2u64]);
+        assert_eq!(client.get_revealed_secret(), Some(Bytes::from(secrets[2].0.clone())));
+
+        client.withdraw_partial_with_proof(&secrets[3].0, &3u64, &reveal(3, 250).proof, &250i128);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000i128);
+    }
+
+    #[test]
+    fn test_multi_secret_tranche_must_end_in_its_part() {
+        use crate::types::SecretReveal;
+        use fusion_shared::merkle;
+        use soroban_sdk::vec;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let secrets: [(BytesN<32>, BytesN<32>); 4] =
+            core::array::from_fn(|i| crate::testutils::secret_and_hashlock(&env, i as u8 + 1));
+        let leaves: [BytesN<32>; 4] =
+            core::array::from_fn(|i| merkle::secret_leaf(&env, i as u64, &secrets[i].1));
+        let pairs = [merkle::hash_pair(&env, &leaves[0], &leaves[1]), merkle::hash_pair(&env, &leaves[2], &leaves[3])];
+        let root = merkle::hash_pair(&env, &pairs[0], &pairs[1]);
+        let reveal = |i: usize, amount: i128| SecretReveal {
+            index: i as u64,
+            secret: secrets[i].0.clone(),
+            proof: vec![&env, leaves[i ^ 1].clone(), pairs[1 - i / 2].clone()],
+            amount,
+        };
+        let deploy = |parts: Option<u32>| {
+            let escrow = env.register(StellarEscrow, ());
+            let client = StellarEscrowClient::new(&env, &escrow);
+            if let Some(parts) = parts {
+                client.set_parts(&parts);
+            }
+            client.deploy(
+                &fixture.order_hash,
+                &root,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &1000i128,
+                &0i128,
+                &fixture.timelocks,
+            );
+            token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+            client.confirm_funding();
+            client
+        };
+        let invalid_fill = Some(crate::errors::Error::InvalidPartialFill as u32);
+        let withdraw = |client: &StellarEscrowClient, i: usize, amount: i128| {
+            contract_error(client.try_withdraw_partial_with_proof(&secrets[i].0, &(i as u64), &reveal(i, amount).proof, &amount))
+        };
+
+        let unparted = deploy(None);
+        let client = deploy(Some(3));
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        assert_eq!(withdraw(&unparted, 0, 250), invalid_fill);
+
+        // 600 of 1000 ends in the second of three parts, so the first secret can't claim it
+        assert_eq!(withdraw(&client, 0, 600), invalid_fill);
+        assert_eq!(contract_error(client.try_reveal_secrets_batch(&root, &vec![&env, reveal(0, 600)])), invalid_fill);
+        // Nor can the completing secret claim a tranche that leaves some unfilled
+        assert_eq!(withdraw(&client, 3, 999), invalid_fill);
+
+        assert_eq!(withdraw(&client, 1, 600), None);
+        // A fill ending in the part the last one ended in has no secret of its own
+        assert_eq!(withdraw(&client, 2, 50), invalid_fill);
+        assert_eq!(withdraw(&client, 2, 300), None);
+        assert_eq!(withdraw(&client, 3, 100), None);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(client.get_revealed_indices(), vec![&env, 1u64, 2u64, 3u64]);
+
+        assert_eq!(contract_error(StellarEscrowClient::new(&env, &env.register(StellarEscrow, ())).try_set_parts(&0)), invalid_fill);
+    }
+
+    #[test]
+    fn test_abort_unfunded_escrow() {
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let invalid_state = crate::errors::Error::InvalidState as u32;
+        assert_eq!(fixture.client(&env).try_abort().err().unwrap().unwrap().get_code(), invalid_state);
+
+        let deploy = |order_seed: u8| {
+            let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+            client.deploy(
+                &BytesN::from_array(&env, &[order_seed; 32]),
+                &fixture.hashlock,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &1000i128,
+                &0i128,
+                &fixture.timelocks,
+            );
+            client
+        };
+
+        // Tokens pushed without confirm_funding keep the escrow from being aborted
+        let pushed = deploy(2);
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&pushed.address, &10i128);
+        let not_empty = crate::errors::Error::EscrowNotEmpty as u32;
+        assert_eq!(pushed.try_abort().err().unwrap().unwrap().get_code(), not_empty);
+
+        let empty = deploy(3);
+        empty.abort();
+        assert_eq!(empty.get_state(), State::Cancelled);
+        assert_eq!(empty.try_confirm_funding().err().unwrap().unwrap().get_code(), invalid_state);
+    }
+
+    #[test]
+    fn test_receipts_record_closing_payout() {
+        let env = Env::default();
+        env.ledger().with_mut(|li| li.sequence_number = 77);
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(&env, 1000i128, 10);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_receipt(), None);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        let receipt = client.get_receipt().unwrap();
+        assert_eq!(receipt.recipient, fixture.taker);
+        assert_eq!(receipt.executor, fixture.taker);
+        assert_eq!((receipt.amount, receipt.fee_paid), (1000, 10));
+        assert_eq!((receipt.timestamp, receipt.tx_context), (env.ledger().timestamp(), 77));
+
+        // Cancellations record the maker's refund and who cancelled
+        let fixture = crate::testutils::create_funded_escrow(&env, 500i128);
+        let client = fixture.client(&env);
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        let canceller = Address::generate(&env);
+        client.cancel(&canceller);
+        let receipt = client.get_receipt().unwrap();
+        assert_eq!((receipt.recipient, receipt.executor), (fixture.maker, canceller));
+        assert_eq!((receipt.amount, receipt.fee_paid), (500, 0));
+    }
+
+    #[test]
+    fn test_hooks_run_around_funds_and_failures_are_isolated() {
+        use soroban_sdk::symbol_short;
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let hook = env.register(crate::testutils::MockHook, ());
+        let hook_client = crate::testutils::MockHookClient::new(&env, &hook);
+        let deploy = |client: &StellarEscrowClient| {
+            client.deploy_with_hooks(
+                &fixture.order_hash,
+                &fixture.hashlock,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &1000i128,
+                &0i128,
+                &fixture.timelocks,
+                &Some(hook.clone()),
+                &Some(hook.clone()),
+            );
+        };
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        deploy(&client);
+        assert_ne!(client.get_immutables_hash(), fixture.client(&env).get_immutables_hash());
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        assert_eq!(hook_client.calls(&symbol_short!("before")), 1);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(hook_client.calls(&symbol_short!("after")), 1);
+        assert_eq!(hook_client.paid(), 1000);
+
+        // A failing hook is skipped: funding and the payout still go through
+        hook_client.set_failing(&true);
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        deploy(&client);
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        assert_eq!(client.get_state(), State::Funded);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.get_state(), State::Withdrawn);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 2000);
+        assert_eq!(hook_client.calls(&symbol_short!("after")), 1);
+    }
+
+    fn contract_error<T, E>(result: Result<T, Result<soroban_sdk::Error, E>>) -> Option<u32> {
+        match result {
+            Ok(_) => None,
+            Err(Ok(error)) => Some(error.get_code()),
+            Err(Err(_)) => panic!("payout failed without a contract error"),
+        }
+    }
+
+    const PAYOUTS: [&str; 6] = ["withdraw", "withdraw_to", "withdraw_preimage", "withdraw_partial", "public_withdraw", "cancel"];
+
+    /// Run a payout path at a stage where its own checks pass, returning its error code if any
+    fn run_payout(env: &Env, fixture: &crate::testutils::EscrowFixture, payout: &str) -> Option<u32> {
+        let client = fixture.client(env);
+        let stage = if payout == "cancel" {
+            crate::types::DST_CANCELLATION_TIMELOCK
+        } else {
+            crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK
+        };
+        crate::testutils::advance_to_stage(env, fixture.timelocks, stage);
+        match payout {
+            "withdraw" => contract_error(client.try_withdraw(&fixture.secret, &false)),
+            "withdraw_to" => contract_error(client.try_withdraw_to(&fixture.secret, &fixture.taker, &None)),
+            "withdraw_preimage" => contract_error(client.try_withdraw_preimage(&fixture.secret.clone().into())),
+            "withdraw_partial" => contract_error(client.try_withdraw_partial(&fixture.secret, &fixture.amount)),
+            "public_withdraw" => contract_error(client.try_public_withdraw(&fixture.secret, &Address::generate(env))),
+            "cancel" => contract_error(client.try_cancel(&fixture.taker)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_every_payout_ordering_is_blocked_after_the_first() {
+        let already_executed = Some(crate::errors::Error::AlreadyExecuted as u32);
+        for first in PAYOUTS {
+            for second in PAYOUTS {
+                let env = Env::default();
+                let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+                assert_eq!(run_payout(&env, &fixture, first), None, "{first}");
+                assert_eq!(run_payout(&env, &fixture, second), already_executed, "{second} after {first}");
+                assert_eq!(fixture.token_client(&env).balance(&fixture.escrow), 0, "{second} after {first}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_executed_actions_allow_tranches_until_filled() {
+        use crate::types::{EXECUTED_PARTIAL, EXECUTED_WITHDRAW};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_executed_actions(), 0);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw_partial(&fixture.secret, &400i128);
+        client.withdraw_partial(&fixture.secret, &100i128);
+        assert_eq!(client.get_executed_actions(), EXECUTED_PARTIAL);
+        assert_eq!(client.get_state(), State::Funded);
+
+        // The remainder can still be withdrawn in full, after which every path is blocked
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(client.get_executed_actions(), EXECUTED_PARTIAL | EXECUTED_WITHDRAW);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000);
+
+        let already_executed = Some(crate::errors::Error::AlreadyExecuted as u32);
+        for payout in PAYOUTS {
+            assert_eq!(run_payout(&env, &fixture, payout), already_executed, "{payout}");
+        }
+        assert_eq!(client.dry_run_withdraw(&fixture.secret).error, already_executed);
+        assert_eq!(client.dry_run_cancel(&fixture.maker).error, already_executed);
+    }
+
+    fn deploy_flagged_escrow(env: &Env, flags: u32) -> crate::testutils::EscrowFixture {
+        let fixture = crate::testutils::create_funded_escrow(env, 1000i128);
+
+        crate::testutils::redeploy_escrow(env, fixture, 0, |client, fixture| {
+            client.set_flags(&flags);
+            client.deploy(
+                &fixture.order_hash,
+                &fixture.hashlock,
+                &fixture.maker,
+                &fixture.taker,
+                &fixture.token,
+                &fixture.amount,
+                &0i128,
+                &fixture.timelocks,
+            );
+        })
+    }
+
+    #[test]
+    fn test_default_flags_keep_the_immutables_hash() {
+        use crate::types::{DEFAULT_FLAGS, FLAG_REQUIRE_TAKER_AUTH};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let unflagged = fixture.client(&env);
+        assert_eq!(unflagged.get_flags(), DEFAULT_FLAGS);
+
+        let defaulted = deploy_flagged_escrow(&env, DEFAULT_FLAGS).client(&env);
+        assert_eq!(defaulted.get_immutables_hash(), unflagged.get_immutables_hash());
+        let flagged = deploy_flagged_escrow(&env, DEFAULT_FLAGS | FLAG_REQUIRE_TAKER_AUTH).client(&env);
+        assert_eq!(flagged.get_flags(), DEFAULT_FLAGS | FLAG_REQUIRE_TAKER_AUTH);
+        assert_ne!(flagged.get_immutables_hash(), unflagged.get_immutables_hash());
+
+        let fresh = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+        assert_eq!(contract_error(fresh.try_set_flags(&(1 << 5))), Some(crate::errors::Error::InvalidFlags as u32));
+        assert_eq!(contract_error(unflagged.try_set_flags(&0)), Some(crate::errors::Error::AlreadyInitialized as u32));
+    }
+
+    #[test]
+    fn test_cleared_flags_disable_partial_public_and_unwrap() {
+        let env = Env::default();
+        let fixture = deploy_flagged_escrow(&env, 0);
+        let client = fixture.client(&env);
+        let flag_disabled = Some(crate::errors::Error::FlagDisabled as u32);
+        let outsider = Address::generate(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_PUBLIC_WITHDRAWAL_TIMELOCK);
+        assert_eq!(contract_error(client.try_withdraw_partial(&fixture.secret, &400i128)), flag_disabled);
+        assert_eq!(contract_error(client.try_public_withdraw(&fixture.secret, &outsider)), flag_disabled);
+        assert_eq!(contract_error(client.try_withdraw(&fixture.secret, &true)), flag_disabled);
+        assert_eq!(client.dry_run_cancel(&outsider).error, flag_disabled);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        assert_eq!(contract_error(client.try_cancel(&outsider)), flag_disabled);
+        client.cancel(&fixture.taker);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.maker), 1000);
+    }
+
+    #[test]
+    fn test_require_taker_auth_flag_gates_withdrawals() {
+        use crate::types::{DEFAULT_FLAGS, FLAG_REQUIRE_TAKER_AUTH};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        fixture.client(&env).withdraw(&fixture.secret, &false);
+        assert!(env.auths().is_empty());
+
+        let fixture = deploy_flagged_escrow(&env, DEFAULT_FLAGS | FLAG_REQUIRE_TAKER_AUTH);
+        let client = fixture.client(&env);
+        client.withdraw_partial(&fixture.secret, &400i128);
+        assert_eq!(env.auths().len(), 1);
+        assert_eq!(env.auths()[0].0, fixture.taker);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(env.auths().len(), 1);
+        assert_eq!(env.auths()[0].0, fixture.taker);
+        assert_eq!(fixture.token_client(&env).balance(&fixture.taker), 1000);
+    }
+
+    #[test]
+    fn test_protocol_share_goes_to_maker_without_fees_flag() {
+        use crate::types::{DEFAULT_FLAGS, FLAG_ENABLE_FEES};
+
+        let env = Env::default();
+        let protocol = Address::generate(&env);
+        let fixture = crate::testutils::create_funded_escrow_with_deposit(&env, 1000i128, 1);
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.set_flags(&(DEFAULT_FLAGS & !FLAG_ENABLE_FEES));
+        client.deploy_with_incentives(
+            &fixture.order_hash,
+            &fixture.hashlock,
+            &fixture.maker,
+            &fixture.taker,
+            &fixture.token,
+            &1000i128,
+            &1000i128,
+            &fixture.timelocks,
+            &crate::types::DepositIncentives {
+                withdraw: split(0, 7_000, 3_000),
+                public_withdraw: split(0, 7_000, 3_000),
+                cancel: split(0, 0, 10_000),
+                public_cancel: split(0, 0, 10_000),
+                protocol: protocol.clone(),
+                remainder: crate::types::DepositParty::Maker,
+                dust_threshold: 0,
+            },
+        );
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        crate::testutils::register_native_token(&env).mint(&escrow, &1000i128);
+        client.confirm_funding();
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        let native = crate::testutils::MockNativeTokenClient::new(&env, &crate::get_native_token_address(&env));
+        assert_eq!(native.balance(&fixture.maker), 1000);
+        assert_eq!(native.balance(&protocol), 0);
+    }
+
+    /// v2 immutables for a fresh escrow with the fixture's swap, paying `receiver`
+    fn fixture_v2(env: &Env, fixture: &crate::testutils::EscrowFixture, receiver: &Address) -> crate::types::ImmutablesV2 {
+        use crate::types::{EscrowSide, ImmutablesV2, IMMUTABLES_V2_VERSION};
+
+        let v1 = fixture.client(env).get_immutables();
+        ImmutablesV2 {
+            version: IMMUTABLES_V2_VERSION,
+            receiver: Some(receiver.clone()),
+            ..ImmutablesV2::from_v1(&v1, EscrowSide::Dst)
+        }
+    }
+
+    #[test]
+    fn test_v2_escrow_hashes_with_its_version() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let receiver = Address::generate(&env);
+        let immutables = fixture_v2(&env, &fixture, &receiver);
+
+        let escrow = env.register(StellarEscrow, ());
+        let client = StellarEscrowClient::new(&env, &escrow);
+        client.deploy_v2(&immutables);
+        assert_eq!(client.get_immutables_version(), 2);
+        assert_eq!(client.get_immutables_hash(), immutables.hash(&env));
+        // The version byte and side keep it apart from a v1 escrow with the same fields
+        assert_ne!(client.get_immutables_hash(), immutables.to_v1().hash(&env));
+
+        let view = client.get_immutables_v2();
+        assert_eq!(view.version, 2);
+        assert_eq!(view.receiver, Some(receiver.clone()));
+        assert_eq!(view.hash(&env), client.get_immutables_hash());
+
+        // The receiver is the v1 beneficiary: withdrawals pay it
+        token::StellarAssetClient::new(&env, &fixture.token).mint(&escrow, &1000i128);
+        client.confirm_funding();
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        client.withdraw(&fixture.secret, &false);
+        assert_eq!(fixture.token_client(&env).balance(&receiver), 1000);
+    }
+
+    #[test]
+    fn test_v2_hash_covers_the_maker_taker_and_token() {
+        use crate::types::{Immutables, ImmutablesV2};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let immutables = fixture_v2(&env, &fixture, &Address::generate(&env));
+        let hash = immutables.hash(&env);
+
+        let other_maker = ImmutablesV2 { maker: Address::generate(&env), ..immutables.clone() };
+        let other_taker = ImmutablesV2 { taker: Address::generate(&env), ..immutables.clone() };
+        let other_token = ImmutablesV2 { token: Address::generate(&env), ..immutables.clone() };
+        for other in [other_maker, other_taker, other_token] {
+            assert_ne!(other.hash(&env), hash);
+        }
+        // v1 hashes leave the addresses out, so existing escrows keep theirs
+        let v1 = immutables.to_v1();
+        assert_eq!(Immutables { maker: Address::generate(&env), ..v1.clone() }.hash(&env), v1.hash(&env));
+    }
+
+    #[test]
+    fn test_v1_escrow_reads_as_v2_version_1() {
+        use crate::types::{EscrowSide, DEFAULT_FLAGS};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        assert_eq!(client.get_immutables_version(), 1);
+
+        let view = client.get_immutables_v2();
+        assert_eq!((view.version, view.side, view.flags), (1, EscrowSide::Dst, DEFAULT_FLAGS));
+        assert_eq!(view.receiver, None);
+        assert_eq!(view.amount, fixture.amount);
+        assert_eq!(view.hash(&env), client.get_immutables_hash());
+        assert_eq!(client.get_immutables_hash(), client.get_immutables().hash(&env));
+    }
+
+    #[test]
+    fn test_deploy_v2_rejects_other_versions_and_unknown_flags() {
+        use crate::errors::Error;
+        use crate::types::{ImmutablesV2, IMMUTABLES_V1_VERSION};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let immutables = fixture_v2(&env, &fixture, &fixture.taker);
+        let client = StellarEscrowClient::new(&env, &env.register(StellarEscrow, ()));
+
+        let v1 = ImmutablesV2 { version: IMMUTABLES_V1_VERSION, ..immutables.clone() };
+        assert_eq!(contract_error(client.try_deploy_v2(&v1)), Some(Error::InvalidImmutablesVersion as u32));
+        let unknown = ImmutablesV2 { flags: 1 << 5, ..immutables };
+        assert_eq!(contract_error(client.try_deploy_v2(&unknown)), Some(Error::InvalidFlags as u32));
+    }
+
+    fn logged_actions(env: &Env, client: &StellarEscrowClient) -> soroban_sdk::Vec<soroban_sdk::Symbol> {
+        let mut actions = soroban_sdk::Vec::new(env);
+        for entry in client.get_action_log().iter() {
+            actions.push_back(entry.action);
+        }
+        actions
+    }
+
+    #[test]
+    fn test_action_log_records_who_did_what_and_when() {
+        use soroban_sdk::{symbol_short, vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        assert_eq!(logged_actions(&env, &client), vec![&env, symbol_short!("deploy"), symbol_short!("fund")]);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_WITHDRAWAL_TIMELOCK);
+        env.ledger().with_mut(|li| li.sequence_number += 10);
+        client.withdraw(&fixture.secret, &false);
+
+        let log = client.get_action_log();
+        let withdrawal = log.get(2).unwrap();
+        assert_eq!(withdrawal.action, symbol_short!("withdraw"));
+        assert_eq!(withdrawal.caller, fixture.taker);
+        assert_eq!(withdrawal.ledger, env.ledger().sequence());
+        assert_eq!(withdrawal.amount, 1000);
+        assert_eq!(log.get(0).unwrap().caller, fixture.maker);
+    }
+
+    #[test]
+    fn test_action_log_survives_finalize_and_keeps_the_latest_entries() {
+        use soroban_sdk::{symbol_short, vec};
+
+        let env = Env::default();
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+
+        crate::testutils::advance_to_stage(&env, fixture.timelocks, crate::types::DST_CANCELLATION_TIMELOCK);
+        client.cancel(&fixture.maker);
+        env.ledger().with_mut(|li| li.timestamp += crate::FINALIZE_GRACE_PERIOD);
+        client.finalize();
+        assert_eq!(logged_actions(&env, &client).slice(2..), vec![&env, symbol_short!("cancel"), symbol_short!("finalize")]);
+        assert_eq!(client.get_action_log().get(2).unwrap().amount, 1000);
+
+        // Beyond MAX_ACTION_LOG the oldest entries go first
+        let fixture = crate::testutils::create_funded_escrow(&env, 1000i128);
+        let client = fixture.client(&env);
+        for _ in 0..crate::MAX_ACTION_LOG {
+            client.set_surplus_sink(&fixture.maker);
+        }
+        let actions = logged_actions(&env, &client);
+        assert_eq!(actions.len(), crate::MAX_ACTION_LOG);
+        assert!(actions.iter().all(|action| action == symbol_short!("set_sink")));
+    }
+}
@@ -0,0 +1,229 @@
+//! Escrow fixtures for tests in this and downstream crates
+//! Enabled with the `testutils` feature
+#![cfg(any(test, feature = "testutils"))]
+
+use soroban_sdk::{
+    contract, contractimpl, symbol_short,
+    testutils::{Address as _, Ledger},
+    token, Address, Bytes, BytesN, Env, MuxedAddress,
+};
+use crate::{get_native_token_address, timelocks, StellarEscrow, StellarEscrowClient};
+
+/// A deployed escrow together with the parties and secret used to create it
+pub struct EscrowFixture {
+    pub escrow: Address,
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub token_admin: Address,
+    pub order_hash: BytesN<32>,
+    pub secret: BytesN<32>,
+    pub hashlock: BytesN<32>,
+    pub amount: i128,
+    pub timelocks: u64,
+}
+
+impl EscrowFixture {
+    /// Get a client for the fixture's escrow
+    pub fn client<'a>(&self, env: &'a Env) -> StellarEscrowClient<'a> {
+        StellarEscrowClient::new(env, &self.escrow)
+    }
+
+    /// Get a token client for the escrowed token
+    pub fn token_client<'a>(&self, env: &'a Env) -> token::TokenClient<'a> {
+        token::TokenClient::new(env, &self.token)
+    }
+}
+
+/// Timelock stage offsets (seconds) used by fixtures, in stage order
+pub const DEFAULT_STAGE_OFFSETS: [u32; 7] = [10, 20, 30, 40, 10, 20, 30];
+
+/// Packed timelocks with every stage set from DEFAULT_STAGE_OFFSETS
+pub fn default_timelocks() -> u64 {
+    timelocks::pack_timelocks(DEFAULT_STAGE_OFFSETS)
+}
+
+/// Move the ledger clock to the start of the given timelock stage
+pub fn advance_to_stage(env: &Env, timelocks: u64, stage: u8) {
+    let timestamp = timelocks::get_timelock(timelocks, stage) as u64;
+    env.ledger().with_mut(|li| {
+        li.timestamp = timestamp;
+    });
+}
+
+/// Generate a secret and its hashlock (sha256 unless built with another EscrowHash)
+pub fn secret_and_hashlock(env: &Env, seed: u8) -> (BytesN<32>, BytesN<32>) {
+    let secret = BytesN::from_array(env, &[seed; 32]);
+    let hashlock = crate::hash_secret(env, &Bytes::from(secret.clone()));
+    (secret, hashlock)
+}
+
+/// Minimal stand-in for the native XLM asset contract
+/// Supports minting, balances and transfers; transfers to blocked accounts fail
+#[contract]
+pub struct MockNativeToken;
+
+#[contractimpl]
+impl MockNativeToken {
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let balance = Self::balance(env.clone(), to.clone());
+        env.storage().persistent().set(&(symbol_short!("balance"), to), &(balance + amount));
+    }
+
+    /// Make transfers to `account` fail (e.g. a missing trustline)
+    pub fn set_blocked(env: Env, account: Address, blocked: bool) {
+        env.storage().persistent().set(&(symbol_short!("blocked"), account), &blocked);
+    }
+
+    pub fn balance(env: Env, id: Address) -> i128 {
+        env.storage().persistent().get(&(symbol_short!("balance"), id)).unwrap_or(0)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: MuxedAddress, amount: i128) {
+        from.require_auth();
+        let to = to.address();
+        let blocked: bool = env.storage().persistent().get(&(symbol_short!("blocked"), to.clone())).unwrap_or(false);
+        let from_balance = Self::balance(env.clone(), from.clone());
+        if blocked || amount < 0 || from_balance < amount {
+            panic!("transfer failed");
+        }
+        env.storage().persistent().set(&(symbol_short!("balance"), from), &(from_balance - amount));
+        Self::mint(env, to, amount);
+    }
+}
+
+/// Finality oracle whose answers the test sets per order
+#[contract]
+pub struct MockFinalityOracle;
+
+#[contractimpl]
+impl MockFinalityOracle {
+    pub fn set_final(env: Env, order_hash: BytesN<32>, is_final: bool) {
+        env.storage().persistent().set(&(symbol_short!("final"), order_hash), &is_final);
+    }
+
+    pub fn is_final(env: Env, order_hash: BytesN<32>) -> bool {
+        env.storage().persistent().get(&(symbol_short!("final"), order_hash)).unwrap_or(false)
+    }
+}
+
+/// Integration hook recording its calls (`before` / `after` counts); panics when told to fail
+#[contract]
+pub struct MockHook;
+
+#[contractimpl]
+impl MockHook {
+    pub fn set_failing(env: Env, failing: bool) {
+        env.storage().instance().set(&symbol_short!("failing"), &failing);
+    }
+
+    pub fn before_funding(env: Env, escrow: Address, order_hash: BytesN<32>, token: Address, amount: i128) {
+        let _ = (escrow, order_hash, token, amount);
+        Self::record(&env, symbol_short!("before"));
+    }
+
+    pub fn after_payout(env: Env, escrow: Address, order_hash: BytesN<32>, token: Address, recipient: Address, amount: i128) {
+        let _ = (escrow, order_hash, token, recipient);
+        Self::record(&env, symbol_short!("after"));
+        env.storage().instance().set(&symbol_short!("paid"), &amount);
+    }
+
+    pub fn calls(env: Env, function: soroban_sdk::Symbol) -> u32 {
+        env.storage().instance().get(&function).unwrap_or(0)
+    }
+
+    pub fn paid(env: Env) -> i128 {
+        env.storage().instance().get(&symbol_short!("paid")).unwrap_or(0)
+    }
+
+    fn record(env: &Env, function: soroban_sdk::Symbol) {
+        if env.storage().instance().get(&symbol_short!("failing")).unwrap_or(false) {
+            panic!("hook failure");
+        }
+        let calls: u32 = env.storage().instance().get(&function).unwrap_or(0);
+        env.storage().instance().set(&function, &(calls + 1));
+    }
+}
+
+/// Register MockNativeToken at the address the escrow uses for native XLM
+pub fn register_native_token(env: &Env) -> MockNativeTokenClient<'_> {
+    let address = env.register_at(&get_native_token_address(env), MockNativeToken, ());
+    MockNativeTokenClient::new(env, &address)
+}
+
+/// Deploy an escrow over a fresh Stellar asset, fund it with `amount` and confirm funding
+/// Mocks all auths on `env`
+pub fn create_funded_escrow(env: &Env, amount: i128) -> EscrowFixture {
+    create_funded_escrow_with_deposit(env, amount, 0)
+}
+
+/// Deploy a fresh escrow for `fixture`'s parties through `deploy`, fund it with `fixture.amount`
+/// of `fixture.token` plus `safety_deposit` in native and confirm funding
+/// A non-zero deposit needs MockNativeToken already registered
+pub fn redeploy_escrow(
+    env: &Env,
+    fixture: EscrowFixture,
+    safety_deposit: i128,
+    deploy: impl FnOnce(&StellarEscrowClient, &EscrowFixture),
+) -> EscrowFixture {
+    let escrow = env.register(StellarEscrow, ());
+    let client = StellarEscrowClient::new(env, &escrow);
+    deploy(&client, &fixture);
+
+    token::StellarAssetClient::new(env, &fixture.token).mint(&escrow, &fixture.amount);
+    if safety_deposit > 0 {
+        MockNativeTokenClient::new(env, &get_native_token_address(env)).mint(&escrow, &safety_deposit);
+    }
+    client.confirm_funding();
+
+    EscrowFixture { escrow, ..fixture }
+}
+
+/// Like create_funded_escrow, but also funds a native safety deposit
+/// Registers MockNativeToken when `safety_deposit` is non-zero
+pub fn create_funded_escrow_with_deposit(env: &Env, amount: i128, safety_deposit: i128) -> EscrowFixture {
+    env.mock_all_auths();
+
+    let escrow = env.register(StellarEscrow, ());
+    let maker = Address::generate(env);
+    let taker = Address::generate(env);
+    let token_admin = Address::generate(env);
+
+    let token = env.register_stellar_asset_contract_v2(token_admin.clone()).address();
+    token::StellarAssetClient::new(env, &token).mint(&maker, &amount);
+
+    let order_hash = BytesN::from_array(env, &[1u8; 32]);
+    let (secret, hashlock) = secret_and_hashlock(env, 42);
+    let timelocks = default_timelocks();
+
+    let client = StellarEscrowClient::new(env, &escrow);
+    client.deploy(
+        &order_hash,
+        &hashlock,
+        &maker,
+        &taker,
+        &token,
+        &amount,
+        &safety_deposit,
+        &timelocks,
+    );
+
+    token::TokenClient::new(env, &token).transfer(&maker, &escrow, &amount);
+    if safety_deposit > 0 {
+        register_native_token(env).mint(&escrow, &safety_deposit);
+    }
+    client.confirm_funding();
+
+    EscrowFixture {
+        escrow,
+        maker,
+        taker,
+        token,
+        token_admin,
+        order_hash,
+        secret,
+        hashlock,
+        amount,
+        timelocks,
+    }
+}
@@ -1,3 +1,4 @@
+use fusion_shared::trace;
 use soroban_sdk::{Address, Env};
 use crate::types::*;
 
@@ -11,21 +12,29 @@ pub fn get_timelock(timelocks: u64, index: u8) -> u32 {
 pub fn can_withdraw(env: &Env, timelocks: &u64, is_public: bool) -> bool {
     let current_time = env.ledger().timestamp();
     
-    if is_public {
-        // For public withdrawal, check DST_PUBLIC_WITHDRAWAL_TIMELOCK
-        let public_timelock = get_timelock(*timelocks, DST_PUBLIC_WITHDRAWAL_TIMELOCK);
-        current_time >= public_timelock as u64
-    } else {
-        // For regular withdrawal, check DST_WITHDRAWAL_TIMELOCK
-        let withdrawal_timelock = get_timelock(*timelocks, DST_WITHDRAWAL_TIMELOCK);
-        current_time >= withdrawal_timelock as u64
-    }
+    // Public withdrawal opens at DST_PUBLIC_WITHDRAWAL_TIMELOCK, regular at DST_WITHDRAWAL_TIMELOCK
+    let stage = if is_public { DST_PUBLIC_WITHDRAWAL_TIMELOCK } else { DST_WITHDRAWAL_TIMELOCK };
+    let timelock = get_timelock(*timelocks, stage);
+    let allowed = current_time >= timelock as u64;
+    trace!(env, "timelock", (stage as u32, timelock, current_time, allowed));
+    allowed
+}
+
+/// Start of a cancellation stage, pushed back by the skew tolerance
+/// Withdrawals open on time; only cancellation waits, so a withdrawal racing the boundary
+/// against a chain whose clock runs ahead isn't cut off by a cancel
+fn cancellation_start(timelocks: u64, stage: u8, skew: u32) -> u64 {
+    get_timelock(timelocks, stage) as u64 + skew as u64
 }
 
-/// Check if cancellation is allowed based on timelocks and caller
+/// Check if cancellation is allowed based on timelocks, escrow side and caller
+/// Dst: only the taker (resolver) during the private window, anyone from DST_CANCELLATION
+/// Src: maker or taker during the private window, anyone from SRC_PUBLIC_CANCELLATION
 pub fn can_cancel(
     env: &Env,
     timelocks: &u64,
+    skew: u32,
+    side: EscrowSide,
     caller: &Address,
     maker: &Address,
     taker: &Address,
@@ -33,20 +42,58 @@ pub fn can_cancel(
     let current_time = env.ledger().timestamp();
     
     // Check if it's public cancellation time
-    let public_cancel_timelock = get_timelock(*timelocks, DST_CANCELLATION_TIMELOCK);
-    if current_time >= public_cancel_timelock as u64 {
+    let public_stage = match side {
+        EscrowSide::Src => SRC_PUBLIC_CANCELLATION_TIMELOCK,
+        EscrowSide::Dst => DST_CANCELLATION_TIMELOCK,
+    };
+    let public_start = cancellation_start(*timelocks, public_stage, skew);
+    trace!(env, "timelock", (public_stage as u32, public_start, current_time, current_time >= public_start));
+    if current_time >= public_start {
         return true; // Anyone can cancel
     }
     
     // Check if it's regular cancellation time and caller is authorized
-    let cancel_timelock = get_timelock(*timelocks, SRC_CANCELLATION_TIMELOCK);
-    if current_time >= cancel_timelock as u64 {
-        return caller == maker || caller == taker;
+    let cancel_start = cancellation_start(*timelocks, SRC_CANCELLATION_TIMELOCK, skew);
+    trace!(env, "timelock", (SRC_CANCELLATION_TIMELOCK as u32, cancel_start, current_time, current_time >= cancel_start));
+    if current_time >= cancel_start {
+        return match side {
+            EscrowSide::Src => caller == maker || caller == taker,
+            EscrowSide::Dst => caller == taker,
+        };
     }
     
     false
 }
 
+/// Whether cancellation by anyone has opened for an escrow on `side`
+pub fn is_public_cancellation(env: &Env, timelocks: &u64, skew: u32, side: EscrowSide) -> bool {
+    let public_stage = match side {
+        EscrowSide::Src => SRC_PUBLIC_CANCELLATION_TIMELOCK,
+        EscrowSide::Dst => DST_CANCELLATION_TIMELOCK,
+    };
+    env.ledger().timestamp() >= cancellation_start(*timelocks, public_stage, skew)
+}
+
+/// Check whether the escrow has timed out (public cancellation reached)
+pub fn is_expired(env: &Env, timelocks: &u64, skew: u32) -> bool {
+    env.ledger().timestamp() >= cancellation_start(*timelocks, DST_CANCELLATION_TIMELOCK, skew)
+}
+
+/// Number of timelock stages packed into the timelocks value
+pub const STAGE_COUNT: u8 = 7;
+
+/// Bitmap of the stages whose timelock has been reached at the current ledger time
+pub fn reached_stages(env: &Env, timelocks: u64) -> u32 {
+    let current_time = env.ledger().timestamp();
+    let mut reached = 0u32;
+    for stage in 0..STAGE_COUNT {
+        if current_time >= get_timelock(timelocks, stage) as u64 {
+            reached |= 1 << stage;
+        }
+    }
+    reached
+}
+
 /// Pack individual timelocks into a single u64
 #[allow(dead_code)]
 pub fn pack_timelocks(timelocks: [u32; 7]) -> u64 {
@@ -1,4 +1,5 @@
-use soroban_sdk::{contracttype, Address, BytesN, Env, Bytes};
+use fusion_shared::conversion::AbiEncoder;
+use soroban_sdk::{contracttype, xdr::ToXdr, Address, BytesN, Env, Bytes, Symbol, Vec};
 
 /// Immutable data stored for each escrow
 #[derive(Clone, Debug)]
@@ -12,27 +13,51 @@ pub struct Immutables {
     pub amount: i128,
     pub safety_deposit: i128,
     pub timelocks: u64, // bit-packed timelocks
+    pub max_secret_len: u32, // longest preimage accepted by withdraw_preimage (32 = fast path only)
+    pub hashlock_b: Option<BytesN<32>>, // second hashlock; when set only withdraw2 can withdraw
+    pub beneficiary: Option<Address>, // receives withdrawals instead of the taker (dst treasury)
+    pub incentives: DepositPolicy, // per-action safety deposit splits
+    pub skew_tolerance: u32, // seconds cancellation stages open late, absorbing cross-chain clock drift
+    pub finality_oracle: Option<Address>, // when set, withdrawals wait for its is_final(order_hash)
+    pub pre_funding_hook: Option<Address>, // before_funding is called before funding is accepted
+    pub post_payout_hook: Option<Address>, // after_payout is called after each withdrawal payout
+    pub swap_id: Option<BytesN<32>>, // cross-chain swap id (fusion_shared::swap_id), set by the factory
+    pub flags: u32, // behavior toggles (FLAG_*), DEFAULT_FLAGS unless the deployer called set_flags
+    pub parts: u32, // equal parts of a multi-secret escrow (parts + 1 secrets), 0 unless the deployer called set_parts
 }
 
 impl Immutables {
     /// Calculate hash of immutables for deterministic address calculation
     pub fn hash(&self, env: &Env) -> BytesN<32> {
+        // v1 hashes predate address encoding and keep the placeholders so they stay stable
+        let mut parties = Bytes::from_slice(env, b"MAKER_ADDR_PLACEHOLDER");
+        parties.append(&Bytes::from_slice(env, b"TAKER_ADDR_PLACEHOLDER"));
+        parties.append(&Bytes::from_slice(env, b"TOKEN_ADDR_PLACEHOLDER"));
+        crate::hash_secret(env, &self.preimage(env, &parties))
+    }
+
+    /// Immutables hash of a v2 escrow: the version byte, the preimage with the maker, taker and
+    /// token XDR-encoded, and the swap leg
+    /// The leading version keeps v1 and v2 escrows with the same fields from sharing a hash
+    pub fn hash_v2(&self, env: &Env, side: EscrowSide) -> BytesN<32> {
+        let mut parties = self.maker.clone().to_xdr(env);
+        parties.append(&self.taker.clone().to_xdr(env));
+        parties.append(&self.token.clone().to_xdr(env));
+        let mut bytes = Bytes::from_array(env, &[IMMUTABLES_V2_VERSION as u8]);
+        bytes.append(&self.preimage(env, &parties));
+        bytes.push_back(side as u8);
+        crate::hash_secret(env, &bytes)
+    }
+
+    /// Fields in a deterministic order, with `parties` standing for the maker, taker and token
+    fn preimage(&self, env: &Env, parties: &Bytes) -> Bytes {
         // Create a bytes buffer and append all fields in a deterministic order
         let mut bytes = Bytes::new(env);
         
         // Append BytesN fields directly
         bytes.append(&Bytes::from(self.order_hash.clone()));
         bytes.append(&Bytes::from(self.hashlock.clone()));
-        
-        // For addresses, we need to serialize them in a deterministic way
-        // Soroban addresses are 32-byte contract IDs or account IDs
-        // We'll use the raw contract/account ID bytes
-        
-        // Note: In a real implementation, you'd need to extract the actual
-        // address bytes. For now, we'll use placeholders that ensure uniqueness
-        bytes.append(&Bytes::from_slice(env, b"MAKER_ADDR_PLACEHOLDER"));
-        bytes.append(&Bytes::from_slice(env, b"TAKER_ADDR_PLACEHOLDER"));  
-        bytes.append(&Bytes::from_slice(env, b"TOKEN_ADDR_PLACEHOLDER"));
+        bytes.append(parties);
         
         // Append numeric values as fixed-size byte arrays
         let amount_bytes: [u8; 16] = self.amount.to_be_bytes();
@@ -44,12 +69,365 @@ impl Immutables {
         let timelock_bytes: [u8; 8] = self.timelocks.to_be_bytes();
         bytes.append(&Bytes::from_array(env, &timelock_bytes));
         
-        // Hash the complete data
-        let hash = env.crypto().sha256(&bytes);
-        BytesN::from_array(env, &hash.to_array())
+        let secret_len_bytes: [u8; 4] = self.max_secret_len.to_be_bytes();
+        bytes.append(&Bytes::from_array(env, &secret_len_bytes));
+        
+        // Optional fields are only appended when set, so escrows without them keep their
+        // original hash; each is preceded by its own tag byte so that two fields with equal
+        // encodings (e.g. the two hooks, or the oracle and the beneficiary) can't collide
+        if let Some(hashlock_b) = &self.hashlock_b {
+            bytes.push_back(1);
+            bytes.append(&Bytes::from(hashlock_b.clone()));
+        }
+        if let Some(beneficiary) = &self.beneficiary {
+            bytes.push_back(2);
+            bytes.append(&beneficiary.clone().to_xdr(env));
+        }
+        if let DepositPolicy::Split(incentives) = &self.incentives {
+            bytes.push_back(3);
+            bytes.append(&incentives.clone().to_xdr(env));
+        }
+        if self.skew_tolerance > 0 {
+            bytes.push_back(4);
+            bytes.extend_from_array(&self.skew_tolerance.to_be_bytes());
+        }
+        if let Some(oracle) = &self.finality_oracle {
+            bytes.push_back(5);
+            bytes.append(&oracle.clone().to_xdr(env));
+        }
+        if let Some(hook) = &self.pre_funding_hook {
+            bytes.push_back(6);
+            bytes.append(&hook.clone().to_xdr(env));
+        }
+        if let Some(hook) = &self.post_payout_hook {
+            bytes.push_back(7);
+            bytes.append(&hook.clone().to_xdr(env));
+        }
+        if let Some(swap_id) = &self.swap_id {
+            bytes.push_back(8);
+            bytes.append(&Bytes::from(swap_id.clone()));
+        }
+        if self.flags != DEFAULT_FLAGS {
+            bytes.push_back(9);
+            bytes.extend_from_array(&self.flags.to_be_bytes());
+        }
+        if self.parts > 0 {
+            bytes.push_back(10);
+            bytes.extend_from_array(&self.parts.to_be_bytes());
+        }
+        
+        bytes
+    }
+
+    /// `abi.encode(immutables)` as the EVM escrow factory computes it
+    /// Only the fields the EVM `Immutables` struct has are encoded, in its field order; their
+    /// keccak256 is the factory's escrow salt
+    pub fn evm_abi(&self, env: &Env) -> Bytes {
+        AbiEncoder::new(env)
+            .bytes32(&self.order_hash)
+            .bytes32(&self.hashlock)
+            .address(&self.maker)
+            .address(&self.taker)
+            .address(&self.token)
+            .int(self.amount)
+            .int(self.safety_deposit)
+            .uint(self.timelocks)
+            .finish()
+    }
+
+    /// Whether `flag` (a FLAG_* bit) is set
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Address that withdrawals pay: the beneficiary if one was designated, else the taker
+    pub fn payout_recipient(&self) -> Address {
+        self.beneficiary.clone().unwrap_or_else(|| self.taker.clone())
+    }
+}
+
+/// Immutables version of escrows deployed with deploy and its variants
+pub const IMMUTABLES_V1_VERSION: u32 = 1;
+/// Immutables version of escrows deployed with deploy_v2
+pub const IMMUTABLES_V2_VERSION: u32 = 2;
+
+/// Immutables in the v2 layout: the v1 core plus side, flags, receiver and fees in one struct
+/// deploy_v2 takes it; get_immutables_v2 presents every escrow in it, v1 ones with version 1
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct ImmutablesV2 {
+    pub version: u32, // IMMUTABLES_V*_VERSION the escrow's immutables hash follows
+    pub order_hash: BytesN<32>,
+    pub hashlock: BytesN<32>,
+    pub maker: Address,
+    pub taker: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub safety_deposit: i128,
+    pub timelocks: u64,
+    pub side: EscrowSide,
+    pub flags: u32,
+    pub receiver: Option<Address>, // v1 beneficiary
+    pub fees: DepositPolicy, // v1 incentives
+}
+
+impl ImmutablesV2 {
+    /// The v2 view of a v1 escrow's immutables (version 1); fields v2 doesn't carry (second
+    /// hashlock, preimage length, oracle, hooks, skew tolerance, swap id, parts) stay readable through
+    /// get_immutables
+    pub fn from_v1(immutables: &Immutables, side: EscrowSide) -> Self {
+        ImmutablesV2 {
+            version: IMMUTABLES_V1_VERSION,
+            order_hash: immutables.order_hash.clone(),
+            hashlock: immutables.hashlock.clone(),
+            maker: immutables.maker.clone(),
+            taker: immutables.taker.clone(),
+            token: immutables.token.clone(),
+            amount: immutables.amount,
+            safety_deposit: immutables.safety_deposit,
+            timelocks: immutables.timelocks,
+            side,
+            flags: immutables.flags,
+            receiver: immutables.beneficiary.clone(),
+            fees: immutables.incentives.clone(),
+        }
+    }
+
+    /// The immutables deploy_v2 stores, the fields v2 doesn't carry at their defaults
+    pub fn to_v1(&self) -> Immutables {
+        Immutables {
+            order_hash: self.order_hash.clone(),
+            hashlock: self.hashlock.clone(),
+            maker: self.maker.clone(),
+            taker: self.taker.clone(),
+            token: self.token.clone(),
+            amount: self.amount,
+            safety_deposit: self.safety_deposit,
+            timelocks: self.timelocks,
+            max_secret_len: crate::SECRET_LEN,
+            hashlock_b: None,
+            beneficiary: self.receiver.clone(),
+            incentives: self.fees.clone(),
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: self.flags,
+            parts: 0,
+        }
+    }
+
+    /// Immutables hash of the escrow these describe, following `version`
+    /// (before a factory-set skew tolerance or swap id, which only get_immutables_hash sees)
+    pub fn hash(&self, env: &Env) -> BytesN<32> {
+        let immutables = self.to_v1();
+        if self.version == IMMUTABLES_V2_VERSION {
+            immutables.hash_v2(env, self.side)
+        } else {
+            immutables.hash(env)
+        }
+    }
+}
+
+/// Shares of the safety deposit for one action, in basis points summing to 10_000
+/// The executor is whoever performs the action (the taker for withdraw)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositSplit {
+    pub executor_bps: u32,
+    pub maker_bps: u32,
+    pub protocol_bps: u32,
+}
+
+impl DepositSplit {
+    pub fn is_valid(&self) -> bool {
+        self.executor_bps as u64 + self.maker_bps as u64 + self.protocol_bps as u64 == BPS_DENOMINATOR as u64
+    }
+}
+
+/// Recipient of a deposit share
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositParty {
+    Executor = 0,
+    Maker = 1,
+    Protocol = 2,
+}
+
+/// Safety deposit splits per action, chosen at deploy (deploy_with_incentives)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DepositIncentives {
+    pub withdraw: DepositSplit,
+    pub public_withdraw: DepositSplit,
+    /// Cancellation during the private window
+    pub cancel: DepositSplit,
+    /// Cancellation once public cancellation has opened
+    pub public_cancel: DepositSplit,
+    /// Receives the protocol shares
+    pub protocol: Address,
+    /// Receives the rounding remainder of every split, and the shares below `dust_threshold`
+    pub remainder: DepositParty,
+    /// Shares smaller than this (in stroops) aren't transferred on their own but go to
+    /// `remainder`; 0 transfers every non-zero share
+    pub dust_threshold: i128,
+}
+
+impl DepositIncentives {
+    pub fn is_valid(&self) -> bool {
+        let splits = [&self.withdraw, &self.public_withdraw, &self.cancel, &self.public_cancel];
+        self.dust_threshold >= 0 && splits.iter().all(|split| split.is_valid())
+    }
+
+    /// Executor, maker and protocol shares of `deposit` under `split`, in `DepositParty` order
+    /// Each share rounds down; the remainder party is paid whatever the others don't receive,
+    /// so the shares always sum to `deposit`
+    pub fn shares(&self, split: &DepositSplit, deposit: i128) -> [i128; 3] {
+        // Split quotient and remainder so large deposits can't overflow
+        let denominator = BPS_DENOMINATOR as i128;
+        let share = |bps: u32| deposit / denominator * bps as i128 + deposit % denominator * bps as i128 / denominator;
+        let mut shares = [share(split.executor_bps), share(split.maker_bps), share(split.protocol_bps)];
+        let remainder = self.remainder as usize;
+        for (party, share) in shares.iter_mut().enumerate() {
+            if party != remainder && *share < self.dust_threshold {
+                *share = 0;
+            }
+        }
+        let paid: i128 = shares.iter().enumerate()
+            .filter(|(party, _)| *party != remainder)
+            .map(|(_, share)| share)
+            .sum();
+        shares[remainder] = deposit - paid;
+        shares
     }
 }
 
+/// Halves of a deposit shared between maker and taker (public_withdraw under
+/// `DepositPolicy::Default`); the odd stroop goes to the maker, like other rounding remainders
+pub fn halve_deposit(deposit: i128) -> (i128, i128) {
+    let taker_share = deposit / 2;
+    (deposit - taker_share, taker_share)
+}
+
+/// How an escrow distributes its safety deposit
+/// (an enum rather than an Option: contracttype structs can't nest an optional struct)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum DepositPolicy {
+    /// Withdraw returns it to the maker, public_withdraw halves it between maker and taker
+    /// (the odd stroop to the maker), cancellation pays it to the taker
+    Default,
+    /// Shares per action
+    Split(DepositIncentives),
+}
+
+/// Action that releases the safety deposit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepositAction {
+    Withdraw,
+    PublicWithdraw,
+    Cancel,
+    PublicCancel,
+}
+
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// One tranche of a reveal_secrets_batch call: a multi-secret fill's secret, the proof of its leaf
+/// at `index` and the amount it withdraws
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct SecretReveal {
+    pub index: u64,
+    pub secret: BytesN<32>,
+    pub proof: Vec<BytesN<32>>,
+    pub amount: i128,
+}
+
+/// One state-changing call, as recorded in the action log (get_action_log)
+/// Kept after finalize like the Receipt, so who did what and when outlives RPC event history
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct ActionLogEntry {
+    /// What the call did (`deploy`, `fund`, `withdraw`, `partial`, `cancel`, ...)
+    pub action: Symbol,
+    /// The authorizing address; calls anyone may make log the party they act for (the taker for
+    /// secret withdrawals, as in the Receipt) or the escrow itself for housekeeping
+    pub caller: Address,
+    /// Ledger sequence of the call
+    pub ledger: u32,
+    /// Token amount moved (refunded, paid or claimed), 0 if none
+    pub amount: i128,
+}
+
+/// Payout record of the withdrawal or cancellation that closed the escrow
+/// Kept after finalize, so it outlives the events RPC history drops
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct Receipt {
+    /// Who received the token amount (the maker for cancellations)
+    pub recipient: Address,
+    /// Token amount the closing call paid (or held for retry_payout)
+    pub amount: i128,
+    /// Safety deposit released by the closing call
+    pub fee_paid: i128,
+    /// Who executed it: the caller of public_withdraw and cancel, the taker otherwise
+    pub executor: Address,
+    pub timestamp: u64,
+    /// Ledger sequence of the closing transaction
+    pub tx_context: u32,
+}
+
+/// Destination and memo of the last withdraw_to payout
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct PayoutMemo {
+    pub recipient: Address,
+    /// Memo ID carried by a muxed (M...) recipient, attached to the token transfer
+    pub muxed_id: Option<u64>,
+    /// Free-form memo (e.g. exchange deposit reference), recorded and emitted only
+    pub memo: Option<Bytes>,
+}
+
+/// Escrow balances against what it should hold, for monitoring
+/// Surpluses are positive, deficits negative
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct EscrowBalances {
+    pub token_balance: i128,
+    /// Native XLM balance (equal to token_balance for native-token escrows)
+    pub native_balance: i128,
+    pub recorded_amount: i128,
+    pub recorded_deposit: i128,
+    /// Token balance minus the amount owed (plus deposits for native-token escrows)
+    pub token_surplus: i128,
+    /// Native balance minus the deposits owed (separate-deposit escrows only)
+    pub native_surplus: i128,
+}
+
+/// Outcome of a dry_run_* check
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[contracttype]
+pub struct DryRunResult {
+    /// Whether the real call would succeed (given the required authorization)
+    pub success: bool,
+    /// The contract error code the real call would fail with
+    pub error: Option<u32>,
+    /// Token amount the call would pay out
+    pub amount: i128,
+    /// Safety deposit the call would pay out
+    pub deposit: i128,
+}
+
+/// Which leg of the swap an escrow holds
+/// Dst escrows lock the resolver's funds for the maker; Src escrows lock the maker's funds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[contracttype]
+pub enum EscrowSide {
+    Src = 0,
+    Dst = 1,
+}
+
 /// Escrow state
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[contracttype]
@@ -57,15 +435,60 @@ pub enum State {
     Active = 0,
     Withdrawn = 1,
     Cancelled = 2,
+    Funded = 3,
+    Expired = 4, // Timed out without a withdrawal; only cancel remains
+}
+
+impl State {
+    /// Whether funds can still be withdrawn or cancelled
+    pub fn is_open(&self) -> bool {
+        matches!(self, State::Active | State::Funded)
+    }
+
+    /// Whether the escrow still holds funds that cancel can return
+    pub fn is_cancellable(&self) -> bool {
+        self.is_open() || *self == State::Expired
+    }
+
+    /// Whether the escrow has paid out and can no longer change state
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, State::Withdrawn | State::Cancelled)
+    }
 }
 
+/// Behavior toggles in Immutables.flags, so optional behaviors don't each widen the immutables
+/// withdraw_partial, withdraw_partial_with_proof and reveal_secrets_batch
+pub const FLAG_ALLOW_PARTIAL_FILLS: u32 = 1 << 0;
+/// public_withdraw, and cancellation by anyone but the maker and taker
+pub const FLAG_ALLOW_PUBLIC_ACTIONS: u32 = 1 << 1;
+/// withdraw(secret, true); XLM is paid through its asset contract, so this records the preference
+/// the EVM leg unwraps WETH with
+pub const FLAG_UNWRAP_NATIVE: u32 = 1 << 2;
+/// Every withdrawal needs the taker's authorization, not just the secret
+pub const FLAG_REQUIRE_TAKER_AUTH: u32 = 1 << 3;
+/// Deposit splits pay their protocol share; without it that share goes to the maker
+pub const FLAG_ENABLE_FEES: u32 = 1 << 4;
+pub const ALL_FLAGS: u32 =
+    FLAG_ALLOW_PARTIAL_FILLS | FLAG_ALLOW_PUBLIC_ACTIONS | FLAG_UNWRAP_NATIVE | FLAG_REQUIRE_TAKER_AUTH | FLAG_ENABLE_FEES;
+/// Behavior of escrows deployed before flags existed; left out of the immutables hash
+pub const DEFAULT_FLAGS: u32 = FLAG_ALLOW_PARTIAL_FILLS | FLAG_ALLOW_PUBLIC_ACTIONS | FLAG_ENABLE_FEES;
+
+/// Bits of the executed-actions bitmap (get_executed_actions), set by the payout paths
+/// A full withdrawal includes the tranche that fills a partially withdrawn escrow
+pub const EXECUTED_WITHDRAW: u32 = 1 << 0;
+pub const EXECUTED_PUBLIC_WITHDRAW: u32 = 1 << 1;
+pub const EXECUTED_PARTIAL: u32 = 1 << 2;
+pub const EXECUTED_CANCEL: u32 = 1 << 3;
+/// Once any of these is set every payout path fails with AlreadyExecuted; further tranches of a
+/// partially withdrawn escrow are still allowed
+pub const EXECUTED_TERMINAL: u32 = EXECUTED_WITHDRAW | EXECUTED_PUBLIC_WITHDRAW | EXECUTED_CANCEL;
+
 /// Timelock indices matching 1inch protocol
 #[allow(dead_code)]
 pub const SRC_WITHDRAWAL_TIMELOCK: u8 = 0;
 #[allow(dead_code)]
 pub const SRC_PUBLIC_WITHDRAWAL_TIMELOCK: u8 = 1;
 pub const SRC_CANCELLATION_TIMELOCK: u8 = 2;
-#[allow(dead_code)]
 pub const SRC_PUBLIC_CANCELLATION_TIMELOCK: u8 = 3;
 pub const DST_WITHDRAWAL_TIMELOCK: u8 = 4;
 pub const DST_PUBLIC_WITHDRAWAL_TIMELOCK: u8 = 5;
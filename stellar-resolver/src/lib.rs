@@ -1,8 +1,128 @@
 #![no_std]
+use fusion_shared::{
+    events,
+    hash::{HashFunction, Sha256},
+    swap_id::stellar_swap_id,
+    trace, EscrowClient, EvmAddress, FactoryClient, Order, State,
+};
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, Address, BytesN, Env, Symbol, Vec,
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contracterror, contractimpl, contracttype, token, Address,
+    vec, xdr::ScErrorType, Bytes, BytesN, Env, IntoVal, InvokeError, Symbol, Val, Vec,
 };
 
+mod queue;
+mod quote;
+
+pub use queue::FillRequest;
+pub use quote::{PendingQuoteConfig, Proceeds, Quote, QuoteConfig};
+
+/// Resolver errors use the 300+ range (see fusion_shared::errors)
+/// Failing factory calls surface as DeployFailed and failing token or escrow funding calls as
+/// FundingFailed, instead of trapping; withdraw_batch reports each escrow's own code
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    HashlockMismatch = 301,
+    LimitExceeded = 302,
+    CircuitBreakerTripped = 303,
+    UnknownPosition = 304,
+    EscrowStillOpen = 305,
+    SecretAlreadyRegistered = 306,
+    QuotesDisabled = 307,
+    NoValidQuote = 308,
+    PriceOutOfBand = 309,
+    TokenMismatch = 310,
+    AlreadyQueued = 311,
+    NotQueued = 312,
+    QueueFull = 313,
+    NoPendingChange = 314,
+    ChangeNotReady = 315,
+    NotInitialized = 316,
+    AlreadyInitialized = 317,
+    NotTaker = 318,
+    DeployFailed = 319,
+    FundingFailed = 320,
+    StaleQuote = 321,
+}
+
+/// Seconds between scheduling a factory or HTLC reference change and applying it
+pub const REFERENCE_CHANGE_DELAY: u64 = 24 * 60 * 60;
+
+/// Most open positions one register_secret call inspects, bounding its escrow calls
+pub const MAX_SECRET_SCAN: u32 = 50;
+
+/// Sub-windows the circuit breaker's rolling window is counted in
+pub const BREAKER_BUCKETS: u64 = 10;
+
+/// Contract references the owner can replace after REFERENCE_CHANGE_DELAY
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Reference {
+    Factory,
+    Htlc,
+}
+
+/// A scheduled reference change
+#[derive(Clone)]
+#[contracttype]
+pub struct PendingChange {
+    pub address: Address,
+    /// Ledger timestamp from which apply_reference_change accepts it
+    pub effective_at: u64,
+}
+
+/// Per-token caps on resolver capital
+#[derive(Clone)]
+#[contracttype]
+pub struct TokenLimits {
+    /// Largest amount a single fund_escrow may commit
+    pub max_order_notional: i128,
+    /// Largest amount that may be locked in open escrows at once
+    pub max_total_exposure: i128,
+}
+
+/// Circuit breaker that blocks new fills when too many positions end cancelled
+#[derive(Clone)]
+#[contracttype]
+pub struct CircuitBreakerConfig {
+    /// Length of the rolling window in seconds, counted in BREAKER_BUCKETS sub-windows
+    pub window: u64,
+    /// Cancellation rate (basis points of settled positions) that trips the breaker
+    pub max_cancel_bps: u32,
+    /// Settled positions needed in a window before the rate is evaluated
+    pub min_samples: u32,
+}
+
+/// Settlement counts over the circuit breaker's rolling window, which started at `start`
+#[derive(Clone)]
+#[contracttype]
+pub struct CancelWindow {
+    pub start: u64,
+    pub settled: u32,
+    pub cancelled: u32,
+}
+
+/// Settlement counts of one sub-window (`window / BREAKER_BUCKETS` seconds from `start`)
+#[derive(Clone)]
+#[contracttype]
+pub struct CancelBucket {
+    pub start: u64,
+    pub settled: u32,
+    pub cancelled: u32,
+}
+
+/// Outcome of one escrow in withdraw_batch
+#[derive(Clone)]
+#[contracttype]
+pub struct BatchWithdrawResult {
+    pub escrow: Address,
+    pub success: bool,
+    /// Contract error code the withdrawal failed with, when it failed with one
+    pub error: Option<u32>,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Immutables {
@@ -21,25 +141,80 @@ pub struct ResolverContract;
 
 #[contractimpl]
 impl ResolverContract {
-    /// Initialize the resolver with owner and HTLC contract reference
-    pub fn initialize(env: Env, owner: Address, htlc_contract: Address) {
+    /// Initialize the resolver with owner, HTLC contract and escrow factory references
+    /// Only once: the owner is replaced by no entrypoint, so a second call fails with AlreadyInitialized
+    pub fn initialize(env: Env, owner: Address, htlc_contract: Address, factory: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&Symbol::new(&env, "owner")) {
+            return Err(Error::AlreadyInitialized);
+        }
         owner.require_auth();
         
         env.storage().instance().set(&Symbol::new(&env, "owner"), &owner);
         env.storage().instance().set(&Symbol::new(&env, "htlc"), &htlc_contract);
+        env.storage().instance().set(&Symbol::new(&env, "factory"), &factory);
+        Ok(())
     }
 
+    /// Schedule replacing the escrow factory (owner only)
+    /// Takes effect through apply_reference_change once REFERENCE_CHANGE_DELAY has passed;
+    /// scheduling again replaces the pending change and restarts the delay
+    pub fn set_factory(env: Env, new_factory: Address) -> Result<(), Error> {
+        schedule_reference_change(&env, Reference::Factory, new_factory)
+    }
+    
+    /// Schedule replacing the HTLC contract (owner only), like set_factory
+    pub fn set_htlc(env: Env, new_htlc: Address) -> Result<(), Error> {
+        schedule_reference_change(&env, Reference::Htlc, new_htlc)
+    }
+    
+    /// Apply a scheduled reference change whose delay has passed (anyone)
+    pub fn apply_reference_change(env: Env, reference: Reference) -> Result<Address, Error> {
+        let pending_key = (Symbol::new(&env, "pending_ref"), reference);
+        let change: PendingChange = match env.storage().instance().get(&pending_key) {
+            Some(change) => change,
+            None => return Err(Error::NoPendingChange),
+        };
+        if env.ledger().timestamp() < change.effective_at {
+            return Err(Error::ChangeNotReady);
+        }
+        
+        env.storage().instance().set(&reference_key(&env, reference), &change.address);
+        env.storage().instance().remove(&pending_key);
+        
+        events::publish(&env, (Symbol::new(&env, "reference_changed"), reference), change.address.clone());
+        
+        Ok(change.address)
+    }
+    
+    /// Drop a scheduled reference change (owner only)
+    pub fn cancel_reference_change(env: Env, reference: Reference) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        let pending_key = (Symbol::new(&env, "pending_ref"), reference);
+        if !env.storage().instance().has(&pending_key) {
+            return Err(Error::NoPendingChange);
+        }
+        env.storage().instance().remove(&pending_key);
+        
+        events::publish(&env, (Symbol::new(&env, "reference_change_cancelled"), reference), ());
+        Ok(())
+    }
+    
+    /// Get the change scheduled for a reference, if any
+    pub fn get_pending_change(env: Env, reference: Reference) -> Option<PendingChange> {
+        env.storage().instance().get(&(Symbol::new(&env, "pending_ref"), reference))
+    }
+    
     /// Deploy a new escrow on Stellar (no LOP here, direct deployment)
     pub fn deploy_escrow(
         env: Env,
         immutables: Immutables,
-    ) -> Address {
+    ) -> Result<Address, Error> {
         // Verify caller is owner
-        let owner: Address = env.storage().instance().get(&Symbol::new(&env, "owner")).unwrap();
-        owner.require_auth();
+        require_owner(&env)?;
         
         // Get HTLC contract address
-        let htlc_contract: Address = env.storage().instance().get(&Symbol::new(&env, "htlc")).unwrap();
+        let htlc_contract = instance_address(&env, "htlc")?;
         
         // Call HTLC contract to deploy escrow
         // In real implementation, this would call the HTLC contract's deploy function
@@ -47,76 +222,822 @@ impl ResolverContract {
         env.storage().persistent().set(&immutables.order_hash, &immutables);
         
         // Emit event
-        env.events().publish((Symbol::new(&env, "escrow_deployed"),), (
+        events::publish(&env, (Symbol::new(&env, "escrow_deployed"),), (
             immutables.order_hash.clone(),
             immutables.maker.clone(),
             immutables.taker.clone(),
             immutables.amount,
         ));
         
-        htlc_contract
+        Ok(htlc_contract)
     }
     
     /// Fund an escrow with tokens
+    /// The amount counts towards the token's exposure until settle_position is called
+    /// With quotes enabled, `proceeds` (what the fill earns) must be within the quoted band. The
+    /// owner states `proceeds` and nothing on-chain checks it, so here the quote is only advisory
     pub fn fund_escrow(
         env: Env,
         escrow: Address,
         token: Address,
         amount: i128,
-    ) {
-        let owner: Address = env.storage().instance().get(&Symbol::new(&env, "owner")).unwrap();
-        owner.require_auth();
+        proceeds: Proceeds,
+    ) -> Result<(), Error> {
+        let owner = require_owner(&env)?;
+        
+        ensure_breaker_closed(&env)?;
+        quote::check_fill(&env, &token, amount, &proceeds.token, proceeds.amount)?;
+        open_position(&env, &escrow, &token, amount)?;
         
         // Transfer tokens from resolver to escrow
-        let token_client = token::Client::new(&env, &token);
-        token_client.transfer(&owner, &escrow, &amount);
+        funding_call(token::Client::new(&env, &token).try_transfer(&owner, &escrow, &amount))?;
         
         // Emit event
-        env.events().publish((Symbol::new(&env, "escrow_funded"),), (
+        events::publish(&env, (Symbol::new(&env, "escrow_funded"),), (
             escrow.clone(),
             token.clone(),
             amount,
         ));
+        Ok(())
+    }
+    
+    /// Deploy and fund the Stellar escrow filling an EVM order (owner only)
+    /// The escrowed token must be the factory's registered equivalent of the order's EVM
+    /// `taker_asset` on `src_chain_id`; it is checked before any funds move
+    /// The factory records the swap id (`fusion_shared::swap_id`) in the escrow, and the resolver
+    /// indexes the escrow by it
+    /// The resolver must be the immutables' taker; the factory requires its authorization
+    /// With quotes enabled, `proceeds` (the order's maker side) must be within the quoted band.
+    /// The maker side lives on the EVM chain, so the owner states it and the quote is only advisory
+    pub fn fill_evm_order(
+        env: Env,
+        src_chain_id: u64,
+        taker_asset: EvmAddress,
+        immutables: Immutables,
+        proceeds: Proceeds,
+    ) -> Result<Address, Error> {
+        let owner = require_owner(&env)?;
+        
+        ensure_breaker_closed(&env)?;
+        if immutables.taker != env.current_contract_address() {
+            return Err(Error::NotTaker);
+        }
+        quote::check_fill(&env, &immutables.token, immutables.amount, &proceeds.token, proceeds.amount)?;
+        
+        let factory = instance_address(&env, "factory")?;
+        let wrapped = match FactoryClient::new(&env, &factory).try_get_wrapped_token(&src_chain_id, &taker_asset) {
+            Ok(Ok(wrapped)) => wrapped,
+            _ => return Err(Error::DeployFailed),
+        };
+        if wrapped != Some(immutables.token.clone()) {
+            return Err(Error::TokenMismatch);
+        }
+        
+        let escrow = match env.try_invoke_contract::<Address, InvokeError>(
+            &factory,
+            &Symbol::new(&env, "deploy_escrow_for_evm_order"),
+            vec![&env, src_chain_id.into_val(&env), taker_asset.into_val(&env), immutables.into_val(&env)],
+        ) {
+            Ok(Ok(escrow)) => escrow,
+            _ => return Err(Error::DeployFailed),
+        };
+        let swap_id = stellar_swap_id(&env, &immutables.order_hash, false, src_chain_id);
+        env.storage().persistent().set(&(Symbol::new(&env, "swap"), swap_id.clone()), &escrow);
+        open_position(&env, &escrow, &immutables.token, immutables.amount)?;
+        funding_call(token::Client::new(&env, &immutables.token).try_transfer(&owner, &escrow, &immutables.amount))?;
+        
+        events::publish(&env, (Symbol::new(&env, "evm_order_filled"),), (
+            escrow.clone(),
+            src_chain_id,
+            taker_asset,
+            immutables.amount,
+            swap_id,
+        ));
+        
+        Ok(escrow)
+    }
+    
+    /// Queue a fill for execute_next instead of filling it now (owner only)
+    /// `priority` is the fill's auction payout; higher priorities run first
+    /// `proceeds` is what the fill earns, checked against the quotes when it runs
+    pub fn queue_fill(env: Env, immutables: Immutables, proceeds: Proceeds, priority: i128) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        let order_hash = immutables.order_hash.clone();
+        queue::push(&env, FillRequest {
+            immutables,
+            proceeds,
+            priority,
+            queued_at: env.ledger().timestamp(),
+        })?;
+        
+        events::publish(&env, (Symbol::new(&env, "fill_queued"), order_hash), priority);
+        Ok(())
+    }
+    
+    /// Drop a queued fill (owner only)
+    pub fn cancel_queued_fill(env: Env, order_hash: BytesN<32>) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        queue::remove(&env, &order_hash)?;
+        
+        events::publish(&env, (Symbol::new(&env, "queued_fill_cancelled"), order_hash), ());
+        Ok(())
+    }
+    
+    /// Deploy and fund up to `n` queued fills in priority order (owner only, e.g. a keeper bot)
+    /// Fills that would exceed their token's limits stay queued until positions settle, and fills
+    /// outside the quoted band until a quote covers them; fills the factory refuses to deploy or
+    /// whose transfer fails are dropped. Returns the escrows funded, in execution order
+    pub fn execute_next(env: Env, n: u32) -> Result<Vec<Address>, Error> {
+        let owner = require_owner(&env)?;
+        
+        ensure_breaker_closed(&env)?;
+        
+        let factory = instance_address(&env, "factory")?;
+        let mut escrows = Vec::new(&env);
+        let mut remaining = Vec::new(&env);
+        for request in queue::get(&env).iter() {
+            let immutables = &request.immutables;
+            let proceeds = &request.proceeds;
+            if escrows.len() >= n
+                || !within_limits(&env, &immutables.token, immutables.amount)
+                || quote::check_fill(&env, &immutables.token, immutables.amount, &proceeds.token, proceeds.amount).is_err()
+            {
+                remaining.push_back(request);
+                continue;
+            }
+            let funded = deploy_via_factory(&env, &factory, immutables).and_then(|escrow| {
+                funding_call(token::Client::new(&env, &immutables.token).try_transfer(&owner, &escrow, &immutables.amount))?;
+                Ok(escrow)
+            });
+            let escrow = match funded {
+                Ok(escrow) => escrow,
+                Err(_) => {
+                    events::publish(&env, (Symbol::new(&env, "queued_fill_dropped"), immutables.order_hash.clone()), ());
+                    continue;
+                }
+            };
+            open_position(&env, &escrow, &immutables.token, immutables.amount)?;
+            escrows.push_back(escrow);
+        }
+        queue::set(&env, &remaining);
+        
+        events::publish(&env, (Symbol::new(&env, "queue_executed"),), (
+            escrows.len(),
+            remaining.len(),
+        ));
+        
+        Ok(escrows)
     }
     
     /// Withdraw from escrow using revealed secret
     pub fn withdraw(
         env: Env,
         escrow: Address,
-        secret: BytesN<32>,
-    ) {
+        _secret: BytesN<32>,
+    ) -> Result<(), Error> {
+        require_initialized(&env)?;
+        
         // Anyone can call withdraw with the correct secret
         // The HTLC contract will verify the secret
         
         // In real implementation, this would call the HTLC contract's withdraw function
         // For demonstration, we emit an event
-        env.events().publish((Symbol::new(&env, "withdraw_initiated"),), (
+        events::publish(&env, (Symbol::new(&env, "withdraw_initiated"),), (
             escrow.clone(),
             env.current_contract_address(),
         ));
+        Ok(())
+    }
+    
+    /// Withdraw from several escrows that share one secret (an order filled across resolvers)
+    /// Failures don't abort the batch; each escrow's outcome is returned in order
+    pub fn withdraw_batch(env: Env, escrows: Vec<Address>, secret: BytesN<32>) -> Result<Vec<BatchWithdrawResult>, Error> {
+        require_initialized(&env)?;
+        
+        let mut results = Vec::new(&env);
+        let mut succeeded = 0u32;
+        for escrow in escrows.iter() {
+            let result = try_withdraw(&env, escrow, &secret);
+            if result.success {
+                succeeded += 1;
+            }
+            results.push_back(result);
+        }
+        
+        events::publish(&env, (Symbol::new(&env, "batch_withdrawn"),), (
+            results.len(),
+            succeeded,
+        ));
+        
+        Ok(results)
+    }
+    
+    /// Publish a revealed secret (anyone) and optionally settle with it in the same transaction
+    /// Up to `max_withdrawals` of the resolver's open positions whose hashlock matches are
+    /// withdrawn, shrinking the window in which public withdrawers can race the resolver;
+    /// pass 0 to only register the secret. Only the first MAX_SECRET_SCAN positions are
+    /// inspected, and escrows that are closed or don't answer get_immutables are skipped
+    pub fn register_secret(env: Env, secret: BytesN<32>, max_withdrawals: u32) -> Result<Vec<BatchWithdrawResult>, Error> {
+        // Same hash as the default escrow build (see stellar-escrow's `EscrowHash`)
+        let hashlock = Sha256::digest(&env, &secret.clone().into());
+        let secret_key = (Symbol::new(&env, "secret"), hashlock.clone());
+        if env.storage().persistent().has(&secret_key) {
+            return Err(Error::SecretAlreadyRegistered);
+        }
+        env.storage().persistent().set(&secret_key, &secret);
+        
+        events::publish(&env, (Symbol::new(&env, "secret_registered"),), (
+            hashlock.clone(),
+            secret.clone(),
+        ));
+        
+        let mut results = Vec::new(&env);
+        if max_withdrawals == 0 {
+            return Ok(results);
+        }
+        let active: Vec<Address> = env.storage().persistent()
+            .get(&Symbol::new(&env, "active"))
+            .unwrap_or(Vec::new(&env));
+        for escrow in active.iter().take(MAX_SECRET_SCAN as usize) {
+            if results.len() >= max_withdrawals {
+                break;
+            }
+            let escrow_client = EscrowClient::new(&env, &escrow);
+            if !matches!(escrow_client.try_get_immutables(), Ok(Ok(immutables)) if immutables.hashlock == hashlock) {
+                continue;
+            }
+            let state = match escrow_client.try_get_state() {
+                Ok(Ok(state)) => state,
+                _ => continue,
+            };
+            trace!(&env, "escrow", (escrow.clone(), state as u32));
+            if !matches!(state, State::Active | State::Funded) {
+                continue;
+            }
+            results.push_back(try_withdraw(&env, escrow, &secret));
+        }
+        
+        Ok(results)
+    }
+    
+    /// Collect `caller`'s keeper rewards from several escrows in one call (caller only)
+    /// Escrows with nothing to claim are skipped; returns the total collected
+    pub fn claim_rewards(env: Env, caller: Address, escrows: Vec<Address>) -> Result<i128, Error> {
+        require_initialized(&env)?;
+        caller.require_auth();
+        
+        let mut total = 0i128;
+        for escrow in escrows.iter() {
+            if let Ok(Ok(amount)) = EscrowClient::new(&env, &escrow).try_claim_rewards(&caller) {
+                total += amount;
+            }
+        }
+        
+        events::publish(&env, (Symbol::new(&env, "rewards_claimed"),), (
+            caller,
+            escrows.len(),
+            total,
+        ));
+        
+        Ok(total)
     }
     
     /// Cancel escrow after timelock expiry
     pub fn cancel(
         env: Env,
         escrow: Address,
-    ) {
+    ) -> Result<(), Error> {
+        require_initialized(&env)?;
+        
         // Anyone can call cancel after timelock
         // The HTLC contract will verify the timelock
         
-        env.events().publish((Symbol::new(&env, "cancel_initiated"),), (
+        events::publish(&env, (Symbol::new(&env, "cancel_initiated"),), (
             escrow.clone(),
             env.current_contract_address(),
         ));
+        Ok(())
+    }
+    
+    /// Deploy and fund both escrows of a swap whose legs both live on Stellar
+    /// Each leg is funded by its maker; both legs must share the same hashlock
+    pub fn fill_internal_swap(
+        env: Env,
+        src_immutables: Immutables,
+        dst_immutables: Immutables,
+    ) -> Result<(Address, Address), Error> {
+        require_owner(&env)?;
+        
+        ensure_breaker_closed(&env)?;
+        
+        if src_immutables.hashlock != dst_immutables.hashlock {
+            return Err(Error::HashlockMismatch);
+        }
+        
+        // The dst leg pays dst amount of its token for the src leg's amount
+        quote::check_fill(
+            &env,
+            &src_immutables.token,
+            src_immutables.amount,
+            &dst_immutables.token,
+            dst_immutables.amount,
+        )?;
+        
+        let factory = instance_address(&env, "factory")?;
+        
+        let src_escrow = deploy_via_factory(&env, &factory, &src_immutables)?;
+        fund_and_confirm(&env, &src_escrow, &src_immutables)?;
+        
+        let dst_escrow = deploy_via_factory(&env, &factory, &dst_immutables)?;
+        fund_and_confirm(&env, &dst_escrow, &dst_immutables)?;
+        
+        env.storage().persistent().set(
+            &(Symbol::new(&env, "internal"), src_immutables.order_hash.clone()),
+            &(src_escrow.clone(), dst_escrow.clone()),
+        );
+        
+        events::publish(&env, (Symbol::new(&env, "internal_swap_filled"),), (
+            src_escrow.clone(),
+            dst_escrow.clone(),
+            src_immutables.hashlock.clone(),
+        ));
+        
+        Ok((src_escrow, dst_escrow))
+    }
+    
+    /// Set the quoter key and tolerance that bound fill prices (owner only)
+    /// Once set, every fill needs an unexpired quote for its pair. The first config applies at
+    /// once; replacing it is scheduled like set_factory and takes effect through
+    /// apply_quote_config after REFERENCE_CHANGE_DELAY
+    pub fn set_quote_config(env: Env, config: QuoteConfig) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        if quote::get_config(&env).is_none() {
+            quote::set_config(&env, &config);
+            events::publish(&env, (Symbol::new(&env, "quote_config_set"),), (
+                config.quoter,
+                config.tolerance_bps,
+            ));
+            return Ok(());
+        }
+        
+        let effective_at = quote::schedule_config(&env, &config);
+        events::publish(&env, (Symbol::new(&env, "quote_config_scheduled"),), (
+            config.quoter,
+            config.tolerance_bps,
+            effective_at,
+        ));
+        Ok(())
+    }
+    
+    /// Apply a scheduled quote config whose delay has passed (anyone)
+    pub fn apply_quote_config(env: Env) -> Result<QuoteConfig, Error> {
+        let pending = quote::get_pending_config(&env).ok_or(Error::NoPendingChange)?;
+        if env.ledger().timestamp() < pending.effective_at {
+            return Err(Error::ChangeNotReady);
+        }
+        
+        quote::set_config(&env, &pending.config);
+        quote::remove_pending_config(&env);
+        
+        events::publish(&env, (Symbol::new(&env, "quote_config_set"),), (
+            pending.config.quoter.clone(),
+            pending.config.tolerance_bps,
+        ));
+        Ok(pending.config)
+    }
+    
+    /// Drop a scheduled quote config (owner only)
+    pub fn cancel_quote_config(env: Env) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        if quote::get_pending_config(&env).is_none() {
+            return Err(Error::NoPendingChange);
+        }
+        quote::remove_pending_config(&env);
+        
+        events::publish(&env, (Symbol::new(&env, "quote_config_cancelled"),), ());
+        Ok(())
+    }
+    
+    /// Store a quote signed by the configured quoter as the latest for its pair (anyone)
+    /// The signature covers this resolver's address and the network (see quote::signed_payload),
+    /// and the quote must expire later than the stored one
+    pub fn submit_quote(env: Env, quote: Quote, signature: BytesN<64>) -> Result<(), Error> {
+        quote::submit(&env, &quote, &signature)?;
+        
+        events::publish(&env, (Symbol::new(&env, "quote_submitted"), quote.sell_token, quote.buy_token), (
+            quote.rate,
+            quote.expires_at,
+        ));
+        Ok(())
+    }
+    
+    /// Set the risk limits for a token (owner only)
+    pub fn set_token_limits(env: Env, token: Address, limits: TokenLimits) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        env.storage().persistent().set(&(Symbol::new(&env, "limits"), token.clone()), &limits);
+        
+        events::publish(&env, (Symbol::new(&env, "token_limits_set"),), (
+            token,
+            limits.max_order_notional,
+            limits.max_total_exposure,
+        ));
+        Ok(())
+    }
+    
+    /// Configure the cancellation-rate circuit breaker (owner only)
+    pub fn set_circuit_breaker(env: Env, config: CircuitBreakerConfig) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        env.storage().instance().set(&Symbol::new(&env, "breaker"), &config);
+        env.storage().instance().remove(&Symbol::new(&env, "buckets"));
+        Ok(())
+    }
+    
+    /// Re-enable fills after the circuit breaker tripped (owner only)
+    pub fn reset_circuit_breaker(env: Env) -> Result<(), Error> {
+        require_owner(&env)?;
+        
+        env.storage().instance().set(&Symbol::new(&env, "tripped"), &false);
+        env.storage().instance().remove(&Symbol::new(&env, "buckets"));
+        
+        events::publish(&env, (Symbol::new(&env, "circuit_reset"),), env.ledger().timestamp());
+        Ok(())
+    }
+    
+    /// Sweep the resolver's whole balance of each of `tokens` to `to` (owner only)
+    /// For incident response, e.g. before rotating a compromised owner key: funds locked in
+    /// escrows stay there, and the circuit breaker trips so no new fills start until
+    /// reset_circuit_breaker. Returns the amount swept per token, in order
+    pub fn emergency_withdraw_all(env: Env, tokens: Vec<Address>, to: Address) -> Result<Vec<i128>, Error> {
+        require_owner(&env)?;
+        
+        let resolver = env.current_contract_address();
+        let mut swept = Vec::new(&env);
+        for token in tokens.iter() {
+            let client = token::Client::new(&env, &token);
+            let balance = client.balance(&resolver);
+            if balance > 0 {
+                client.transfer(&resolver, &to, &balance);
+            }
+            swept.push_back(balance);
+        }
+        
+        env.storage().instance().set(&Symbol::new(&env, "tripped"), &true);
+        events::publish(&env, (Symbol::new(&env, "emergency_withdrawal"), to), (tokens, swept.clone()));
+        
+        Ok(swept)
+    }
+    
+    /// Release the exposure of a funded escrow once it has closed (anyone)
+    /// Cancelled escrows count towards the circuit breaker's cancellation rate; an escrow whose
+    /// state can't be read counts as still open
+    pub fn settle_position(env: Env, escrow: Address) -> Result<(), Error> {
+        let position_key = (Symbol::new(&env, "position"), escrow.clone());
+        let (token, amount): (Address, i128) = match env.storage().persistent().get(&position_key) {
+            Some(position) => position,
+            None => return Err(Error::UnknownPosition),
+        };
+        
+        let state = match EscrowClient::new(&env, &escrow).try_get_state() {
+            Ok(Ok(state)) => state,
+            _ => return Err(Error::EscrowStillOpen),
+        };
+        trace!(&env, "escrow", (escrow.clone(), state as u32));
+        let cancelled = match state {
+            State::Withdrawn => false,
+            State::Cancelled => true,
+            _ => return Err(Error::EscrowStillOpen),
+        };
+        
+        env.storage().persistent().remove(&position_key);
+        let active_key = Symbol::new(&env, "active");
+        let mut active: Vec<Address> = env.storage().persistent().get(&active_key).unwrap_or(Vec::new(&env));
+        if let Some(index) = active.first_index_of(&escrow) {
+            active.remove(index);
+            env.storage().persistent().set(&active_key, &active);
+        }
+        let exposure_key = (Symbol::new(&env, "exposure"), token.clone());
+        let exposure: i128 = env.storage().persistent().get(&exposure_key).unwrap_or(0);
+        env.storage().persistent().set(&exposure_key, &(exposure - amount).max(0));
+        
+        record_settlement(&env, cancelled);
+        
+        events::publish(&env, (Symbol::new(&env, "position_settled"),), (
+            escrow,
+            token,
+            amount,
+            cancelled,
+        ));
+        Ok(())
+    }
+    
+    /// Get a secret published through register_secret by its hashlock
+    pub fn get_registered_secret(env: Env, hashlock: BytesN<32>) -> Option<BytesN<32>> {
+        env.storage().persistent().get(&(Symbol::new(&env, "secret"), hashlock))
+    }
+    
+    /// Get the escrows with an open position, in funding order
+    pub fn get_active_escrows(env: Env) -> Vec<Address> {
+        env.storage().persistent().get(&Symbol::new(&env, "active")).unwrap_or(Vec::new(&env))
+    }
+    
+    /// Get the latest quote submitted for a pair, expired or not
+    pub fn get_quote(env: Env, sell_token: Address, buy_token: Address) -> Option<Quote> {
+        quote::get_latest(&env, &sell_token, &buy_token)
+    }
+    
+    /// Get the quoter key and tolerance, if quotes are enabled
+    pub fn get_quote_config(env: Env) -> Option<QuoteConfig> {
+        quote::get_config(&env)
+    }
+    
+    /// Get the quote config scheduled by set_quote_config, if any
+    pub fn get_pending_quote_config(env: Env) -> Option<PendingQuoteConfig> {
+        quote::get_pending_config(&env)
+    }
+    
+    /// Get the risk limits configured for a token
+    pub fn get_token_limits(env: Env, token: Address) -> Option<TokenLimits> {
+        env.storage().persistent().get(&(Symbol::new(&env, "limits"), token))
+    }
+    
+    /// Get the queued fills in execution order
+    pub fn get_queued_fills(env: Env) -> Vec<FillRequest> {
+        queue::get(&env)
+    }
+    
+    /// Get the amount of a token currently locked in open escrows
+    pub fn get_exposure(env: Env, token: Address) -> i128 {
+        env.storage().persistent().get(&(Symbol::new(&env, "exposure"), token)).unwrap_or(0)
+    }
+    
+    /// Check whether the circuit breaker is blocking new fills
+    pub fn is_circuit_tripped(env: Env) -> bool {
+        env.storage().instance().get(&Symbol::new(&env, "tripped")).unwrap_or(false)
+    }
+    
+    /// Get the settlement counts of the last `window` seconds, if the circuit breaker is configured
+    pub fn get_cancel_window(env: Env) -> Option<CancelWindow> {
+        let config: CircuitBreakerConfig = env.storage().instance().get(&Symbol::new(&env, "breaker"))?;
+        Some(sum_buckets(&env, &config, &live_buckets(&env, &config)))
+    }
+    
+    /// Get the (src, dst) escrows of an internal swap by its src order hash
+    pub fn get_internal_swap(env: Env, order_hash: BytesN<32>) -> Option<(Address, Address)> {
+        env.storage().persistent().get(&(Symbol::new(&env, "internal"), order_hash))
+    }
+    
+    /// Get the escrow fill_evm_order deployed for a swap id
+    pub fn get_escrow_by_swap_id(env: Env, swap_id: BytesN<32>) -> Option<Address> {
+        env.storage().persistent().get(&(Symbol::new(&env, "swap"), swap_id))
     }
     
     /// Get owner address
-    pub fn get_owner(env: Env) -> Address {
-        env.storage().instance().get(&Symbol::new(&env, "owner")).unwrap()
+    pub fn get_owner(env: Env) -> Result<Address, Error> {
+        instance_address(&env, "owner")
     }
     
     /// Get HTLC contract address
-    pub fn get_htlc(env: Env) -> Address {
-        env.storage().instance().get(&Symbol::new(&env, "htlc")).unwrap()
+    pub fn get_htlc(env: Env) -> Result<Address, Error> {
+        instance_address(&env, "htlc")
     }
+    
+    /// Get escrow factory address
+    pub fn get_factory(env: Env) -> Result<Address, Error> {
+        instance_address(&env, "factory")
+    }
+    
+    /// Hash of a Stellar-origin order as the configured factory verifies it (create_src_escrow)
+    /// Lets the operator check what the maker signed before submitting
+    pub fn hash_order(env: Env, order: Order) -> Result<BytesN<32>, Error> {
+        let factory = instance_address(&env, "factory")?;
+        Ok(fusion_shared::order::hash_order(&env, &factory, &order))
+    }
+}
+
+/// Instance key holding a reference's current address
+fn reference_key(env: &Env, reference: Reference) -> Symbol {
+    match reference {
+        Reference::Factory => Symbol::new(env, "factory"),
+        Reference::Htlc => Symbol::new(env, "htlc"),
+    }
+}
+
+/// An address reference stored at initialization (owner, htlc, factory)
+fn instance_address(env: &Env, key: &str) -> Result<Address, Error> {
+    env.storage().instance().get(&Symbol::new(env, key)).ok_or(Error::NotInitialized)
+}
+
+/// Fail with NotInitialized until initialize has run
+fn require_initialized(env: &Env) -> Result<(), Error> {
+    instance_address(env, "owner").map(|_| ())
+}
+
+/// Require the owner's authorization, returning the owner
+fn require_owner(env: &Env) -> Result<Address, Error> {
+    let owner = instance_address(env, "owner")?;
+    owner.require_auth();
+    Ok(owner)
+}
+
+/// Record a pending reference change (owner only), announced so monitoring can flag it
+fn schedule_reference_change(env: &Env, reference: Reference, address: Address) -> Result<(), Error> {
+    require_owner(env)?;
+    
+    let effective_at = env.ledger().timestamp() + REFERENCE_CHANGE_DELAY;
+    env.storage().instance().set(
+        &(Symbol::new(env, "pending_ref"), reference),
+        &PendingChange { address: address.clone(), effective_at },
+    );
+    
+    events::publish(env, (Symbol::new(env, "reference_change_scheduled"), reference), (
+        address,
+        effective_at,
+    ));
+    Ok(())
+}
+
+/// Reject new fills while the circuit breaker is tripped
+fn ensure_breaker_closed(env: &Env) -> Result<(), Error> {
+    let tripped: bool = env.storage().instance().get(&Symbol::new(env, "tripped")).unwrap_or(false);
+    if tripped {
+        return Err(Error::CircuitBreakerTripped);
+    }
+    Ok(())
 }
+
+/// Whether committing `amount` more of a token stays within its limits
+fn within_limits(env: &Env, token: &Address, amount: i128) -> bool {
+    let exposure: i128 = env.storage().persistent()
+        .get(&(Symbol::new(env, "exposure"), token.clone()))
+        .unwrap_or(0) + amount;
+    
+    let limits: Option<TokenLimits> = env.storage().persistent().get(&(Symbol::new(env, "limits"), token.clone()));
+    match limits {
+        Some(limits) => {
+            trace!(env, "limits", (token.clone(), amount, exposure, limits.max_order_notional, limits.max_total_exposure));
+            amount <= limits.max_order_notional && exposure <= limits.max_total_exposure
+        }
+        None => true,
+    }
+}
+
+/// Check the token's limits and record the escrow's exposure
+fn open_position(env: &Env, escrow: &Address, token: &Address, amount: i128) -> Result<(), Error> {
+    if !within_limits(env, token, amount) {
+        return Err(Error::LimitExceeded);
+    }
+    let exposure_key = (Symbol::new(env, "exposure"), token.clone());
+    let exposure: i128 = env.storage().persistent().get(&exposure_key).unwrap_or(0) + amount;
+    
+    let position_key = (Symbol::new(env, "position"), escrow.clone());
+    let previous: i128 = env.storage().persistent()
+        .get::<_, (Address, i128)>(&position_key)
+        .map(|(_, amount)| amount)
+        .unwrap_or(0);
+    env.storage().persistent().set(&position_key, &(token.clone(), previous + amount));
+    if previous == 0 {
+        let active_key = Symbol::new(env, "active");
+        let mut active: Vec<Address> = env.storage().persistent().get(&active_key).unwrap_or(Vec::new(env));
+        active.push_back(escrow.clone());
+        env.storage().persistent().set(&active_key, &active);
+    }
+    env.storage().persistent().set(&exposure_key, &exposure);
+    Ok(())
+}
+
+/// Sub-windows that started within the last `window` seconds, oldest first
+/// At most BREAKER_BUCKETS + 1 of them, so the instance entry stays bounded
+fn live_buckets(env: &Env, config: &CircuitBreakerConfig) -> Vec<CancelBucket> {
+    let now = env.ledger().timestamp();
+    let mut buckets: Vec<CancelBucket> = env.storage().instance()
+        .get(&Symbol::new(env, "buckets"))
+        .unwrap_or(Vec::new(env));
+    while buckets.first().is_some_and(|oldest| oldest.start + config.window <= now) {
+        buckets.pop_front_unchecked();
+    }
+    buckets
+}
+
+fn sum_buckets(env: &Env, config: &CircuitBreakerConfig, buckets: &Vec<CancelBucket>) -> CancelWindow {
+    let mut window = CancelWindow {
+        start: env.ledger().timestamp().saturating_sub(config.window),
+        settled: 0,
+        cancelled: 0,
+    };
+    for bucket in buckets.iter() {
+        window.settled += bucket.settled;
+        window.cancelled += bucket.cancelled;
+    }
+    window
+}
+
+/// Count a settled position in its sub-window and trip the breaker if the cancellation rate over
+/// the rolling window (the sub-windows of the last `window` seconds) is too high
+fn record_settlement(env: &Env, cancelled: bool) {
+    let config: CircuitBreakerConfig = match env.storage().instance().get(&Symbol::new(env, "breaker")) {
+        Some(config) => config,
+        None => return,
+    };
+    
+    let now = env.ledger().timestamp();
+    let width = (config.window / BREAKER_BUCKETS).max(1);
+    let bucket_start = now - now % width;
+    let mut buckets = live_buckets(env, &config);
+    let mut bucket = match buckets.last() {
+        Some(last) if last.start == bucket_start => buckets.pop_back_unchecked(),
+        _ => CancelBucket { start: bucket_start, settled: 0, cancelled: 0 },
+    };
+    bucket.settled += 1;
+    if cancelled {
+        bucket.cancelled += 1;
+    }
+    buckets.push_back(bucket);
+    env.storage().instance().set(&Symbol::new(env, "buckets"), &buckets);
+    
+    let window = sum_buckets(env, &config, &buckets);
+    let cancel_bps = window.cancelled as u64 * 10_000 / window.settled as u64;
+    trace!(env, "breaker", (window.settled, window.cancelled, cancel_bps));
+    if window.settled >= config.min_samples && cancel_bps > config.max_cancel_bps as u64 {
+        env.storage().instance().set(&Symbol::new(env, "tripped"), &true);
+        events::publish(env, (Symbol::new(env, "circuit_tripped"),), (
+            window.settled,
+            window.cancelled,
+        ));
+    }
+}
+
+/// Withdraw from one escrow, reporting a failure instead of aborting
+/// Host failures (e.g. an address that isn't an escrow) carry no contract code
+fn try_withdraw(env: &Env, escrow: Address, secret: &BytesN<32>) -> BatchWithdrawResult {
+    let (success, error) = match EscrowClient::new(env, &escrow).try_withdraw(secret, &false) {
+        Ok(_) => (true, None),
+        Err(Ok(error)) if error.is_type(ScErrorType::Contract) => (false, Some(error.get_code())),
+        Err(Ok(_)) => (false, None),
+        Err(Err(InvokeError::Contract(code))) => (false, Some(code)),
+        Err(Err(InvokeError::Abort)) => (false, None),
+    };
+    BatchWithdrawResult { escrow, success, error }
+}
+
+/// Authorize the factory's deploy_escrow_for on exactly these immutables, as the resolver
+/// The direct call is authorized implicitly; the entry covers the factory being reached through
+/// another contract, which then can't change the amounts (or any other field) on the way
+/// The factory checks `(immutables)` with require_auth_for_args; returns the call's arguments
+fn authorize_factory_deploy(env: &Env, factory: &Address, immutables: &Immutables) -> Vec<Val> {
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: factory.clone(),
+                fn_name: Symbol::new(env, "deploy_escrow_for"),
+                args: (immutables.clone(),).into_val(env),
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+    (env.current_contract_address(), immutables.clone()).into_val(env)
+}
+
+/// Deploy an escrow through the factory, which derives the salt from the immutables
+/// Fails with DeployFailed when the factory refuses the immutables
+fn deploy_via_factory(env: &Env, factory: &Address, immutables: &Immutables) -> Result<Address, Error> {
+    let args = authorize_factory_deploy(env, factory, immutables);
+    match env.try_invoke_contract::<Address, InvokeError>(factory, &Symbol::new(env, "deploy_escrow_for"), args) {
+        Ok(Ok(escrow)) => Ok(escrow),
+        _ => Err(Error::DeployFailed),
+    }
+}
+
+/// Map a failed token transfer or escrow call of a funding step to FundingFailed
+fn funding_call<T, E, F>(result: Result<Result<T, E>, F>) -> Result<(), Error> {
+    match result {
+        Ok(Ok(_)) => Ok(()),
+        _ => Err(Error::FundingFailed),
+    }
+}
+
+/// Move a leg's amount and safety deposit from its maker into the escrow and confirm funding
+fn fund_and_confirm(env: &Env, escrow: &Address, immutables: &Immutables) -> Result<(), Error> {
+    immutables.maker.require_auth();
+    
+    funding_call(token::Client::new(env, &immutables.token).try_transfer(&immutables.maker, escrow, &immutables.amount))?;
+    
+    if immutables.safety_deposit > 0 {
+        // Native XLM Stellar Asset Contract (Asset::Native XDR)
+        let native = env.deployer()
+            .with_stellar_asset(Bytes::from_array(env, &[0, 0, 0, 0]))
+            .deployed_address();
+        funding_call(token::Client::new(env, &native).try_transfer(&immutables.maker, escrow, &immutables.safety_deposit))?;
+    }
+    
+    funding_call(EscrowClient::new(env, escrow).try_confirm_funding())
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,75 @@
+//! Fill requests waiting for a keeper
+//!
+//! The operator records fills as they are won, each with its priority (the auction payout), and
+//! a keeper drains them with execute_next. Requests run highest priority first, oldest first
+//! among equals, so every keeper processes the queue in the same order.
+
+use soroban_sdk::{contracttype, BytesN, Env, Symbol, Vec};
+
+use crate::{Error, Immutables, Proceeds};
+
+/// Longest the queue may grow; execute_next reads all of it
+pub const MAX_QUEUED: u32 = 100;
+
+/// A fill waiting to be deployed and funded
+#[derive(Clone)]
+#[contracttype]
+pub struct FillRequest {
+    pub immutables: Immutables,
+    /// What the fill earns, checked against the quotes when it runs
+    pub proceeds: Proceeds,
+    /// Higher runs first
+    pub priority: i128,
+    pub queued_at: u64,
+}
+
+fn queue_key(env: &Env) -> Symbol {
+    Symbol::new(env, "queue")
+}
+
+pub fn get(env: &Env) -> Vec<FillRequest> {
+    env.storage().persistent().get(&queue_key(env)).unwrap_or(Vec::new(env))
+}
+
+pub fn set(env: &Env, queue: &Vec<FillRequest>) {
+    if queue.is_empty() {
+        env.storage().persistent().remove(&queue_key(env));
+    } else {
+        env.storage().persistent().set(&queue_key(env), queue);
+    }
+}
+
+/// Insert a request after every request of the same or higher priority
+pub fn push(env: &Env, request: FillRequest) -> Result<(), Error> {
+    let mut queue = get(env);
+    if queue.len() >= MAX_QUEUED {
+        return Err(Error::QueueFull);
+    }
+    if position(&queue, &request.immutables.order_hash).is_some() {
+        return Err(Error::AlreadyQueued);
+    }
+    let index = queue
+        .iter()
+        .position(|queued| queued.priority < request.priority)
+        .map_or(queue.len(), |index| index as u32);
+    queue.insert(index, request);
+    set(env, &queue);
+    Ok(())
+}
+
+/// Remove a request by order hash
+pub fn remove(env: &Env, order_hash: &BytesN<32>) -> Result<FillRequest, Error> {
+    let mut queue = get(env);
+    let index = position(&queue, order_hash).ok_or(Error::NotQueued)?;
+    let request = queue.get_unchecked(index);
+    queue.remove(index);
+    set(env, &queue);
+    Ok(request)
+}
+
+fn position(queue: &Vec<FillRequest>, order_hash: &BytesN<32>) -> Option<u32> {
+    queue
+        .iter()
+        .position(|queued| queued.immutables.order_hash == *order_hash)
+        .map(|index| index as u32)
+}
@@ -0,0 +1,148 @@
+//! Signed price quotes bounding the rates the resolver fills at
+//!
+//! A quoter key, separate from the operator (owner) key, signs quotes off-chain; anyone can
+//! submit them. Fills are checked against the latest unexpired quote for their token pair, so a
+//! compromised operator key can't fill orders at prices outside the quoted band. For the same
+//! reason the operator can't replace the quoter at once: a new config waits
+//! REFERENCE_CHANGE_DELAY like a factory change.
+//!
+//! The bound only holds where both sides of the fill are on-chain, as in fill_internal_swap.
+//! fund_escrow, fill_evm_order and queued fills take the counter-amount (`Proceeds`) from the
+//! owner, so on those paths a compromised operator key can claim any proceeds and the quote is
+//! only advisory.
+
+use fusion_shared::trace;
+use soroban_sdk::{contracttype, xdr::ToXdr, Address, Bytes, BytesN, Env, Symbol};
+
+use crate::{Error, REFERENCE_CHANGE_DELAY};
+
+/// Rates are amounts of `buy_token` per unit of `sell_token`, scaled by 10^7 (Stellar's precision)
+pub const RATE_SCALE: i128 = 10_000_000;
+
+/// A quote for selling `sell_token` and receiving `buy_token`
+/// The quoter signs `signed_payload`, binding the quote to one resolver on one network
+#[derive(Clone)]
+#[contracttype]
+pub struct Quote {
+    pub sell_token: Address,
+    pub buy_token: Address,
+    pub rate: i128,
+    /// Ledger timestamp after which the quote is no longer used
+    pub expires_at: u64,
+}
+
+/// Quoter key and allowed deviation from its quotes
+#[derive(Clone)]
+#[contracttype]
+pub struct QuoteConfig {
+    pub quoter: BytesN<32>,
+    /// Largest deviation of a fill's rate from the quoted rate, in basis points
+    pub tolerance_bps: u32,
+}
+
+/// A scheduled quote config replacement
+#[derive(Clone)]
+#[contracttype]
+pub struct PendingQuoteConfig {
+    pub config: QuoteConfig,
+    /// Ledger timestamp from which apply_quote_config accepts it
+    pub effective_at: u64,
+}
+
+/// What the resolver receives for a fill, e.g. the maker asset of an EVM order, as the amount of
+/// the Stellar token the quoter prices it in
+#[derive(Clone)]
+#[contracttype]
+pub struct Proceeds {
+    pub token: Address,
+    pub amount: i128,
+}
+
+fn config_key(env: &Env) -> Symbol {
+    Symbol::new(env, "quote_cfg")
+}
+
+fn pending_key(env: &Env) -> Symbol {
+    Symbol::new(env, "quote_pend")
+}
+
+fn quote_key(env: &Env, sell_token: &Address, buy_token: &Address) -> (Symbol, Address, Address) {
+    (Symbol::new(env, "quote"), sell_token.clone(), buy_token.clone())
+}
+
+pub fn set_config(env: &Env, config: &QuoteConfig) {
+    env.storage().instance().set(&config_key(env), config);
+}
+
+pub fn get_config(env: &Env) -> Option<QuoteConfig> {
+    env.storage().instance().get(&config_key(env))
+}
+
+/// Schedule replacing the config, REFERENCE_CHANGE_DELAY from now; replaces any pending change
+pub fn schedule_config(env: &Env, config: &QuoteConfig) -> u64 {
+    let effective_at = env.ledger().timestamp() + REFERENCE_CHANGE_DELAY;
+    env.storage().instance().set(&pending_key(env), &PendingQuoteConfig { config: config.clone(), effective_at });
+    effective_at
+}
+
+pub fn get_pending_config(env: &Env) -> Option<PendingQuoteConfig> {
+    env.storage().instance().get(&pending_key(env))
+}
+
+pub fn remove_pending_config(env: &Env) {
+    env.storage().instance().remove(&pending_key(env));
+}
+
+/// Bytes the quoter signs: the XDR encoding of (resolver address, network id, quote), so a
+/// quote can't be replayed on another resolver or network
+pub fn signed_payload(env: &Env, quote: &Quote) -> Bytes {
+    (env.current_contract_address(), env.ledger().network_id(), quote.clone()).to_xdr(env)
+}
+
+/// Verify a quote's signature and store it as the latest for its pair
+/// A quote must expire later than the one it replaces (StaleQuote otherwise), so an older
+/// signed quote can't be resubmitted to roll the rate back
+pub fn submit(env: &Env, quote: &Quote, signature: &BytesN<64>) -> Result<(), Error> {
+    let config = get_config(env).ok_or(Error::QuotesDisabled)?;
+    if quote.rate <= 0 || quote.expires_at <= env.ledger().timestamp() {
+        return Err(Error::NoValidQuote);
+    }
+    if get_latest(env, &quote.sell_token, &quote.buy_token).is_some_and(|latest| quote.expires_at <= latest.expires_at) {
+        return Err(Error::StaleQuote);
+    }
+
+    // Panics unless the quoter signed exactly this quote
+    env.crypto().ed25519_verify(&config.quoter, &signed_payload(env, quote), signature);
+
+    env.storage().persistent().set(&quote_key(env, &quote.sell_token, &quote.buy_token), quote);
+    Ok(())
+}
+
+pub fn get_latest(env: &Env, sell_token: &Address, buy_token: &Address) -> Option<Quote> {
+    env.storage().persistent().get(&quote_key(env, sell_token, buy_token))
+}
+
+/// Check that receiving `buy_amount` for `sell_amount` is within tolerance of the latest quote
+/// No-op until a quote config is set
+pub fn check_fill(env: &Env, sell_token: &Address, sell_amount: i128, buy_token: &Address, buy_amount: i128) -> Result<(), Error> {
+    let config = match get_config(env) {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+    let quote = match get_latest(env, sell_token, buy_token) {
+        Some(quote) if quote.expires_at > env.ledger().timestamp() => quote,
+        _ => return Err(Error::NoValidQuote),
+    };
+
+    let quoted = sell_amount.checked_mul(quote.rate).ok_or(Error::PriceOutOfBand)? / RATE_SCALE;
+    let deviation = (buy_amount - quoted).abs();
+    trace!(env, "quote", (quote.rate, quoted, buy_amount, config.tolerance_bps));
+    let within_band = deviation
+        .checked_mul(10_000)
+        .zip(quoted.checked_mul(config.tolerance_bps as i128))
+        .is_some_and(|(deviation, allowed)| deviation <= allowed);
+    if !within_band {
+        return Err(Error::PriceOutOfBand);
+    }
+    Ok(())
+}
@@ -0,0 +1,793 @@
+#![cfg(test)]
+extern crate std;
+
+use super::*;
+use ed25519_dalek::{Signer, SigningKey};
+use soroban_sdk::{
+    contract, contractimpl, symbol_short,
+    testutils::{Address as _, Ledger},
+    Address, BytesN, Env, IntoVal,
+};
+
+/// Factory stand-in with deploy_escrow_for's taker and authorization checks; "deploys" by returning
+/// the next escrow given to set_escrows, or the resolver once there are none
+#[contract]
+struct MockFactory;
+
+#[contractimpl]
+impl MockFactory {
+    pub fn set_escrows(env: Env, escrows: Vec<Address>) {
+        env.storage().instance().set(&symbol_short!("escrows"), &escrows);
+    }
+
+    pub fn deploy_escrow_for(env: Env, resolver: Address, immutables: Immutables) -> Address {
+        assert_eq!(resolver, immutables.taker);
+        resolver.require_auth_for_args((immutables,).into_val(&env));
+        let mut escrows: Vec<Address> = env.storage().instance().get(&symbol_short!("escrows")).unwrap_or(Vec::new(&env));
+        match escrows.pop_front() {
+            Some(escrow) => {
+                env.storage().instance().set(&symbol_short!("escrows"), &escrows);
+                escrow
+            }
+            None => resolver,
+        }
+    }
+}
+
+/// Escrow stand-in answering get_immutables and get_state; withdraw closes it, failing with the
+/// escrow's InvalidState (101) unless it is open
+#[contract]
+struct MockEscrow;
+
+#[contractimpl]
+impl MockEscrow {
+    pub fn init(env: Env, hashlock: BytesN<32>, state: State) {
+        env.storage().instance().set(&symbol_short!("hashlock"), &hashlock);
+        env.storage().instance().set(&symbol_short!("state"), &state);
+    }
+
+    pub fn get_immutables(env: Env) -> fusion_shared::Immutables {
+        let owner = env.current_contract_address();
+        fusion_shared::Immutables {
+            order_hash: BytesN::from_array(&env, &[1; 32]),
+            hashlock: env.storage().instance().get(&symbol_short!("hashlock")).unwrap(),
+            maker: owner.clone(),
+            taker: owner.clone(),
+            token: owner,
+            amount: 1_000,
+            safety_deposit: 0,
+            timelocks: 0,
+            max_secret_len: 32,
+            hashlock_b: None,
+            beneficiary: None,
+            incentives: fusion_shared::DepositPolicy::Default,
+            skew_tolerance: 0,
+            finality_oracle: None,
+            pre_funding_hook: None,
+            post_payout_hook: None,
+            swap_id: None,
+            flags: 0,
+            parts: 0,
+        }
+    }
+
+    pub fn get_state(env: Env) -> State {
+        env.storage().instance().get(&symbol_short!("state")).unwrap()
+    }
+
+    pub fn withdraw(env: Env, _secret: BytesN<32>, _unwrap_native: bool) {
+        if !matches!(Self::get_state(env.clone()), State::Active | State::Funded) {
+            soroban_sdk::panic_with_error!(&env, soroban_sdk::Error::from_contract_error(101));
+        }
+        env.storage().instance().set(&symbol_short!("state"), &State::Withdrawn);
+    }
+
+    pub fn confirm_funding(env: Env) {
+        env.storage().instance().set(&symbol_short!("state"), &State::Funded);
+    }
+}
+
+/// A contract between the resolver and the factory, adding `extra` to the amount on the way
+#[contract]
+struct Forwarder;
+
+#[contractimpl]
+impl Forwarder {
+    pub fn forward(env: Env, factory: Address, resolver: Address, immutables: Immutables, extra: i128) -> Address {
+        let mut immutables = immutables;
+        immutables.amount += extra;
+        env.invoke_contract(
+            &factory,
+            &Symbol::new(&env, "deploy_escrow_for"),
+            (resolver, immutables).into_val(&env),
+        )
+    }
+}
+
+fn immutables(env: &Env, taker: &Address, token: &Address) -> Immutables {
+    Immutables {
+        order_hash: BytesN::from_array(env, &[1; 32]),
+        hashlock: BytesN::from_array(env, &[2; 32]),
+        maker: Address::generate(env),
+        taker: taker.clone(),
+        token: token.clone(),
+        amount: 1_000,
+        safety_deposit: 0,
+        timelocks: 0,
+    }
+}
+
+struct Setup {
+    env: Env,
+    resolver: Address,
+    factory: Address,
+    forwarder: Address,
+}
+
+fn setup() -> Setup {
+    let env = Env::default();
+    let resolver = env.register(ResolverContract, ());
+    let factory = env.register(MockFactory, ());
+    let forwarder = env.register(Forwarder, ());
+    Setup { env, resolver, factory, forwarder }
+}
+
+/// Forward a deploy as the resolver, with or without its auth entry for the factory call
+fn forward(setup: &Setup, immutables: &Immutables, extra: i128, authorize: bool) -> bool {
+    setup.env.as_contract(&setup.resolver, || {
+        if authorize {
+            authorize_factory_deploy(&setup.env, &setup.factory, immutables);
+        }
+        ForwarderClient::new(&setup.env, &setup.forwarder)
+            .try_forward(&setup.factory, &setup.resolver, immutables, &extra)
+            .is_ok()
+    })
+}
+
+#[test]
+fn test_factory_deploy_through_an_intermediary_is_authorized_for_the_exact_immutables() {
+    let setup = setup();
+    let immutables = immutables(&setup.env, &setup.resolver, &Address::generate(&setup.env));
+
+    assert!(forward(&setup, &immutables, 0, true));
+}
+
+#[test]
+fn test_intermediary_altering_the_amount_fails_the_factory_auth_check() {
+    let setup = setup();
+    let immutables = immutables(&setup.env, &setup.resolver, &Address::generate(&setup.env));
+
+    assert!(!forward(&setup, &immutables, 1, true));
+    assert!(!forward(&setup, &immutables, -1, true));
+}
+
+#[test]
+fn test_factory_deploy_through_an_intermediary_needs_the_auth_entry() {
+    let setup = setup();
+    let immutables = immutables(&setup.env, &setup.resolver, &Address::generate(&setup.env));
+
+    assert!(!forward(&setup, &immutables, 0, false));
+}
+
+#[test]
+fn test_execute_next_deploys_through_deploy_escrow_for() {
+    let setup = setup();
+    let env = &setup.env;
+    env.mock_all_auths();
+    let owner = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(owner.clone()).address();
+    token::StellarAssetClient::new(env, &token).mint(&owner, &1_000);
+    let client = ResolverContractClient::new(env, &setup.resolver);
+    client.initialize(&owner, &Address::generate(env), &setup.factory);
+
+    let proceeds = Proceeds { token: token.clone(), amount: 1_000 };
+    client.queue_fill(&immutables(env, &setup.resolver, &token), &proceeds, &1);
+    let escrows = client.execute_next(&1);
+
+    assert_eq!(escrows, vec![env, setup.resolver.clone()]);
+    assert_eq!(token::Client::new(env, &token).balance(&setup.resolver), 1_000);
+}
+
+#[test]
+fn test_fill_evm_order_requires_the_resolver_to_be_the_taker() {
+    let setup = setup();
+    let env = &setup.env;
+    env.mock_all_auths();
+    let client = ResolverContractClient::new(env, &setup.resolver);
+    client.initialize(&Address::generate(env), &Address::generate(env), &setup.factory);
+    let immutables = immutables(env, &Address::generate(env), &Address::generate(env));
+
+    let proceeds = Proceeds { token: immutables.token.clone(), amount: 1_000 };
+    let result = client.try_fill_evm_order(&1, &EvmAddress(BytesN::from_array(env, &[3; 20])), &immutables, &proceeds);
+
+    assert_eq!(result, Err(Ok(Error::NotTaker)));
+}
+
+/// An initialized resolver whose owner holds 10_000 of a token
+fn initialized(setup: &Setup) -> (ResolverContractClient<'_>, Address) {
+    let env = &setup.env;
+    env.mock_all_auths();
+    let owner = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(owner.clone()).address();
+    token::StellarAssetClient::new(env, &token).mint(&owner, &10_000);
+    let client = ResolverContractClient::new(env, &setup.resolver);
+    client.initialize(&owner, &Address::generate(env), &setup.factory);
+    (client, token)
+}
+
+/// An initialized resolver (see `initialized`) with quotes signed by `quoter`
+struct Quoted<'a> {
+    client: ResolverContractClient<'a>,
+    sell_token: Address,
+    buy_token: Address,
+    quoter: SigningKey,
+}
+
+fn quoted(setup: &Setup) -> Quoted<'_> {
+    let env = &setup.env;
+    let (client, sell_token) = initialized(setup);
+
+    let quoter = SigningKey::from_bytes(&[7; 32]);
+    client.set_quote_config(&QuoteConfig { quoter: BytesN::from_array(env, &quoter.verifying_key().to_bytes()), tolerance_bps: 100 });
+    Quoted { client, sell_token, buy_token: Address::generate(env), quoter }
+}
+
+/// A quote of 2 buy tokens per sell token, valid for 100 seconds
+fn quote(env: &Env, quoted: &Quoted) -> Quote {
+    Quote {
+        sell_token: quoted.sell_token.clone(),
+        buy_token: quoted.buy_token.clone(),
+        rate: 2 * quote::RATE_SCALE,
+        expires_at: env.ledger().timestamp() + 100,
+    }
+}
+
+/// `key`'s signature of `quote` for the resolver at `resolver`
+fn sign(env: &Env, resolver: &Address, key: &SigningKey, quote: &Quote) -> BytesN<64> {
+    let message: std::vec::Vec<u8> = env.as_contract(resolver, || quote::signed_payload(env, quote)).iter().collect();
+    BytesN::from_array(env, &key.sign(&message).to_bytes())
+}
+
+#[test]
+fn test_submit_quote_rejects_a_signature_by_another_key() {
+    let setup = setup();
+    let env = &setup.env;
+    let quoted = quoted(&setup);
+    let quote = quote(env, &quoted);
+
+    assert!(quoted.client.try_submit_quote(&quote, &sign(env, &quoted.client.address, &SigningKey::from_bytes(&[8; 32]), &quote)).is_err());
+    assert!(quoted.client.get_quote(&quoted.sell_token, &quoted.buy_token).is_none());
+
+    quoted.client.submit_quote(&quote, &sign(env, &quoted.client.address, &quoted.quoter, &quote));
+    assert_eq!(quoted.client.get_quote(&quoted.sell_token, &quoted.buy_token).unwrap().rate, quote.rate);
+}
+
+#[test]
+fn test_submit_quote_rejects_stale_and_foreign_quotes() {
+    let setup = setup();
+    let env = &setup.env;
+    let quoted = quoted(&setup);
+    let quote = quote(env, &quoted);
+    quoted.client.submit_quote(&quote, &sign(env, &quoted.client.address, &quoted.quoter, &quote));
+
+    // Replaying the stored quote or an older one can't roll the rate back
+    let older = Quote { rate: quote::RATE_SCALE, expires_at: quote.expires_at - 1, ..quote.clone() };
+    for stale in [&quote, &older] {
+        let signature = sign(env, &quoted.client.address, &quoted.quoter, stale);
+        assert_eq!(quoted.client.try_submit_quote(stale, &signature), Err(Ok(Error::StaleQuote)));
+    }
+
+    // A quote signed for another resolver doesn't verify here
+    let newer = Quote { rate: quote::RATE_SCALE, expires_at: quote.expires_at + 1, ..quote.clone() };
+    let foreign = sign(env, &env.register(ResolverContract, ()), &quoted.quoter, &newer);
+    assert!(quoted.client.try_submit_quote(&newer, &foreign).is_err());
+    assert_eq!(quoted.client.get_quote(&quoted.sell_token, &quoted.buy_token).unwrap().rate, quote.rate);
+
+    quoted.client.submit_quote(&newer, &sign(env, &quoted.client.address, &quoted.quoter, &newer));
+    assert_eq!(quoted.client.get_quote(&quoted.sell_token, &quoted.buy_token).unwrap().rate, newer.rate);
+}
+
+#[test]
+fn test_fills_outside_the_quoted_band_are_rejected_on_every_path() {
+    let setup = setup();
+    let env = &setup.env;
+    let quoted = quoted(&setup);
+    let quote = quote(env, &quoted);
+    quoted.client.submit_quote(&quote, &sign(env, &quoted.client.address, &quoted.quoter, &quote));
+    let proceeds = |amount: i128| Proceeds { token: quoted.buy_token.clone(), amount };
+    let escrow = Address::generate(env);
+
+    // 1% either side of 2_000 for 1_000
+    assert_eq!(quoted.client.try_fund_escrow(&escrow, &quoted.sell_token, &1_000, &proceeds(1_970)), Err(Ok(Error::PriceOutOfBand)));
+    assert_eq!(quoted.client.try_fund_escrow(&escrow, &quoted.sell_token, &1_000, &proceeds(2_030)), Err(Ok(Error::PriceOutOfBand)));
+    quoted.client.fund_escrow(&escrow, &quoted.sell_token, &1_000, &proceeds(1_990));
+    assert_eq!(token::Client::new(env, &quoted.sell_token).balance(&escrow), 1_000);
+
+    let immutables = immutables(env, &setup.resolver, &quoted.sell_token);
+    let taker_asset = EvmAddress(BytesN::from_array(env, &[3; 20]));
+    assert_eq!(quoted.client.try_fill_evm_order(&1, &taker_asset, &immutables, &proceeds(1_000)), Err(Ok(Error::PriceOutOfBand)));
+
+    quoted.client.queue_fill(&immutables, &proceeds(1_000), &1);
+    assert_eq!(quoted.client.execute_next(&1).len(), 0);
+    assert_eq!(quoted.client.get_queued_fills().len(), 1);
+}
+
+#[test]
+fn test_expired_quote_blocks_fills_until_a_fresh_one() {
+    let setup = setup();
+    let env = &setup.env;
+    let quoted = quoted(&setup);
+    let quote = quote(env, &quoted);
+    quoted.client.submit_quote(&quote, &sign(env, &quoted.client.address, &quoted.quoter, &quote));
+    let proceeds = Proceeds { token: quoted.buy_token.clone(), amount: 2_000 };
+    let escrow = Address::generate(env);
+
+    env.ledger().with_mut(|li| li.timestamp = quote.expires_at);
+    assert_eq!(quoted.client.try_fund_escrow(&escrow, &quoted.sell_token, &1_000, &proceeds), Err(Ok(Error::NoValidQuote)));
+    // Expired quotes can't be submitted either
+    assert_eq!(quoted.client.try_submit_quote(&quote, &sign(env, &quoted.client.address, &quoted.quoter, &quote)), Err(Ok(Error::NoValidQuote)));
+
+    let immutables = immutables(env, &setup.resolver, &quoted.sell_token);
+    quoted.client.queue_fill(&immutables, &proceeds, &1);
+    assert_eq!(quoted.client.execute_next(&1).len(), 0);
+
+    let fresh = Quote { expires_at: quote.expires_at + 100, ..quote };
+    quoted.client.submit_quote(&fresh, &sign(env, &quoted.client.address, &quoted.quoter, &fresh));
+    assert_eq!(quoted.client.execute_next(&1), vec![env, setup.resolver.clone()]);
+    quoted.client.fund_escrow(&escrow, &quoted.sell_token, &1_000, &proceeds);
+}
+
+#[test]
+fn test_replacing_the_quoter_waits_for_the_reference_change_delay() {
+    let setup = setup();
+    let env = &setup.env;
+    let quoted = quoted(&setup);
+    let original = quoted.client.get_quote_config().unwrap();
+    let attacker = SigningKey::from_bytes(&[9; 32]);
+    let replacement = QuoteConfig { quoter: BytesN::from_array(env, &attacker.verifying_key().to_bytes()), tolerance_bps: 10_000 };
+
+    quoted.client.set_quote_config(&replacement);
+    assert_eq!(quoted.client.get_quote_config().unwrap().quoter, original.quoter);
+    let quote = quote(env, &quoted);
+    assert!(quoted.client.try_submit_quote(&quote, &sign(env, &quoted.client.address, &attacker, &quote)).is_err());
+    assert_eq!(quoted.client.try_apply_quote_config().err(), Some(Ok(Error::ChangeNotReady)));
+
+    quoted.client.cancel_quote_config();
+    assert_eq!(quoted.client.try_apply_quote_config().err(), Some(Ok(Error::NoPendingChange)));
+
+    quoted.client.set_quote_config(&replacement);
+    let effective_at = quoted.client.get_pending_quote_config().unwrap().effective_at;
+    assert_eq!(effective_at, env.ledger().timestamp() + REFERENCE_CHANGE_DELAY);
+    env.ledger().with_mut(|li| li.timestamp = effective_at);
+    assert_eq!(quoted.client.apply_quote_config().tolerance_bps, 10_000);
+    assert_eq!(quoted.client.get_quote_config().unwrap().quoter, replacement.quoter);
+    assert!(quoted.client.get_pending_quote_config().is_none());
+}
+
+/// A resolver with an open position in each of `escrows`
+fn with_positions<'a>(setup: &'a Setup, escrows: &[Address]) -> ResolverContractClient<'a> {
+    let (client, token) = initialized(setup);
+    for escrow in escrows {
+        client.fund_escrow(escrow, &token, &1, &Proceeds { token: token.clone(), amount: 1 });
+    }
+    client
+}
+
+fn mock_escrow(env: &Env, hashlock: &BytesN<32>, state: State) -> Address {
+    let escrow = env.register(MockEscrow, ());
+    MockEscrowClient::new(env, &escrow).init(hashlock, &state);
+    escrow
+}
+
+#[test]
+fn test_register_secret_withdraws_only_open_escrows_with_its_hashlock() {
+    let setup = setup();
+    let env = &setup.env;
+    let secret = BytesN::from_array(env, &[5; 32]);
+    let hashlock: BytesN<32> = env.crypto().sha256(&secret.clone().into()).into();
+    let funded = mock_escrow(env, &hashlock, State::Funded);
+    let withdrawn = mock_escrow(env, &hashlock, State::Withdrawn);
+    let cancelled = mock_escrow(env, &hashlock, State::Cancelled);
+    let other = mock_escrow(env, &BytesN::from_array(env, &[6; 32]), State::Funded);
+    let active = mock_escrow(env, &hashlock, State::Active);
+    let client = with_positions(&setup, &[funded.clone(), withdrawn, setup.forwarder.clone(), cancelled, other.clone(), active.clone()]);
+
+    let results = client.register_secret(&secret, &10);
+
+    let withdrawals: std::vec::Vec<_> = results.iter().map(|result| (result.escrow, result.success)).collect();
+    assert_eq!(withdrawals, [(funded, true), (active, true)]);
+    assert_eq!(MockEscrowClient::new(env, &other).get_state(), State::Funded);
+    assert_eq!(client.get_registered_secret(&hashlock), Some(secret));
+}
+
+#[test]
+fn test_register_secret_inspects_at_most_max_secret_scan_positions() {
+    let setup = setup();
+    let env = &setup.env;
+    env.cost_estimate().budget().reset_unlimited();
+    let secret = BytesN::from_array(env, &[5; 32]);
+    let hashlock: BytesN<32> = env.crypto().sha256(&secret.clone().into()).into();
+    let escrows: std::vec::Vec<Address> = (0..=MAX_SECRET_SCAN).map(|_| mock_escrow(env, &hashlock, State::Funded)).collect();
+    let client = with_positions(&setup, &escrows);
+
+    assert_eq!(client.register_secret(&secret, &u32::MAX).len(), MAX_SECRET_SCAN);
+    assert_eq!(MockEscrowClient::new(env, &escrows[MAX_SECRET_SCAN as usize]).get_state(), State::Funded);
+}
+
+#[test]
+fn test_entrypoints_fail_with_not_initialized_before_initialize() {
+    let setup = setup();
+    let env = &setup.env;
+    env.mock_all_auths();
+    let client = ResolverContractClient::new(env, &setup.resolver);
+    let escrow = Address::generate(env);
+    let secret = BytesN::from_array(env, &[5; 32]);
+    let not_initialized = Some(Ok(Error::NotInitialized));
+
+    assert_eq!(client.try_withdraw(&escrow, &secret).err(), not_initialized);
+    assert_eq!(client.try_cancel(&escrow).err(), not_initialized);
+    assert_eq!(client.try_withdraw_batch(&vec![env, escrow.clone()], &secret).err(), not_initialized);
+    assert_eq!(client.try_claim_rewards(&escrow, &vec![env, escrow.clone()]).err(), not_initialized);
+    assert_eq!(client.try_get_owner().err(), not_initialized);
+    assert_eq!(client.try_execute_next(&1).err(), not_initialized);
+}
+
+#[test]
+fn test_initialize_runs_once() {
+    let setup = setup();
+    let env = &setup.env;
+    env.mock_all_auths();
+    let client = ResolverContractClient::new(env, &setup.resolver);
+    let owner = Address::generate(env);
+    client.initialize(&owner, &Address::generate(env), &setup.factory);
+
+    let second = client.try_initialize(&Address::generate(env), &Address::generate(env), &Address::generate(env));
+
+    assert_eq!(second.err(), Some(Ok(Error::AlreadyInitialized)));
+    assert_eq!(client.get_owner(), owner);
+    assert_eq!(client.get_factory(), setup.factory);
+}
+
+#[test]
+fn test_failing_factory_and_token_calls_surface_as_resolver_errors() {
+    let setup = setup();
+    let env = &setup.env;
+    let client = with_positions(&setup, &[]);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+
+    // The mock factory refuses deploys whose taker isn't the resolver
+    let leg = immutables(env, &Address::generate(env), &token);
+    assert_eq!(client.try_fill_internal_swap(&leg, &leg).err(), Some(Ok(Error::DeployFailed)));
+
+    // A maker without the leg's amount
+    let leg = immutables(env, &setup.resolver, &token);
+    assert_eq!(client.try_fill_internal_swap(&leg, &leg).err(), Some(Ok(Error::FundingFailed)));
+}
+
+/// A leg of an internal swap whose maker holds its amount of a fresh token
+fn funded_leg(setup: &Setup, amount: i128) -> Immutables {
+    let env = &setup.env;
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    let leg = Immutables { amount, ..immutables(env, &setup.resolver, &token) };
+    token::StellarAssetClient::new(env, &token).mint(&leg.maker, &amount);
+    leg
+}
+
+#[test]
+fn test_fill_internal_swap_rejects_legs_with_different_hashlocks() {
+    let setup = setup();
+    let env = &setup.env;
+    let client = with_positions(&setup, &[]);
+    let src = funded_leg(&setup, 1_000);
+    let dst = Immutables { hashlock: BytesN::from_array(env, &[9; 32]), ..funded_leg(&setup, 500) };
+
+    assert_eq!(client.try_fill_internal_swap(&src, &dst).err(), Some(Ok(Error::HashlockMismatch)));
+    assert_eq!(token::Client::new(env, &src.token).balance(&src.maker), 1_000);
+}
+
+#[test]
+fn test_fill_internal_swap_funds_and_confirms_both_legs() {
+    let setup = setup();
+    let env = &setup.env;
+    let client = with_positions(&setup, &[]);
+    let src = funded_leg(&setup, 1_000);
+    let dst = funded_leg(&setup, 500);
+    let src_escrow = mock_escrow(env, &src.hashlock, State::Active);
+    let dst_escrow = mock_escrow(env, &dst.hashlock, State::Active);
+    MockFactoryClient::new(env, &setup.factory).set_escrows(&vec![env, src_escrow.clone(), dst_escrow.clone()]);
+
+    assert_eq!(client.fill_internal_swap(&src, &dst), (src_escrow.clone(), dst_escrow.clone()));
+
+    assert_eq!(token::Client::new(env, &src.token).balance(&src_escrow), 1_000);
+    assert_eq!(token::Client::new(env, &dst.token).balance(&dst_escrow), 500);
+    assert_eq!(MockEscrowClient::new(env, &src_escrow).get_state(), State::Funded);
+    assert_eq!(MockEscrowClient::new(env, &dst_escrow).get_state(), State::Funded);
+    assert_eq!(client.get_internal_swap(&src.order_hash), Some((src_escrow, dst_escrow)));
+}
+
+#[test]
+fn test_token_limits_cap_single_fills_and_open_exposure() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    client.set_token_limits(&token, &TokenLimits { max_order_notional: 500, max_total_exposure: 800 });
+    let proceeds = |amount: i128| Proceeds { token: token.clone(), amount };
+    let hashlock = BytesN::from_array(env, &[2; 32]);
+    let first = mock_escrow(env, &hashlock, State::Funded);
+    let second = mock_escrow(env, &hashlock, State::Funded);
+
+    assert_eq!(client.try_fund_escrow(&first, &token, &501, &proceeds(501)).err(), Some(Ok(Error::LimitExceeded)));
+    client.fund_escrow(&first, &token, &500, &proceeds(500));
+    assert_eq!(client.try_fund_escrow(&second, &token, &301, &proceeds(301)).err(), Some(Ok(Error::LimitExceeded)));
+    client.fund_escrow(&second, &token, &300, &proceeds(300));
+    assert_eq!(client.get_exposure(&token), 800);
+
+    // Settling releases the exposure; open escrows can't be settled
+    assert_eq!(client.try_settle_position(&first).err(), Some(Ok(Error::EscrowStillOpen)));
+    MockEscrowClient::new(env, &first).init(&hashlock, &State::Withdrawn);
+    client.settle_position(&first);
+    assert_eq!(client.get_exposure(&token), 300);
+    assert_eq!(client.get_active_escrows(), vec![env, second]);
+    assert_eq!(client.try_settle_position(&first).err(), Some(Ok(Error::UnknownPosition)));
+}
+
+#[test]
+fn test_circuit_breaker_trips_on_the_cancellation_rate_and_resets() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    client.set_circuit_breaker(&CircuitBreakerConfig { window: 100, max_cancel_bps: 5_000, min_samples: 2 });
+    let hashlock = BytesN::from_array(env, &[2; 32]);
+    let proceeds = Proceeds { token: token.clone(), amount: 1 };
+    let escrows: std::vec::Vec<Address> = (0..3).map(|_| mock_escrow(env, &hashlock, State::Funded)).collect();
+    for escrow in &escrows {
+        client.fund_escrow(escrow, &token, &1, &proceeds);
+    }
+
+    // One cancellation isn't enough samples, and one of two (50%) isn't above the rate
+    MockEscrowClient::new(env, &escrows[0]).init(&hashlock, &State::Cancelled);
+    client.settle_position(&escrows[0]);
+    assert!(!client.is_circuit_tripped());
+    MockEscrowClient::new(env, &escrows[1]).init(&hashlock, &State::Withdrawn);
+    client.settle_position(&escrows[1]);
+    assert!(!client.is_circuit_tripped());
+    MockEscrowClient::new(env, &escrows[2]).init(&hashlock, &State::Cancelled);
+    client.settle_position(&escrows[2]);
+    assert!(client.is_circuit_tripped());
+
+    assert_eq!(client.try_fund_escrow(&escrows[0], &token, &1, &proceeds).err(), Some(Ok(Error::CircuitBreakerTripped)));
+    assert_eq!(client.try_execute_next(&1).err(), Some(Ok(Error::CircuitBreakerTripped)));
+
+    client.reset_circuit_breaker();
+    assert!(!client.is_circuit_tripped());
+    assert_eq!(client.get_cancel_window().unwrap().settled, 0);
+    client.fund_escrow(&escrows[0], &token, &1, &proceeds);
+}
+
+#[test]
+fn test_circuit_breaker_window_rolls_instead_of_resetting() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    // 10-second sub-windows
+    client.set_circuit_breaker(&CircuitBreakerConfig { window: 100, max_cancel_bps: 5_000, min_samples: 3 });
+    let hashlock = BytesN::from_array(env, &[2; 32]);
+    let proceeds = Proceeds { token: token.clone(), amount: 1 };
+    let settle = |at: u64, state: State| {
+        let escrow = mock_escrow(env, &hashlock, State::Funded);
+        client.fund_escrow(&escrow, &token, &1, &proceeds);
+        env.ledger().with_mut(|li| li.timestamp = at);
+        MockEscrowClient::new(env, &escrow).init(&hashlock, &state);
+        client.settle_position(&escrow);
+    };
+
+    settle(0, State::Withdrawn);
+    settle(5, State::Withdrawn);
+    settle(95, State::Cancelled);
+    assert!(!client.is_circuit_tripped());
+
+    // The withdrawals' sub-window has rolled out; the cancellation at 95 still counts
+    settle(105, State::Cancelled);
+    let window = client.get_cancel_window().unwrap();
+    assert_eq!((window.start, window.settled, window.cancelled), (5, 2, 2));
+    assert!(!client.is_circuit_tripped());
+    settle(106, State::Cancelled);
+    assert!(client.is_circuit_tripped());
+}
+
+#[test]
+fn test_withdraw_batch_reports_each_failure_without_aborting() {
+    let setup = setup();
+    let env = &setup.env;
+    let client = with_positions(&setup, &[]);
+    let secret = BytesN::from_array(env, &[5; 32]);
+    let hashlock = BytesN::from_array(env, &[2; 32]);
+    let open = mock_escrow(env, &hashlock, State::Funded);
+    let closed = mock_escrow(env, &hashlock, State::Cancelled);
+    let later = mock_escrow(env, &hashlock, State::Active);
+
+    let results = client.withdraw_batch(&vec![env, open.clone(), closed.clone(), setup.forwarder.clone(), later.clone()], &secret);
+
+    let outcomes: std::vec::Vec<_> = results.iter().map(|result| (result.escrow, result.success, result.error)).collect();
+    assert_eq!(outcomes, [
+        (open.clone(), true, None),
+        (closed, false, Some(101)),
+        // Not an escrow: the call aborts without a contract error
+        (setup.forwarder.clone(), false, None),
+        (later.clone(), true, None),
+    ]);
+    assert_eq!(MockEscrowClient::new(env, &open).get_state(), State::Withdrawn);
+    assert_eq!(MockEscrowClient::new(env, &later).get_state(), State::Withdrawn);
+
+    // A second batch finds them closed
+    let again = client.withdraw_batch(&vec![env, open], &secret);
+    assert_eq!(again.get(0).unwrap().error, Some(101));
+}
+
+/// A fill of `amount` with its own order hash, queued at `priority`
+fn queue(client: &ResolverContractClient, setup: &Setup, token: &Address, seed: u8, amount: i128, priority: i128) -> BytesN<32> {
+    let env = &setup.env;
+    let order_hash = BytesN::from_array(env, &[seed; 32]);
+    let immutables = Immutables { order_hash: order_hash.clone(), amount, ..immutables(env, &setup.resolver, token) };
+    client.queue_fill(&immutables, &Proceeds { token: token.clone(), amount }, &priority);
+    order_hash
+}
+
+fn queued_hashes(client: &ResolverContractClient) -> std::vec::Vec<BytesN<32>> {
+    client.get_queued_fills().iter().map(|request| request.immutables.order_hash).collect()
+}
+
+#[test]
+fn test_queue_runs_highest_priority_first_and_oldest_first_among_equals() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    let low = queue(&client, &setup, &token, 1, 100, 1);
+    let first = queue(&client, &setup, &token, 2, 100, 5);
+    let middle = queue(&client, &setup, &token, 3, 100, 3);
+    let second = queue(&client, &setup, &token, 4, 100, 5);
+
+    assert_eq!(queued_hashes(&client), [first.clone(), second.clone(), middle.clone(), low.clone()]);
+    let duplicate = Immutables { order_hash: middle.clone(), ..immutables(env, &setup.resolver, &token) };
+    assert_eq!(
+        client.try_queue_fill(&duplicate, &Proceeds { token: token.clone(), amount: 1 }, &9).err(),
+        Some(Ok(Error::AlreadyQueued))
+    );
+
+    client.cancel_queued_fill(&middle);
+    assert_eq!(client.try_cancel_queued_fill(&middle).err(), Some(Ok(Error::NotQueued)));
+
+    let escrows: std::vec::Vec<Address> = (0..3).map(|_| Address::generate(env)).collect();
+    MockFactoryClient::new(env, &setup.factory).set_escrows(&vec![env, escrows[0].clone(), escrows[1].clone(), escrows[2].clone()]);
+    assert_eq!(client.execute_next(&2), vec![env, escrows[0].clone(), escrows[1].clone()]);
+    assert_eq!(queued_hashes(&client), [low]);
+    assert_eq!(client.execute_next(&5), vec![env, escrows[2].clone()]);
+    assert!(client.get_queued_fills().is_empty());
+}
+
+#[test]
+fn test_queue_defers_fills_over_limits_and_drops_refused_ones() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    client.set_token_limits(&token, &TokenLimits { max_order_notional: 500, max_total_exposure: 10_000 });
+    let oversized = queue(&client, &setup, &token, 1, 600, 9);
+    let refused = Immutables { order_hash: BytesN::from_array(env, &[2; 32]), amount: 100, ..immutables(env, &Address::generate(env), &token) };
+    client.queue_fill(&refused, &Proceeds { token: token.clone(), amount: 100 }, &5);
+    queue(&client, &setup, &token, 3, 400, 1);
+    let escrow = Address::generate(env);
+    MockFactoryClient::new(env, &setup.factory).set_escrows(&vec![env, escrow.clone()]);
+
+    // The oversized fill stays queued, the one the factory refuses is dropped
+    assert_eq!(client.execute_next(&5), vec![env, escrow.clone()]);
+    assert_eq!(queued_hashes(&client), core::slice::from_ref(&oversized));
+    assert_eq!(token::Client::new(env, &token).balance(&escrow), 400);
+
+    client.set_token_limits(&token, &TokenLimits { max_order_notional: 600, max_total_exposure: 10_000 });
+    assert_eq!(client.execute_next(&5).len(), 1);
+    assert!(client.get_queued_fills().is_empty());
+}
+
+#[test]
+fn test_failed_transfers_drop_the_fill_instead_of_trapping() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    queue(&client, &setup, &token, 1, 20_000, 9);
+    queue(&client, &setup, &token, 2, 400, 1);
+    let escrows = [Address::generate(env), Address::generate(env)];
+    MockFactoryClient::new(env, &setup.factory).set_escrows(&vec![env, escrows[0].clone(), escrows[1].clone()]);
+
+    // The owner only holds 10_000: the first fill is dropped and the batch goes on
+    assert_eq!(client.execute_next(&5), vec![env, escrows[1].clone()]);
+    assert!(client.get_queued_fills().is_empty());
+    assert_eq!(client.get_active_escrows(), vec![env, escrows[1].clone()]);
+    assert_eq!(client.get_exposure(&token), 400);
+
+    let proceeds = Proceeds { token: token.clone(), amount: 20_000 };
+    assert_eq!(client.try_fund_escrow(&escrows[0], &token, &20_000, &proceeds).err(), Some(Ok(Error::FundingFailed)));
+    // escrows[1] isn't a contract, so its state can't be read
+    assert_eq!(client.try_settle_position(&escrows[1]).err(), Some(Ok(Error::EscrowStillOpen)));
+}
+
+#[test]
+fn test_reference_changes_wait_for_the_delay() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, _) = initialized(&setup);
+    let factory = Address::generate(env);
+
+    client.set_factory(&factory);
+    let pending = client.get_pending_change(&Reference::Factory).unwrap();
+    assert_eq!(pending.effective_at, env.ledger().timestamp() + REFERENCE_CHANGE_DELAY);
+    assert_eq!(client.try_apply_reference_change(&Reference::Factory).err(), Some(Ok(Error::ChangeNotReady)));
+    assert_eq!(client.get_factory(), setup.factory);
+
+    env.ledger().with_mut(|li| li.timestamp = pending.effective_at - 1);
+    assert_eq!(client.try_apply_reference_change(&Reference::Factory).err(), Some(Ok(Error::ChangeNotReady)));
+    env.ledger().with_mut(|li| li.timestamp = pending.effective_at);
+    assert_eq!(client.apply_reference_change(&Reference::Factory), factory);
+    assert_eq!(client.get_factory(), factory);
+    assert!(client.get_pending_change(&Reference::Factory).is_none());
+    assert_eq!(client.try_apply_reference_change(&Reference::Factory).err(), Some(Ok(Error::NoPendingChange)));
+}
+
+#[test]
+fn test_rescheduling_restarts_the_delay_and_cancel_drops_the_change() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, _) = initialized(&setup);
+    let htlc = client.get_htlc();
+
+    client.set_htlc(&Address::generate(env));
+    env.ledger().with_mut(|li| li.timestamp = REFERENCE_CHANGE_DELAY - 1);
+    let replacement = Address::generate(env);
+    client.set_htlc(&replacement);
+    env.ledger().with_mut(|li| li.timestamp = REFERENCE_CHANGE_DELAY);
+    assert_eq!(client.try_apply_reference_change(&Reference::Htlc).err(), Some(Ok(Error::ChangeNotReady)));
+
+    // Changes to one reference leave the other alone
+    assert!(client.get_pending_change(&Reference::Factory).is_none());
+    assert_eq!(client.try_cancel_reference_change(&Reference::Factory).err(), Some(Ok(Error::NoPendingChange)));
+
+    client.cancel_reference_change(&Reference::Htlc);
+    env.ledger().with_mut(|li| li.timestamp = 2 * REFERENCE_CHANGE_DELAY);
+    assert_eq!(client.try_apply_reference_change(&Reference::Htlc).err(), Some(Ok(Error::NoPendingChange)));
+    assert_eq!(client.get_htlc(), htlc);
+}
+
+#[test]
+fn test_emergency_withdraw_all_sweeps_balances_and_trips_the_breaker() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    let other = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    token::StellarAssetClient::new(env, &token).mint(&setup.resolver, &700);
+    let safe = Address::generate(env);
+
+    let swept = client.emergency_withdraw_all(&vec![env, token.clone(), other.clone()], &safe);
+
+    assert_eq!(swept, vec![env, 700, 0]);
+    assert_eq!(token::Client::new(env, &token).balance(&safe), 700);
+    assert_eq!(token::Client::new(env, &token).balance(&setup.resolver), 0);
+    assert!(client.is_circuit_tripped());
+    let escrow = Address::generate(env);
+    let proceeds = Proceeds { token: token.clone(), amount: 1 };
+    assert_eq!(client.try_fund_escrow(&escrow, &token, &1, &proceeds).err(), Some(Ok(Error::CircuitBreakerTripped)));
+
+    client.reset_circuit_breaker();
+    client.fund_escrow(&escrow, &token, &1, &proceeds);
+}
+
+#[test]
+fn test_emergency_withdraw_all_is_owner_only() {
+    let setup = setup();
+    let env = &setup.env;
+    let (client, token) = initialized(&setup);
+    token::StellarAssetClient::new(env, &token).mint(&setup.resolver, &700);
+    env.set_auths(&[]);
+
+    assert!(client.try_emergency_withdraw_all(&vec![env, token.clone()], &Address::generate(env)).is_err());
+    assert_eq!(token::Client::new(env, &token).balance(&setup.resolver), 700);
+    assert!(!client.is_circuit_tripped());
+}
@@ -0,0 +1,69 @@
+import { describe, it, expect } from 'vitest';
+import { Interface, keccak256, toUtf8Bytes } from 'ethers';
+import vectors from '../fusion-interop/vectors/1inch-cross-chain-swap.json';
+import {
+  EvmMirrorPlanner,
+  RESOLVER_ABI,
+  encodeImmutables,
+  immutablesHash,
+  packEvmTimelocks,
+  toEvmImmutables,
+  unpackStellarTimelocks,
+} from '../extended-resolver/src/resolver/EvmMirrorPlanner';
+
+const IMMUTABLES_TYPE = '(bytes32,bytes32,uint256,uint256,uint256,uint256,uint256,uint256)';
+
+describe('EVM mirror-call planner', () => {
+  it('encodes immutables like ImmutablesLib', () => {
+    for (const vector of vectors.immutables) {
+      const immutables = {
+        orderHash: vector.orderHash,
+        hashlock: vector.hashlock,
+        maker: BigInt(vector.maker),
+        taker: BigInt(vector.taker),
+        token: BigInt(vector.token),
+        amount: BigInt(vector.amount),
+        safetyDeposit: BigInt(vector.safetyDeposit),
+        timelocks: BigInt(vector.timelocks),
+      };
+      expect(encodeImmutables(immutables), vector.name).toBe(vector.abiEncoded);
+      expect(immutablesHash(immutables), vector.name).toBe(vector.hash);
+    }
+  });
+
+  it('packs timelocks like TimelocksLib', () => {
+    for (const vector of vectors.timelocks) {
+      expect(packEvmTimelocks(vector.deployedAt, vector.offsets), vector.name).toBe(BigInt(vector.packed));
+    }
+  });
+
+  it('mirrors a Stellar escrow into withdraw calldata', () => {
+    const offsets = [10, 20, 30, 40, 10, 20, 30];
+    const stellarTimelocks = offsets.reduce((packed, offset, stage) => packed | (BigInt(offset) << BigInt(8 * stage)), 0n);
+    expect(unpackStellarTimelocks(stellarTimelocks)).toEqual(offsets);
+
+    const immutables = toEvmImmutables(
+      { orderHash: '0x' + '11'.repeat(32), hashlock: '0x' + '22'.repeat(32), timelocks: stellarTimelocks },
+      {
+        maker: '0x' + '33'.repeat(20),
+        taker: '0x' + '44'.repeat(20),
+        token: '0x' + '55'.repeat(20),
+        amount: 1_000_000n,
+        safetyDeposit: 1_000n,
+      },
+      1_700_000_000,
+    );
+    expect(immutables.timelocks).toBe(packEvmTimelocks(1_700_000_000, offsets));
+    expect(immutables.maker).toBe(BigInt('0x' + '33'.repeat(20)));
+
+    const planner = new EvmMirrorPlanner('0x' + '66'.repeat(20));
+    const escrow = '0x' + '77'.repeat(20);
+    const request = planner.withdraw(escrow, '0x' + '88'.repeat(32), immutables);
+    const selector = keccak256(toUtf8Bytes(`withdraw(address,bytes32,${IMMUTABLES_TYPE})`)).slice(0, 10);
+    expect(request.data!.slice(0, 10)).toBe(selector);
+
+    const decoded = new Interface(RESOLVER_ABI).decodeFunctionData('withdraw', request.data!);
+    expect(decoded[0].toLowerCase()).toBe(escrow);
+    expect(decoded[2].timelocks).toBe(immutables.timelocks);
+  });
+});
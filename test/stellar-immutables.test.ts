@@ -0,0 +1,36 @@
+import { describe, it, expect } from 'vitest';
+import { decodeImmutables, EscrowSide, DEFAULT_FLAGS } from '../src/services/resolver/utils/immutables';
+
+const core = {
+  order_hash: Buffer.alloc(32, 1),
+  hashlock: Buffer.alloc(32, 2),
+  maker: 'GMAKER',
+  taker: 'GTAKER',
+  token: 'CTOKEN',
+  amount: 1000n,
+  safety_deposit: 10n,
+  timelocks: 42n,
+};
+
+describe('stellar immutables decoding', () => {
+  it('decodes v1 get_immutables as version 1', () => {
+    const decoded = decodeImmutables({ ...core, max_secret_len: 32, beneficiary: 'GTREASURY', incentives: ['Default'] });
+    expect(decoded.version).toBe(1);
+    expect(decoded.side).toBe(EscrowSide.Dst);
+    expect(decoded.flags).toBe(DEFAULT_FLAGS);
+    expect(decoded.receiver).toBe('GTREASURY');
+    expect(decoded.orderHash).toBe(`0x${'01'.repeat(32)}`);
+  });
+
+  it('decodes get_immutables_v2 with its own version and side', () => {
+    const decoded = decodeImmutables(
+      { ...core, version: 2, side: 0, flags: 3, receiver: undefined, fees: ['Default'] },
+      EscrowSide.Dst,
+    );
+    expect(decoded.version).toBe(2);
+    expect(decoded.side).toBe(EscrowSide.Src);
+    expect(decoded.flags).toBe(3);
+    expect(decoded.receiver).toBeUndefined();
+    expect(decoded.safetyDeposit).toBe(10n);
+  });
+});
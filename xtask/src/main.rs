@@ -0,0 +1,146 @@
+//! cargo xtask bindings [--check]
+//!
+//! Builds the escrow, factory and resolver contracts to wasm and regenerates
+//! their bindings with the Stellar CLI:
+//!
+//! - `interfaces/spec/<contract>.json`: contract spec
+//! - `interfaces/src/generated/<contract>.rs`: Rust client and types
+//! - `interfaces/ts/<contract>/`: TypeScript client package for the relayer
+//!
+//! `--check` regenerates the spec and Rust bindings in memory and fails if the
+//! committed files are out of date. The CLI binary defaults to `stellar` and can
+//! be overridden with `STELLAR_CLI`.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+/// A contract crate and the module name its bindings are written under
+struct Contract {
+    dir: &'static str,
+    package: &'static str,
+    module: &'static str,
+}
+
+const CONTRACTS: [Contract; 3] = [
+    Contract { dir: "stellar-fusion", package: "stellar-escrow", module: "escrow" },
+    Contract { dir: "stellar-fusion-factory", package: "stellar-escrow-factory", module: "factory" },
+    Contract { dir: "stellar-resolver", package: "stellar-resolver", module: "resolver" },
+];
+
+const WASM_TARGET: &str = "wasm32v1-none";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("xtask: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["bindings"] => bindings(false),
+        ["bindings", "--check"] => bindings(true),
+        _ => Err("usage: cargo xtask bindings [--check]".into()),
+    }
+}
+
+fn bindings(check: bool) -> Result<(), String> {
+    let root = repo_root();
+    let interfaces = root.join("interfaces");
+    let mut stale = Vec::new();
+
+    for contract in &CONTRACTS {
+        let wasm = build_wasm(&root, contract)?;
+
+        let outputs = [
+            (interfaces.join("spec").join(format!("{}.json", contract.module)), "json"),
+            (interfaces.join("src/generated").join(format!("{}.rs", contract.module)), "rust"),
+        ];
+        for (path, language) in outputs {
+            let generated = stellar(&["contract", "bindings", language, "--wasm", path_str(&wasm)?])?;
+            if check {
+                if std::fs::read_to_string(&path).ok().as_deref() != Some(generated.as_str()) {
+                    stale.push(path);
+                }
+            } else {
+                write(&path, &generated)?;
+            }
+        }
+
+        if !check {
+            let ts_dir = interfaces.join("ts").join(contract.module);
+            stellar(&[
+                "contract", "bindings", "typescript",
+                "--wasm", path_str(&wasm)?,
+                "--output-dir", path_str(&ts_dir)?,
+                "--overwrite",
+            ])?;
+        }
+        println!("{}: bindings {}", contract.module, if check { "checked" } else { "written" });
+    }
+
+    if !stale.is_empty() {
+        let files: Vec<String> = stale.iter().map(|path| path.display().to_string()).collect();
+        return Err(format!("bindings out of date, run `cargo xtask bindings`:\n  {}", files.join("\n  ")));
+    }
+    Ok(())
+}
+
+/// Build a contract crate for the wasm target and return the path of its wasm
+fn build_wasm(root: &Path, contract: &Contract) -> Result<PathBuf, String> {
+    let dir = root.join(contract.dir);
+    let status = Command::new("cargo")
+        .args(["build", "--release", "--target", WASM_TARGET])
+        .current_dir(&dir)
+        .status()
+        .map_err(|err| format!("cargo build in {}: {err}", contract.dir))?;
+    if !status.success() {
+        return Err(format!("cargo build in {} failed", contract.dir));
+    }
+
+    let wasm = dir
+        .join("target")
+        .join(WASM_TARGET)
+        .join("release")
+        .join(format!("{}.wasm", contract.package.replace('-', "_")));
+    if !wasm.exists() {
+        return Err(format!("{} not found", wasm.display()));
+    }
+    Ok(wasm)
+}
+
+/// Run the Stellar CLI and return its stdout
+fn stellar(args: &[&str]) -> Result<String, String> {
+    let cli = std::env::var("STELLAR_CLI").unwrap_or_else(|_| "stellar".into());
+    let output = Command::new(&cli)
+        .args(args)
+        .output()
+        .map_err(|err| format!("{cli}: {err}"))?;
+    if !output.status.success() {
+        return Err(format!("{cli} {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr)));
+    }
+    String::from_utf8(output.stdout).map_err(|err| format!("{cli} output: {err}"))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| format!("{}: {err}", parent.display()))?;
+    }
+    std::fs::write(path, contents).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn path_str(path: &Path) -> Result<&str, String> {
+    path.to_str().ok_or_else(|| format!("non-UTF-8 path {}", path.display()))
+}
+
+/// The repository root (xtask lives one level below it)
+fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask is inside the repository")
+        .to_path_buf()
+}